@@ -1,22 +1,190 @@
 use crate::catalog::Catalog;
 use crate::catalog::CatalogError;
+use crate::catalog::TableStatistics;
+use crate::catalog::{
+    list_files, unify_schemas, CsvOptions, IpcReader, JsonOptions, MultiFileOptions, TableSource,
+};
+use crate::logical_plan::udaf::AggregateUdf;
 use arrow::datatypes::SchemaRef;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
 use std::collections::HashMap;
+use std::fs::File;
+use std::sync::Arc;
 
+/// Number of rows `analyze` reads at a time.
+const ANALYZE_BATCH_SIZE: usize = 8192;
+
+#[derive(Default)]
 pub struct DummyCatalog {
     tables: HashMap<String, SchemaRef>,
+    udafs: HashMap<String, Arc<AggregateUdf>>,
+    statistics: HashMap<String, TableStatistics>,
+    /// Files of the tables registered with them, like with `register_parquet`.
+    sources: HashMap<String, TableSource>,
 }
 
 impl DummyCatalog {
+    #[must_use]
     pub fn new() -> Self {
         Self {
             tables: HashMap::new(),
+            udafs: HashMap::new(),
+            statistics: HashMap::new(),
+            sources: HashMap::new(),
         }
     }
 
     pub fn add_table(&mut self, name: &str, schema: SchemaRef) {
         self.tables.insert(name.to_string(), schema);
     }
+
+    pub fn set_statistics(&mut self, name: &str, statistics: TableStatistics) {
+        self.statistics.insert(name.to_string(), statistics);
+    }
+
+    /// Adds the table stored in the parquet file at `path`, with the
+    /// statistics from the file's footer.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file can't be opened or has an invalid footer.
+    pub fn register_parquet(&mut self, name: &str, path: &str) -> anyhow::Result<()> {
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?;
+        let schema = builder.schema().clone();
+        let statistics = TableStatistics::from_parquet_metadata(builder.metadata(), &schema)?;
+        self.add_table(name, schema);
+        self.set_statistics(name, statistics);
+        self.sources
+            .insert(name.to_string(), TableSource::Parquet(path.to_string()));
+        Ok(())
+    }
+
+    /// Adds the table stored in the parquet files at `location`, a directory or
+    /// a file name pattern, with the statistics from their footers. The schemas
+    /// of the files are unified as `options` allow.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no file matches `location`, a file can't be read, or the
+    /// schemas can't be unified.
+    pub fn register_parquet_files(
+        &mut self,
+        name: &str,
+        location: &str,
+        options: MultiFileOptions,
+    ) -> anyhow::Result<()> {
+        let paths = list_files(location)?;
+        let mut schemas = Vec::with_capacity(paths.len());
+        let mut statistics: Option<TableStatistics> = None;
+        for path in &paths {
+            let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?;
+            let file_statistics =
+                TableStatistics::from_parquet_metadata(builder.metadata(), builder.schema())?;
+            match &mut statistics {
+                Some(statistics) => statistics.merge(&file_statistics),
+                None => statistics = Some(file_statistics),
+            }
+            schemas.push(builder.schema().clone());
+        }
+        let schema = unify_schemas(&paths, &schemas, options)?;
+        if let Some(mut statistics) = statistics {
+            statistics
+                .columns
+                .retain(|column, _| schema.field_with_name(column).is_ok());
+            self.set_statistics(name, statistics);
+        }
+        self.add_table(name, schema);
+        self.sources
+            .insert(name.to_string(), TableSource::ParquetFiles(paths));
+        Ok(())
+    }
+
+    /// Adds the table stored in the CSV file at `path`, whose schema is
+    /// inferred from its first rows. Whether it has a header is decided once,
+    /// here, if `options` leave it open.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file can't be read or its schema can't be inferred.
+    pub fn register_csv(
+        &mut self,
+        name: &str,
+        path: &str,
+        options: CsvOptions,
+    ) -> anyhow::Result<()> {
+        let options = CsvOptions {
+            has_header: Some(options.header(path)?),
+            ..options
+        };
+        self.add_table(name, options.infer_schema(path)?);
+        let path = path.to_string();
+        self.sources
+            .insert(name.to_string(), TableSource::Csv { path, options });
+        Ok(())
+    }
+
+    /// Adds the table stored in the newline-delimited JSON file at `path`, with
+    /// the schema of `options` or else one inferred from its first objects.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file can't be read or its schema can't be inferred.
+    pub fn register_json(
+        &mut self,
+        name: &str,
+        path: &str,
+        mut options: JsonOptions,
+    ) -> anyhow::Result<()> {
+        let schema = options.infer_schema(path)?;
+        self.add_table(name, schema.clone());
+        options.schema = Some(schema);
+        let path = path.to_string();
+        self.sources
+            .insert(name.to_string(), TableSource::Json { path, options });
+        Ok(())
+    }
+
+    /// Adds the table stored in the Arrow IPC file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file isn't an Arrow IPC file or stream.
+    pub fn register_ipc(&mut self, name: &str, path: &str) -> anyhow::Result<()> {
+        let schema = IpcReader::try_new(path, None)?.schema()?;
+        self.add_table(name, schema);
+        self.sources
+            .insert(name.to_string(), TableSource::Ipc(path.to_string()));
+        Ok(())
+    }
+
+    /// Files the rows of `table_name` are read from, if it was registered with
+    /// them.
+    #[must_use]
+    pub fn get_source(&self, table_name: &str) -> Option<&TableSource> {
+        self.sources.get(table_name)
+    }
+
+    /// Refreshes the statistics of a table registered with its files by reading
+    /// all of its rows, which also estimates the distinct counts the parquet
+    /// footer usually lacks.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the table has no files or they can't be read.
+    pub fn analyze(&mut self, table_name: &str) -> anyhow::Result<()> {
+        let schema = self.get_schema(table_name)?;
+        let Some(source) = self.sources.get(table_name) else {
+            anyhow::bail!("Table {table_name} has no data to analyze");
+        };
+        let batches = source.read(&schema, ANALYZE_BATCH_SIZE)?;
+        let statistics = TableStatistics::from_batches(&schema, batches)?;
+        self.set_statistics(table_name, statistics);
+        Ok(())
+    }
+
+    pub fn register_udaf(&mut self, udaf: AggregateUdf) {
+        self.udafs.insert(udaf.name().to_string(), Arc::new(udaf));
+    }
 }
 
 impl Catalog for DummyCatalog {
@@ -27,11 +195,22 @@ impl Catalog for DummyCatalog {
             Err(CatalogError::TableNotFound(table_name.to_string()))
         }
     }
+
+    fn get_udaf(&self, name: &str) -> Option<Arc<AggregateUdf>> {
+        self.udafs.get(&name.to_lowercase()).cloned()
+    }
+
+    fn get_statistics(&self, table_name: &str) -> Option<TableStatistics> {
+        self.statistics.get(table_name).cloned()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::catalog::ScalarValue;
+    use crate::logical_plan::errors::PlanError;
+    use crate::parser::sql_parser::{parse_sql_statement, Statement};
     use arrow::datatypes::Schema;
     use std::sync::Arc;
 
@@ -47,4 +226,45 @@ mod tests {
         catalog.add_table("table", Arc::new(Schema::empty()));
         assert_eq!(catalog.get_schema("table"), Ok(Arc::new(Schema::empty())));
     }
+
+    #[test]
+    fn test_parquet_statistics() -> anyhow::Result<()> {
+        let mut catalog = DummyCatalog::new();
+        catalog.register_parquet("users", "samples/sample-data/parquet/userdata1.parquet")?;
+
+        // The footer has bounds of numeric columns, but no distinct counts.
+        let footer = catalog.get_statistics("users").unwrap();
+        assert_eq!(footer.row_count, 1000);
+        assert!(footer.total_bytes > 0);
+        let id = &footer.columns["id"];
+        assert_eq!(id.min, Some(ScalarValue::Int64(1)));
+        assert_eq!(id.max, Some(ScalarValue::Int64(1000)));
+        assert_eq!(id.distinct_count, None);
+        assert_eq!(footer.columns["salary"].null_count, Some(68));
+
+        let Statement::Analyze { table_name } =
+            parse_sql_statement("ANALYZE TABLE users", &catalog)?
+        else {
+            panic!("expected ANALYZE");
+        };
+        catalog.analyze(&table_name)?;
+        let analyzed = catalog.get_statistics("users").unwrap();
+        assert_eq!(analyzed.row_count, 1000);
+        assert_eq!(analyzed.columns["id"].min, id.min);
+        assert_eq!(analyzed.columns["salary"].null_count, Some(68));
+        let gender = &analyzed.columns["gender"];
+        assert_eq!(gender.max, Some(ScalarValue::Utf8("Male".to_string())));
+        assert_eq!(gender.distinct_count, Some(3));
+        let distinct = analyzed.columns["id"].distinct_count.unwrap();
+        assert!((990..=1000).contains(&distinct), "{distinct}");
+
+        assert!(parse_sql_statement("ANALYZE TABLE missing", &catalog).is_err());
+        for sql in ["", "ANALYZE TABLE users; ANALYZE TABLE users"] {
+            assert!(matches!(
+                parse_sql_statement(sql, &catalog),
+                Err(PlanError::Unsupported(_))
+            ));
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,358 @@
+use arrow::array::ArrayRef;
+use arrow::buffer::Buffer;
+use arrow::datatypes::{Schema, SchemaRef};
+use arrow::ipc::convert::fb_to_schema;
+use arrow::ipc::reader::{read_dictionary, read_footer_length, read_record_batch, FileDecoder};
+use arrow::ipc::{root_as_footer, root_as_message, Block, Message, MessageHeader};
+use arrow::record_batch::RecordBatch;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Leading bytes of the IPC file format, which the stream format lacks.
+const FILE_MAGIC: &[u8] = b"ARROW1";
+
+/// Reads the record batches of an Arrow IPC file, in the file or stream format.
+///
+/// The file (Feather v2) format is told apart by its leading magic bytes. The
+/// file is memory-mapped, and the arrays of the batches point into the mapping
+/// unless they are compressed.
+pub struct IpcReader {
+    buffer: Buffer,
+    /// Schema of the file, before the projection.
+    schema: SchemaRef,
+    projection: Option<Vec<usize>>,
+    format: Format,
+}
+
+enum Format {
+    File {
+        decoder: FileDecoder,
+        blocks: Vec<Block>,
+        next: usize,
+    },
+    Stream {
+        dictionaries: HashMap<i64, ArrayRef>,
+        offset: usize,
+    },
+}
+
+impl IpcReader {
+    /// Opens the file at `path`, which reads only the columns at the indices
+    /// of `projection` if given.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file can't be mapped, isn't an Arrow IPC file or stream, or
+    /// a projected column doesn't exist.
+    pub fn try_new(path: &str, projection: Option<Vec<usize>>) -> anyhow::Result<Self> {
+        let buffer = mmap::map_file(path)?;
+        let (schema, format) = if buffer.starts_with(FILE_MAGIC) {
+            Self::open_file(&buffer, projection.clone())?
+        } else {
+            Self::open_stream(&buffer)?
+        };
+        if let Some(index) = projection
+            .iter()
+            .flatten()
+            .find(|&&i| i >= schema.fields().len())
+        {
+            anyhow::bail!("Column index {index} is out of bounds in {path}");
+        }
+        Ok(Self {
+            buffer,
+            schema: Arc::new(schema),
+            projection,
+            format,
+        })
+    }
+
+    /// Schema of the returned batches.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the projection doesn't match the schema of the file.
+    pub fn schema(&self) -> anyhow::Result<SchemaRef> {
+        Ok(match &self.projection {
+            Some(projection) => Arc::new(self.schema.project(projection)?),
+            None => self.schema.clone(),
+        })
+    }
+
+    /// Reads the footer at the end of the file, and the dictionaries it lists.
+    fn open_file(
+        buffer: &Buffer,
+        projection: Option<Vec<usize>>,
+    ) -> anyhow::Result<(Schema, Format)> {
+        let trailer = buffer.len().saturating_sub(10);
+        let footer_len = read_footer_length(buffer[trailer..].try_into()?)?;
+        let footer_start = trailer
+            .checked_sub(footer_len)
+            .ok_or_else(|| anyhow::anyhow!("IPC footer is longer than the file"))?;
+        let footer = root_as_footer(&buffer[footer_start..trailer])
+            .map_err(|e| anyhow::anyhow!("Invalid IPC footer: {e}"))?;
+        let fb_schema = footer
+            .schema()
+            .ok_or_else(|| anyhow::anyhow!("IPC footer has no schema"))?;
+        let schema = fb_to_schema(fb_schema);
+
+        let mut decoder = FileDecoder::new(Arc::new(schema.clone()), footer.version());
+        if let Some(projection) = projection {
+            decoder = decoder.with_projection(projection);
+        }
+        for block in footer.dictionaries().iter().flatten() {
+            decoder.read_dictionary(block, &block_data(buffer, block)?)?;
+        }
+        let blocks = footer.recordBatches().iter().flatten().copied().collect();
+        let format = Format::File {
+            decoder,
+            blocks,
+            next: 0,
+        };
+        Ok((schema, format))
+    }
+
+    /// Reads the schema message at the start of the stream.
+    fn open_stream(buffer: &Buffer) -> anyhow::Result<(Schema, Format)> {
+        let mut offset = 0;
+        let schema = match next_message(buffer, &mut offset)? {
+            Some((message, _)) if message.header_type() == MessageHeader::Schema => {
+                fb_to_schema(message.header_as_schema().unwrap())
+            }
+            _ => anyhow::bail!("IPC stream does not start with a schema"),
+        };
+        let format = Format::Stream {
+            dictionaries: HashMap::new(),
+            offset,
+        };
+        Ok((schema, format))
+    }
+
+    fn next_batch(&mut self) -> anyhow::Result<Option<RecordBatch>> {
+        match &mut self.format {
+            Format::File {
+                decoder,
+                blocks,
+                next,
+            } => {
+                while let Some(block) = blocks.get(*next) {
+                    *next += 1;
+                    let data = block_data(&self.buffer, block)?;
+                    if let Some(batch) = decoder.read_record_batch(block, &data)? {
+                        return Ok(Some(batch));
+                    }
+                }
+                Ok(None)
+            }
+            Format::Stream {
+                dictionaries,
+                offset,
+            } => {
+                while let Some((message, body)) = next_message(&self.buffer, offset)? {
+                    match message.header_type() {
+                        MessageHeader::DictionaryBatch => {
+                            let batch = message.header_as_dictionary_batch().unwrap();
+                            let version = message.version();
+                            read_dictionary(&body, batch, &self.schema, dictionaries, &version)?;
+                        }
+                        MessageHeader::RecordBatch => {
+                            let batch = read_record_batch(
+                                &body,
+                                message.header_as_record_batch().unwrap(),
+                                self.schema.clone(),
+                                dictionaries,
+                                self.projection.as_deref(),
+                                &message.version(),
+                            )?;
+                            return Ok(Some(batch));
+                        }
+                        header => anyhow::bail!("Unexpected {header:?} message in IPC stream"),
+                    }
+                }
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl Iterator for IpcReader {
+    type Item = anyhow::Result<RecordBatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_batch().transpose()
+    }
+}
+
+/// Slice of `buffer` with the message of `block` and its body.
+fn block_data(buffer: &Buffer, block: &Block) -> anyhow::Result<Buffer> {
+    let start = usize::try_from(block.offset())?;
+    let len = usize::try_from(block.metaDataLength())?
+        .checked_add(usize::try_from(block.bodyLength())?)
+        .filter(|len| {
+            start
+                .checked_add(*len)
+                .is_some_and(|end| end <= buffer.len())
+        })
+        .ok_or_else(|| anyhow::anyhow!("IPC block is out of bounds"))?;
+    Ok(buffer.slice_with_length(start, len))
+}
+
+/// Reads the stream message at `offset`, and its body, and advances `offset`
+/// past them. Returns `None` at the end of the stream.
+fn next_message<'a>(
+    buffer: &'a Buffer,
+    offset: &mut usize,
+) -> anyhow::Result<Option<(Message<'a>, Buffer)>> {
+    let read_i32 = |offset: &mut usize| -> anyhow::Result<Option<i32>> {
+        let end = offset.checked_add(4);
+        let Some(bytes) = end.and_then(|end| buffer.get(*offset..end)) else {
+            return Ok(None);
+        };
+        *offset += 4;
+        Ok(Some(i32::from_le_bytes(bytes.try_into()?)))
+    };
+    // Messages start with their length, which older writers don't prefix
+    // with the continuation marker.
+    let len = match read_i32(offset)? {
+        Some(-1) => read_i32(offset)?,
+        len => len,
+    };
+    let len = match len {
+        None | Some(0) => return Ok(None),
+        Some(len) => usize::try_from(len)?,
+    };
+    let metadata = offset
+        .checked_add(len)
+        .and_then(|end| buffer.get(*offset..end))
+        .ok_or_else(|| anyhow::anyhow!("IPC stream is truncated"))?;
+    let message =
+        root_as_message(metadata).map_err(|e| anyhow::anyhow!("Invalid IPC message: {e}"))?;
+    *offset += len;
+    let body_len = usize::try_from(message.bodyLength())?;
+    let end = offset
+        .checked_add(body_len)
+        .filter(|&end| end <= buffer.len())
+        .ok_or_else(|| anyhow::anyhow!("IPC stream is truncated"))?;
+    let body = buffer.slice_with_length(*offset, body_len);
+    *offset = end;
+    Ok(Some((message, body)))
+}
+
+#[cfg(unix)]
+mod mmap {
+    use arrow::buffer::Buffer;
+    use std::fs::File;
+    use std::os::fd::AsRawFd;
+    use std::ptr::NonNull;
+    use std::sync::Arc;
+
+    /// Read-only mapping of a file, unmapped once no buffer points into it.
+    struct Mmap {
+        ptr: NonNull<u8>,
+        len: usize,
+    }
+
+    // SAFETY: the mapping is never written to, and unmapped only on drop.
+    unsafe impl Send for Mmap {}
+    unsafe impl Sync for Mmap {}
+
+    impl Drop for Mmap {
+        fn drop(&mut self) {
+            // SAFETY: `ptr` and `len` are those of a mapping by `map_file`.
+            unsafe {
+                libc::munmap(self.ptr.as_ptr().cast(), self.len);
+            }
+        }
+    }
+
+    /// Maps the file at `path` into memory as a buffer.
+    pub fn map_file(path: &str) -> anyhow::Result<Buffer> {
+        let file = File::open(path)?;
+        let len = usize::try_from(file.metadata()?.len())?;
+        if len == 0 {
+            return Ok(Buffer::from_vec(Vec::<u8>::new()));
+        }
+        // SAFETY: the mapping is private and read-only. Files which are
+        // truncated while mapped are not supported.
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        let ptr = NonNull::new(ptr.cast::<u8>())
+            .ok_or_else(|| anyhow::anyhow!("mmap of {path} returned a null pointer"))?;
+        let mmap = Arc::new(Mmap { ptr, len });
+        // SAFETY: the mapping is valid for `len` bytes as long as `mmap` lives.
+        Ok(unsafe { Buffer::from_custom_allocation(ptr, len, mmap) })
+    }
+}
+
+#[cfg(not(unix))]
+mod mmap {
+    use arrow::buffer::Buffer;
+
+    /// Reads the file at `path` into a buffer where it can't be mapped.
+    pub fn map_file(path: &str) -> anyhow::Result<Buffer> {
+        Ok(Buffer::from_vec(std::fs::read(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{AsArray, DictionaryArray, Int64Array};
+    use arrow::datatypes::{Int32Type, Int64Type};
+    use arrow::ipc::writer::{FileWriter, StreamWriter};
+    use std::fs::File;
+
+    #[test]
+    fn test_ipc_reader() -> anyhow::Result<()> {
+        let batch = RecordBatch::try_from_iter([
+            ("id", Arc::new(Int64Array::from(vec![1, 2, 3])) as ArrayRef),
+            (
+                "kind",
+                Arc::new(DictionaryArray::<Int32Type>::from_iter(["a", "b", "a"])) as ArrayRef,
+            ),
+        ])?;
+        let dir = std::env::temp_dir();
+        let file_path = dir.join(format!("inudb-ipc-{}.arrow", std::process::id()));
+        let stream_path = dir.join(format!("inudb-ipc-{}.arrows", std::process::id()));
+        let mut writer = FileWriter::try_new(File::create(&file_path)?, &batch.schema())?;
+        writer.write(&batch)?;
+        writer.write(&batch)?;
+        writer.finish()?;
+        let mut writer = StreamWriter::try_new(File::create(&stream_path)?, &batch.schema())?;
+        writer.write(&batch)?;
+        writer.write(&batch)?;
+        writer.finish()?;
+
+        for path in [&file_path, &stream_path] {
+            let reader = IpcReader::try_new(path.to_str().unwrap(), None)?;
+            assert_eq!(reader.schema()?, batch.schema());
+            let range = reader.buffer.as_ptr_range();
+            let batches = reader.collect::<anyhow::Result<Vec<_>>>()?;
+            assert_eq!(batches, [batch.clone(), batch.clone()]);
+            // The values are not copied out of the mapping.
+            let ids = batches[1].column(0).as_primitive::<Int64Type>();
+            assert!(range.contains(&ids.values().inner().as_ptr()));
+
+            let reader = IpcReader::try_new(path.to_str().unwrap(), Some(vec![1]))?;
+            assert_eq!(reader.schema()?.field(0).name(), "kind");
+            for projected in reader {
+                let projected = projected?;
+                assert_eq!(projected.num_columns(), 1);
+                assert_eq!(projected.column(0).as_any_dictionary().keys().len(), 3);
+            }
+            std::fs::remove_file(path)?;
+        }
+
+        Ok(())
+    }
+}
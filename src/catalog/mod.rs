@@ -1,12 +1,33 @@
 mod dummy_catalog;
 mod errors;
+mod ipc;
+mod multi_file;
+mod source;
+mod statistics;
 
-use crate::catalog::CatalogError;
+use crate::logical_plan::udaf::AggregateUdf;
 use arrow::datatypes::SchemaRef;
+use std::sync::Arc;
 
 pub use dummy_catalog::*;
 pub use errors::*;
+pub use ipc::*;
+pub use multi_file::*;
+pub use source::*;
+pub use statistics::*;
 
 pub trait Catalog {
+    /// Schema of the table `table_name`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if there's no such table.
     fn get_schema(&self, table_name: &str) -> Result<SchemaRef, CatalogError>;
+
+    fn get_udaf(&self, name: &str) -> Option<Arc<AggregateUdf>>;
+
+    /// Statistics of the table, if known.
+    fn get_statistics(&self, table_name: &str) -> Option<TableStatistics> {
+        None
+    }
 }
@@ -0,0 +1,226 @@
+use crate::logical_plan::dag_builder::set_operation_type;
+use arrow::array::new_null_array;
+use arrow::compute::{self, CastOptions};
+use arrow::datatypes::{Field, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// How the schemas of the files of a table are unified. By default, all files
+/// must have the same columns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MultiFileOptions {
+    pub missing_columns: MissingColumns,
+    pub extra_columns: ExtraColumns,
+}
+
+/// What to do with a column of the table which a file lacks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MissingColumns {
+    #[default]
+    Error,
+    /// Reads the column as NULL in the rows of the file.
+    Null,
+}
+
+/// What to do with a column of a file which the files before it lack.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExtraColumns {
+    #[default]
+    Error,
+    /// Leaves the column out of the table.
+    Ignore,
+    /// Adds the column to the table, which makes it missing from the files
+    /// before.
+    Add,
+}
+
+/// Paths of the files at `location`, sorted. That is the parquet files of a
+/// directory, the files matching a file name with `*` and `?` wildcards, or
+/// else the file itself.
+pub fn list_files(location: &str) -> anyhow::Result<Vec<String>> {
+    let path = Path::new(location);
+    let (dir, pattern) = if path.is_dir() {
+        (path, wildcard_regex("*.parquet")?)
+    } else {
+        match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) if name.contains(['*', '?']) => {
+                let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+                (dir.unwrap_or_else(|| Path::new(".")), wildcard_regex(name)?)
+            }
+            _ => return Ok(vec![location.to_string()]),
+        }
+    };
+
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if entry.file_type()?.is_file() && name.to_str().is_some_and(|n| pattern.is_match(n)) {
+            paths.push(entry.path().to_string_lossy().into_owned());
+        }
+    }
+    if paths.is_empty() {
+        anyhow::bail!("No files match {location}");
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+fn wildcard_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    let mut regex = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex)
+}
+
+/// Schema of the table made of files with `schemas`, those of `paths`. The
+/// columns are those of the first file, followed by the ones `options` add,
+/// with the types their values in all files are coerced to. Columns some file
+/// lacks become nullable.
+pub fn unify_schemas(
+    paths: &[String],
+    schemas: &[SchemaRef],
+    options: MultiFileOptions,
+) -> anyhow::Result<SchemaRef> {
+    let mut fields = Vec::<Field>::new();
+    for (i, (path, schema)) in paths.iter().zip(schemas).enumerate() {
+        for field in &mut fields {
+            let Ok(file_field) = schema.field_with_name(field.name()) else {
+                if options.missing_columns == MissingColumns::Error {
+                    anyhow::bail!("Column {} is missing from {path}", field.name());
+                }
+                *field = field.clone().with_nullable(true);
+                continue;
+            };
+            let Some(data_type) = set_operation_type(field.data_type(), file_field.data_type())
+            else {
+                anyhow::bail!(
+                    "Column {} is {} in {path} but {} in the files before",
+                    field.name(),
+                    file_field.data_type(),
+                    field.data_type()
+                );
+            };
+            let nullable = field.is_nullable() || file_field.is_nullable();
+            *field = field
+                .clone()
+                .with_data_type(data_type)
+                .with_nullable(nullable);
+        }
+
+        for file_field in schema.fields() {
+            if i == 0 {
+                fields.push(file_field.as_ref().clone());
+                continue;
+            }
+            if fields.iter().any(|field| field.name() == file_field.name()) {
+                continue;
+            }
+            match (options.extra_columns, options.missing_columns) {
+                (ExtraColumns::Ignore, _) => {}
+                (ExtraColumns::Add, MissingColumns::Null) => {
+                    fields.push(file_field.as_ref().clone().with_nullable(true));
+                }
+                _ => anyhow::bail!(
+                    "Column {} of {path} is missing from the files before",
+                    file_field.name()
+                ),
+            }
+        }
+    }
+    Ok(Arc::new(Schema::new(fields)))
+}
+
+/// Converts `batch`, read from one of the files of a table, to the table's
+/// `schema`. Its columns the table lacks are left out, and values which don't
+/// fit into the table's types are an error.
+pub fn conform_batch(batch: &RecordBatch, schema: &SchemaRef) -> anyhow::Result<RecordBatch> {
+    let options = CastOptions {
+        safe: false,
+        ..CastOptions::default()
+    };
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|field| match batch.column_by_name(field.name()) {
+            Some(column) if column.data_type() == field.data_type() => Ok(column.clone()),
+            Some(column) => Ok(compute::cast_with_options(
+                column,
+                field.data_type(),
+                &options,
+            )?),
+            None => Ok(new_null_array(field.data_type(), batch.num_rows())),
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::datatypes::DataType;
+
+    #[test]
+    fn test_unify_schemas() -> anyhow::Result<()> {
+        let paths = ["a.parquet".to_string(), "b.parquet".to_string()];
+        let schemas = [
+            Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new("name", DataType::Utf8, false),
+            ])),
+            Arc::new(Schema::new(vec![
+                Field::new("score", DataType::Float64, false),
+                Field::new("id", DataType::Int64, false),
+            ])),
+        ];
+
+        let error = unify_schemas(&paths, &schemas, MultiFileOptions::default()).unwrap_err();
+        assert_eq!(error.to_string(), "Column name is missing from b.parquet");
+
+        let options = MultiFileOptions {
+            missing_columns: MissingColumns::Null,
+            extra_columns: ExtraColumns::Ignore,
+        };
+        let schema = unify_schemas(&paths, &schemas, options)?;
+        assert_eq!(
+            schema.fields().as_ref(),
+            [
+                Arc::new(Field::new("id", DataType::Int64, false)),
+                Arc::new(Field::new("name", DataType::Utf8, true)),
+            ]
+        );
+
+        let options = MultiFileOptions {
+            extra_columns: ExtraColumns::Add,
+            ..options
+        };
+        let schema = unify_schemas(&paths, &schemas, options)?;
+        assert_eq!(
+            schema.field(2),
+            &Field::new("score", DataType::Float64, true)
+        );
+
+        // The columns of a batch are widened and filled in.
+        let batch = RecordBatch::try_new(
+            schemas[0].clone(),
+            vec![
+                Arc::new(arrow::array::Int32Array::from(vec![1, 2])),
+                Arc::new(arrow::array::StringArray::from(vec!["a", "b"])),
+            ],
+        )?;
+        let batch = conform_batch(&batch, &schema)?;
+        assert_eq!(batch.schema(), schema);
+        assert_eq!(batch.column(2).null_count(), 2);
+
+        Ok(())
+    }
+}
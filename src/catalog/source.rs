@@ -0,0 +1,237 @@
+use crate::catalog::{conform_batch, IpcReader};
+use arrow::csv::reader::Format;
+use arrow::csv::{Reader, ReaderBuilder};
+use arrow::datatypes::{DataType, SchemaRef};
+use arrow::json;
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use regex::Regex;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+
+/// Files the rows of a table are read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TableSource {
+    Parquet(String),
+    /// Parquet files read as one table, whose schema they are converted to.
+    ParquetFiles(Vec<String>),
+    Csv {
+        path: String,
+        options: CsvOptions,
+    },
+    Json {
+        path: String,
+        options: JsonOptions,
+    },
+    /// Arrow IPC file, in the file or the stream format.
+    Ipc(String),
+}
+
+impl TableSource {
+    /// Reads all rows of the source in batches of `batch_size` rows.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a file can't be opened or doesn't match `schema`.
+    pub fn read(
+        &self,
+        schema: &SchemaRef,
+        batch_size: usize,
+    ) -> anyhow::Result<Box<dyn Iterator<Item = anyhow::Result<RecordBatch>>>> {
+        Ok(match self {
+            Self::Parquet(path) => {
+                let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?
+                    .with_batch_size(batch_size)
+                    .build()?;
+                Box::new(reader.map(|batch| Ok(batch?)))
+            }
+            Self::ParquetFiles(paths) => {
+                let readers = paths
+                    .iter()
+                    .map(|path| {
+                        Ok(ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?
+                            .with_batch_size(batch_size)
+                            .build()?)
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                let schema = schema.clone();
+                Box::new(
+                    readers
+                        .into_iter()
+                        .flatten()
+                        .map(move |batch| conform_batch(&batch?, &schema)),
+                )
+            }
+            Self::Csv { path, options } => {
+                let reader = options.reader(path, schema.clone(), batch_size)?;
+                Box::new(reader.map(|batch| Ok(batch?)))
+            }
+            Self::Json { path, options } => {
+                let reader = options.reader(path, schema.clone(), batch_size)?;
+                Box::new(reader.map(|batch| Ok(batch?)))
+            }
+            // The batches are returned as they were written.
+            Self::Ipc(path) => Box::new(IpcReader::try_new(path, None)?),
+        })
+    }
+}
+
+/// Format of a CSV file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvOptions {
+    /// Whether the first line holds the column names. Detected from the first
+    /// `infer_rows` rows if unknown.
+    pub has_header: Option<bool>,
+    pub delimiter: u8,
+    pub quote: u8,
+    /// Character which makes the next one literal within quotes, besides
+    /// doubling quotes.
+    pub escape: Option<u8>,
+    /// Field which is read as NULL. Without one, empty fields are NULL.
+    pub null_string: Option<String>,
+    /// Number of rows the types of the columns are inferred from.
+    pub infer_rows: usize,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            has_header: None,
+            delimiter: b',',
+            quote: b'"',
+            escape: None,
+            null_string: None,
+            infer_rows: 1000,
+        }
+    }
+}
+
+impl CsvOptions {
+    fn format(&self, has_header: bool) -> anyhow::Result<Format> {
+        let mut format = Format::default()
+            .with_header(has_header)
+            .with_delimiter(self.delimiter)
+            .with_quote(self.quote);
+        if let Some(escape) = self.escape {
+            format = format.with_escape(escape);
+        }
+        if let Some(null_string) = &self.null_string {
+            let null_regex = Regex::new(&format!("^{}$", regex::escape(null_string)))?;
+            format = format.with_null_regex(null_regex);
+        }
+        Ok(format)
+    }
+
+    /// Whether the file at `path` starts with a header, if not set. It does
+    /// unless the values of the first row have the types inferred from the
+    /// following rows. If all of those are strings, a header is assumed.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file can't be read or its rows can't be parsed.
+    pub fn header(&self, path: &str) -> anyhow::Result<bool> {
+        if let Some(has_header) = self.has_header {
+            return Ok(has_header);
+        }
+        let (rest, _) = self
+            .format(true)?
+            .infer_schema(File::open(path)?, Some(self.infer_rows))?;
+        let (first, _) = self
+            .format(false)?
+            .infer_schema(File::open(path)?, Some(1))?;
+        let mut typed = rest
+            .fields()
+            .iter()
+            .zip(first.fields())
+            .filter(|(rest, _)| !matches!(rest.data_type(), DataType::Utf8 | DataType::Null))
+            .peekable();
+        if typed.peek().is_none() {
+            return Ok(true);
+        }
+        Ok(typed.any(|(_, first)| first.data_type() == &DataType::Utf8))
+    }
+
+    /// Infers the schema of the file at `path` from its first `infer_rows`
+    /// rows. Columns are named `column_1`, `column_2`, ... without a header.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file can't be read or its rows can't be parsed.
+    pub fn infer_schema(&self, path: &str) -> anyhow::Result<SchemaRef> {
+        let format = self.format(self.header(path)?)?;
+        let (schema, _) = format.infer_schema(File::open(path)?, Some(self.infer_rows))?;
+        Ok(Arc::new(schema))
+    }
+
+    /// Returns a reader of the rows of the file at `path` with `schema`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file can't be opened or `options` are invalid.
+    pub fn reader(
+        &self,
+        path: &str,
+        schema: SchemaRef,
+        batch_size: usize,
+    ) -> anyhow::Result<Reader<File>> {
+        let format = self.format(self.header(path)?)?;
+        Ok(ReaderBuilder::new(schema)
+            .with_format(format)
+            .with_batch_size(batch_size)
+            .build(File::open(path)?)?)
+    }
+}
+
+/// Format of a newline-delimited JSON file, with an object per line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonOptions {
+    /// Schema the objects are read with instead of the inferred one. Keys
+    /// missing from it are ignored, and fields missing from an object are NULL.
+    pub schema: Option<SchemaRef>,
+    /// Number of objects the types of the fields are inferred from.
+    pub infer_rows: usize,
+}
+
+impl Default for JsonOptions {
+    fn default() -> Self {
+        Self {
+            schema: None,
+            infer_rows: 1000,
+        }
+    }
+}
+
+impl JsonOptions {
+    /// Returns the schema to read the file at `path` with, inferring it from
+    /// the first `infer_rows` objects if not set. Objects become structs and
+    /// arrays lists, and fields are sorted by name.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file can't be read or a line isn't a JSON object.
+    pub fn infer_schema(&self, path: &str) -> anyhow::Result<SchemaRef> {
+        if let Some(schema) = &self.schema {
+            return Ok(schema.clone());
+        }
+        let file = BufReader::new(File::open(path)?);
+        let (schema, _) = json::reader::infer_json_schema(file, Some(self.infer_rows))?;
+        Ok(Arc::new(schema))
+    }
+
+    /// Returns a reader of the objects of the file at `path` with `schema`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file can't be opened.
+    pub fn reader(
+        &self,
+        path: &str,
+        schema: SchemaRef,
+        batch_size: usize,
+    ) -> anyhow::Result<json::Reader<BufReader<File>>> {
+        Ok(json::ReaderBuilder::new(schema)
+            .with_batch_size(batch_size)
+            .build(BufReader::new(File::open(path)?))?)
+    }
+}
@@ -0,0 +1,310 @@
+use crate::execution::accumulator::Accumulator;
+use crate::execution::approx::HyperLogLogAccumulator;
+use arrow::array::{Array, ArrayRef, AsArray, RecordBatch};
+use arrow::compute;
+use arrow::datatypes::{DataType, Float64Type, Int64Type, Schema};
+use parquet::file::metadata::ParquetMetaData;
+use parquet::file::statistics::Statistics;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Size and value distribution of a table, used for cost-based optimization.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableStatistics {
+    pub row_count: usize,
+    /// Size of the table's data in bytes when decoded.
+    pub total_bytes: usize,
+    /// Statistics by column name, missing for unknown columns.
+    pub columns: HashMap<String, ColumnStatistics>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColumnStatistics {
+    /// Smallest non-NULL value.
+    pub min: Option<ScalarValue>,
+    /// Largest non-NULL value.
+    pub max: Option<ScalarValue>,
+    pub null_count: Option<usize>,
+    /// Estimated number of distinct non-NULL values.
+    pub distinct_count: Option<usize>,
+}
+
+/// Single value of a column. Integer, date and time types are widened to
+/// `Int64`, floating point types to `Float64`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScalarValue {
+    Boolean(bool),
+    Int64(i64),
+    Float64(f64),
+    Utf8(String),
+}
+
+impl ScalarValue {
+    /// Orders values of the same type, `None` for different types.
+    fn compare(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Self::Boolean(lhs), Self::Boolean(rhs)) => Some(lhs.cmp(rhs)),
+            (Self::Int64(lhs), Self::Int64(rhs)) => Some(lhs.cmp(rhs)),
+            (Self::Float64(lhs), Self::Float64(rhs)) => Some(lhs.total_cmp(rhs)),
+            (Self::Utf8(lhs), Self::Utf8(rhs)) => Some(lhs.cmp(rhs)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ScalarValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Boolean(value) => write!(f, "{value}"),
+            Self::Int64(value) => write!(f, "{value}"),
+            Self::Float64(value) => write!(f, "{value:?}"),
+            Self::Utf8(value) => write!(f, "'{value}'"),
+        }
+    }
+}
+
+impl TableStatistics {
+    /// Statistics from the footer of a parquet file, without reading its data.
+    /// Distinct counts are only known for files with a single row group, as
+    /// those of several row groups can't be combined.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the footer has negative or too large counts.
+    pub fn from_parquet_metadata(
+        metadata: &ParquetMetaData,
+        schema: &Schema,
+    ) -> anyhow::Result<Self> {
+        let row_groups = metadata.row_groups();
+        let mut statistics = Self::default();
+        for row_group in row_groups {
+            statistics.row_count += usize::try_from(row_group.num_rows())?;
+            statistics.total_bytes += usize::try_from(row_group.total_byte_size())?;
+        }
+
+        for field in schema.fields() {
+            let mut column = ColumnStatistics {
+                null_count: Some(0),
+                ..ColumnStatistics::default()
+            };
+            let mut has_min_max = true;
+            for row_group in row_groups {
+                let chunk = row_group
+                    .columns()
+                    .iter()
+                    .find(|chunk| chunk.column_path().parts() == [field.name().as_str()]);
+                let Some(stats) = chunk.and_then(|chunk| chunk.statistics()) else {
+                    column.null_count = None;
+                    has_min_max = false;
+                    continue;
+                };
+                let null_count = usize::try_from(stats.null_count())?;
+                column.null_count = column.null_count.map(|count| count + null_count);
+                column.distinct_count = stats.distinct_count().map(usize::try_from).transpose()?;
+                match parquet_min_max(stats, field.data_type()) {
+                    Some((min, max)) => column.update_min_max(min, max),
+                    // Row groups with only NULLs have no bounds but don't widen them.
+                    None if i64::try_from(stats.null_count()) == Ok(row_group.num_rows()) => {}
+                    None => has_min_max = false,
+                }
+            }
+            if !has_min_max {
+                column.min = None;
+                column.max = None;
+            }
+            if row_groups.len() != 1 {
+                column.distinct_count = None;
+            }
+            statistics.columns.insert(field.name().clone(), column);
+        }
+        Ok(statistics)
+    }
+
+    /// Exact statistics, apart from the estimated distinct counts, computed
+    /// from all rows of a table.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a batch can't be read or its values can't be summarized.
+    pub fn from_batches(
+        schema: &Schema,
+        batches: impl IntoIterator<Item = anyhow::Result<RecordBatch>>,
+    ) -> anyhow::Result<Self> {
+        let mut distinct = schema
+            .fields()
+            .iter()
+            .map(|field| HyperLogLogAccumulator::new(field.data_type()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let mut columns = vec![
+            ColumnStatistics {
+                null_count: Some(0),
+                ..ColumnStatistics::default()
+            };
+            schema.fields().len()
+        ];
+        let mut statistics = Self::default();
+
+        for batch in batches {
+            let batch = batch?;
+            statistics.row_count += batch.num_rows();
+            statistics.total_bytes += batch.get_array_memory_size();
+            for (i, array) in batch.columns().iter().enumerate() {
+                let column = &mut columns[i];
+                column.null_count = column.null_count.map(|count| count + array.null_count());
+                if let Some((min, max)) = array_min_max(array)? {
+                    column.update_min_max(min, max);
+                }
+                distinct[i].update_batch(std::slice::from_ref(array))?;
+            }
+        }
+
+        for ((field, mut column), distinct) in schema.fields().iter().zip(columns).zip(distinct) {
+            let count = usize::try_from(distinct.evaluate()?.as_primitive::<Int64Type>().value(0))?;
+            let non_null = statistics.row_count - column.null_count.unwrap_or(0);
+            column.distinct_count = Some(count.min(non_null));
+            statistics.columns.insert(field.name().clone(), column);
+        }
+        Ok(statistics)
+    }
+}
+
+impl TableStatistics {
+    /// Statistics of the rows of both tables. Columns only one of them has are
+    /// NULL in the rows of the other, and distinct counts can't be combined.
+    pub fn merge(&mut self, other: &Self) {
+        let names = self
+            .columns
+            .keys()
+            .chain(other.columns.keys())
+            .cloned()
+            .collect::<HashSet<_>>();
+        for name in names {
+            let mut column = match (self.columns.remove(&name), other.columns.get(&name)) {
+                (Some(mut column), Some(other)) => {
+                    column.null_count = column.null_count.zip(other.null_count).map(|(a, b)| a + b);
+                    match (&column.min, &other.min, &other.max) {
+                        (Some(current), Some(min), Some(max)) if current.compare(min).is_some() => {
+                            column.update_min_max(min.clone(), max.clone());
+                        }
+                        _ => {
+                            column.min = None;
+                            column.max = None;
+                        }
+                    }
+                    column
+                }
+                (Some(column), None) => ColumnStatistics {
+                    null_count: column.null_count.map(|count| count + other.row_count),
+                    ..column
+                },
+                (None, Some(other)) => ColumnStatistics {
+                    null_count: other.null_count.map(|count| count + self.row_count),
+                    ..other.clone()
+                },
+                (None, None) => continue,
+            };
+            column.distinct_count = None;
+            self.columns.insert(name, column);
+        }
+        self.row_count += other.row_count;
+        self.total_bytes += other.total_bytes;
+    }
+}
+
+impl ColumnStatistics {
+    fn update_min_max(&mut self, min: ScalarValue, max: ScalarValue) {
+        match &self.min {
+            Some(current) if min.compare(current) != Some(Ordering::Less) => {}
+            _ => self.min = Some(min),
+        }
+        match &self.max {
+            Some(current) if max.compare(current) != Some(Ordering::Greater) => {}
+            _ => self.max = Some(max),
+        }
+    }
+}
+
+/// Bounds of a column chunk, if they are known and comparable as values of
+/// `data_type`.
+fn parquet_min_max(stats: &Statistics, data_type: &DataType) -> Option<(ScalarValue, ScalarValue)> {
+    if !stats.has_min_max_set() {
+        return None;
+    }
+    let signed_integer = matches!(
+        data_type,
+        DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64 | DataType::Date32
+    );
+    Some(match stats {
+        Statistics::Boolean(s) if data_type == &DataType::Boolean => (
+            ScalarValue::Boolean(*s.min()),
+            ScalarValue::Boolean(*s.max()),
+        ),
+        Statistics::Int32(s) if signed_integer => (
+            ScalarValue::Int64(i64::from(*s.min())),
+            ScalarValue::Int64(i64::from(*s.max())),
+        ),
+        Statistics::Int64(s) if signed_integer => {
+            (ScalarValue::Int64(*s.min()), ScalarValue::Int64(*s.max()))
+        }
+        Statistics::Float(s) if data_type == &DataType::Float32 => (
+            ScalarValue::Float64(f64::from(*s.min())),
+            ScalarValue::Float64(f64::from(*s.max())),
+        ),
+        Statistics::Double(s) if data_type == &DataType::Float64 => (
+            ScalarValue::Float64(*s.min()),
+            ScalarValue::Float64(*s.max()),
+        ),
+        // Older writers ordered strings by signed bytes, which isn't the order of values.
+        Statistics::ByteArray(s)
+            if data_type == &DataType::Utf8 && !stats.is_min_max_deprecated() =>
+        {
+            (
+                ScalarValue::Utf8(s.min().as_utf8().ok()?.to_string()),
+                ScalarValue::Utf8(s.max().as_utf8().ok()?.to_string()),
+            )
+        }
+        _ => return None,
+    })
+}
+
+/// Smallest and largest non-NULL value of `array`, `None` if it has none or
+/// its type has no `ScalarValue`.
+fn array_min_max(array: &ArrayRef) -> anyhow::Result<Option<(ScalarValue, ScalarValue)>> {
+    let data_type = array.data_type();
+    let min_max = match data_type {
+        DataType::Boolean => {
+            let array = array.as_boolean();
+            compute::min_boolean(array)
+                .zip(compute::max_boolean(array))
+                .map(|(min, max)| (ScalarValue::Boolean(min), ScalarValue::Boolean(max)))
+        }
+        DataType::Utf8 | DataType::LargeUtf8 => {
+            let array = compute::cast(array, &DataType::Utf8)?;
+            let array = array.as_string::<i32>();
+            compute::min_string(array)
+                .zip(compute::max_string(array))
+                .map(|(min, max)| (ScalarValue::Utf8(min.into()), ScalarValue::Utf8(max.into())))
+        }
+        DataType::Float16 | DataType::Float32 | DataType::Float64 => {
+            let array = compute::cast(array, &DataType::Float64)?;
+            let array = array.as_primitive::<Float64Type>();
+            compute::min(array)
+                .zip(compute::max(array))
+                .map(|(min, max)| (ScalarValue::Float64(min), ScalarValue::Float64(max)))
+        }
+        // Values of UInt64 may not fit.
+        DataType::UInt64 => None,
+        _ if data_type.is_integer() || data_type.is_temporal() => {
+            let Ok(array) = compute::cast(array, &DataType::Int64) else {
+                return Ok(None);
+            };
+            let array = array.as_primitive::<Int64Type>();
+            compute::min(array)
+                .zip(compute::max(array))
+                .map(|(min, max)| (ScalarValue::Int64(min), ScalarValue::Int64(max)))
+        }
+        _ => None,
+    };
+    Ok(min_max)
+}
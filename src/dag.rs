@@ -2,7 +2,7 @@ use std::collections::HashSet;
 
 pub type NodeId = usize;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Dag<Node> {
     nodes: Vec<Node>,
     usages: Vec<HashSet<NodeId>>,
@@ -10,6 +10,7 @@ pub struct Dag<Node> {
 }
 
 impl<Node> Dag<Node> {
+    #[allow(clippy::missing_const_for_fn)]
     pub fn new() -> Self {
         Self {
             nodes: Vec::new(),
@@ -22,6 +23,10 @@ impl<Node> Dag<Node> {
         &self.nodes[id]
     }
 
+    pub fn get_node_mut(&mut self, id: NodeId) -> &mut Node {
+        &mut self.nodes[id]
+    }
+
     pub fn new_node(&mut self, node: Node) -> NodeId {
         self.nodes.push(node);
         self.usages.push(HashSet::new());
@@ -34,7 +39,30 @@ impl<Node> Dag<Node> {
         self.usages[input].insert(node);
     }
 
+    /// Replaces the inputs of `node`, keeping the usages of all nodes in sync.
+    pub fn replace_inputs(&mut self, node: NodeId, inputs: Vec<NodeId>) {
+        for input in std::mem::take(&mut self.inputs[node]) {
+            self.usages[input].remove(&node);
+        }
+        for &input in &inputs {
+            self.usages[input].insert(node);
+        }
+        self.inputs[node] = inputs;
+    }
+
+    pub const fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
     pub fn get_inputs(&self, node: NodeId) -> &Vec<NodeId> {
         &self.inputs[node]
     }
+
+    pub fn get_usages(&self, node: NodeId) -> &HashSet<NodeId> {
+        &self.usages[node]
+    }
 }
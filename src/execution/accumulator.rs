@@ -0,0 +1,485 @@
+use crate::execution::approx::{
+    HyperLogLogAccumulator, SpaceSavingAccumulator, TDigestAccumulator,
+};
+use crate::execution::operators::distinct::DistinctRows;
+use crate::logical_plan::expr::{AggregateFunc, AggregateFunction, Expr};
+use arrow::array::{
+    build_compare, new_null_array, Array, ArrayRef, ArrowNumericType, AsArray, Float64Array,
+    Int64Array, ListArray, PrimitiveArray, UInt32Array,
+};
+use arrow::buffer::OffsetBuffer;
+use arrow::compute;
+use arrow::datatypes::{ArrowNativeTypeOp, DataType, Field, Float64Type, Int64Type, Schema};
+use std::cmp::Ordering;
+use std::mem::size_of;
+use std::sync::Arc;
+
+/// Running state of one aggregate function for a single group.
+///
+/// Intermediate state is exposed through `state` so that partial results built
+/// independently (by parallel workers or from spilled data) can be combined with
+/// `merge_batch` before producing the final value.
+pub trait Accumulator: Send {
+    /// Updates the state with the argument columns of a batch of rows.
+    ///
+    /// # Errors
+    ///
+    /// Fails on arguments of an unexpected type, or if the state overflows.
+    fn update_batch(&mut self, values: &[ArrayRef]) -> anyhow::Result<()>;
+
+    /// Returns the intermediate state, one single-row array per state column.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the state can't be converted to arrays.
+    fn state(&self) -> anyhow::Result<Vec<ArrayRef>>;
+
+    /// Merges intermediate states, where each row of `states` is the output of
+    /// `state` of another accumulator.
+    ///
+    /// # Errors
+    ///
+    /// Fails on states of an unexpected type, or if the state overflows.
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> anyhow::Result<()>;
+
+    /// Returns the final aggregate value as a single-row array.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the value can't be computed from the state.
+    fn evaluate(&self) -> anyhow::Result<ArrayRef>;
+
+    /// Approximate number of bytes used by the accumulator, including `self`.
+    fn size(&self) -> usize;
+}
+
+pub fn create_accumulator(
+    aggregate: &AggregateFunction,
+    arg_types: &[DataType],
+) -> anyhow::Result<Box<dyn Accumulator>> {
+    let func = &aggregate.func;
+    let return_type = func.return_type(arg_types)?;
+    let accumulator: Box<dyn Accumulator> = match func {
+        AggregateFunc::Count => Box::new(CountAccumulator::new()),
+        AggregateFunc::Sum if return_type == DataType::Int64 => {
+            Box::new(SumAccumulator::<Int64Type>::new())
+        }
+        AggregateFunc::Sum => Box::new(SumAccumulator::<Float64Type>::new()),
+        AggregateFunc::Avg => Box::new(AvgAccumulator::new()),
+        AggregateFunc::Min => Box::new(MinMaxAccumulator::new(&return_type, Ordering::Less)),
+        AggregateFunc::Max => Box::new(MinMaxAccumulator::new(&return_type, Ordering::Greater)),
+        AggregateFunc::ApproxCountDistinct => Box::new(HyperLogLogAccumulator::new(&arg_types[0])?),
+        AggregateFunc::ApproxPercentileCont => {
+            Box::new(TDigestAccumulator::new(literal_arg(aggregate)?))
+        }
+        AggregateFunc::ApproxMedian => Box::new(TDigestAccumulator::new(0.5)),
+        AggregateFunc::ApproxTopK => {
+            let Some(Expr::IntegerLiteral(k)) = aggregate.args.last() else {
+                anyhow::bail!(
+                    "Last argument of {} must be a constant integer",
+                    aggregate.func
+                );
+            };
+            Box::new(SpaceSavingAccumulator::new(
+                &arg_types[0],
+                usize::try_from(k.value)?,
+            )?)
+        }
+        // Every argument is grouped by a plain GROUP BY.
+        AggregateFunc::Grouping => Box::new(GroupingAccumulator::new(0)),
+        AggregateFunc::Udaf(udaf) => udaf.create_accumulator(arg_types)?,
+    };
+    if aggregate.distinct {
+        return Ok(Box::new(DistinctAccumulator::new(accumulator, arg_types)?));
+    }
+    Ok(accumulator)
+}
+
+/// Constant parameter of an aggregate, e.g. the percentile of `approx_percentile_cont`.
+fn literal_arg(aggregate: &AggregateFunction) -> anyhow::Result<f64> {
+    match aggregate.args.last() {
+        Some(Expr::FloatLiteral(literal)) => Ok(literal.value),
+        Some(Expr::IntegerLiteral(literal)) => Ok(f64::from(literal.value)),
+        _ => anyhow::bail!("Last argument of {} must be a constant", aggregate.func),
+    }
+}
+
+struct CountAccumulator {
+    count: i64,
+}
+
+impl CountAccumulator {
+    const fn new() -> Self {
+        Self { count: 0 }
+    }
+}
+
+impl Accumulator for CountAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> anyhow::Result<()> {
+        let values = &values[0];
+        self.count += i64::try_from(values.len() - values.null_count())?;
+        Ok(())
+    }
+
+    fn state(&self) -> anyhow::Result<Vec<ArrayRef>> {
+        Ok(vec![self.evaluate()?])
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> anyhow::Result<()> {
+        let counts = states[0].as_primitive::<Int64Type>();
+        self.count += compute::sum(counts).unwrap_or_default();
+        Ok(())
+    }
+
+    fn evaluate(&self) -> anyhow::Result<ArrayRef> {
+        Ok(Arc::new(Int64Array::from(vec![self.count])))
+    }
+
+    fn size(&self) -> usize {
+        size_of::<Self>()
+    }
+}
+
+/// `GROUPING(...)`, which is the same for all rows of a group, so it is fixed
+/// when the group is created.
+pub struct GroupingAccumulator {
+    value: i64,
+}
+
+impl GroupingAccumulator {
+    pub const fn new(value: i64) -> Self {
+        Self { value }
+    }
+}
+
+impl Accumulator for GroupingAccumulator {
+    fn update_batch(&mut self, _values: &[ArrayRef]) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn state(&self) -> anyhow::Result<Vec<ArrayRef>> {
+        Ok(vec![self.evaluate()?])
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> anyhow::Result<()> {
+        if let Some(value) = states[0]
+            .as_primitive::<Int64Type>()
+            .iter()
+            .flatten()
+            .next()
+        {
+            self.value = value;
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self) -> anyhow::Result<ArrayRef> {
+        Ok(Arc::new(Int64Array::from(vec![self.value])))
+    }
+
+    fn size(&self) -> usize {
+        size_of::<Self>()
+    }
+}
+
+struct SumAccumulator<T: ArrowNumericType> {
+    sum: Option<T::Native>,
+}
+
+impl<T: ArrowNumericType> SumAccumulator<T> {
+    const fn new() -> Self {
+        Self { sum: None }
+    }
+}
+
+impl<T: ArrowNumericType> Accumulator for SumAccumulator<T> {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> anyhow::Result<()> {
+        let values = compute::cast(&values[0], &T::DATA_TYPE)?;
+        // Integer sums which overflow are an error rather than wrapping around.
+        if let Some(sum) = compute::sum_checked(values.as_primitive::<T>())? {
+            self.sum = Some(match self.sum {
+                Some(acc) => acc.add_checked(sum)?,
+                None => sum,
+            });
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> anyhow::Result<Vec<ArrayRef>> {
+        Ok(vec![self.evaluate()?])
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> anyhow::Result<()> {
+        self.update_batch(states)
+    }
+
+    fn evaluate(&self) -> anyhow::Result<ArrayRef> {
+        Ok(Arc::new(
+            std::iter::once(self.sum).collect::<PrimitiveArray<T>>(),
+        ))
+    }
+
+    fn size(&self) -> usize {
+        size_of::<Self>()
+    }
+}
+
+struct AvgAccumulator {
+    sum: f64,
+    count: i64,
+}
+
+impl AvgAccumulator {
+    const fn new() -> Self {
+        Self { sum: 0.0, count: 0 }
+    }
+}
+
+impl Accumulator for AvgAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> anyhow::Result<()> {
+        let values = compute::cast(&values[0], &DataType::Float64)?;
+        let values = values.as_primitive::<Float64Type>();
+        self.sum += compute::sum(values).unwrap_or_default();
+        self.count += i64::try_from(values.len() - values.null_count())?;
+        Ok(())
+    }
+
+    fn state(&self) -> anyhow::Result<Vec<ArrayRef>> {
+        Ok(vec![
+            Arc::new(Float64Array::from(vec![self.sum])),
+            Arc::new(Int64Array::from(vec![self.count])),
+        ])
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> anyhow::Result<()> {
+        self.sum += compute::sum(states[0].as_primitive::<Float64Type>()).unwrap_or_default();
+        self.count += compute::sum(states[1].as_primitive::<Int64Type>()).unwrap_or_default();
+        Ok(())
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn evaluate(&self) -> anyhow::Result<ArrayRef> {
+        let avg = (self.count > 0).then(|| self.sum / self.count as f64);
+        Ok(Arc::new(Float64Array::from(vec![avg])))
+    }
+
+    fn size(&self) -> usize {
+        size_of::<Self>()
+    }
+}
+
+/// Keeps the smallest (`Ordering::Less`) or the largest (`Ordering::Greater`)
+/// non-null value of any type with a natural order.
+struct MinMaxAccumulator {
+    value: ArrayRef,
+    keep: Ordering,
+}
+
+impl MinMaxAccumulator {
+    fn new(data_type: &DataType, keep: Ordering) -> Self {
+        Self {
+            value: new_null_array(data_type, 1),
+            keep,
+        }
+    }
+}
+
+impl Accumulator for MinMaxAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> anyhow::Result<()> {
+        let values = &values[0];
+        let compare = build_compare(values, values)?;
+        let mut best: Option<usize> = None;
+        for i in 0..values.len() {
+            if values.is_valid(i) && best.is_none_or(|b| compare(i, b) == self.keep) {
+                best = Some(i);
+            }
+        }
+        let Some(best) = best else {
+            return Ok(());
+        };
+
+        let candidate = values.slice(best, 1);
+        if self.value.is_null(0) || build_compare(&candidate, &self.value)?(0, 0) == self.keep {
+            // Copy the value so the accumulator doesn't keep the whole input batch alive.
+            self.value = compute::concat(&[&candidate])?;
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> anyhow::Result<Vec<ArrayRef>> {
+        Ok(vec![self.value.clone()])
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> anyhow::Result<()> {
+        self.update_batch(states)
+    }
+
+    fn evaluate(&self) -> anyhow::Result<ArrayRef> {
+        Ok(self.value.clone())
+    }
+
+    fn size(&self) -> usize {
+        size_of::<Self>() + self.value.get_array_memory_size()
+    }
+}
+
+/// Passes each distinct combination of argument values to the wrapped
+/// accumulator only once, for aggregates like `count(DISTINCT x)`. The state is
+/// the list of distinct values of each argument.
+struct DistinctAccumulator {
+    accumulator: Box<dyn Accumulator>,
+    seen: DistinctRows,
+    arg_types: Vec<DataType>,
+}
+
+impl DistinctAccumulator {
+    fn new(accumulator: Box<dyn Accumulator>, arg_types: &[DataType]) -> anyhow::Result<Self> {
+        let schema = Schema::new(
+            arg_types
+                .iter()
+                .enumerate()
+                .map(|(i, data_type)| Field::new(format!("arg{i}"), data_type.clone(), true))
+                .collect::<Vec<_>>(),
+        );
+        Ok(Self {
+            accumulator,
+            seen: DistinctRows::new(&schema)?,
+            arg_types: arg_types.to_vec(),
+        })
+    }
+}
+
+impl Accumulator for DistinctAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> anyhow::Result<()> {
+        let indices = self.seen.insert_all(values)?;
+        if indices.is_empty() {
+            return Ok(());
+        }
+        let indices = UInt32Array::from(indices);
+        let values = values
+            .iter()
+            .map(|values| compute::take(values, &indices, None))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.accumulator.update_batch(&values)
+    }
+
+    fn state(&self) -> anyhow::Result<Vec<ArrayRef>> {
+        let columns = self.seen.columns()?;
+        Ok(columns
+            .into_iter()
+            .zip(&self.arg_types)
+            .map(|(values, data_type)| {
+                let field = Arc::new(Field::new("item", data_type.clone(), true));
+                let offsets = OffsetBuffer::from_lengths([values.len()]);
+                Arc::new(ListArray::new(field, offsets, values, None)) as ArrayRef
+            })
+            .collect())
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> anyhow::Result<()> {
+        let lists = states
+            .iter()
+            .map(AsArray::as_list::<i32>)
+            .collect::<Vec<_>>();
+        for i in 0..states.first().map_or(0, Array::len) {
+            let values = lists.iter().map(|list| list.value(i)).collect::<Vec<_>>();
+            self.update_batch(&values)?;
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self) -> anyhow::Result<ArrayRef> {
+        self.accumulator.evaluate()
+    }
+
+    fn size(&self) -> usize {
+        size_of::<Self>() + self.accumulator.size() + self.seen.size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::StringArray;
+
+    #[test]
+    fn test_merge_partial_states() -> anyhow::Result<()> {
+        let funcs = [
+            (AggregateFunc::Avg, DataType::Int32),
+            (AggregateFunc::Max, DataType::Utf8),
+        ];
+        let inputs: [[ArrayRef; 2]; 2] = [
+            [
+                Arc::new(arrow::array::Int32Array::from(vec![Some(1), None, Some(2)])),
+                Arc::new(arrow::array::Int32Array::from(vec![6])),
+            ],
+            [
+                Arc::new(StringArray::from(vec![Some("b"), None])),
+                Arc::new(StringArray::from(vec!["c", "a"])),
+            ],
+        ];
+
+        let mut results = Vec::new();
+        for ((func, data_type), [first, second]) in funcs.iter().zip(inputs) {
+            let aggregate = AggregateFunction {
+                func: func.clone(),
+                args: Vec::new(),
+                distinct: false,
+                filter: None,
+            };
+            let mut partial = create_accumulator(&aggregate, std::slice::from_ref(data_type))?;
+            partial.update_batch(&[first])?;
+
+            let mut total = create_accumulator(&aggregate, std::slice::from_ref(data_type))?;
+            total.update_batch(&[second])?;
+            total.merge_batch(&partial.state()?)?;
+            results.push(total.evaluate()?);
+        }
+
+        assert!((results[0].as_primitive::<Float64Type>().value(0) - 3.0).abs() < f64::EPSILON);
+        assert_eq!(results[1].as_string::<i32>().value(0), "c");
+
+        // Values seen by both accumulators of a distinct count are counted once.
+        let aggregate = AggregateFunction {
+            func: AggregateFunc::Count,
+            args: Vec::new(),
+            distinct: true,
+            filter: None,
+        };
+        let mut partial = create_accumulator(&aggregate, &[DataType::Int64])?;
+        partial.update_batch(&[Arc::new(Int64Array::from(vec![
+            Some(1),
+            Some(1),
+            None,
+            Some(2),
+        ]))])?;
+        let mut total = create_accumulator(&aggregate, &[DataType::Int64])?;
+        total.update_batch(&[Arc::new(Int64Array::from(vec![2, 3]))])?;
+        total.merge_batch(&partial.state()?)?;
+        assert_eq!(total.evaluate()?.as_primitive::<Int64Type>().value(0), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sum_overflow() -> anyhow::Result<()> {
+        let aggregate = AggregateFunction {
+            func: AggregateFunc::Sum,
+            args: Vec::new(),
+            distinct: false,
+            filter: None,
+        };
+        let mut sum = create_accumulator(&aggregate, &[DataType::Int64])?;
+        assert!(sum
+            .update_batch(&[Arc::new(Int64Array::from(vec![i64::MAX, 1]))])
+            .is_err());
+
+        let mut sum = create_accumulator(&aggregate, &[DataType::Int64])?;
+        sum.update_batch(&[Arc::new(Int64Array::from(vec![i64::MAX]))])?;
+        assert!(sum
+            .update_batch(&[Arc::new(Int64Array::from(vec![1]))])
+            .is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,518 @@
+use crate::execution::accumulator::Accumulator;
+use arrow::array::{Array, ArrayRef, AsArray, BinaryArray, Float64Array, Int64Array, ListArray};
+use arrow::buffer::OffsetBuffer;
+use arrow::compute;
+use arrow::datatypes::{DataType, Field, Float64Type, Int64Type};
+use arrow::row::{RowConverter, SortField};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::hash::Hasher;
+use std::mem::size_of;
+use std::sync::Arc;
+use twox_hash::XxHash64;
+
+const HLL_PRECISION: u32 = 14;
+const HLL_REGISTERS: usize = 1 << HLL_PRECISION;
+/// Seed of the hash of the values, fixed so that sketches stored or built
+/// elsewhere can be merged.
+const HLL_SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// `approx_count_distinct`: `HyperLogLog` sketch with 2^14 registers, which gives
+/// a standard error of about 0.8%.
+pub struct HyperLogLogAccumulator {
+    converter: RowConverter,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLogAccumulator {
+    pub fn new(data_type: &DataType) -> anyhow::Result<Self> {
+        Ok(Self {
+            converter: RowConverter::new(vec![SortField::new(data_type.clone())])?,
+            registers: vec![0; HLL_REGISTERS],
+        })
+    }
+
+    fn add(&mut self, value: &[u8]) {
+        let mut hasher = XxHash64::with_seed(HLL_SEED);
+        hasher.write(value);
+        let hash = hasher.finish();
+
+        let index = (hash >> (64 - HLL_PRECISION)) as usize;
+        // The sentinel bit bounds the rank when all remaining bits are zero.
+        let rank = ((hash << HLL_PRECISION) | (1 << (HLL_PRECISION - 1))).leading_zeros() + 1;
+        let rank = u8::try_from(rank).expect("a rank is at most 64");
+        self.registers[index] = self.registers[index].max(rank);
+    }
+
+    fn estimate(&self) -> f64 {
+        let m = f64::from(1u32 << HLL_PRECISION);
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|rank| 2f64.powi(-i32::from(*rank)))
+            .sum();
+        let estimate = alpha * m * m / sum;
+
+        // Small cardinalities are estimated more precisely with linear counting.
+        let zeros: u32 = self
+            .registers
+            .iter()
+            .map(|rank| u32::from(*rank == 0))
+            .sum();
+        if estimate <= 2.5 * m && zeros > 0 {
+            m * (m / f64::from(zeros)).ln()
+        } else {
+            estimate
+        }
+    }
+}
+
+impl Accumulator for HyperLogLogAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> anyhow::Result<()> {
+        let values = &values[0];
+        let rows = self
+            .converter
+            .convert_columns(std::slice::from_ref(values))?;
+        for i in 0..values.len() {
+            if values.is_valid(i) {
+                self.add(rows.row(i).as_ref());
+            }
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> anyhow::Result<Vec<ArrayRef>> {
+        Ok(vec![Arc::new(BinaryArray::from(vec![self
+            .registers
+            .as_slice()]))])
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> anyhow::Result<()> {
+        for registers in states[0].as_binary::<i32>().iter().flatten() {
+            anyhow::ensure!(
+                registers.len() == HLL_REGISTERS,
+                "Invalid HyperLogLog state of {} registers",
+                registers.len()
+            );
+            for (own, other) in self.registers.iter_mut().zip(registers) {
+                *own = (*own).max(*other);
+            }
+        }
+        Ok(())
+    }
+
+    // The estimate is at most a few times the number of rows.
+    #[allow(clippy::cast_possible_truncation)]
+    fn evaluate(&self) -> anyhow::Result<ArrayRef> {
+        Ok(Arc::new(Int64Array::from(vec![
+            self.estimate().round() as i64
+        ])))
+    }
+
+    fn size(&self) -> usize {
+        size_of::<Self>() + self.registers.capacity() + self.converter.size()
+    }
+}
+
+const TDIGEST_COMPRESSION: f64 = 100.0;
+const TDIGEST_BUFFER_SIZE: usize = 1000;
+
+#[derive(Clone, Copy)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+/// `approx_percentile_cont` and `approx_median`: merging t-digest which keeps
+/// tails of the distribution in small centroids, so extreme percentiles stay precise.
+pub struct TDigestAccumulator {
+    percentile: f64,
+    centroids: Vec<Centroid>,
+    unmerged: Vec<Centroid>,
+    min: f64,
+    max: f64,
+}
+
+impl TDigestAccumulator {
+    pub const fn new(percentile: f64) -> Self {
+        Self {
+            percentile,
+            centroids: Vec::new(),
+            unmerged: Vec::new(),
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    fn add(&mut self, centroid: Centroid) {
+        self.min = self.min.min(centroid.mean);
+        self.max = self.max.max(centroid.mean);
+        self.unmerged.push(centroid);
+        if self.unmerged.len() >= TDIGEST_BUFFER_SIZE {
+            self.centroids = self.merged();
+            self.unmerged.clear();
+        }
+    }
+
+    /// Returns centroids with all buffered values merged in, using the k1 scale
+    /// function `k(q) = δ / 2π * asin(2q - 1)` to bound centroid sizes.
+    fn merged(&self) -> Vec<Centroid> {
+        let mut all: Vec<Centroid> = self
+            .centroids
+            .iter()
+            .chain(&self.unmerged)
+            .copied()
+            .collect();
+        all.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+        let total: f64 = all.iter().map(|c| c.weight).sum();
+
+        let k = |q: f64| TDIGEST_COMPRESSION / (2.0 * PI) * 2.0f64.mul_add(q, -1.0).asin();
+        let k_inv = |k: f64| ((2.0 * PI * k / TDIGEST_COMPRESSION).sin() + 1.0) * 0.5;
+
+        let mut result = Vec::new();
+        let mut all = all.into_iter();
+        let Some(mut current) = all.next() else {
+            return result;
+        };
+        let mut weight_so_far = 0.0;
+        let mut q_limit = k_inv(k(0.0) + 1.0);
+        for next in all {
+            let q = (weight_so_far + current.weight + next.weight) / total;
+            if q <= q_limit {
+                let weight = current.weight + next.weight;
+                current.mean += (next.mean - current.mean) * next.weight / weight;
+                current.weight = weight;
+            } else {
+                weight_so_far += current.weight;
+                q_limit = k_inv(k(weight_so_far / total) + 1.0);
+                result.push(current);
+                current = next;
+            }
+        }
+        result.push(current);
+        result
+    }
+
+    fn quantile(&self, q: f64) -> Option<f64> {
+        let centroids = self.merged();
+        if centroids.len() <= 1 {
+            return centroids.first().map(|c| c.mean);
+        }
+
+        let total: f64 = centroids.iter().map(|c| c.weight).sum();
+        let target = q * total;
+
+        let mut cumulative = 0.0;
+        for (i, centroid) in centroids.iter().enumerate() {
+            let center = cumulative + centroid.weight / 2.0;
+            if target < center {
+                let value = if i == 0 {
+                    self.min + (centroid.mean - self.min) * target / center
+                } else {
+                    let prev = centroids[i - 1];
+                    let prev_center = cumulative - prev.weight / 2.0;
+                    let t = (target - prev_center) / (center - prev_center);
+                    prev.mean + t * (centroid.mean - prev.mean)
+                };
+                return Some(value.clamp(self.min, self.max));
+            }
+            cumulative += centroid.weight;
+        }
+
+        let last = centroids[centroids.len() - 1];
+        let last_center = total - last.weight / 2.0;
+        let t = (target - last_center) / (total - last_center);
+        Some((last.mean + t * (self.max - last.mean)).clamp(self.min, self.max))
+    }
+}
+
+impl Accumulator for TDigestAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> anyhow::Result<()> {
+        let values = compute::cast(&values[0], &DataType::Float64)?;
+        for value in values.as_primitive::<Float64Type>().iter().flatten() {
+            self.add(Centroid {
+                mean: value,
+                weight: 1.0,
+            });
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> anyhow::Result<Vec<ArrayRef>> {
+        let centroids = self.merged();
+        let means = Float64Array::from_iter_values(centroids.iter().map(|c| c.mean));
+        let weights = Float64Array::from_iter_values(centroids.iter().map(|c| c.weight));
+        Ok(vec![
+            single_list(Arc::new(means))?,
+            single_list(Arc::new(weights))?,
+        ])
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> anyhow::Result<()> {
+        let means = states[0].as_list::<i32>();
+        let weights = states[1].as_list::<i32>();
+        for (means, weights) in means.iter().zip(weights.iter()) {
+            let (Some(means), Some(weights)) = (means, weights) else {
+                continue;
+            };
+            let means = means.as_primitive::<Float64Type>();
+            let weights = weights.as_primitive::<Float64Type>();
+            for (mean, weight) in means.values().iter().zip(weights.values()) {
+                self.add(Centroid {
+                    mean: *mean,
+                    weight: *weight,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self) -> anyhow::Result<ArrayRef> {
+        Ok(Arc::new(Float64Array::from(vec![
+            self.quantile(self.percentile)
+        ])))
+    }
+
+    fn size(&self) -> usize {
+        size_of::<Self>()
+            + (self.centroids.capacity() + self.unmerged.capacity()) * size_of::<Centroid>()
+    }
+}
+
+/// `approx_top_k`: space-saving summary which tracks a bounded number of
+/// candidate values and evicts the least frequent one when a new value arrives.
+pub struct SpaceSavingAccumulator {
+    k: usize,
+    capacity: usize,
+    data_type: DataType,
+    converter: RowConverter,
+    counters: HashMap<Box<[u8]>, i64>,
+}
+
+impl SpaceSavingAccumulator {
+    pub fn new(data_type: &DataType, k: usize) -> anyhow::Result<Self> {
+        Ok(Self {
+            k,
+            capacity: (k * 10).max(100),
+            data_type: data_type.clone(),
+            converter: RowConverter::new(vec![SortField::new(data_type.clone())])?,
+            counters: HashMap::new(),
+        })
+    }
+
+    fn add(&mut self, value: &[u8], count: i64) {
+        if let Some(counter) = self.counters.get_mut(value) {
+            *counter += count;
+        } else if self.counters.len() < self.capacity {
+            self.counters.insert(value.into(), count);
+        } else {
+            let (evicted, min) = self
+                .counters
+                .iter()
+                .min_by_key(|(_, count)| **count)
+                .map(|(value, count)| (value.clone(), *count))
+                .unwrap();
+            self.counters.remove(&evicted);
+            self.counters.insert(value.into(), min + count);
+        }
+    }
+
+    /// Smallest count a value absent from the summary could have had.
+    fn min_count(&self) -> i64 {
+        if self.counters.len() < self.capacity {
+            0
+        } else {
+            self.counters.values().copied().min().unwrap_or_default()
+        }
+    }
+
+    fn sorted(&self) -> Vec<(&[u8], i64)> {
+        let mut counters: Vec<_> = self
+            .counters
+            .iter()
+            .map(|(value, count)| (value.as_ref(), *count))
+            .collect();
+        counters.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        counters
+    }
+
+    fn decode(&self, values: &[(&[u8], i64)]) -> anyhow::Result<ArrayRef> {
+        let parser = self.converter.parser();
+        let mut columns = self
+            .converter
+            .convert_rows(values.iter().map(|(value, _)| parser.parse(value)))?;
+        Ok(columns.remove(0))
+    }
+}
+
+impl Accumulator for SpaceSavingAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> anyhow::Result<()> {
+        let values = &values[0];
+        let rows = self
+            .converter
+            .convert_columns(std::slice::from_ref(values))?;
+        for i in 0..values.len() {
+            if values.is_valid(i) {
+                self.add(rows.row(i).as_ref(), 1);
+            }
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> anyhow::Result<Vec<ArrayRef>> {
+        let counters = self.sorted();
+        let values = BinaryArray::from_iter_values(counters.iter().map(|(value, _)| value));
+        let counts = Int64Array::from_iter_values(counters.iter().map(|(_, count)| *count));
+        Ok(vec![
+            single_list(Arc::new(values))?,
+            single_list(Arc::new(counts))?,
+        ])
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> anyhow::Result<()> {
+        let values = states[0].as_list::<i32>();
+        let counts = states[1].as_list::<i32>();
+        for (values, counts) in values.iter().zip(counts.iter()) {
+            let (Some(values), Some(counts)) = (values, counts) else {
+                continue;
+            };
+            let values = values.as_binary::<i32>();
+            let counts = counts.as_primitive::<Int64Type>();
+
+            // Values missing on one side might still have been seen up to its
+            // minimum count, so they are credited with it (parallel space-saving).
+            let own_min = self.min_count();
+            let other_min = if counts.len() < self.capacity {
+                0
+            } else {
+                counts.values().iter().copied().min().unwrap_or_default()
+            };
+
+            let mut merged: HashMap<Box<[u8]>, i64> = HashMap::new();
+            for (value, count) in values.iter().flatten().zip(counts.values()) {
+                let own = self.counters.get(value).copied().unwrap_or(own_min);
+                merged.insert(value.into(), own + count);
+            }
+            for (value, count) in &self.counters {
+                merged.entry(value.clone()).or_insert(count + other_min);
+            }
+
+            let mut merged: Vec<_> = merged.into_iter().collect();
+            merged.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            merged.truncate(self.capacity);
+            self.counters = merged.into_iter().collect();
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self) -> anyhow::Result<ArrayRef> {
+        let mut counters = self.sorted();
+        counters.truncate(self.k);
+        let values = self.decode(&counters)?;
+        let field = Arc::new(Field::new_list_field(self.data_type.clone(), true));
+        Ok(Arc::new(ListArray::try_new(
+            field,
+            OffsetBuffer::from_lengths([values.len()]),
+            values,
+            None,
+        )?))
+    }
+
+    fn size(&self) -> usize {
+        size_of::<Self>()
+            + self.converter.size()
+            + self
+                .counters
+                .keys()
+                .map(|value| value.len() + size_of::<(Box<[u8]>, i64)>())
+                .sum::<usize>()
+    }
+}
+
+fn single_list(values: ArrayRef) -> anyhow::Result<ArrayRef> {
+    let field = Arc::new(Field::new_list_field(values.data_type().clone(), true));
+    Ok(Arc::new(ListArray::try_new(
+        field,
+        OffsetBuffer::from_lengths([values.len()]),
+        values,
+        None,
+    )?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int32Array, StringArray};
+
+    fn update_and_merge(
+        mut partial: Box<dyn Accumulator>,
+        mut total: Box<dyn Accumulator>,
+        first: &ArrayRef,
+        second: &ArrayRef,
+    ) -> anyhow::Result<ArrayRef> {
+        partial.update_batch(std::slice::from_ref(first))?;
+        total.update_batch(std::slice::from_ref(second))?;
+        total.merge_batch(&partial.state()?)?;
+        total.evaluate()
+    }
+
+    #[test]
+    fn test_approx_aggregates_merge() -> anyhow::Result<()> {
+        let first: ArrayRef = Arc::new(Int32Array::from_iter_values(0..5000));
+        let second: ArrayRef = Arc::new(Int32Array::from_iter_values(2500..10000));
+
+        let distinct = update_and_merge(
+            Box::new(HyperLogLogAccumulator::new(&DataType::Int32)?),
+            Box::new(HyperLogLogAccumulator::new(&DataType::Int32)?),
+            &first,
+            &second,
+        )?;
+        let distinct = distinct.as_primitive::<Int64Type>().value(0);
+        assert!((distinct - 10000).abs() < 200, "{distinct}");
+
+        // Values 2500..5000 are present in both halves, so the merged input has
+        // 12500 values and its median is 4375.
+        let median = update_and_merge(
+            Box::new(TDigestAccumulator::new(0.5)),
+            Box::new(TDigestAccumulator::new(0.5)),
+            &first,
+            &second,
+        )?;
+        let median = median.as_primitive::<Float64Type>().value(0);
+        assert!((median - 4375.0).abs() < 50.0, "{median}");
+
+        let p99 = update_and_merge(
+            Box::new(TDigestAccumulator::new(0.99)),
+            Box::new(TDigestAccumulator::new(0.99)),
+            &first,
+            &second,
+        )?;
+        let p99 = p99.as_primitive::<Float64Type>().value(0);
+        assert!((p99 - 9875.0).abs() < 10.0, "{p99}");
+
+        let skewed = |offset: usize| -> ArrayRef {
+            let mut values: Vec<String> = Vec::new();
+            for i in 0..250 {
+                values.push(format!("unique-{}", offset + i));
+                match i % 10 {
+                    0..=1 => values.push("a".to_string()),
+                    2 => values.push("b".to_string()),
+                    _ => {}
+                }
+            }
+            Arc::new(StringArray::from(values))
+        };
+        let top = update_and_merge(
+            Box::new(SpaceSavingAccumulator::new(&DataType::Utf8, 2)?),
+            Box::new(SpaceSavingAccumulator::new(&DataType::Utf8, 2)?),
+            &skewed(0),
+            &skewed(250),
+        )?;
+        let top = top.as_list::<i32>().value(0);
+        let top = top.as_string::<i32>();
+        assert_eq!(top.iter().flatten().collect::<Vec<_>>(), vec!["a", "b"]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,214 @@
+use crate::logical_plan::dag_builder::{decimal_type, is_decimal};
+use crate::logical_plan::expr::{arithmetic_type, Binary, BinaryOp, Expr};
+use arrow::array::{ArrayRef, AsArray, Float64Array, Int32Array, RecordBatch, StringArray};
+use arrow::compute::kernels::{boolean, cmp, numeric};
+use arrow::compute::{cast, is_null};
+use arrow::datatypes::DataType;
+use std::sync::Arc;
+
+/// Evaluates a scalar expression over every row of `batch`.
+pub fn evaluate(expr: &Expr, batch: &RecordBatch) -> anyhow::Result<ArrayRef> {
+    match expr {
+        Expr::Ident(ident) => batch
+            .column_by_name(&ident.name)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Column with name {} not found", ident.name)),
+        Expr::IntegerLiteral(literal) => Ok(Arc::new(Int32Array::from(vec![
+            literal.value;
+            batch.num_rows()
+        ]))),
+        Expr::FloatLiteral(literal) => Ok(Arc::new(Float64Array::from(vec![
+            literal.value;
+            batch.num_rows()
+        ]))),
+        Expr::StringLiteral(literal) => Ok(Arc::new(StringArray::from(vec![
+            literal.value.as_str();
+            batch.num_rows()
+        ]))),
+        Expr::Binary(binary) => evaluate_binary(binary, batch),
+        Expr::Alias(alias) => evaluate(&alias.expr, batch),
+        Expr::Not(expr) => {
+            let value = evaluate(expr, batch)?;
+            let value = value
+                .as_boolean_opt()
+                .ok_or_else(|| anyhow::anyhow!("Operand {expr} of NOT must be boolean"))?;
+            Ok(Arc::new(boolean::not(value)?))
+        }
+        Expr::IsNull(expr) => Ok(Arc::new(is_null(&evaluate(expr, batch)?)?)),
+        Expr::AggregateFunction(_) | Expr::WindowFunction(_) | Expr::Wildcard => {
+            anyhow::bail!("Expression {expr} can't be evaluated per row")
+        }
+    }
+}
+
+fn evaluate_binary(binary: &Binary, batch: &RecordBatch) -> anyhow::Result<ArrayRef> {
+    let lhs = evaluate(&binary.lhs, batch)?;
+    let rhs = evaluate(&binary.rhs, batch)?;
+
+    if binary.op.is_logical() {
+        let lhs = lhs.as_boolean_opt().ok_or_else(|| {
+            anyhow::anyhow!("Operand {} of {} must be boolean", binary.lhs, binary.op)
+        })?;
+        let rhs = rhs.as_boolean_opt().ok_or_else(|| {
+            anyhow::anyhow!("Operand {} of {} must be boolean", binary.rhs, binary.op)
+        })?;
+        let result = match binary.op {
+            BinaryOp::And => boolean::and_kleene(lhs, rhs)?,
+            _ => boolean::or_kleene(lhs, rhs)?,
+        };
+        return Ok(Arc::new(result));
+    }
+
+    let (lhs, rhs) = coerce(binary.op, &lhs, &rhs)?;
+    let result: ArrayRef = match binary.op {
+        BinaryOp::Lt => Arc::new(cmp::lt(&lhs, &rhs)?),
+        BinaryOp::Gt => Arc::new(cmp::gt(&lhs, &rhs)?),
+        BinaryOp::LtEq => Arc::new(cmp::lt_eq(&lhs, &rhs)?),
+        BinaryOp::GtEq => Arc::new(cmp::gt_eq(&lhs, &rhs)?),
+        BinaryOp::Eq => Arc::new(cmp::eq(&lhs, &rhs)?),
+        BinaryOp::NotEq => Arc::new(cmp::neq(&lhs, &rhs)?),
+        BinaryOp::Plus => numeric::add(&lhs, &rhs)?,
+        BinaryOp::Minus => numeric::sub(&lhs, &rhs)?,
+        BinaryOp::Multiply => numeric::mul(&lhs, &rhs)?,
+        BinaryOp::Divide => numeric::div(&lhs, &rhs)?,
+        BinaryOp::And | BinaryOp::Or => unreachable!(),
+    };
+    Ok(result)
+}
+
+/// Casts both operands of `op` to a common type: arithmetic operands to the
+/// type of the result, the same way `Expr::to_field` does, and the operands of
+/// comparisons to their `common_type`.
+fn coerce(op: BinaryOp, lhs: &ArrayRef, rhs: &ArrayRef) -> anyhow::Result<(ArrayRef, ArrayRef)> {
+    let (l, r) = (lhs.data_type(), rhs.data_type());
+    if l == r {
+        return Ok((lhs.clone(), rhs.clone()));
+    }
+    let target = if op.is_arithmetic() {
+        arithmetic_type(l, r)?
+    } else {
+        common_type(l, r)?
+    };
+    Ok((cast(lhs, &target)?, cast(rhs, &target)?))
+}
+
+/// Type both operands of a comparison are cast to, which holds all values of
+/// both, so that no value is compared as another one. `UInt64` and signed
+/// integers are compared as `Decimal128(20, 0)`, and decimals with integers as
+/// a decimal wide enough for both. Floats, and decimals which need more than
+/// 76 digits, are compared as `Float64`.
+pub fn common_type(l: &DataType, r: &DataType) -> anyhow::Result<DataType> {
+    let unsigned_with_signed =
+        |l: &DataType, r: &DataType| l == &DataType::UInt64 && r.is_signed_integer();
+    if l == r {
+        Ok(l.clone())
+    } else if l == &DataType::Null {
+        Ok(r.clone())
+    } else if r == &DataType::Null {
+        Ok(l.clone())
+    } else if !l.is_numeric() || !r.is_numeric() {
+        anyhow::bail!("Can't compare values of types {l} and {r}")
+    } else if l.is_floating() || r.is_floating() {
+        Ok(DataType::Float64)
+    } else if is_decimal(l)
+        || is_decimal(r)
+        || unsigned_with_signed(l, r)
+        || unsigned_with_signed(r, l)
+    {
+        Ok(decimal_type(l, r))
+    } else if l.is_unsigned_integer() && r.is_unsigned_integer() {
+        Ok(DataType::UInt64)
+    } else {
+        Ok(DataType::Int64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logical_plan::expr::{Ident, IntegerLiteral};
+    use arrow::array::Array;
+    use arrow::datatypes::{Field, Schema};
+
+    #[test]
+    fn test_evaluate_mixed_arithmetic() -> anyhow::Result<()> {
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::Int32, false),
+            Field::new("b", DataType::Float64, true),
+        ]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2, 3])),
+                Arc::new(Float64Array::from(vec![Some(0.5), None, Some(1.5)])),
+            ],
+        )?;
+
+        let expr = Expr::Binary(Binary {
+            lhs: Box::new(Expr::Binary(Binary {
+                lhs: Box::new(Expr::Ident(Ident {
+                    name: "a".to_string(),
+                })),
+                op: BinaryOp::Plus,
+                rhs: Box::new(Expr::Ident(Ident {
+                    name: "b".to_string(),
+                })),
+            })),
+            op: BinaryOp::Gt,
+            rhs: Box::new(Expr::IntegerLiteral(IntegerLiteral { value: 2 })),
+        });
+
+        let result = evaluate(&expr, &batch)?;
+        let result = result.as_boolean();
+        assert!(!result.value(0));
+        assert!(result.is_null(1));
+        assert!(result.value(2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_common_type() -> anyhow::Result<()> {
+        assert_eq!(
+            common_type(&DataType::UInt64, &DataType::Int8)?,
+            DataType::Decimal128(20, 0)
+        );
+        assert_eq!(
+            common_type(&DataType::UInt32, &DataType::UInt64)?,
+            DataType::UInt64
+        );
+        assert_eq!(
+            common_type(&DataType::Decimal128(10, 2), &DataType::Int32)?,
+            DataType::Decimal128(12, 2)
+        );
+        assert!(common_type(&DataType::Utf8, &DataType::Int32).is_err());
+
+        // The largest UInt64 isn't taken for any Int64.
+        let batch = RecordBatch::try_from_iter([
+            (
+                "a",
+                Arc::new(arrow::array::UInt64Array::from(vec![u64::MAX, 1])) as ArrayRef,
+            ),
+            (
+                "b",
+                Arc::new(arrow::array::Int64Array::from(vec![-1, 1])) as _,
+            ),
+        ])?;
+        let expr = Expr::Binary(Binary {
+            lhs: Box::new(Expr::Ident(Ident {
+                name: "a".to_string(),
+            })),
+            op: BinaryOp::Gt,
+            rhs: Box::new(Expr::Ident(Ident {
+                name: "b".to_string(),
+            })),
+        });
+        let result = evaluate(&expr, &batch)?;
+        assert_eq!(
+            result.as_boolean().iter().collect::<Vec<_>>(),
+            [Some(true), Some(false)]
+        );
+
+        Ok(())
+    }
+}
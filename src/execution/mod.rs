@@ -1 +1,6 @@
+pub mod accumulator;
+pub mod approx;
+mod evaluator;
 mod operators;
+mod planner;
+mod spill;
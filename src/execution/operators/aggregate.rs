@@ -0,0 +1,805 @@
+use crate::execution::accumulator::{create_accumulator, Accumulator, GroupingAccumulator};
+use crate::execution::evaluator::evaluate;
+use crate::execution::operators::select::take_batch;
+use crate::execution::operators::{Operator, OperatorState};
+use crate::execution::spill::{SpillFile, SpillWriter};
+use crate::logical_plan::expr::{AggregateFunc, AggregateFunction, Expr};
+use arrow::array::{
+    new_empty_array, new_null_array, Array, ArrayRef, AsArray, BooleanArray, RecordBatch,
+    UInt32Array,
+};
+use arrow::compute;
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef, UInt32Type};
+use arrow::row::{OwnedRow, RowConverter, SortField};
+use std::collections::HashMap;
+use std::hash::Hasher;
+use std::ops::Range;
+use std::sync::Arc;
+use twox_hash::XxHash64;
+
+/// Number of partitions the partial states of the groups are written to once
+/// a `HashAggregate` exceeds its memory limit.
+const PARTITIONS: usize = 16;
+
+/// Groups every input row by each grouping set, so that all sets are computed
+/// in a single pass. Keys which aren't part of a set are NULL in its groups.
+///
+/// With a memory limit, the partial states of all groups are written to
+/// partitions on disk by the hash of their key whenever the groups in memory
+/// grow beyond the limit. The partitions are then merged one by one at the end.
+pub struct HashAggregate<'i> {
+    successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    group_expr: Vec<Expr>,
+    grouping_sets: Vec<GroupingSet>,
+    aggr_expr: Vec<AggregateFunction>,
+    arg_types: Vec<Vec<DataType>>,
+    schema: SchemaRef,
+    converter: RowConverter,
+    group_keys: Vec<OwnedRow>,
+    accumulators: Vec<Vec<Box<dyn Accumulator>>>,
+    memory_limit: Option<usize>,
+    /// Approximate number of bytes used by the groups in memory.
+    bytes: usize,
+    partitions: Option<Vec<SpillWriter>>,
+    /// Number of state columns of each aggregate in the partitions.
+    state_widths: Vec<usize>,
+}
+
+struct GroupingSet {
+    /// Whether each key of the aggregate is part of the set.
+    grouped: Vec<bool>,
+    groups: HashMap<Box<[u8]>, usize>,
+}
+
+impl<'i> HashAggregate<'i> {
+    pub(crate) fn new(
+        group_expr: Vec<Expr>,
+        grouping_sets: &[Vec<usize>],
+        aggr_expr: &[Expr],
+        input_schema: &Schema,
+        schema: SchemaRef,
+        successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    ) -> anyhow::Result<Self> {
+        let sort_fields = schema.fields()[..group_expr.len()]
+            .iter()
+            .map(|field| SortField::new(field.data_type().clone()))
+            .collect();
+        let grouping_sets = grouping_sets
+            .iter()
+            .map(|set| GroupingSet {
+                grouped: (0..group_expr.len()).map(|i| set.contains(&i)).collect(),
+                groups: HashMap::new(),
+            })
+            .collect();
+        let aggr_expr = aggr_expr
+            .iter()
+            .map(|expr| match expr {
+                Expr::AggregateFunction(aggregate) => Ok(aggregate.clone()),
+                _ => Err(anyhow::anyhow!("Expression {expr} is not an aggregate")),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let mut arg_types = Vec::with_capacity(aggr_expr.len());
+        for aggregate in &aggr_expr {
+            let mut types = Vec::with_capacity(aggregate.args.len());
+            for arg in &aggregate.args {
+                types.push(match arg {
+                    Expr::Wildcard => DataType::Boolean,
+                    _ => arg.to_field(input_schema)?.data_type().clone(),
+                });
+            }
+            arg_types.push(types);
+        }
+        Ok(Self {
+            successor,
+            group_expr,
+            grouping_sets,
+            aggr_expr,
+            arg_types,
+            schema,
+            converter: RowConverter::new(sort_fields)?,
+            group_keys: Vec::new(),
+            accumulators: Vec::new(),
+            memory_limit: None,
+            bytes: 0,
+            partitions: None,
+            state_widths: Vec::new(),
+        })
+    }
+
+    /// Limits the memory used for the groups to roughly `bytes`.
+    pub(crate) const fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    fn evaluate_args(&self, batch: &RecordBatch) -> anyhow::Result<Vec<Vec<ArrayRef>>> {
+        let mut args = Vec::with_capacity(self.aggr_expr.len());
+        for aggregate in &self.aggr_expr {
+            let mut values = Vec::with_capacity(aggregate.args.len());
+            for arg in &aggregate.args {
+                // count(*) counts every row, so it gets a column without nulls.
+                let value: ArrayRef = match arg {
+                    Expr::Wildcard => Arc::new(BooleanArray::from(vec![true; batch.num_rows()])),
+                    _ => evaluate(arg, batch)?,
+                };
+                values.push(value);
+            }
+            args.push(values);
+        }
+        Ok(args)
+    }
+
+    /// Evaluates the `FILTER` predicates, where NULL rejects a row like false.
+    fn evaluate_filters(&self, batch: &RecordBatch) -> anyhow::Result<Vec<Option<ArrayRef>>> {
+        self.aggr_expr
+            .iter()
+            .map(|aggregate| {
+                aggregate
+                    .filter
+                    .as_ref()
+                    .map(|filter| evaluate(filter, batch))
+                    .transpose()
+            })
+            .collect()
+    }
+
+    /// Adds a group of the grouping set `set` and returns its index.
+    fn create_group(
+        &mut self,
+        set: usize,
+        key: &[u8],
+        row: Option<OwnedRow>,
+    ) -> anyhow::Result<usize> {
+        let grouped = &self.grouping_sets[set].grouped;
+        let accumulators = self
+            .aggr_expr
+            .iter()
+            .zip(&self.arg_types)
+            .map(|(aggregate, arg_types)| match aggregate.func {
+                AggregateFunc::Grouping => {
+                    let value = aggregate.args.iter().fold(0, |value, arg| {
+                        let position = self.group_expr.iter().position(|expr| expr == arg);
+                        let rolled_up = position.is_some_and(|i| !grouped[i]);
+                        (value << 1) | i64::from(rolled_up)
+                    });
+                    Ok(Box::new(GroupingAccumulator::new(value)) as Box<dyn Accumulator>)
+                }
+                _ => create_accumulator(aggregate, arg_types),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let index = self.accumulators.len();
+        self.bytes += key.len() + accumulators.iter().map(|a| a.size()).sum::<usize>();
+        self.accumulators.push(accumulators);
+        self.group_keys.extend(row);
+        self.grouping_sets[set].groups.insert(key.into(), index);
+        Ok(index)
+    }
+
+    /// Returns the group of the grouping set `set` without keys, which exists
+    /// even if there are no input rows.
+    fn grand_total(&mut self, set: usize) -> anyhow::Result<usize> {
+        if let Some(index) = self.grouping_sets[set].groups.values().next() {
+            return Ok(*index);
+        }
+        // All keys are NULL, unless there are none.
+        let row = if self.group_expr.is_empty() {
+            None
+        } else {
+            let nulls = self.schema.fields()[..self.group_expr.len()]
+                .iter()
+                .map(|field| new_null_array(field.data_type(), 1))
+                .collect::<Vec<_>>();
+            Some(self.converter.convert_columns(&nulls)?.row(0).owned())
+        };
+        self.create_group(set, &[], row)
+    }
+
+    fn group_indices(
+        &mut self,
+        set: usize,
+        keys: &[ArrayRef],
+        num_rows: usize,
+    ) -> anyhow::Result<Vec<usize>> {
+        let grouped = &self.grouping_sets[set].grouped;
+        if !grouped.contains(&true) {
+            return Ok(vec![self.grand_total(set)?; num_rows]);
+        }
+
+        let keys = keys
+            .iter()
+            .zip(grouped)
+            .map(|(key, &grouped)| {
+                if grouped {
+                    key.clone()
+                } else {
+                    new_null_array(key.data_type(), num_rows)
+                }
+            })
+            .collect::<Vec<_>>();
+        let rows = self.converter.convert_columns(&keys)?;
+
+        let mut indices = Vec::with_capacity(num_rows);
+        for row in &rows {
+            let index = match self.grouping_sets[set].groups.get(row.as_ref()) {
+                Some(index) => *index,
+                None => self.create_group(set, row.as_ref(), Some(row.owned()))?,
+            };
+            indices.push(index);
+        }
+        Ok(indices)
+    }
+
+    fn output(&self) -> anyhow::Result<RecordBatch> {
+        let mut columns = self
+            .converter
+            .convert_rows(self.group_keys.iter().map(OwnedRow::row))?;
+        for (i, field) in self.schema.fields()[self.group_expr.len()..]
+            .iter()
+            .enumerate()
+        {
+            let values = self
+                .accumulators
+                .iter()
+                .map(|group| group[i].evaluate())
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let column = if values.is_empty() {
+                new_empty_array(field.data_type())
+            } else {
+                compute::concat(&values.iter().map(AsRef::as_ref).collect::<Vec<_>>())?
+            };
+            columns.push(column);
+        }
+        Ok(RecordBatch::try_new(self.schema.clone(), columns)?)
+    }
+
+    /// Writes the partial states of all groups to the partitions and removes
+    /// them from memory.
+    fn spill(&mut self) -> anyhow::Result<()> {
+        let mut sets = vec![0; self.accumulators.len()];
+        for (set, grouping_set) in self.grouping_sets.iter_mut().enumerate() {
+            for (_, index) in grouping_set.groups.drain() {
+                sets[index] = u32::try_from(set)?;
+            }
+        }
+        let keys = std::mem::take(&mut self.group_keys);
+        let accumulators = std::mem::take(&mut self.accumulators);
+        self.bytes = 0;
+
+        let mut fields = self.schema.fields()[..self.group_expr.len()].to_vec();
+        fields.push(Arc::new(Field::new(
+            "grouping_set",
+            DataType::UInt32,
+            false,
+        )));
+        let mut columns = self
+            .converter
+            .convert_rows(keys.iter().map(OwnedRow::row))?;
+        columns.push(Arc::new(UInt32Array::from(sets)));
+        self.state_widths.clear();
+        for i in 0..self.aggr_expr.len() {
+            let states = accumulators
+                .iter()
+                .map(|group| group[i].state())
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let width = states.first().map_or(0, Vec::len);
+            for j in 0..width {
+                let state = states.iter().map(|state| state[j].as_ref());
+                let column = compute::concat(&state.collect::<Vec<_>>())?;
+                let name = format!("state_{i}_{j}");
+                fields.push(Arc::new(Field::new(name, column.data_type().clone(), true)));
+                columns.push(column);
+            }
+            self.state_widths.push(width);
+        }
+        let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)?;
+
+        let partitions = match &mut self.partitions {
+            Some(partitions) => partitions,
+            partitions => partitions.insert(
+                (0..PARTITIONS)
+                    .map(|_| SpillWriter::try_new(&batch.schema()))
+                    .collect::<anyhow::Result<_>>()?,
+            ),
+        };
+        let mut indices = vec![Vec::new(); partitions.len()];
+        for (i, key) in keys.iter().enumerate() {
+            let mut hasher = XxHash64::with_seed(0);
+            hasher.write(key.row().as_ref());
+            let partition = usize::try_from(hasher.finish() % partitions.len() as u64)?;
+            indices[partition].push(u32::try_from(i)?);
+        }
+        for (writer, indices) in partitions.iter_mut().zip(indices) {
+            if !indices.is_empty() {
+                writer.write(&take_batch(&batch, &UInt32Array::from(indices))?)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges partial states written by `spill` into the groups in memory.
+    fn merge(&mut self, batch: &RecordBatch) -> anyhow::Result<()> {
+        let num_keys = self.group_expr.len();
+        let rows = self
+            .converter
+            .convert_columns(&batch.columns()[..num_keys])?;
+        let sets = batch.column(num_keys).as_primitive::<UInt32Type>();
+        let mut indices = Vec::with_capacity(batch.num_rows());
+        for (row, set) in rows.iter().zip(sets.values()) {
+            let set = *set as usize;
+            let index = match self.grouping_sets[set].groups.get(row.as_ref()) {
+                Some(index) => *index,
+                None => self.create_group(set, row.as_ref(), Some(row.owned()))?,
+            };
+            indices.push(index);
+        }
+
+        let GroupedRows { rows, groups } = sort_by_group(&indices, 0..batch.num_rows())?;
+        let mut states = batch.columns()[num_keys + 1..].iter();
+        for (i, width) in self.state_widths.iter().enumerate() {
+            let values = states
+                .by_ref()
+                .take(*width)
+                .map(|state| compute::take(state, &rows, None))
+                .collect::<Result<Vec<_>, _>>()?;
+            for (group, range) in &groups {
+                let values = slice_all(&values, range);
+                self.accumulators[*group][i].merge_batch(&values)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Rows of a batch ordered by their group, and the range of each group among
+/// them.
+struct GroupedRows {
+    rows: UInt32Array,
+    groups: Vec<(usize, Range<usize>)>,
+}
+
+/// Orders `rows` of a batch by their group in `indices`.
+fn sort_by_group(
+    indices: &[usize],
+    rows: impl Iterator<Item = usize>,
+) -> anyhow::Result<GroupedRows> {
+    let mut rows = rows.collect::<Vec<_>>();
+    rows.sort_by_key(|&row| indices[row]);
+    let mut groups = Vec::new();
+    let mut start = 0;
+    for end in 1..=rows.len() {
+        if end == rows.len() || indices[rows[end]] != indices[rows[start]] {
+            groups.push((indices[rows[start]], start..end));
+            start = end;
+        }
+    }
+    let rows = rows
+        .into_iter()
+        .map(u32::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(GroupedRows {
+        rows: UInt32Array::from(rows),
+        groups,
+    })
+}
+
+fn slice_all(values: &[ArrayRef], range: &Range<usize>) -> Vec<ArrayRef> {
+    values
+        .iter()
+        .map(|value| value.slice(range.start, range.len()))
+        .collect()
+}
+
+impl Operator<Arc<RecordBatch>> for HashAggregate<'_> {
+    fn execute(&mut self, input: Arc<RecordBatch>) -> anyhow::Result<OperatorState> {
+        let args = self.evaluate_args(&input)?;
+        let filters = self
+            .evaluate_filters(&input)?
+            .into_iter()
+            .map(|filter| {
+                filter
+                    .map(|filter| {
+                        filter
+                            .as_boolean_opt()
+                            .cloned()
+                            .ok_or_else(|| anyhow::anyhow!("FILTER predicate must be boolean"))
+                    })
+                    .transpose()
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let keys = self
+            .group_expr
+            .iter()
+            .map(|expr| evaluate(expr, &input))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        for set in 0..self.grouping_sets.len() {
+            let indices = self.group_indices(set, &keys, input.num_rows())?;
+            // The rows of each group are adjacent once sorted, so each argument
+            // is taken once and sliced for the groups.
+            let all_rows = sort_by_group(&indices, 0..input.num_rows())?;
+            for (i, (values, filter)) in args.iter().zip(&filters).enumerate() {
+                let filtered;
+                let GroupedRows { rows, groups } = match filter {
+                    Some(filter) => {
+                        let selected = (0..input.num_rows())
+                            .filter(|&row| filter.is_valid(row) && filter.value(row));
+                        filtered = sort_by_group(&indices, selected)?;
+                        &filtered
+                    }
+                    None => &all_rows,
+                };
+                let values = values
+                    .iter()
+                    .map(|v| compute::take(v, rows, None))
+                    .collect::<Result<Vec<_>, _>>()?;
+                for (group, range) in groups {
+                    let accumulator = &mut self.accumulators[*group][i];
+                    let size = accumulator.size();
+                    accumulator.update_batch(&slice_all(&values, range))?;
+                    self.bytes = (self.bytes + accumulator.size()).saturating_sub(size);
+                }
+            }
+        }
+
+        // Without keys, each grouping set has a single group not worth spilling.
+        if !self.group_expr.is_empty() && self.memory_limit.is_some_and(|limit| self.bytes > limit)
+        {
+            self.spill()?;
+        }
+        Ok(OperatorState::NeedMoreInput)
+    }
+
+    fn all_inputs_received(&mut self) -> anyhow::Result<()> {
+        // A grouping set without keys produces a row even for empty input.
+        for set in 0..self.grouping_sets.len() {
+            if !self.grouping_sets[set].grouped.contains(&true) {
+                self.grand_total(set)?;
+            }
+        }
+        if self.partitions.is_none() {
+            let batch = self.output()?;
+            self.successor.execute(Arc::new(batch))?;
+            return self.successor.all_inputs_received();
+        }
+
+        self.spill()?;
+        let partitions = self
+            .partitions
+            .take()
+            .into_iter()
+            .flatten()
+            .map(SpillWriter::finish)
+            .collect::<anyhow::Result<Vec<SpillFile>>>()?;
+        for partition in partitions {
+            for batch in partition.read()? {
+                self.merge(&batch?)?;
+            }
+            let batch = self.output()?;
+            if batch.num_rows() == 0 {
+                continue;
+            }
+            self.group_keys.clear();
+            self.accumulators.clear();
+            for grouping_set in &mut self.grouping_sets {
+                grouping_set.groups.clear();
+            }
+            if self.successor.execute(Arc::new(batch))? == OperatorState::Finished {
+                break;
+            }
+        }
+        self.successor.all_inputs_received()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::DummyCatalog;
+    use crate::execution::operators::collect::Collect;
+    use crate::logical_plan::errors::PlanError;
+    use crate::logical_plan::udaf::AggregateUdf;
+    use crate::logical_plan::{Dag, LogicalPlan};
+    use crate::parser::sql_parser::parse_sql_query;
+    use arrow::array::{AsArray, Float64Array, StringArray};
+    use arrow::datatypes::{Field, Float64Type, Int64Type};
+    use std::mem::size_of;
+
+    struct WeightedAvg {
+        weighted_sum: f64,
+        weight: f64,
+    }
+
+    impl Accumulator for WeightedAvg {
+        fn update_batch(&mut self, values: &[ArrayRef]) -> anyhow::Result<()> {
+            let value = values[0].as_primitive::<Float64Type>();
+            let weight = values[1].as_primitive::<Float64Type>();
+            for i in 0..value.len() {
+                self.weighted_sum += value.value(i) * weight.value(i);
+                self.weight += weight.value(i);
+            }
+            Ok(())
+        }
+
+        fn state(&self) -> anyhow::Result<Vec<ArrayRef>> {
+            Ok(vec![
+                Arc::new(Float64Array::from(vec![self.weighted_sum])),
+                Arc::new(Float64Array::from(vec![self.weight])),
+            ])
+        }
+
+        fn merge_batch(&mut self, states: &[ArrayRef]) -> anyhow::Result<()> {
+            self.weighted_sum += compute::sum(states[0].as_primitive::<Float64Type>()).unwrap();
+            self.weight += compute::sum(states[1].as_primitive::<Float64Type>()).unwrap();
+            Ok(())
+        }
+
+        fn evaluate(&self) -> anyhow::Result<ArrayRef> {
+            Ok(Arc::new(Float64Array::from(vec![
+                self.weighted_sum / self.weight,
+            ])))
+        }
+
+        fn size(&self) -> usize {
+            size_of::<Self>()
+        }
+    }
+
+    #[test]
+    fn test_hash_aggregate_with_udaf() -> anyhow::Result<()> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("country", DataType::Utf8, false),
+            Field::new("salary", DataType::Float64, false),
+            Field::new("weight", DataType::Float64, false),
+        ]));
+
+        let mut catalog = DummyCatalog::new();
+        catalog.add_table("users", schema.clone());
+        catalog.register_udaf(AggregateUdf::new(
+            "weighted_avg",
+            Arc::new(|_| Ok(DataType::Float64)),
+            Arc::new(|_| {
+                Ok(Box::new(WeightedAvg {
+                    weighted_sum: 0.0,
+                    weight: 0.0,
+                }))
+            }),
+        ));
+
+        let dag = parse_sql_query(
+            "SELECT country, weighted_avg(salary, weight), count(*) FROM users GROUP BY country",
+            &catalog,
+        )?;
+        let LogicalPlan::Aggregate(aggregate) = dag.get_node(1) else {
+            panic!("expected aggregate node");
+        };
+
+        let batches = [
+            (
+                vec!["de", "fr", "de"],
+                vec![10.0, 20.0, 40.0],
+                vec![3.0, 1.0, 1.0],
+            ),
+            (vec!["fr"], vec![30.0], vec![1.0]),
+        ];
+
+        let mut res = Vec::new();
+
+        {
+            let collect = Box::new(Collect::new(&mut res));
+            let mut hash_aggregate = HashAggregate::new(
+                aggregate.group_expr.clone(),
+                &aggregate.grouping_sets,
+                &aggregate.aggr_expr,
+                &schema,
+                aggregate.schema.clone(),
+                collect,
+            )?;
+
+            for (country, salary, weight) in batches {
+                let batch = RecordBatch::try_new(
+                    schema.clone(),
+                    vec![
+                        Arc::new(StringArray::from(country)),
+                        Arc::new(Float64Array::from(salary)),
+                        Arc::new(Float64Array::from(weight)),
+                    ],
+                )?;
+                hash_aggregate.execute(Arc::new(batch))?;
+            }
+            hash_aggregate.all_inputs_received()?;
+        }
+
+        assert_eq!(res.len(), 1);
+        let result = &res[0];
+        assert_eq!(result.num_rows(), 2);
+        assert_eq!(result.schema(), aggregate.schema);
+
+        let country = result.column(0).as_string::<i32>();
+        let weighted = result.column(1).as_primitive::<Float64Type>();
+        let count = result.column(2).as_primitive::<Int64Type>();
+        assert_eq!(country.value(0), "de");
+        assert!((weighted.value(0) - 17.5).abs() < f64::EPSILON);
+        assert_eq!(count.value(0), 2);
+        assert_eq!(country.value(1), "fr");
+        assert!((weighted.value(1) - 25.0).abs() < f64::EPSILON);
+        assert_eq!(count.value(1), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_aggregate_with_grouping_sets() -> anyhow::Result<()> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("country", DataType::Utf8, false),
+            Field::new("gender", DataType::Utf8, true),
+        ]));
+        let mut catalog = DummyCatalog::new();
+        catalog.add_table("users", schema.clone());
+        let aggregate = |dag: &Dag<LogicalPlan>| match dag.get_node(1) {
+            LogicalPlan::Aggregate(aggregate) => (
+                aggregate
+                    .group_expr
+                    .iter()
+                    .map(Expr::name)
+                    .collect::<Vec<_>>(),
+                aggregate.grouping_sets.clone(),
+            ),
+            _ => panic!("expected aggregate node"),
+        };
+
+        let dag = parse_sql_query(
+            "SELECT 1 FROM users GROUP BY country, CUBE(gender, (country, 2))",
+            &catalog,
+        )?;
+        let (keys, sets) = aggregate(&dag);
+        assert_eq!(keys, ["country", "gender", "2"]);
+        assert_eq!(sets, [vec![0, 1, 2], vec![0, 1], vec![0, 2], vec![0]]);
+        let dag = parse_sql_query(
+            "SELECT 1 FROM users GROUP BY GROUPING SETS ((gender), ()), ROLLUP(country)",
+            &catalog,
+        )?;
+        let (keys, sets) = aggregate(&dag);
+        assert_eq!(keys, ["gender", "country"]);
+        assert_eq!(sets, [vec![0, 1], vec![0], vec![1], vec![]]);
+        assert_eq!(
+            parse_sql_query(
+                "SELECT grouping(gender) FROM users GROUP BY country",
+                &catalog
+            )
+            .unwrap_err(),
+            PlanError::NotGrouped("gender".to_string())
+        );
+
+        let dag = parse_sql_query(
+            "SELECT country, gender, grouping(country, gender), count(*) \
+             FROM users GROUP BY ROLLUP(country, gender)",
+            &catalog,
+        )?;
+        let LogicalPlan::Aggregate(aggregate) = dag.get_node(1) else {
+            panic!("expected aggregate node");
+        };
+        // Rolled up keys are NULL, so they are nullable.
+        assert!(aggregate.schema.field(0).is_nullable());
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(StringArray::from(vec!["de", "fr", "de", "de"])),
+                Arc::new(StringArray::from(vec![
+                    Some("f"),
+                    None,
+                    Some("m"),
+                    Some("f"),
+                ])),
+            ],
+        )?;
+        let mut res = Vec::new();
+        {
+            let collect = Box::new(Collect::new(&mut res));
+            let mut hash_aggregate = HashAggregate::new(
+                aggregate.group_expr.clone(),
+                &aggregate.grouping_sets,
+                &aggregate.aggr_expr,
+                &schema,
+                aggregate.schema.clone(),
+                collect,
+            )?;
+            hash_aggregate.execute(Arc::new(batch))?;
+            hash_aggregate.all_inputs_received()?;
+        }
+
+        let result = &res[0];
+        let rows = (0..result.num_rows())
+            .map(|i| {
+                (
+                    result.column(0).as_string::<i32>().iter().nth(i).unwrap(),
+                    result.column(1).as_string::<i32>().iter().nth(i).unwrap(),
+                    result.column(2).as_primitive::<Int64Type>().value(i),
+                    result.column(3).as_primitive::<Int64Type>().value(i),
+                )
+            })
+            .collect::<Vec<_>>();
+        // The NULL gender of fr is grouped separately from its rolled up row.
+        assert_eq!(
+            rows,
+            [
+                (Some("de"), Some("f"), 0, 2),
+                (Some("fr"), None, 0, 1),
+                (Some("de"), Some("m"), 0, 1),
+                (Some("de"), None, 1, 3),
+                (Some("fr"), None, 1, 1),
+                (None, None, 3, 4),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_aggregate_with_pivot() -> anyhow::Result<()> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("country", DataType::Utf8, false),
+            Field::new("month", DataType::Utf8, false),
+            Field::new("amount", DataType::Float64, false),
+        ]));
+        let mut catalog = DummyCatalog::new();
+        catalog.add_table("sales", schema.clone());
+
+        // Each value gets its own filtered copy of the aggregate.
+        let dag = parse_sql_query(
+            "SELECT * FROM sales PIVOT (sum(amount) FOR month IN ('jan', 'feb')) AS p",
+            &catalog,
+        )?;
+        let LogicalPlan::Aggregate(aggregate) = dag.get_node(1) else {
+            panic!("expected aggregate node");
+        };
+        let names = |schema: &Schema| {
+            let fields = schema.fields().iter();
+            fields.map(|field| field.name().clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(&aggregate.schema),
+            [
+                "country",
+                "sum(amount) FILTER (WHERE month = 'jan')",
+                "sum(amount) FILTER (WHERE month = 'feb')"
+            ]
+        );
+        assert_eq!(
+            names(&dag.get_node(2).get_schema()),
+            ["country", "jan", "feb"]
+        );
+
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(StringArray::from(vec!["de", "de", "fr", "de"])),
+                Arc::new(StringArray::from(vec!["jan", "feb", "feb", "jan"])),
+                Arc::new(Float64Array::from(vec![1.0, 2.0, 3.0, 4.0])),
+            ],
+        )?;
+        let mut res = Vec::new();
+        {
+            let collect = Box::new(Collect::new(&mut res));
+            let mut hash_aggregate = HashAggregate::new(
+                aggregate.group_expr.clone(),
+                &aggregate.grouping_sets,
+                &aggregate.aggr_expr,
+                &schema,
+                aggregate.schema.clone(),
+                collect,
+            )?;
+            hash_aggregate.execute(Arc::new(batch))?;
+            hash_aggregate.all_inputs_received()?;
+        }
+
+        // fr has no rows for jan, so its sum is NULL.
+        let result = &res[0];
+        let jan = result.column(1).as_primitive::<Float64Type>();
+        let feb = result.column(2).as_primitive::<Float64Type>();
+        assert_eq!(jan.iter().collect::<Vec<_>>(), [Some(5.0), None]);
+        assert_eq!(feb.iter().collect::<Vec<_>>(), [Some(2.0), Some(3.0)]);
+
+        Ok(())
+    }
+}
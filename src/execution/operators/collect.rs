@@ -1,4 +1,4 @@
-use crate::execution::operators::Operator;
+use crate::execution::operators::{Operator, OperatorState};
 use arrow::array::RecordBatch;
 use std::sync::Arc;
 
@@ -7,16 +7,17 @@ pub struct Collect<'i> {
 }
 
 impl<'i> Collect<'i> {
+    #[allow(clippy::missing_const_for_fn)]
     pub(crate) fn new(res: &'i mut Vec<Arc<RecordBatch>>) -> Self {
         Self { res }
     }
 }
 
 impl Operator<Arc<RecordBatch>> for Collect<'_> {
-    fn execute(&mut self, input: Arc<RecordBatch>) -> anyhow::Result<()> {
+    fn execute(&mut self, input: Arc<RecordBatch>) -> anyhow::Result<OperatorState> {
         let batch = input;
         self.res.push(batch);
-        Ok(())
+        Ok(OperatorState::NeedMoreInput)
     }
 
     fn all_inputs_received(&mut self) -> anyhow::Result<()> {
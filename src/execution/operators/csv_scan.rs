@@ -0,0 +1,140 @@
+use crate::catalog::CsvOptions;
+use crate::execution::operators::{Operator, OperatorState};
+use arrow::datatypes::SchemaRef;
+use arrow::record_batch::RecordBatch;
+use std::sync::Arc;
+
+/// Reads the rows of a CSV file with `schema`, typically the one the catalog
+/// inferred when the file was registered.
+pub struct CsvScan<'i> {
+    successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    schema: SchemaRef,
+    options: CsvOptions,
+}
+
+impl<'i> CsvScan<'i> {
+    pub(crate) fn new(
+        schema: SchemaRef,
+        options: CsvOptions,
+        successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    ) -> Self {
+        Self {
+            successor,
+            schema,
+            options,
+        }
+    }
+}
+
+impl Operator<(String, usize)> for CsvScan<'_> {
+    fn execute(&mut self, input: (String, usize)) -> anyhow::Result<OperatorState> {
+        let (file_path, chunk_size) = input;
+        let reader = self
+            .options
+            .reader(&file_path, self.schema.clone(), chunk_size)?;
+
+        // Batches are parsed as they are pulled, so stopping here skips the rest of the file.
+        for b in reader {
+            if self.successor.execute(Arc::new(b?))? == OperatorState::Finished {
+                return Ok(OperatorState::Finished);
+            }
+        }
+
+        Ok(OperatorState::NeedMoreInput)
+    }
+
+    fn all_inputs_received(&mut self) -> anyhow::Result<()> {
+        self.successor.all_inputs_received()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::{Catalog, DummyCatalog, TableSource};
+    use crate::execution::operators::collect::Collect;
+    use arrow::array::AsArray;
+    use arrow::datatypes::{DataType, Float64Type, Int64Type};
+
+    fn scan(
+        catalog: &DummyCatalog,
+        table: &str,
+        chunk_size: usize,
+    ) -> anyhow::Result<Vec<Arc<RecordBatch>>> {
+        let Some(TableSource::Csv { path, options }) = catalog.get_source(table) else {
+            anyhow::bail!("Table {table} is not a CSV file");
+        };
+        let mut res = Vec::new();
+        {
+            let collect = Box::new(Collect::new(&mut res));
+            let schema = catalog.get_schema(table)?;
+            let mut scan = CsvScan::new(schema, options.clone(), collect);
+            scan.execute((path.clone(), chunk_size))?;
+            scan.all_inputs_received()?;
+        }
+        Ok(res)
+    }
+
+    #[test]
+    fn test_csv_scan() -> anyhow::Result<()> {
+        let mut catalog = DummyCatalog::new();
+        let options = CsvOptions {
+            null_string: Some("NA".to_string()),
+            ..CsvOptions::default()
+        };
+        catalog.register_csv("people", "samples/sample-data/csv/people.csv", options)?;
+        let options = CsvOptions {
+            delimiter: b';',
+            ..CsvOptions::default()
+        };
+        catalog.register_csv("scores", "samples/sample-data/csv/scores.csv", options)?;
+
+        // The header is detected, and the types come from the rows below it.
+        let schema = catalog.get_schema("people")?;
+        let types = schema
+            .fields()
+            .iter()
+            .map(|field| (field.name().as_str(), field.data_type().clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            [
+                ("id", DataType::Int64),
+                ("name", DataType::Utf8),
+                ("score", DataType::Float64),
+                ("joined", DataType::Date32),
+            ]
+        );
+        let res = scan(&catalog, "people", 3)?;
+        assert_eq!(res.iter().map(|b| b.num_rows()).collect::<Vec<_>>(), [3, 1]);
+        let names = res
+            .iter()
+            .flat_map(|b| b.column(1).as_string::<i32>().iter().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [Some("Ann"), Some("Smith, Bob"), Some("Say \"hi\""), None]
+        );
+        assert_eq!(res[0].column(2).null_count(), 1);
+        assert_eq!(res[0].column(3).null_count(), 1);
+
+        // Without a header, the columns get positional names.
+        let schema = catalog.get_schema("scores")?;
+        assert_eq!(schema.field(0).name(), "column_1");
+        let res = scan(&catalog, "scores", 1024)?;
+        assert_eq!(
+            res[0].column(0).as_primitive::<Int64Type>().values(),
+            &[1, 2, 3]
+        );
+        let scores = res[0].column(2).as_primitive::<Float64Type>();
+        assert_eq!(
+            scores.iter().collect::<Vec<_>>(),
+            [Some(7.5), None, Some(9.0)]
+        );
+
+        catalog.analyze("people")?;
+        assert_eq!(catalog.get_statistics("people").unwrap().row_count, 4);
+
+        Ok(())
+    }
+}
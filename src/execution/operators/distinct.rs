@@ -0,0 +1,392 @@
+use crate::execution::evaluator::evaluate;
+use crate::execution::operators::select::take_batch;
+use crate::execution::operators::{Operator, OperatorState};
+use crate::execution::spill::{SpillFile, SpillWriter};
+use crate::logical_plan::expr::Expr;
+use arrow::array::{ArrayRef, RecordBatch, UInt32Array};
+use arrow::datatypes::Schema;
+use arrow::row::{OwnedRow, Row, RowConverter, Rows, SortField};
+use std::collections::HashSet;
+use std::hash::Hasher;
+use std::mem::size_of;
+use std::sync::Arc;
+use twox_hash::XxHash64;
+
+/// Number of partitions the unseen rows are split into once the hash table of
+/// a `Distinct` exceeds its memory limit.
+const PARTITIONS: usize = 16;
+/// Partitions are split again at most this often.
+const MAX_DEPTH: usize = 3;
+
+/// Rows seen so far, by their row encoding, for removing duplicate rows.
+pub struct DistinctRows {
+    converter: RowConverter,
+    rows: HashSet<Box<[u8]>>,
+    bytes: usize,
+}
+
+impl DistinctRows {
+    /// Distinct rows of the columns of `schema`.
+    pub fn new(schema: &Schema) -> anyhow::Result<Self> {
+        let fields = schema
+            .fields()
+            .iter()
+            .map(|field| SortField::new(field.data_type().clone()))
+            .collect();
+        Ok(Self {
+            converter: RowConverter::new(fields)?,
+            rows: HashSet::new(),
+            bytes: 0,
+        })
+    }
+
+    pub fn convert(&self, columns: &[ArrayRef]) -> anyhow::Result<Rows> {
+        Ok(self.converter.convert_columns(columns)?)
+    }
+
+    pub fn contains(&self, row: Row) -> bool {
+        self.rows.contains(row.as_ref())
+    }
+
+    /// Remembers `row` and returns whether it wasn't seen before.
+    pub fn insert(&mut self, row: Row) -> bool {
+        if self.contains(row) {
+            return false;
+        }
+        self.bytes += row.as_ref().len() + size_of::<Box<[u8]>>();
+        self.rows.insert(row.as_ref().into())
+    }
+
+    /// Indices of the first occurrence of each row of `columns` which wasn't
+    /// seen before, which are remembered.
+    pub fn insert_all(&mut self, columns: &[ArrayRef]) -> anyhow::Result<Vec<u32>> {
+        let rows = self.convert(columns)?;
+        Ok((0..rows.num_rows())
+            .filter(|&i| self.insert(rows.row(i)))
+            .map(u32::try_from)
+            .collect::<Result<_, _>>()?)
+    }
+
+    /// Returns the rows of `batch` which weren't seen before, without duplicates.
+    pub fn retain_new(&mut self, batch: RecordBatch) -> anyhow::Result<RecordBatch> {
+        let indices = self.insert_all(batch.columns())?;
+        if indices.len() == batch.num_rows() {
+            return Ok(batch);
+        }
+        take_batch(&batch, &UInt32Array::from(indices))
+    }
+
+    /// The remembered rows, in no particular order.
+    pub fn columns(&self) -> anyhow::Result<Vec<ArrayRef>> {
+        let parser = self.converter.parser();
+        let rows = self.rows.iter().map(|row| parser.parse(row));
+        Ok(self.converter.convert_rows(rows)?)
+    }
+
+    /// Approximate number of bytes used for the remembered rows.
+    pub const fn size(&self) -> usize {
+        self.bytes
+    }
+}
+
+/// Rows seen by one level of a partitioned `Distinct`.
+struct HashTable {
+    rows: DistinctRows,
+    /// Unseen rows by the hash of their key, once `rows` grew too large.
+    partitions: Option<Vec<SpillWriter>>,
+    /// Number of times the rows were partitioned before, also used as hash seed.
+    depth: usize,
+}
+
+/// Keeps the first row of each group of rows with equal values of the `on`
+/// expressions, or of all columns without `on` expressions. NULLs are equal to
+/// each other.
+///
+/// Sorted inputs, whose equal keys are adjacent, are deduplicated by comparing
+/// each key with the previous one. Otherwise the keys seen so far are kept in a
+/// hash table. With a memory limit, rows whose keys are not in the table are
+/// written to partitions on disk by the hash of their key, once the table grows
+/// beyond the limit. The partitions are deduplicated one by one at the end, so
+/// the rows keep their order only if nothing was spilled.
+pub struct Distinct<'i> {
+    successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    on: Vec<Expr>,
+    key_schema: Schema,
+    sorted: bool,
+    /// Key of the last row for sorted inputs.
+    last: Option<OwnedRow>,
+    table: Option<HashTable>,
+    memory_limit: Option<usize>,
+    finished: bool,
+}
+
+impl<'i> Distinct<'i> {
+    pub(crate) fn new(
+        on: Vec<Expr>,
+        input_schema: &Schema,
+        sorted: bool,
+        successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    ) -> anyhow::Result<Self> {
+        let key_schema = if on.is_empty() {
+            input_schema.clone()
+        } else {
+            Schema::new(
+                on.iter()
+                    .map(|expr| expr.to_field(input_schema))
+                    .collect::<Result<Vec<_>, _>>()?,
+            )
+        };
+        let table = HashTable {
+            rows: DistinctRows::new(&key_schema)?,
+            partitions: None,
+            depth: 0,
+        };
+        Ok(Self {
+            successor,
+            on,
+            key_schema,
+            sorted,
+            last: None,
+            table: Some(table),
+            memory_limit: None,
+            finished: false,
+        })
+    }
+
+    /// Limits the memory used for the keys of unsorted inputs to roughly `bytes`.
+    pub(crate) const fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    fn keys(&self, batch: &RecordBatch) -> anyhow::Result<Vec<ArrayRef>> {
+        if self.on.is_empty() {
+            return Ok(batch.columns().to_vec());
+        }
+        self.on.iter().map(|expr| evaluate(expr, batch)).collect()
+    }
+
+    fn forward(&mut self, batch: &RecordBatch, indices: Vec<u32>) -> anyhow::Result<()> {
+        if indices.is_empty() {
+            return Ok(());
+        }
+        let batch = if indices.len() == batch.num_rows() {
+            batch.clone()
+        } else {
+            take_batch(batch, &UInt32Array::from(indices))?
+        };
+        if self.successor.execute(Arc::new(batch))? == OperatorState::Finished {
+            self.finished = true;
+        }
+        Ok(())
+    }
+
+    fn execute_sorted(&mut self, batch: &RecordBatch) -> anyhow::Result<()> {
+        let table = self
+            .table
+            .as_ref()
+            .expect("hash table is only taken while in use");
+        let rows = table.rows.convert(&self.keys(batch)?)?;
+        let mut indices = Vec::new();
+        for (i, row) in rows.iter().enumerate() {
+            if self.last.as_ref().is_none_or(|last| last.row() != row) {
+                indices.push(u32::try_from(i)?);
+                self.last = Some(row.owned());
+            }
+        }
+        self.forward(batch, indices)
+    }
+
+    fn insert(&mut self, table: &mut HashTable, batch: &RecordBatch) -> anyhow::Result<()> {
+        let keys = self.keys(batch)?;
+        let Some(partitions) = &mut table.partitions else {
+            let indices = table.rows.insert_all(&keys)?;
+            self.forward(batch, indices)?;
+            if self
+                .memory_limit
+                .is_some_and(|limit| table.rows.size() > limit)
+                && table.depth < MAX_DEPTH
+            {
+                let schema = batch.schema();
+                table.partitions = Some(
+                    (0..PARTITIONS)
+                        .map(|_| SpillWriter::try_new(&schema))
+                        .collect::<anyhow::Result<_>>()?,
+                );
+            }
+            return Ok(());
+        };
+
+        // Rows with a key in memory were already forwarded.
+        let rows = table.rows.convert(&keys)?;
+        let mut indices = vec![Vec::new(); partitions.len()];
+        for (i, row) in rows.iter().enumerate() {
+            if !table.rows.contains(row) {
+                let mut hasher = XxHash64::with_seed(table.depth as u64);
+                hasher.write(row.as_ref());
+                let partition = usize::try_from(hasher.finish() % partitions.len() as u64)?;
+                indices[partition].push(u32::try_from(i)?);
+            }
+        }
+        for (writer, indices) in partitions.iter_mut().zip(indices) {
+            if !indices.is_empty() {
+                writer.write(&take_batch(batch, &UInt32Array::from(indices))?)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Deduplicates the partitions of `table`, if it has any.
+    fn finish(&mut self, table: HashTable) -> anyhow::Result<()> {
+        let Some(partitions) = table.partitions else {
+            return Ok(());
+        };
+        let partitions = partitions
+            .into_iter()
+            .map(SpillWriter::finish)
+            .collect::<anyhow::Result<Vec<SpillFile>>>()?;
+        drop(table.rows);
+        for partition in partitions {
+            let mut table = HashTable {
+                rows: DistinctRows::new(&self.key_schema)?,
+                partitions: None,
+                depth: table.depth + 1,
+            };
+            for batch in partition.read()? {
+                self.insert(&mut table, &batch?)?;
+                if self.finished {
+                    return Ok(());
+                }
+            }
+            self.finish(table)?;
+        }
+        Ok(())
+    }
+}
+
+impl Operator<Arc<RecordBatch>> for Distinct<'_> {
+    fn execute(&mut self, input: Arc<RecordBatch>) -> anyhow::Result<OperatorState> {
+        if !self.finished {
+            if self.sorted {
+                self.execute_sorted(&input)?;
+            } else {
+                let mut table = self
+                    .table
+                    .take()
+                    .expect("hash table is only taken while in use");
+                let res = self.insert(&mut table, &input);
+                self.table = Some(table);
+                res?;
+            }
+        }
+        if self.finished {
+            Ok(OperatorState::Finished)
+        } else {
+            Ok(OperatorState::NeedMoreInput)
+        }
+    }
+
+    fn all_inputs_received(&mut self) -> anyhow::Result<()> {
+        if let Some(table) = self.table.take() {
+            if !self.finished {
+                self.finish(table)?;
+            }
+        }
+        self.successor.all_inputs_received()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::operators::collect::Collect;
+    use crate::logical_plan::expr::Ident;
+    use arrow::array::{Array, AsArray, Int64Array};
+    use arrow::datatypes::{DataType, Field, Int64Type};
+    use std::collections::HashMap;
+
+    fn run(
+        batches: &[Arc<RecordBatch>],
+        sorted: bool,
+        memory_limit: Option<usize>,
+    ) -> anyhow::Result<Vec<(Option<i64>, i64)>> {
+        let on = vec![Expr::Ident(Ident {
+            name: "a".to_string(),
+        })];
+        let mut res = Vec::new();
+        {
+            let collect = Box::new(Collect::new(&mut res));
+            let mut distinct = Distinct::new(on, &batches[0].schema(), sorted, collect)?;
+            if let Some(limit) = memory_limit {
+                distinct = distinct.with_memory_limit(limit);
+            }
+            for batch in batches {
+                distinct.execute(batch.clone())?;
+            }
+            distinct.all_inputs_received()?;
+        }
+        let mut rows = Vec::new();
+        for batch in &res {
+            let a = batch.column(0).as_primitive::<Int64Type>();
+            let b = batch.column(1).as_primitive::<Int64Type>();
+            for i in 0..batch.num_rows() {
+                rows.push((a.is_valid(i).then(|| a.value(i)), b.value(i)));
+            }
+        }
+        Ok(rows)
+    }
+
+    #[test]
+    fn test_distinct() -> anyhow::Result<()> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int64, true),
+            Field::new("b", DataType::Int64, false),
+        ]));
+        let batch = |a: Vec<Option<i64>>, b: Vec<i64>| {
+            RecordBatch::try_new(
+                schema.clone(),
+                vec![Arc::new(Int64Array::from(a)), Arc::new(Int64Array::from(b))],
+            )
+            .map(Arc::new)
+        };
+
+        // Equal keys of sorted inputs may continue in the next batch.
+        let sorted = [
+            batch(vec![Some(1), Some(1), Some(2)], vec![0, 1, 2])?,
+            batch(vec![Some(2), None, None], vec![3, 4, 5])?,
+        ];
+        assert_eq!(
+            run(&sorted, true, None)?,
+            vec![(Some(1), 0), (Some(2), 2), (None, 4)]
+        );
+
+        // 1000 values of `a` and NULL, in the first row of each batch which
+        // has them, with and without spilling.
+        let batches = (0..20)
+            .map(|i| {
+                let a = (0..500).map(|j| (j % 7 != 0).then_some((i * 37 + j) % 1000));
+                batch(a.collect(), (0..500).map(|j| i * 500 + j).collect())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut expected = HashMap::new();
+        for batch in &batches {
+            let a = batch.column(0).as_primitive::<Int64Type>();
+            let b = batch.column(1).as_primitive::<Int64Type>();
+            for i in 0..batch.num_rows() {
+                let key = a.is_valid(i).then(|| a.value(i));
+                expected.entry(key).or_insert_with(|| b.value(i));
+            }
+        }
+        let mut expected = expected.into_iter().collect::<Vec<_>>();
+        expected.sort_unstable();
+        assert_eq!(expected.len(), 1001);
+
+        for memory_limit in [None, Some(1024)] {
+            let mut rows = run(&batches, false, memory_limit)?;
+            rows.sort_unstable();
+            assert_eq!(rows, expected, "memory limit {memory_limit:?}");
+        }
+
+        Ok(())
+    }
+}
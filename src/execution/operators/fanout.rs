@@ -0,0 +1,72 @@
+use crate::execution::operators::{Operator, OperatorState};
+use arrow::array::RecordBatch;
+use std::sync::Arc;
+
+/// Passes each batch to several successors, like the consumers of a CTE which
+/// is referenced more than once. It's finished once all of them are.
+pub struct Fanout<'i> {
+    successors: Vec<Box<dyn Operator<Arc<RecordBatch>> + 'i>>,
+    states: Vec<OperatorState>,
+}
+
+impl<'i> Fanout<'i> {
+    pub(crate) fn new(successors: Vec<Box<dyn Operator<Arc<RecordBatch>> + 'i>>) -> Self {
+        let states = vec![OperatorState::NeedMoreInput; successors.len()];
+        Self { successors, states }
+    }
+}
+
+impl Operator<Arc<RecordBatch>> for Fanout<'_> {
+    fn execute(&mut self, input: Arc<RecordBatch>) -> anyhow::Result<OperatorState> {
+        let mut state = OperatorState::Finished;
+        for (successor, successor_state) in self.successors.iter_mut().zip(&mut self.states) {
+            if *successor_state == OperatorState::NeedMoreInput {
+                *successor_state = successor.execute(input.clone())?;
+            }
+            if *successor_state == OperatorState::NeedMoreInput {
+                state = OperatorState::NeedMoreInput;
+            }
+        }
+        Ok(state)
+    }
+
+    fn all_inputs_received(&mut self) -> anyhow::Result<()> {
+        for successor in &mut self.successors {
+            successor.all_inputs_received()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::operators::collect::Collect;
+    use crate::execution::operators::limit::Limit;
+    use arrow::array::Int32Array;
+
+    #[test]
+    fn test_fanout() -> anyhow::Result<()> {
+        let batch = Arc::new(RecordBatch::try_from_iter([(
+            "id",
+            Arc::new(Int32Array::from(vec![1, 2, 3])) as _,
+        )])?);
+        let mut first = Vec::new();
+        let mut second = Vec::new();
+        {
+            let limited = Box::new(Limit::new(0, Some(4), Box::new(Collect::new(&mut first))));
+            let limited_more =
+                Box::new(Limit::new(0, Some(7), Box::new(Collect::new(&mut second))));
+            let mut fanout = Fanout::new(vec![limited, limited_more]);
+            assert_eq!(fanout.execute(batch.clone())?, OperatorState::NeedMoreInput);
+            assert_eq!(fanout.execute(batch.clone())?, OperatorState::NeedMoreInput);
+            // Only the second successor still needs rows.
+            assert_eq!(fanout.execute(batch)?, OperatorState::Finished);
+            fanout.all_inputs_received()?;
+        }
+        assert_eq!(first.iter().map(|b| b.num_rows()).sum::<usize>(), 4);
+        assert_eq!(second.iter().map(|b| b.num_rows()).sum::<usize>(), 7);
+
+        Ok(())
+    }
+}
@@ -1,8 +1,11 @@
-use crate::execution::operators::Operator;
-use crate::logical_plan::expr::{Binary, BinaryOp, Expr, Ident, IntegerLiteral};
-use arrow::array::{Array, Int32Array, RecordBatch};
+use crate::execution::evaluator::evaluate;
+use crate::execution::operators::{Operator, OperatorState};
+use crate::logical_plan::expr::Expr;
+use arrow::array::{Array, AsArray, RecordBatch};
 use std::sync::Arc;
 
+/// Passes the indices of the rows of each batch which satisfy the predicate to
+/// the successor, which selects them.
 pub struct Filter<'i> {
     successor: Box<dyn Operator<(Vec<usize>, Arc<RecordBatch>)> + 'i>,
     expression: Box<Expr>,
@@ -21,21 +24,18 @@ impl<'i> Filter<'i> {
 }
 
 impl Operator<Arc<RecordBatch>> for Filter<'_> {
-    fn execute(&mut self, input: Arc<RecordBatch>) -> anyhow::Result<()> {
-        let exec = FilterExec::new(&input);
-
-        let mut indices: Vec<usize> = Vec::new();
-        let map = exec.evaluate(&self.expression);
-
-        for (i, elem) in map.iter().enumerate() {
-            if *elem {
-                indices.push(i);
-            }
-        }
-
-        self.successor.execute((indices, input.clone()))?;
-
-        Ok(())
+    fn execute(&mut self, input: Arc<RecordBatch>) -> anyhow::Result<OperatorState> {
+        let predicate = evaluate(&self.expression, &input)?;
+        let predicate = predicate.as_boolean_opt().ok_or_else(|| {
+            anyhow::anyhow!("Filter predicate {} must be boolean", self.expression)
+        })?;
+
+        // Rows for which the predicate is NULL are dropped.
+        let indices = (0..predicate.len())
+            .filter(|&i| predicate.is_valid(i) && predicate.value(i))
+            .collect();
+
+        self.successor.execute((indices, input))
     }
 
     fn all_inputs_received(&mut self) -> anyhow::Result<()> {
@@ -43,99 +43,13 @@ impl Operator<Arc<RecordBatch>> for Filter<'_> {
     }
 }
 
-#[derive(Clone, Copy)]
-struct FilterExec<'e> {
-    record_batch: &'e RecordBatch,
-}
-
-impl<'e> FilterExec<'e> {
-    pub const fn new(record_batch: &'e RecordBatch) -> Self {
-        Self { record_batch }
-    }
-
-    fn visit_expression(self, expression: &Expr) -> Vec<bool> {
-        match expression {
-            Expr::Binary(binary) => match binary.op {
-                BinaryOp::And | BinaryOp::Or => self.visit_logical_binary(binary),
-                BinaryOp::Lt | BinaryOp::Gt => self.visit_compare_binary(binary),
-            },
-            _ => unimplemented!(),
-        }
-    }
-
-    fn visit_logical_binary(self, expr: &Binary) -> Vec<bool> {
-        let lhs = self.visit_expression(&expr.lhs);
-        let rhs = self.visit_expression(&expr.rhs);
-        let mut result = Vec::with_capacity(lhs.len());
-        for i in 0..lhs.len() {
-            match expr.op {
-                BinaryOp::And => result.push(lhs[i] && rhs[i]),
-                BinaryOp::Or => result.push(lhs[i] || rhs[i]),
-                _ => panic!("Binary op must be logical type"),
-            }
-        }
-        result
-    }
-
-    fn visit_compare_binary(self, expr: &Binary) -> Vec<bool> {
-        let lhs = self.visit_binary_value(&expr.lhs);
-        let rhs = self.visit_binary_value(&expr.rhs);
-        let mut result = Vec::with_capacity(lhs.len());
-        for i in 0..lhs.len() {
-            match expr.op {
-                BinaryOp::Lt => result.push(lhs[i] < rhs[i]),
-                BinaryOp::Gt => result.push(lhs[i] > rhs[i]),
-                _ => panic!("Binary op must be compare type"),
-            }
-        }
-        result
-    }
-
-    fn visit_binary_value(self, expr: &Expr) -> Vec<i32> {
-        match expr {
-            Expr::Binary(_) => {
-                panic!("visit_binary_value")
-            }
-            Expr::Ident(ident) => self.visit_ident(ident),
-            Expr::IntegerLiteral(literal) => self.visit_integer_literal(literal),
-        }
-    }
-
-    fn visit_integer_literal(self, expr: &IntegerLiteral) -> Vec<i32> {
-        let len = self.record_batch.num_rows();
-        let mut result: Vec<i32> = Vec::with_capacity(len);
-        for _ in 0..len {
-            result.push(expr.value);
-        }
-        result
-    }
-
-    fn visit_ident(self, expr: &Ident) -> Vec<i32> {
-        let column = self
-            .record_batch
-            .column_by_name(&expr.name)
-            .unwrap()
-            .as_any()
-            .downcast_ref::<Int32Array>()
-            .unwrap();
-        let mut result: Vec<i32> = Vec::with_capacity(column.len());
-        for i in 0..column.len() {
-            result.push(column.value(i));
-        }
-        result
-    }
-
-    fn evaluate(self, expression: &Expr) -> Vec<bool> {
-        self.visit_expression(expression)
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::execution::operators::collect::Collect;
     use crate::execution::operators::select::Select;
     use crate::logical_plan::expr::{Binary, BinaryOp, Ident, IntegerLiteral};
+    use arrow::array::Int32Array;
     use arrow::datatypes::{DataType, Field, Schema};
     use std::sync::Arc;
 
@@ -0,0 +1,649 @@
+use crate::execution::evaluator::{common_type, evaluate};
+use crate::execution::operators::join::JoinOutput;
+use crate::execution::operators::select::take_batch;
+use crate::execution::operators::{BinaryOperator, Operator, OperatorState, Side};
+use crate::execution::spill::{SpillFile, SpillWriter};
+use crate::logical_plan::expr::Expr;
+use crate::logical_plan::Join;
+use arrow::array::{Array, RecordBatch, UInt32Array};
+use arrow::compute;
+use arrow::datatypes::{DataType, SchemaRef};
+use arrow::row::{RowConverter, Rows, SortField};
+use std::collections::HashMap;
+use std::hash::Hasher;
+use std::sync::Arc;
+use twox_hash::XxHash64;
+
+/// Number of partitions each input is split into when the build side spills.
+const PARTITIONS: usize = 16;
+/// Partitions are split again at most this often, so inputs with a single huge
+/// key still finish, even if they exceed the memory limit.
+const MAX_DEPTH: usize = 3;
+
+/// Build and probe rows written to disk by the hash of their key.
+struct Partitions {
+    build: Vec<SpillWriter>,
+    probe: Vec<SpillWriter>,
+}
+
+/// Hash table over the fully consumed build input.
+struct BuildTable {
+    batch: RecordBatch,
+    rows: HashMap<Box<[u8]>, Vec<u32>>,
+    /// Build rows which matched at least one probe row.
+    matched: Vec<bool>,
+}
+
+/// Equi-join which loads the build input into a hash table on the join keys and
+/// streams the probe input through it. Rows with a NULL key never match, so they
+/// only show up in the result as unmatched rows of outer and anti joins.
+///
+/// Probe batches which arrive before the build input is complete are buffered.
+///
+/// With a memory limit, once the buffered build and probe batches grow beyond
+/// the limit, both inputs are split into partitions on disk by the hash of the
+/// key.
+/// Each pair of partitions is then joined on its own (grace hash join), which
+/// splits the partition further if it still doesn't fit.
+pub struct HashJoin<'i> {
+    output: JoinOutput<'i>,
+    build_keys: Vec<Expr>,
+    probe_keys: Vec<Expr>,
+    key_types: Vec<DataType>,
+    converter: RowConverter,
+    build_batches: Vec<RecordBatch>,
+    memory_limit: Option<usize>,
+    /// Size of the buffered build batches and pending probe batches.
+    buffered_bytes: usize,
+    /// Number of times the inputs were partitioned before, also used as hash seed.
+    depth: usize,
+    partitions: Option<Partitions>,
+    table: Option<BuildTable>,
+    build_done: bool,
+    pending_probe: Vec<Arc<RecordBatch>>,
+    probe_done: bool,
+}
+
+impl<'i> HashJoin<'i> {
+    pub(crate) fn new(
+        join: &Join,
+        left_schema: SchemaRef,
+        right_schema: SchemaRef,
+        build_side: Side,
+        successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    ) -> anyhow::Result<Self> {
+        if join.on.is_empty() {
+            anyhow::bail!("Hash join requires at least one pair of equal keys");
+        }
+        let key_types = key_types(join, &left_schema, &right_schema)?;
+        let (left_keys, right_keys) = join.on.iter().cloned().unzip();
+        let (build_keys, probe_keys) = match build_side {
+            Side::Left => (left_keys, right_keys),
+            Side::Right => (right_keys, left_keys),
+        };
+        let output = JoinOutput::new(join, left_schema, right_schema, build_side, successor);
+        Self::with_output(output, build_keys, probe_keys, key_types)
+    }
+
+    fn with_output(
+        output: JoinOutput<'i>,
+        build_keys: Vec<Expr>,
+        probe_keys: Vec<Expr>,
+        key_types: Vec<DataType>,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            output,
+            build_keys,
+            probe_keys,
+            converter: RowConverter::new(key_types.iter().cloned().map(SortField::new).collect())?,
+            key_types,
+            build_batches: Vec::new(),
+            memory_limit: None,
+            buffered_bytes: 0,
+            depth: 0,
+            partitions: None,
+            table: None,
+            build_done: false,
+            pending_probe: Vec::new(),
+            probe_done: false,
+        })
+    }
+
+    /// Limits the memory used for the buffered inputs to roughly `bytes`.
+    pub(crate) const fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    fn build(&mut self) -> anyhow::Result<()> {
+        let batch = compute::concat_batches(&self.output.build_schema(), &self.build_batches)?;
+        self.build_batches.clear();
+
+        let (keys, valid) = join_keys(&self.converter, &self.key_types, &self.build_keys, &batch)?;
+        let mut rows: HashMap<Box<[u8]>, Vec<u32>> = HashMap::new();
+        for (i, key) in keys.iter().enumerate() {
+            if valid[i] {
+                rows.entry(key.as_ref().into())
+                    .or_default()
+                    .push(u32::try_from(i)?);
+            }
+        }
+        self.table = Some(BuildTable {
+            matched: vec![false; batch.num_rows()],
+            batch,
+            rows,
+        });
+        self.build_done = true;
+        Ok(())
+    }
+
+    fn start_partitioning(&mut self) -> anyhow::Result<()> {
+        let (build_schema, probe_schema) = match self.output.build_side {
+            Side::Left => (&self.output.left_schema, &self.output.right_schema),
+            Side::Right => (&self.output.right_schema, &self.output.left_schema),
+        };
+        let mut partitions = Partitions {
+            build: Vec::with_capacity(PARTITIONS),
+            probe: Vec::with_capacity(PARTITIONS),
+        };
+        for _ in 0..PARTITIONS {
+            partitions.build.push(SpillWriter::try_new(build_schema)?);
+            partitions.probe.push(SpillWriter::try_new(probe_schema)?);
+        }
+        for batch in std::mem::take(&mut self.build_batches) {
+            self.partition(&batch, &self.build_keys, &mut partitions.build)?;
+        }
+        for batch in std::mem::take(&mut self.pending_probe) {
+            self.partition(&batch, &self.probe_keys, &mut partitions.probe)?;
+        }
+        self.buffered_bytes = 0;
+        self.partitions = Some(partitions);
+        Ok(())
+    }
+
+    /// Partitions the inputs once the buffered batches exceed the memory limit.
+    fn check_memory_limit(&mut self) -> anyhow::Result<()> {
+        if self.depth < MAX_DEPTH
+            && self
+                .memory_limit
+                .is_some_and(|limit| self.buffered_bytes > limit)
+        {
+            self.start_partitioning()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the rows of `batch` to the partitions of their `keys`. Rows with
+    /// a NULL key never match and can go to any partition.
+    fn partition(
+        &self,
+        batch: &RecordBatch,
+        keys: &[Expr],
+        writers: &mut [SpillWriter],
+    ) -> anyhow::Result<()> {
+        let (keys, _) = join_keys(&self.converter, &self.key_types, keys, batch)?;
+        let mut rows = vec![Vec::new(); writers.len()];
+        for (i, key) in keys.iter().enumerate() {
+            let mut hasher = XxHash64::with_seed(self.depth as u64);
+            hasher.write(key.as_ref());
+            let partition = usize::try_from(hasher.finish() % writers.len() as u64)?;
+            rows[partition].push(u32::try_from(i)?);
+        }
+        for (writer, rows) in writers.iter_mut().zip(rows) {
+            if !rows.is_empty() {
+                writer.write(&take_batch(batch, &UInt32Array::from(rows))?)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Joins each pair of build and probe partitions.
+    fn join_partitions(&mut self, partitions: Partitions) -> anyhow::Result<()> {
+        let build = finish_all(partitions.build)?;
+        let probe = finish_all(partitions.probe)?;
+        let (build_side, probe_side) = (self.output.build_side, self.output.probe_side());
+        for (build, probe) in build.iter().zip(&probe) {
+            if build.num_rows() == 0 && probe.num_rows() == 0 {
+                continue;
+            }
+            let mut join = HashJoin::with_output(
+                self.output.partial(),
+                self.build_keys.clone(),
+                self.probe_keys.clone(),
+                self.key_types.clone(),
+            )?;
+            join.memory_limit = self.memory_limit;
+            join.depth = self.depth + 1;
+            for batch in build.read()? {
+                join.execute(build_side, Arc::new(batch?))?;
+            }
+            join.all_inputs_received(build_side)?;
+            for batch in probe.read()? {
+                if join.execute(probe_side, Arc::new(batch?))? == OperatorState::Finished {
+                    break;
+                }
+            }
+            join.all_inputs_received(probe_side)?;
+        }
+        Ok(())
+    }
+
+    fn probe(&mut self, probe: &RecordBatch) -> anyhow::Result<OperatorState> {
+        let mut table = self.table.take().expect("build side is complete");
+        let state = self.probe_table(&mut table, probe);
+        self.table = Some(table);
+        state
+    }
+
+    fn probe_table(
+        &mut self,
+        table: &mut BuildTable,
+        probe: &RecordBatch,
+    ) -> anyhow::Result<OperatorState> {
+        let (keys, valid) = join_keys(&self.converter, &self.key_types, &self.probe_keys, probe)?;
+        let mut build_idx = Vec::new();
+        let mut probe_idx = Vec::new();
+        for (i, key) in keys.iter().enumerate() {
+            if let Some(matches) = valid[i].then(|| table.rows.get(key.as_ref())).flatten() {
+                build_idx.extend_from_slice(matches);
+                probe_idx.resize(build_idx.len(), u32::try_from(i)?);
+            }
+        }
+        let (build_idx, probe_idx) = self.output.filter_pairs(
+            &table.batch,
+            UInt32Array::from(build_idx),
+            probe,
+            UInt32Array::from(probe_idx),
+        )?;
+        let state = self.output.probe_matches(
+            &table.batch,
+            &mut table.matched,
+            probe,
+            &build_idx,
+            &probe_idx,
+        )?;
+
+        if table.batch.num_rows() == 0 && !self.output.preserves(self.output.probe_side()) {
+            // No probe row can be part of the result anymore.
+            return Ok(OperatorState::Finished);
+        }
+        Ok(state)
+    }
+
+    /// Emits build rows which are part of the result independent of the probe
+    /// rows, then finishes the join.
+    fn finish(&mut self) -> anyhow::Result<()> {
+        if let Some(partitions) = self.partitions.take() {
+            self.join_partitions(partitions)?;
+        } else {
+            let table = self.table.take().expect("build side is complete");
+            self.output.build_rows(&table.batch, &table.matched)?;
+        }
+        self.output.finish()
+    }
+}
+
+impl BinaryOperator for HashJoin<'_> {
+    fn execute(&mut self, side: Side, input: Arc<RecordBatch>) -> anyhow::Result<OperatorState> {
+        if side == self.output.build_side {
+            if self.build_done {
+                anyhow::bail!("Build input of the hash join received after it was complete");
+            }
+            if let Some(mut partitions) = self.partitions.take() {
+                let result = self.partition(&input, &self.build_keys, &mut partitions.build);
+                self.partitions = Some(partitions);
+                result?;
+                return Ok(OperatorState::NeedMoreInput);
+            }
+            self.buffered_bytes += input.get_array_memory_size();
+            self.build_batches.push(input.as_ref().clone());
+            self.check_memory_limit()?;
+            Ok(OperatorState::NeedMoreInput)
+        } else if let Some(mut partitions) = self.partitions.take() {
+            let result = self.partition(&input, &self.probe_keys, &mut partitions.probe);
+            self.partitions = Some(partitions);
+            result?;
+            Ok(OperatorState::NeedMoreInput)
+        } else if !self.build_done {
+            self.buffered_bytes += input.get_array_memory_size();
+            self.pending_probe.push(input);
+            self.check_memory_limit()?;
+            Ok(OperatorState::NeedMoreInput)
+        } else {
+            self.probe(&input)
+        }
+    }
+
+    fn all_inputs_received(&mut self, side: Side) -> anyhow::Result<()> {
+        if side == self.output.build_side {
+            if self.partitions.is_some() {
+                self.build_done = true;
+            } else {
+                self.build()?;
+            }
+            let probe_side = self.output.probe_side();
+            for batch in std::mem::take(&mut self.pending_probe) {
+                if self.execute(probe_side, batch)? == OperatorState::Finished {
+                    break;
+                }
+            }
+        } else {
+            self.probe_done = true;
+        }
+        if self.probe_done && self.build_done {
+            self.finish()?;
+        }
+        Ok(())
+    }
+}
+
+/// Common types the left and right keys of `join` are compared as.
+pub(super) fn key_types(
+    join: &Join,
+    left_schema: &SchemaRef,
+    right_schema: &SchemaRef,
+) -> anyhow::Result<Vec<DataType>> {
+    let mut key_types = Vec::with_capacity(join.on.len());
+    for (left, right) in &join.on {
+        let left = left.to_field(left_schema)?;
+        let right = right.to_field(right_schema)?;
+        key_types.push(common_type(left.data_type(), right.data_type())?);
+    }
+    Ok(key_types)
+}
+
+/// Row-encoded join keys and whether each row's key is free of NULLs.
+pub(super) fn join_keys(
+    converter: &RowConverter,
+    key_types: &[DataType],
+    exprs: &[Expr],
+    batch: &RecordBatch,
+) -> anyhow::Result<(Rows, Vec<bool>)> {
+    let mut valid = vec![true; batch.num_rows()];
+    let mut columns = Vec::with_capacity(exprs.len());
+    for (expr, data_type) in exprs.iter().zip(key_types) {
+        let column = compute::cast(&evaluate(expr, batch)?, data_type)?;
+        if let Some(nulls) = column.nulls() {
+            for (valid, is_valid) in valid.iter_mut().zip(nulls.iter()) {
+                *valid &= is_valid;
+            }
+        }
+        columns.push(column);
+    }
+    Ok((converter.convert_columns(&columns)?, valid))
+}
+
+fn finish_all(writers: Vec<SpillWriter>) -> anyhow::Result<Vec<SpillFile>> {
+    writers.into_iter().map(SpillWriter::finish).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::DummyCatalog;
+    use crate::execution::operators::collect::Collect;
+    use crate::execution::operators::input_ports;
+    use crate::logical_plan::LogicalPlan;
+    use crate::parser::sql_parser::parse_sql_query;
+    use arrow::array::{Decimal128Array, Int32Array, Int64Array, StringArray, UInt64Array};
+    use arrow::datatypes::{Field, Schema};
+    use arrow::util::display::{ArrayFormatter, FormatOptions};
+
+    fn format_rows(batches: &[Arc<RecordBatch>]) -> anyhow::Result<Vec<String>> {
+        let options = FormatOptions::default().with_null("NULL");
+        let mut rows = Vec::new();
+        for batch in batches {
+            let formatters = batch
+                .columns()
+                .iter()
+                .map(|column| ArrayFormatter::try_new(column, &options))
+                .collect::<Result<Vec<_>, _>>()?;
+            for row in 0..batch.num_rows() {
+                let values = formatters.iter().map(|f| f.value(row).to_string());
+                rows.push(values.collect::<Vec<_>>().join(","));
+            }
+        }
+        rows.sort();
+        Ok(rows)
+    }
+
+    #[test]
+    fn test_hash_join_types() -> anyhow::Result<()> {
+        let left_schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, true),
+            Field::new("a", DataType::Utf8, false),
+        ]));
+        let right_schema = Arc::new(Schema::new(vec![
+            Field::new("rid", DataType::Int64, true),
+            Field::new("b", DataType::Utf8, false),
+        ]));
+        let mut catalog = DummyCatalog::new();
+        catalog.add_table("l", left_schema.clone());
+        catalog.add_table("r", right_schema.clone());
+
+        let left = [
+            RecordBatch::try_new(
+                left_schema.clone(),
+                vec![
+                    Arc::new(Int32Array::from(vec![Some(1), Some(2)])),
+                    Arc::new(StringArray::from(vec!["x", "y"])),
+                ],
+            )?,
+            RecordBatch::try_new(
+                left_schema.clone(),
+                vec![
+                    Arc::new(Int32Array::from(vec![None, Some(3)])),
+                    Arc::new(StringArray::from(vec!["z", "w"])),
+                ],
+            )?,
+        ];
+        let right = RecordBatch::try_new(
+            right_schema.clone(),
+            vec![
+                Arc::new(Int64Array::from(vec![
+                    Some(1),
+                    Some(1),
+                    Some(2),
+                    None,
+                    Some(4),
+                ])),
+                Arc::new(StringArray::from(vec!["x", "q", "p", "n", "m"])),
+            ],
+        )?;
+
+        let inner = vec!["1,x,1,q", "2,y,2,p"];
+        let left_only = vec!["3,w,NULL,NULL", "NULL,z,NULL,NULL"];
+        let right_only = vec!["NULL,NULL,1,x", "NULL,NULL,4,m", "NULL,NULL,NULL,n"];
+        let cases = [
+            ("JOIN", vec![inner.clone()]),
+            ("LEFT JOIN", vec![inner.clone(), left_only.clone()]),
+            ("RIGHT JOIN", vec![inner.clone(), right_only.clone()]),
+            ("FULL JOIN", vec![inner, left_only, right_only]),
+            ("LEFT SEMI JOIN", vec![vec!["1,x", "2,y"]]),
+            ("LEFT ANTI JOIN", vec![vec!["3,w", "NULL,z"]]),
+        ];
+
+        for (join, expected) in cases {
+            let sql = format!("SELECT * FROM l {join} r ON id = rid AND a <> b");
+            let dag = parse_sql_query(&sql, &catalog)?;
+            let LogicalPlan::Join(join) = dag.get_node(2) else {
+                panic!("expected join node");
+            };
+            let mut expected = expected.concat();
+            expected.sort_unstable();
+
+            for build_side in [Side::Left, Side::Right] {
+                let mut res = Vec::new();
+                {
+                    let collect = Box::new(Collect::new(&mut res));
+                    let hash_join = HashJoin::new(
+                        join,
+                        left_schema.clone(),
+                        right_schema.clone(),
+                        build_side,
+                        collect,
+                    )?;
+                    let (mut left_input, mut right_input) = input_ports(hash_join);
+                    for batch in &left {
+                        left_input.execute(Arc::new(batch.clone()))?;
+                    }
+                    left_input.all_inputs_received()?;
+                    right_input.execute(Arc::new(right.clone()))?;
+                    right_input.all_inputs_received()?;
+                }
+                assert_eq!(format_rows(&res)?, expected, "{} {build_side:?}", join.kind);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_join_key_types() -> anyhow::Result<()> {
+        let left_schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::UInt64, false),
+            Field::new("price", DataType::Decimal128(10, 2), false),
+        ]));
+        let right_schema = Arc::new(Schema::new(vec![
+            Field::new("rid", DataType::Int64, false),
+            Field::new("amount", DataType::Int32, false),
+        ]));
+        let mut catalog = DummyCatalog::new();
+        catalog.add_table("l", left_schema.clone());
+        catalog.add_table("r", right_schema.clone());
+        let left = RecordBatch::try_new(
+            left_schema.clone(),
+            vec![
+                Arc::new(UInt64Array::from(vec![u64::MAX, 1, 2])),
+                Arc::new(
+                    Decimal128Array::from(vec![200, 150, 300]).with_precision_and_scale(10, 2)?,
+                ),
+            ],
+        )?;
+        let right = RecordBatch::try_new(
+            right_schema.clone(),
+            vec![
+                Arc::new(Int64Array::from(vec![-1, 1, 2])),
+                Arc::new(Int32Array::from(vec![2, 2, 3])),
+            ],
+        )?;
+
+        // UInt64 keys match signed keys without loss, and decimals integers.
+        for (on, expected) in [
+            ("id = rid", vec!["1,1.50,1,2", "2,3.00,2,3"]),
+            (
+                "price = amount",
+                vec![
+                    "18446744073709551615,2.00,-1,2",
+                    "18446744073709551615,2.00,1,2",
+                    "2,3.00,2,3",
+                ],
+            ),
+        ] {
+            let dag = parse_sql_query(&format!("SELECT * FROM l JOIN r ON {on}"), &catalog)?;
+            let LogicalPlan::Join(join) = dag.get_node(2) else {
+                panic!("expected join node");
+            };
+            let mut res = Vec::new();
+            {
+                let collect = Box::new(Collect::new(&mut res));
+                let hash_join = HashJoin::new(
+                    join,
+                    left_schema.clone(),
+                    right_schema.clone(),
+                    Side::Right,
+                    collect,
+                )?;
+                let (mut left_input, mut right_input) = input_ports(hash_join);
+                right_input.execute(Arc::new(right.clone()))?;
+                right_input.all_inputs_received()?;
+                left_input.execute(Arc::new(left.clone()))?;
+                left_input.all_inputs_received()?;
+            }
+            assert_eq!(format_rows(&res)?, expected, "{on}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_grace_hash_join() -> anyhow::Result<()> {
+        let left_schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, true),
+            Field::new("a", DataType::Int64, false),
+        ]));
+        let right_schema = Arc::new(Schema::new(vec![
+            Field::new("rid", DataType::Int64, true),
+            Field::new("b", DataType::Int64, false),
+        ]));
+        let mut catalog = DummyCatalog::new();
+        catalog.add_table("l", left_schema.clone());
+        catalog.add_table("r", right_schema.clone());
+        let dag = parse_sql_query(
+            "SELECT * FROM l FULL JOIN r ON id = rid AND a < b",
+            &catalog,
+        )?;
+        let LogicalPlan::Join(join) = dag.get_node(2) else {
+            panic!("expected join node");
+        };
+
+        let batches = |schema: &SchemaRef, rows: i64, keys: i64| {
+            (0..rows)
+                .step_by(1000)
+                .map(|start| {
+                    let ids = (start..start + 1000)
+                        .map(|i| (i % 97 != 0).then_some(i % keys))
+                        .collect::<Vec<_>>();
+                    let values = (start..start + 1000).map(|i| i % 13).collect::<Vec<_>>();
+                    RecordBatch::try_new(
+                        schema.clone(),
+                        vec![
+                            Arc::new(Int64Array::from(ids)),
+                            Arc::new(Int64Array::from(values)),
+                        ],
+                    )
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let left = batches(&left_schema, 20_000, 6_000)?;
+        let right = batches(&right_schema, 10_000, 8_000)?;
+
+        for build_side in [Side::Left, Side::Right] {
+            let mut results = Vec::new();
+            for memory_limit in [None, Some(16 * 1024)] {
+                let mut res = Vec::new();
+                {
+                    let collect = Box::new(Collect::new(&mut res));
+                    let mut hash_join = HashJoin::new(
+                        join,
+                        left_schema.clone(),
+                        right_schema.clone(),
+                        build_side,
+                        collect,
+                    )?;
+                    if let Some(bytes) = memory_limit {
+                        hash_join = hash_join.with_memory_limit(bytes);
+                    }
+                    for batch in &left {
+                        hash_join.execute(Side::Left, Arc::new(batch.clone()))?;
+                    }
+                    // Probe rows which arrive first are partitioned as well.
+                    if memory_limit.is_some() {
+                        assert!(hash_join.pending_probe.is_empty());
+                    }
+                    hash_join.all_inputs_received(Side::Left)?;
+                    for batch in &right {
+                        hash_join.execute(Side::Right, Arc::new(batch.clone()))?;
+                    }
+                    assert_eq!(hash_join.partitions.is_some(), memory_limit.is_some());
+                    hash_join.all_inputs_received(Side::Right)?;
+                }
+                results.push(format_rows(&res)?);
+            }
+            assert!(results[0].len() > 20_000);
+            assert_eq!(results[0], results[1], "{build_side:?}");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use crate::catalog::IpcReader;
+use crate::execution::operators::{Operator, OperatorState};
+use arrow::record_batch::RecordBatch;
+use std::sync::Arc;
+
+/// Reads the batches of an Arrow IPC file without copying them, only the
+/// columns of `projection` if given. Batches larger than the chunk size are
+/// split into slices.
+pub struct IpcScan<'i> {
+    successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    projection: Option<Vec<usize>>,
+}
+
+impl<'i> IpcScan<'i> {
+    pub(crate) fn new(
+        projection: Option<Vec<usize>>,
+        successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    ) -> Self {
+        Self {
+            successor,
+            projection,
+        }
+    }
+}
+
+impl Operator<(String, usize)> for IpcScan<'_> {
+    fn execute(&mut self, input: (String, usize)) -> anyhow::Result<OperatorState> {
+        let (file_path, chunk_size) = input;
+        let chunk_size = chunk_size.max(1);
+        let reader = IpcReader::try_new(&file_path, self.projection.clone())?;
+
+        for b in reader {
+            let b = b?;
+            for offset in (0..b.num_rows()).step_by(chunk_size) {
+                let len = chunk_size.min(b.num_rows() - offset);
+                if self.successor.execute(Arc::new(b.slice(offset, len)))?
+                    == OperatorState::Finished
+                {
+                    return Ok(OperatorState::Finished);
+                }
+            }
+        }
+
+        Ok(OperatorState::NeedMoreInput)
+    }
+
+    fn all_inputs_received(&mut self) -> anyhow::Result<()> {
+        self.successor.all_inputs_received()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::{Catalog, DummyCatalog};
+    use crate::execution::operators::collect::Collect;
+    use arrow::array::RecordBatchReader;
+    use arrow::ipc::writer::FileWriter;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::fs::File;
+
+    #[test]
+    fn test_ipc_scan() -> anyhow::Result<()> {
+        let parquet = File::open("samples/sample-data/parquet/userdata1.parquet")?;
+        let reader = ParquetRecordBatchReaderBuilder::try_new(parquet)?
+            .with_batch_size(1000)
+            .build()?;
+        let path = std::env::temp_dir().join(format!("inudb-scan-{}.arrow", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let mut writer = FileWriter::try_new(File::create(&path)?, &reader.schema())?;
+        for batch in reader {
+            writer.write(&batch?)?;
+        }
+        writer.finish()?;
+
+        let mut catalog = DummyCatalog::new();
+        catalog.register_parquet("parquet", "samples/sample-data/parquet/userdata1.parquet")?;
+        catalog.register_ipc("ipc", &path)?;
+        assert_eq!(catalog.get_schema("ipc")?, catalog.get_schema("parquet")?);
+
+        let mut res = Vec::new();
+        {
+            let collect = Box::new(Collect::new(&mut res));
+            let mut scan = IpcScan::new(Some(vec![1, 2]), collect);
+            scan.execute((path.clone(), 400))?;
+            scan.all_inputs_received()?;
+        }
+        std::fs::remove_file(&path)?;
+
+        assert_eq!(
+            res.iter().map(|b| b.num_rows()).collect::<Vec<_>>(),
+            [400, 400, 200]
+        );
+        let schema = res[0].schema();
+        assert_eq!(schema.field(0).name(), "id");
+        assert_eq!(schema.field(1).name(), "first_name");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,285 @@
+use crate::execution::evaluator::evaluate;
+use crate::execution::operators::{Operator, OperatorState, Side};
+use crate::logical_plan::expr::{Binary, BinaryOp, Expr};
+use crate::logical_plan::{Join, JoinType};
+use arrow::array::{new_null_array, Array, ArrayRef, AsArray, RecordBatch, UInt32Array};
+use arrow::compute;
+use arrow::datatypes::{Schema, SchemaRef};
+use std::sync::Arc;
+
+/// Produces the result rows of a join from matching pairs of rows, shared by
+/// the join operators. One input is buffered completely or in groups (the
+/// build side), the other one is streamed through (the probe side).
+pub struct JoinOutput<'i> {
+    successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    pub join_type: JoinType,
+    pub build_side: Side,
+    filter: Option<Expr>,
+    pub left_schema: SchemaRef,
+    pub right_schema: SchemaRef,
+    /// Columns of both inputs, the schema `filter` is evaluated on.
+    combined_schema: SchemaRef,
+    schema: SchemaRef,
+}
+
+impl<'i> JoinOutput<'i> {
+    pub fn new(
+        join: &Join,
+        left_schema: SchemaRef,
+        right_schema: SchemaRef,
+        build_side: Side,
+        successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    ) -> Self {
+        let combined_schema = Arc::new(Schema::new(
+            left_schema
+                .fields()
+                .iter()
+                .chain(right_schema.fields())
+                .cloned()
+                .collect::<Vec<_>>(),
+        ));
+        Self {
+            successor,
+            join_type: join.kind,
+            build_side,
+            filter: join.filter.as_deref().cloned(),
+            left_schema,
+            right_schema,
+            combined_schema,
+            schema: join.schema.clone(),
+        }
+    }
+
+    /// Also evaluates the equality of the `on` keys as part of the filter, for
+    /// joins which don't look up rows by their keys.
+    pub fn filter_on_keys(mut self, on: &[(Expr, Expr)]) -> Self {
+        let mut conjuncts = on
+            .iter()
+            .map(|(left, right)| {
+                Expr::Binary(Binary {
+                    lhs: Box::new(left.clone()),
+                    op: BinaryOp::Eq,
+                    rhs: Box::new(right.clone()),
+                })
+            })
+            .collect::<Vec<_>>();
+        conjuncts.extend(self.filter.take());
+        self.filter = Expr::conjunction(conjuncts);
+        self
+    }
+
+    /// Output with the same configuration which forwards its rows to this
+    /// output's successor, but doesn't finish it.
+    pub fn partial(&mut self) -> JoinOutput<'_> {
+        JoinOutput {
+            successor: Box::new(PartialOutput {
+                successor: self.successor.as_mut(),
+            }),
+            join_type: self.join_type,
+            build_side: self.build_side,
+            filter: self.filter.clone(),
+            left_schema: self.left_schema.clone(),
+            right_schema: self.right_schema.clone(),
+            combined_schema: self.combined_schema.clone(),
+            schema: self.schema.clone(),
+        }
+    }
+
+    pub const fn probe_side(&self) -> Side {
+        match self.build_side {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+
+    pub fn build_schema(&self) -> SchemaRef {
+        match self.build_side {
+            Side::Left => self.left_schema.clone(),
+            Side::Right => self.right_schema.clone(),
+        }
+    }
+
+    /// Whether rows of `side` without a match are part of the result.
+    pub fn preserves(&self, side: Side) -> bool {
+        match self.join_type {
+            JoinType::Left | JoinType::Anti => side == Side::Left,
+            JoinType::Right => side == Side::Right,
+            JoinType::Full => true,
+            JoinType::Inner | JoinType::Semi | JoinType::Cross => false,
+        }
+    }
+
+    pub const fn outputs_right(&self) -> bool {
+        !matches!(self.join_type, JoinType::Semi | JoinType::Anti)
+    }
+
+    /// Keeps the candidate pairs of build and probe rows for which the join
+    /// filter holds.
+    pub fn filter_pairs(
+        &self,
+        build: &RecordBatch,
+        build_idx: UInt32Array,
+        probe: &RecordBatch,
+        probe_idx: UInt32Array,
+    ) -> anyhow::Result<(UInt32Array, UInt32Array)> {
+        let Some(filter) = &self.filter else {
+            return Ok((build_idx, probe_idx));
+        };
+        let (left, right) = match self.build_side {
+            Side::Left => ((build, &build_idx), (probe, &probe_idx)),
+            Side::Right => ((probe, &probe_idx), (build, &build_idx)),
+        };
+        let mut columns = take_columns(left.0, left.1)?;
+        columns.extend(take_columns(right.0, right.1)?);
+        let candidates = RecordBatch::try_new(self.combined_schema.clone(), columns)?;
+        let mask = evaluate(filter, &candidates)?;
+        // Pairs for which the filter is NULL don't match either.
+        let mask = match mask.as_boolean() {
+            mask if mask.null_count() > 0 => compute::prep_null_mask_filter(mask),
+            mask => mask.clone(),
+        };
+        Ok((
+            compute::filter(&build_idx, &mask)?.as_primitive().clone(),
+            compute::filter(&probe_idx, &mask)?.as_primitive().clone(),
+        ))
+    }
+
+    /// Emits the result rows of `probe` given its matching pairs with rows of
+    /// `build`, which must already have passed the filter, and marks the
+    /// matched build rows.
+    pub fn probe_matches(
+        &mut self,
+        build: &RecordBatch,
+        build_matched: &mut [bool],
+        probe: &RecordBatch,
+        build_idx: &UInt32Array,
+        probe_idx: &UInt32Array,
+    ) -> anyhow::Result<OperatorState> {
+        for i in build_idx.values() {
+            build_matched[*i as usize] = true;
+        }
+        let mut probe_matched = vec![false; probe.num_rows()];
+        for i in probe_idx.values() {
+            probe_matched[*i as usize] = true;
+        }
+
+        let probe_side = self.probe_side();
+        if self.outputs_right() {
+            if self.preserves(probe_side) {
+                // Unmatched probe rows are padded with NULLs on the build side.
+                let unmatched = rows_where(&probe_matched, false)?;
+                let mut build_idx = build_idx.iter().collect::<Vec<_>>();
+                build_idx.resize(build_idx.len() + unmatched.len(), None);
+                let probe_idx = probe_idx.values().iter().chain(unmatched.values());
+                let probe_idx = UInt32Array::from_iter_values(probe_idx.copied());
+                let build_idx = UInt32Array::from(build_idx);
+                self.emit(build, &build_idx, Some(probe), &probe_idx)
+            } else {
+                self.emit(build, build_idx, Some(probe), probe_idx)
+            }
+        } else if probe_side == Side::Left {
+            // Semi and anti joins output each matching or non-matching left row once.
+            let rows = rows_where(&probe_matched, self.join_type == JoinType::Semi)?;
+            self.emit(build, &rows, Some(probe), &rows)
+        } else {
+            Ok(OperatorState::NeedMoreInput)
+        }
+    }
+
+    /// Emits the build rows which are part of the result independent of the
+    /// probe rows, once no more probe rows can match them.
+    pub fn build_rows(
+        &mut self,
+        build: &RecordBatch,
+        build_matched: &[bool],
+    ) -> anyhow::Result<OperatorState> {
+        if self.outputs_right() && self.preserves(self.build_side) {
+            let build_idx = rows_where(build_matched, false)?;
+            let probe_idx = UInt32Array::new_null(build_idx.len());
+            self.emit(build, &build_idx, None, &probe_idx)
+        } else if !self.outputs_right() && self.build_side == Side::Left {
+            let rows = rows_where(build_matched, self.join_type == JoinType::Semi)?;
+            self.emit(build, &rows, None, &rows)
+        } else {
+            Ok(OperatorState::NeedMoreInput)
+        }
+    }
+
+    /// Emits the build rows at `build_idx` next to the probe rows at
+    /// `probe_idx`. NULL indices and a missing probe batch produce NULLs.
+    fn emit(
+        &mut self,
+        build: &RecordBatch,
+        build_idx: &UInt32Array,
+        probe: Option<&RecordBatch>,
+        probe_idx: &UInt32Array,
+    ) -> anyhow::Result<OperatorState> {
+        if build_idx.is_empty() {
+            return Ok(OperatorState::NeedMoreInput);
+        }
+        let build = Some(build);
+        let (left, right) = match self.build_side {
+            Side::Left => ((build, build_idx), (probe, probe_idx)),
+            Side::Right => ((probe, probe_idx), (build, build_idx)),
+        };
+
+        let mut columns = match left.0 {
+            Some(batch) => take_columns(batch, left.1)?,
+            None => null_columns(&self.left_schema, left.1.len()),
+        };
+        if self.outputs_right() {
+            columns.extend(match right.0 {
+                Some(batch) => take_columns(batch, right.1)?,
+                None => null_columns(&self.right_schema, right.1.len()),
+            });
+        }
+        let batch = RecordBatch::try_new(self.schema.clone(), columns)?;
+        self.successor.execute(Arc::new(batch))
+    }
+
+    pub fn finish(&mut self) -> anyhow::Result<()> {
+        self.successor.all_inputs_received()
+    }
+}
+
+/// Forwards rows to the successor of another output without finishing it.
+struct PartialOutput<'a, 'i> {
+    successor: &'a mut (dyn Operator<Arc<RecordBatch>> + 'i),
+}
+
+impl Operator<Arc<RecordBatch>> for PartialOutput<'_, '_> {
+    fn execute(&mut self, input: Arc<RecordBatch>) -> anyhow::Result<OperatorState> {
+        self.successor.execute(input)
+    }
+
+    fn all_inputs_received(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+fn take_columns(batch: &RecordBatch, indices: &UInt32Array) -> anyhow::Result<Vec<ArrayRef>> {
+    Ok(batch
+        .columns()
+        .iter()
+        .map(|column| compute::take(column, indices, None))
+        .collect::<Result<_, _>>()?)
+}
+
+fn null_columns(schema: &Schema, len: usize) -> Vec<ArrayRef> {
+    schema
+        .fields()
+        .iter()
+        .map(|field| new_null_array(field.data_type(), len))
+        .collect()
+}
+
+/// Indices of the rows whose flag equals `value`.
+fn rows_where(flags: &[bool], value: bool) -> anyhow::Result<UInt32Array> {
+    let rows = flags
+        .iter()
+        .enumerate()
+        .filter(|(_, flag)| **flag == value)
+        .map(|(i, _)| u32::try_from(i))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(UInt32Array::from(rows))
+}
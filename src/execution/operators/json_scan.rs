@@ -0,0 +1,141 @@
+use crate::catalog::JsonOptions;
+use crate::execution::operators::{Operator, OperatorState};
+use arrow::datatypes::SchemaRef;
+use arrow::record_batch::RecordBatch;
+use std::sync::Arc;
+
+/// Reads the objects of a newline-delimited JSON file with `schema`, typically the one the catalog
+/// inferred when the file was registered.
+pub struct JsonScan<'i> {
+    successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    schema: SchemaRef,
+    options: JsonOptions,
+}
+
+impl<'i> JsonScan<'i> {
+    pub(crate) fn new(
+        schema: SchemaRef,
+        options: JsonOptions,
+        successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    ) -> Self {
+        Self {
+            successor,
+            schema,
+            options,
+        }
+    }
+}
+
+impl Operator<(String, usize)> for JsonScan<'_> {
+    fn execute(&mut self, input: (String, usize)) -> anyhow::Result<OperatorState> {
+        let (file_path, chunk_size) = input;
+        let reader = self
+            .options
+            .reader(&file_path, self.schema.clone(), chunk_size)?;
+
+        // Lines are decoded as batches are pulled, so stopping here skips the rest of the file.
+        for b in reader {
+            if self.successor.execute(Arc::new(b?))? == OperatorState::Finished {
+                return Ok(OperatorState::Finished);
+            }
+        }
+
+        Ok(OperatorState::NeedMoreInput)
+    }
+
+    fn all_inputs_received(&mut self) -> anyhow::Result<()> {
+        self.successor.all_inputs_received()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::{Catalog, DummyCatalog, TableSource};
+    use crate::execution::operators::collect::Collect;
+    use arrow::array::{Array, AsArray};
+    use arrow::datatypes::{DataType, Field, Fields, Int64Type, Schema};
+
+    fn scan(
+        catalog: &DummyCatalog,
+        table: &str,
+        chunk_size: usize,
+    ) -> anyhow::Result<Vec<Arc<RecordBatch>>> {
+        let Some(TableSource::Json { path, options }) = catalog.get_source(table) else {
+            anyhow::bail!("Table {table} is not a JSON file");
+        };
+        let mut res = Vec::new();
+        {
+            let collect = Box::new(Collect::new(&mut res));
+            let schema = catalog.get_schema(table)?;
+            let mut scan = JsonScan::new(schema, options.clone(), collect);
+            scan.execute((path.clone(), chunk_size))?;
+            scan.all_inputs_received()?;
+        }
+        Ok(res)
+    }
+
+    #[test]
+    fn test_json_scan() -> anyhow::Result<()> {
+        let path = "samples/sample-data/json/events.ndjson";
+        let mut catalog = DummyCatalog::new();
+        catalog.register_json("events", path, JsonOptions::default())?;
+
+        // Nested objects and arrays are inferred as structs and lists, and
+        // the fields are sorted by name.
+        let schema = catalog.get_schema("events")?;
+        let user = Fields::from(vec![
+            Field::new("age", DataType::Int64, true),
+            Field::new("name", DataType::Utf8, true),
+        ]);
+        let tags = Field::new("item", DataType::Utf8, true);
+        assert_eq!(
+            schema.field_with_name("user")?.data_type(),
+            &DataType::Struct(user)
+        );
+        assert_eq!(
+            schema.field_with_name("tags")?.data_type(),
+            &DataType::List(Arc::new(tags))
+        );
+        assert_eq!(
+            schema.field_with_name("duration")?.data_type(),
+            &DataType::Float64
+        );
+
+        let res = scan(&catalog, "events", 3)?;
+        assert_eq!(res.iter().map(|b| b.num_rows()).collect::<Vec<_>>(), [3, 1]);
+        let users = res[0].column_by_name("user").unwrap().as_struct();
+        assert_eq!(users.null_count(), 1);
+        assert_eq!(
+            users
+                .column_by_name("name")
+                .unwrap()
+                .as_string::<i32>()
+                .value(1),
+            "Bob"
+        );
+        let tags = res[0].column_by_name("tags").unwrap().as_list::<i32>();
+        let lengths = (0..3).map(|i| tags.value_length(i)).collect::<Vec<_>>();
+        assert_eq!(lengths, [2, 0, 1]);
+
+        // An explicit schema reads only its fields.
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("kind", DataType::Utf8, false),
+        ]));
+        let options = JsonOptions {
+            schema: Some(schema.clone()),
+            ..JsonOptions::default()
+        };
+        catalog.register_json("kinds", path, options)?;
+        assert_eq!(catalog.get_schema("kinds")?, schema);
+        let res = scan(&catalog, "kinds", 1024)?;
+        assert_eq!(res[0].num_columns(), 2);
+        assert_eq!(
+            res[0].column(0).as_primitive::<Int64Type>().values(),
+            &[1, 2, 3, 4]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+use crate::execution::operators::{Operator, OperatorState};
+use arrow::array::RecordBatch;
+use std::sync::Arc;
+
+/// Skips the first `skip` rows and forwards at most `fetch` rows afterwards.
+/// Once `fetch` rows were forwarded it reports `OperatorState::Finished`, so the
+/// producers upstream can stop reading.
+pub struct Limit<'i> {
+    successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    skip: usize,
+    fetch: Option<usize>,
+}
+
+impl<'i> Limit<'i> {
+    #[allow(clippy::missing_const_for_fn)]
+    pub(crate) fn new(
+        skip: usize,
+        fetch: Option<usize>,
+        successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    ) -> Self {
+        Self {
+            successor,
+            skip,
+            fetch,
+        }
+    }
+}
+
+impl Operator<Arc<RecordBatch>> for Limit<'_> {
+    fn execute(&mut self, input: Arc<RecordBatch>) -> anyhow::Result<OperatorState> {
+        if self.fetch == Some(0) {
+            return Ok(OperatorState::Finished);
+        }
+
+        let rows = input.num_rows();
+        if rows <= self.skip {
+            self.skip -= rows;
+            return Ok(OperatorState::NeedMoreInput);
+        }
+
+        let offset = std::mem::take(&mut self.skip);
+        let mut len = rows - offset;
+        if let Some(fetch) = self.fetch.as_mut() {
+            len = len.min(*fetch);
+            *fetch -= len;
+        }
+
+        let batch = if offset == 0 && len == rows {
+            input
+        } else {
+            Arc::new(input.slice(offset, len))
+        };
+        let state = self.successor.execute(batch)?;
+
+        if self.fetch == Some(0) {
+            Ok(OperatorState::Finished)
+        } else {
+            Ok(state)
+        }
+    }
+
+    fn all_inputs_received(&mut self) -> anyhow::Result<()> {
+        self.successor.all_inputs_received()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::operators::collect::Collect;
+    use crate::execution::operators::scan::Scan;
+    use arrow::array::AsArray;
+    use arrow::datatypes::Int32Type;
+
+    #[test]
+    fn test_limit_stops_scan() -> anyhow::Result<()> {
+        let mut res = Vec::new();
+
+        let state = {
+            let collect = Box::new(Collect::new(&mut res));
+            let limit = Box::new(Limit::new(150, Some(120), collect));
+            let mut scan = Scan::new(limit);
+            let state = scan.execute((
+                "samples/sample-data/parquet/userdata1.parquet".to_string(),
+                100,
+            ))?;
+            scan.all_inputs_received()?;
+            state
+        };
+
+        assert_eq!(state, OperatorState::Finished);
+        assert_eq!(
+            res.iter().map(|b| b.num_rows()).collect::<Vec<_>>(),
+            vec![50, 70]
+        );
+        let ids = res[0]
+            .column_by_name("id")
+            .unwrap()
+            .as_primitive::<Int32Type>();
+        assert_eq!(ids.value(0), 151);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,352 @@
+use crate::execution::operators::hash_join::{join_keys, key_types};
+use crate::execution::operators::join::JoinOutput;
+use crate::execution::operators::{BinaryOperator, Operator, OperatorState, Side};
+use crate::logical_plan::expr::Expr;
+use crate::logical_plan::Join;
+use arrow::array::{RecordBatch, UInt32Array};
+use arrow::compute;
+use arrow::datatypes::{DataType, SchemaRef};
+use arrow::row::{OwnedRow, RowConverter, Rows, SortField};
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+/// Buffered rows of one input of the merge join, from `start` on.
+struct MergeInput {
+    batch: RecordBatch,
+    keys: Rows,
+    /// Whether each row's key is free of NULLs.
+    valid: Vec<bool>,
+    start: usize,
+    /// Largest key of the rows which were already joined.
+    last_key: Option<OwnedRow>,
+    done: bool,
+}
+
+impl MergeInput {
+    const fn len(&self) -> usize {
+        self.valid.len()
+    }
+
+    /// End of the group of rows with the same key as the row at `start`.
+    fn group_end(&self, start: usize) -> usize {
+        let key = self.keys.row(start);
+        (start + 1..self.len())
+            .find(|&i| self.keys.row(i) != key)
+            .unwrap_or(self.len())
+    }
+}
+
+/// Equi-join of two inputs which are both sorted ascending on their join keys.
+/// Rows are buffered only until all rows with the same key arrived on both
+/// sides, so inputs whose batches arrive interleaved are joined in a streaming
+/// fashion. Rows with a NULL key never match, wherever they are sorted.
+pub struct MergeJoin<'i> {
+    output: JoinOutput<'i>,
+    left_keys: Vec<Expr>,
+    right_keys: Vec<Expr>,
+    key_types: Vec<DataType>,
+    converter: RowConverter,
+    left: MergeInput,
+    right: MergeInput,
+}
+
+impl<'i> MergeJoin<'i> {
+    pub(crate) fn new(
+        join: &Join,
+        left_schema: SchemaRef,
+        right_schema: SchemaRef,
+        successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    ) -> anyhow::Result<Self> {
+        if join.on.is_empty() {
+            anyhow::bail!("Merge join requires at least one pair of equal keys");
+        }
+        let key_types = key_types(join, &left_schema, &right_schema)?;
+        let converter = RowConverter::new(key_types.iter().cloned().map(SortField::new).collect())?;
+        let empty_input = |schema: &SchemaRef| MergeInput {
+            batch: RecordBatch::new_empty(schema.clone()),
+            keys: converter.empty_rows(0, 0),
+            valid: Vec::new(),
+            start: 0,
+            last_key: None,
+            done: false,
+        };
+        let left = empty_input(&left_schema);
+        let right = empty_input(&right_schema);
+        let (left_keys, right_keys) = join.on.iter().cloned().unzip();
+        // The left input is buffered by key groups, the right one streamed
+        // past them.
+        let output = JoinOutput::new(join, left_schema, right_schema, Side::Left, successor);
+
+        Ok(Self {
+            output,
+            left_keys,
+            right_keys,
+            key_types,
+            converter,
+            left,
+            right,
+        })
+    }
+
+    fn push(&mut self, side: Side, batch: &RecordBatch) -> anyhow::Result<()> {
+        let (input, exprs) = match side {
+            Side::Left => (&mut self.left, &self.left_keys),
+            Side::Right => (&mut self.right, &self.right_keys),
+        };
+        if input.done {
+            anyhow::bail!("Input of the merge join received after it was complete");
+        }
+        let remaining = input.batch.slice(input.start, input.len() - input.start);
+        let batch = compute::concat_batches(&batch.schema(), [&remaining, batch])?;
+        let (keys, valid) = join_keys(&self.converter, &self.key_types, exprs, &batch)?;
+
+        let mut previous = input.last_key.as_ref().map(OwnedRow::row);
+        for (key, valid) in keys.iter().zip(&valid) {
+            if !valid {
+                continue;
+            }
+            if previous.is_some_and(|previous| previous > key) {
+                anyhow::bail!("Input of the merge join is not sorted on the join keys");
+            }
+            previous = Some(key);
+        }
+
+        input.batch = batch;
+        input.keys = keys;
+        input.valid = valid;
+        input.start = 0;
+        Ok(())
+    }
+
+    /// Joins the buffered rows whose matches are all known, which are the rows
+    /// before the first group of keys that may still continue on either side.
+    fn advance(&mut self) -> anyhow::Result<OperatorState> {
+        let (left, right) = (&self.left, &self.right);
+        let (mut i, mut j) = (left.start, right.start);
+        let mut left_idx = Vec::new();
+        let mut right_idx = Vec::new();
+        loop {
+            if i < left.len() && !left.valid[i] {
+                i += 1;
+            } else if j < right.len() && !right.valid[j] {
+                j += 1;
+            } else if i == left.len() || j == right.len() {
+                // Once one side is exhausted, the buffered rows of the other one can't match.
+                if i == left.len() && left.done {
+                    j = right.len();
+                }
+                if j == right.len() && right.done {
+                    i = left.len();
+                }
+                break;
+            } else {
+                match left.keys.row(i).cmp(&right.keys.row(j)) {
+                    Ordering::Less => i += 1,
+                    Ordering::Greater => j += 1,
+                    Ordering::Equal => {
+                        let (left_end, right_end) = (left.group_end(i), right.group_end(j));
+                        if (left_end == left.len() && !left.done)
+                            || (right_end == right.len() && !right.done)
+                        {
+                            break;
+                        }
+                        for l in i..left_end {
+                            for r in j..right_end {
+                                left_idx.push(u32::try_from(l - left.start)?);
+                                right_idx.push(u32::try_from(r - right.start)?);
+                            }
+                        }
+                        (i, j) = (left_end, right_end);
+                    }
+                }
+            }
+        }
+
+        let left_rows = left.batch.slice(left.start, i - left.start);
+        let right_rows = right.batch.slice(right.start, j - right.start);
+        self.consume(Side::Left, i);
+        self.consume(Side::Right, j);
+        if left_rows.num_rows() == 0 && right_rows.num_rows() == 0 {
+            return Ok(OperatorState::NeedMoreInput);
+        }
+
+        let (left_idx, right_idx) = self.output.filter_pairs(
+            &left_rows,
+            UInt32Array::from(left_idx),
+            &right_rows,
+            UInt32Array::from(right_idx),
+        )?;
+        let mut left_matched = vec![false; left_rows.num_rows()];
+        let state = self.output.probe_matches(
+            &left_rows,
+            &mut left_matched,
+            &right_rows,
+            &left_idx,
+            &right_idx,
+        )?;
+        if state == OperatorState::Finished {
+            return Ok(state);
+        }
+        self.output.build_rows(&left_rows, &left_matched)
+    }
+
+    /// Drops the buffered rows of `side` before `end`.
+    fn consume(&mut self, side: Side, end: usize) {
+        let input = match side {
+            Side::Left => &mut self.left,
+            Side::Right => &mut self.right,
+        };
+        if let Some(last) = (input.start..end).rev().find(|&i| input.valid[i]) {
+            input.last_key = Some(input.keys.row(last).owned());
+        }
+        input.start = end;
+    }
+
+    /// Whether rows of `side` can't be part of the result anymore.
+    fn is_finished(&self, side: Side) -> bool {
+        let other = match side {
+            Side::Left => &self.right,
+            Side::Right => &self.left,
+        };
+        other.done && other.start == other.len() && !self.output.preserves(side)
+    }
+}
+
+impl BinaryOperator for MergeJoin<'_> {
+    fn execute(&mut self, side: Side, input: Arc<RecordBatch>) -> anyhow::Result<OperatorState> {
+        self.push(side, &input)?;
+        if self.advance()? == OperatorState::Finished || self.is_finished(side) {
+            return Ok(OperatorState::Finished);
+        }
+        Ok(OperatorState::NeedMoreInput)
+    }
+
+    fn all_inputs_received(&mut self, side: Side) -> anyhow::Result<()> {
+        match side {
+            Side::Left => self.left.done = true,
+            Side::Right => self.right.done = true,
+        }
+        self.advance()?;
+        if self.left.done && self.right.done {
+            self.output.finish()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::DummyCatalog;
+    use crate::execution::operators::collect::Collect;
+    use crate::execution::operators::hash_join::HashJoin;
+    use crate::logical_plan::LogicalPlan;
+    use crate::parser::sql_parser::parse_sql_query;
+    use arrow::array::Int64Array;
+    use arrow::datatypes::{Field, Schema};
+    use arrow::util::display::{ArrayFormatter, FormatOptions};
+
+    fn sorted_rows(batches: &[Arc<RecordBatch>]) -> anyhow::Result<Vec<String>> {
+        let options = FormatOptions::default().with_null("NULL");
+        let mut rows = Vec::new();
+        for batch in batches {
+            let formatters = batch
+                .columns()
+                .iter()
+                .map(|column| ArrayFormatter::try_new(column, &options))
+                .collect::<Result<Vec<_>, _>>()?;
+            for row in 0..batch.num_rows() {
+                let values = formatters.iter().map(|f| f.value(row).to_string());
+                rows.push(values.collect::<Vec<_>>().join(","));
+            }
+        }
+        rows.sort();
+        Ok(rows)
+    }
+
+    #[test]
+    fn test_merge_join_matches_hash_join() -> anyhow::Result<()> {
+        let left_schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, true),
+            Field::new("a", DataType::Int64, false),
+        ]));
+        let right_schema = Arc::new(Schema::new(vec![
+            Field::new("rid", DataType::Int64, true),
+            Field::new("b", DataType::Int64, false),
+        ]));
+        let mut catalog = DummyCatalog::new();
+        catalog.add_table("l", left_schema.clone());
+        catalog.add_table("r", right_schema.clone());
+
+        // Sorted keys with duplicates, gaps and NULLs sorted first.
+        let batches = |schema: &SchemaRef, keys: &[i64], step: usize| {
+            let ids = keys
+                .iter()
+                .map(|&k| (k >= 0).then_some(k))
+                .collect::<Vec<_>>();
+            let batch = RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(Int64Array::from(ids)),
+                    Arc::new(Int64Array::from_iter_values(0..i64::try_from(keys.len())?)),
+                ],
+            )?;
+            Ok::<_, anyhow::Error>(
+                (0..keys.len())
+                    .step_by(step)
+                    .map(|i| batch.slice(i, step.min(keys.len() - i)))
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let left = batches(&left_schema, &[-1, 1, 1, 2, 4, 4, 4, 5, 7, 9, 9], 2)?;
+        let right = batches(&right_schema, &[-1, -1, 0, 1, 4, 4, 6, 7, 7, 9, 10], 3)?;
+
+        for join in [
+            "JOIN",
+            "LEFT JOIN",
+            "RIGHT JOIN",
+            "FULL JOIN",
+            "LEFT SEMI JOIN",
+            "LEFT ANTI JOIN",
+        ] {
+            let sql = format!("SELECT * FROM l {join} r ON id = rid AND a + 1 <> b");
+            let dag = parse_sql_query(&sql, &catalog)?;
+            let LogicalPlan::Join(join) = dag.get_node(2) else {
+                panic!("expected join node");
+            };
+
+            let mut expected = Vec::new();
+            let mut res = Vec::new();
+            {
+                let collect = Box::new(Collect::new(&mut expected));
+                let mut hash_join = HashJoin::new(
+                    join,
+                    left_schema.clone(),
+                    right_schema.clone(),
+                    Side::Right,
+                    collect,
+                )?;
+                let collect = Box::new(Collect::new(&mut res));
+                let mut merge_join =
+                    MergeJoin::new(join, left_schema.clone(), right_schema.clone(), collect)?;
+
+                // Interleave the inputs, so the merge join has to wait for groups.
+                for k in 0..left.len().max(right.len()) {
+                    for (side, batches) in [(Side::Left, &left), (Side::Right, &right)] {
+                        if let Some(batch) = batches.get(k) {
+                            hash_join.execute(side, Arc::new(batch.clone()))?;
+                            merge_join.execute(side, Arc::new(batch.clone()))?;
+                        }
+                    }
+                }
+                for side in [Side::Left, Side::Right] {
+                    hash_join.all_inputs_received(side)?;
+                    merge_join.all_inputs_received(side)?;
+                }
+            }
+            assert_eq!(sorted_rows(&res)?, sorted_rows(&expected)?, "{}", join.kind);
+        }
+
+        Ok(())
+    }
+}
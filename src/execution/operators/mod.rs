@@ -1,10 +1,90 @@
-mod collect;
-mod filter;
-mod scan;
-mod select;
+pub mod aggregate;
+pub mod collect;
+pub mod csv_scan;
+pub mod distinct;
+pub mod fanout;
+pub mod filter;
+pub mod hash_join;
+pub mod ipc_scan;
+mod join;
+pub mod json_scan;
+pub mod limit;
+pub mod merge_join;
+pub mod multi_file_scan;
+pub mod nested_loop_join;
+pub mod project;
+pub mod recursive_union;
+pub mod scan;
+pub mod select;
+pub mod set_operation;
+pub mod sort;
+pub mod top_n;
+pub mod union;
+pub mod unpivot;
+pub mod window;
+
+use arrow::array::RecordBatch;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Returned by `Operator::execute` so that producers can stop early once the
+/// consumers don't need any more rows (e.g. after a `LIMIT` is satisfied).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperatorState {
+    NeedMoreInput,
+    Finished,
+}
 
 pub trait Operator<In> {
-    fn execute(&mut self, input: In) -> anyhow::Result<()>;
+    fn execute(&mut self, input: In) -> anyhow::Result<OperatorState>;
 
     fn all_inputs_received(&mut self) -> anyhow::Result<()>;
 }
+
+/// Input of an operator with two inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// Operator with two inputs, like a join. Each input is fed by its own chain of
+/// operators through the `InputPort`s returned by `input_ports`.
+pub trait BinaryOperator {
+    fn execute(&mut self, side: Side, input: Arc<RecordBatch>) -> anyhow::Result<OperatorState>;
+
+    /// Called once for each side after its last batch.
+    fn all_inputs_received(&mut self, side: Side) -> anyhow::Result<()>;
+}
+
+/// One input of a shared `BinaryOperator`.
+pub struct InputPort<'i> {
+    operator: Rc<RefCell<dyn BinaryOperator + 'i>>,
+    side: Side,
+}
+
+impl Operator<Arc<RecordBatch>> for InputPort<'_> {
+    fn execute(&mut self, input: Arc<RecordBatch>) -> anyhow::Result<OperatorState> {
+        self.operator.borrow_mut().execute(self.side, input)
+    }
+
+    fn all_inputs_received(&mut self) -> anyhow::Result<()> {
+        self.operator.borrow_mut().all_inputs_received(self.side)
+    }
+}
+
+/// Returns the left and the right input of `operator`.
+pub fn input_ports<'i>(operator: impl BinaryOperator + 'i) -> (InputPort<'i>, InputPort<'i>) {
+    let operator: Rc<RefCell<dyn BinaryOperator + 'i>> = Rc::new(RefCell::new(operator));
+    (
+        InputPort {
+            operator: operator.clone(),
+            side: Side::Left,
+        },
+        InputPort {
+            operator,
+            side: Side::Right,
+        },
+    )
+}
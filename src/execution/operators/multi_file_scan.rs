@@ -0,0 +1,105 @@
+use crate::catalog::conform_batch;
+use crate::execution::operators::{Operator, OperatorState};
+use arrow::datatypes::SchemaRef;
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use std::fs::File;
+use std::sync::Arc;
+
+/// Reads parquet files one after the other as a single table with `schema`,
+/// which the batches of each file are converted to.
+pub struct MultiFileScan<'i> {
+    successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    schema: SchemaRef,
+}
+
+impl<'i> MultiFileScan<'i> {
+    pub(crate) fn new(
+        schema: SchemaRef,
+        successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    ) -> Self {
+        Self { successor, schema }
+    }
+}
+
+impl Operator<(Vec<String>, usize)> for MultiFileScan<'_> {
+    fn execute(&mut self, input: (Vec<String>, usize)) -> anyhow::Result<OperatorState> {
+        let (file_paths, chunk_size) = input;
+        for file_path in file_paths {
+            let file = File::open(file_path)?;
+            let reader = ParquetRecordBatchReaderBuilder::try_new(file)?
+                .with_batch_size(chunk_size)
+                .build()?;
+
+            // Files are opened one at a time, so stopping here skips the rest.
+            for b in reader {
+                let b = conform_batch(&b?, &self.schema)?;
+                if self.successor.execute(Arc::new(b))? == OperatorState::Finished {
+                    return Ok(OperatorState::Finished);
+                }
+            }
+        }
+
+        Ok(OperatorState::NeedMoreInput)
+    }
+
+    fn all_inputs_received(&mut self) -> anyhow::Result<()> {
+        self.successor.all_inputs_received()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::{Catalog, DummyCatalog, MultiFileOptions, TableSource};
+    use crate::execution::operators::collect::Collect;
+    use crate::parser::sql_parser::parse_sql_query;
+
+    #[test]
+    fn test_multi_file_scan() -> anyhow::Result<()> {
+        let options = MultiFileOptions::default();
+        let mut catalog = DummyCatalog::new();
+        catalog.register_parquet_files(
+            "userdata",
+            "samples/sample-data/parquet/userdata*.parquet",
+            options,
+        )?;
+        catalog.register_parquet_files("directory", "samples/sample-data/parquet", options)?;
+        catalog.register_parquet("userdata1", "samples/sample-data/parquet/userdata1.parquet")?;
+        assert_eq!(
+            catalog.get_schema("userdata")?,
+            catalog.get_schema("userdata1")?
+        );
+        assert_eq!(
+            catalog.get_source("userdata"),
+            catalog.get_source("directory")
+        );
+        let row_count = catalog.get_statistics("userdata").unwrap().row_count;
+        assert!(row_count > catalog.get_statistics("userdata1").unwrap().row_count);
+        parse_sql_query(
+            "SELECT first_name, count(*) FROM userdata GROUP BY first_name",
+            &catalog,
+        )?;
+
+        let Some(TableSource::ParquetFiles(paths)) = catalog.get_source("userdata") else {
+            anyhow::bail!("userdata is not made of parquet files");
+        };
+        assert_eq!(paths.len(), 5);
+        let mut res = Vec::new();
+        {
+            let collect = Box::new(Collect::new(&mut res));
+            let mut scan = MultiFileScan::new(catalog.get_schema("userdata")?, collect);
+            scan.execute((paths.clone(), 1000))?;
+            scan.all_inputs_received()?;
+        }
+        assert_eq!(res.iter().map(|b| b.num_rows()).sum::<usize>(), row_count);
+
+        catalog.analyze("userdata")?;
+        assert_eq!(
+            catalog.get_statistics("userdata").unwrap().row_count,
+            row_count
+        );
+
+        Ok(())
+    }
+}
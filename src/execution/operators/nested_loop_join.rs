@@ -0,0 +1,230 @@
+use crate::execution::operators::join::JoinOutput;
+use crate::execution::operators::{BinaryOperator, Operator, OperatorState, Side};
+use crate::logical_plan::Join;
+use arrow::array::{RecordBatch, UInt32Array};
+use arrow::compute;
+use arrow::datatypes::SchemaRef;
+use std::sync::Arc;
+
+/// Maximum number of candidate pairs the filter is evaluated on at once.
+const BLOCK_PAIRS: usize = 64 * 1024;
+
+/// Join for arbitrary predicates, which buffers the build input and evaluates
+/// the filter on all pairs of build rows and a block of probe rows at a time.
+/// Keys in `on` are compared as part of the filter.
+pub struct NestedLoopJoin<'i> {
+    output: JoinOutput<'i>,
+    build_batches: Vec<RecordBatch>,
+    /// Concatenated build input and its rows which matched, once complete.
+    build: Option<(RecordBatch, Vec<bool>)>,
+    pending_probe: Vec<Arc<RecordBatch>>,
+    probe_done: bool,
+}
+
+impl<'i> NestedLoopJoin<'i> {
+    pub(crate) fn new(
+        join: &Join,
+        left_schema: SchemaRef,
+        right_schema: SchemaRef,
+        build_side: Side,
+        successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    ) -> Self {
+        let output = JoinOutput::new(join, left_schema, right_schema, build_side, successor)
+            .filter_on_keys(&join.on);
+        Self {
+            output,
+            build_batches: Vec::new(),
+            build: None,
+            pending_probe: Vec::new(),
+            probe_done: false,
+        }
+    }
+
+    fn probe(&mut self, probe: &RecordBatch) -> anyhow::Result<OperatorState> {
+        let (build, mut matched) = self.build.take().expect("build side is complete");
+        let state = self.probe_blocks(&build, &mut matched, probe);
+        self.build = Some((build, matched));
+        state
+    }
+
+    fn probe_blocks(
+        &mut self,
+        build: &RecordBatch,
+        matched: &mut [bool],
+        probe: &RecordBatch,
+    ) -> anyhow::Result<OperatorState> {
+        let build_rows = build.num_rows();
+        if build_rows == 0 && !self.output.preserves(self.output.probe_side()) {
+            // No probe row can be part of the result.
+            return Ok(OperatorState::Finished);
+        }
+        let block_size = (BLOCK_PAIRS / build_rows.max(1)).max(1);
+        let mut offset = 0;
+        while offset < probe.num_rows() {
+            let block = probe.slice(offset, block_size.min(probe.num_rows() - offset));
+            offset += block.num_rows();
+
+            let pairs = build_rows * block.num_rows();
+            let build_idx = (0..pairs)
+                .map(|i| u32::try_from(i % build_rows))
+                .collect::<Result<Vec<_>, _>>()?;
+            let probe_idx = (0..pairs)
+                .map(|i| u32::try_from(i / build_rows))
+                .collect::<Result<Vec<_>, _>>()?;
+            let (build_idx, probe_idx) = self.output.filter_pairs(
+                build,
+                UInt32Array::from(build_idx),
+                &block,
+                UInt32Array::from(probe_idx),
+            )?;
+            let state = self
+                .output
+                .probe_matches(build, matched, &block, &build_idx, &probe_idx)?;
+            if state == OperatorState::Finished {
+                return Ok(state);
+            }
+        }
+        Ok(OperatorState::NeedMoreInput)
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        let (build, matched) = self.build.take().expect("build side is complete");
+        self.output.build_rows(&build, &matched)?;
+        self.output.finish()
+    }
+}
+
+impl BinaryOperator for NestedLoopJoin<'_> {
+    fn execute(&mut self, side: Side, input: Arc<RecordBatch>) -> anyhow::Result<OperatorState> {
+        if side == self.output.build_side {
+            if self.build.is_some() {
+                anyhow::bail!("Build input of the nested loop join received after it was complete");
+            }
+            self.build_batches.push(input.as_ref().clone());
+            Ok(OperatorState::NeedMoreInput)
+        } else if self.build.is_none() {
+            self.pending_probe.push(input);
+            Ok(OperatorState::NeedMoreInput)
+        } else {
+            self.probe(&input)
+        }
+    }
+
+    fn all_inputs_received(&mut self, side: Side) -> anyhow::Result<()> {
+        if side == self.output.build_side {
+            let batch = compute::concat_batches(&self.output.build_schema(), &self.build_batches)?;
+            self.build_batches.clear();
+            self.build = Some((batch.clone(), vec![false; batch.num_rows()]));
+            for batch in std::mem::take(&mut self.pending_probe) {
+                if self.probe(&batch)? == OperatorState::Finished {
+                    break;
+                }
+            }
+        } else {
+            self.probe_done = true;
+        }
+        if self.probe_done && self.build.is_some() {
+            self.finish()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::DummyCatalog;
+    use crate::execution::operators::collect::Collect;
+    use crate::execution::operators::input_ports;
+    use crate::logical_plan::LogicalPlan;
+    use crate::parser::sql_parser::parse_sql_query;
+    use arrow::array::Int64Array;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::util::display::{ArrayFormatter, FormatOptions};
+
+    #[test]
+    fn test_nested_loop_range_join() -> anyhow::Result<()> {
+        let left_schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("ts", DataType::Int64, true),
+        ]));
+        let right_schema = Arc::new(Schema::new(vec![
+            Field::new("rid", DataType::Int64, false),
+            Field::new("lo", DataType::Int64, false),
+            Field::new("hi", DataType::Int64, false),
+        ]));
+        let mut catalog = DummyCatalog::new();
+        catalog.add_table("l", left_schema.clone());
+        catalog.add_table("r", right_schema.clone());
+
+        let left = RecordBatch::try_new(
+            left_schema.clone(),
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2, 3, 4])),
+                Arc::new(Int64Array::from(vec![Some(5), Some(12), Some(40), None])),
+            ],
+        )?;
+        let right = RecordBatch::try_new(
+            right_schema.clone(),
+            vec![
+                Arc::new(Int64Array::from(vec![10, 20, 30])),
+                Arc::new(Int64Array::from(vec![0, 10, 12])),
+                Arc::new(Int64Array::from(vec![10, 20, 14])),
+            ],
+        )?;
+
+        let inner = vec!["1,5,10,0,10", "2,12,20,10,20", "2,12,30,12,14"];
+        let cases = [
+            ("JOIN", inner.clone()),
+            (
+                "FULL JOIN",
+                [inner, vec!["3,40,NULL,NULL,NULL", "4,NULL,NULL,NULL,NULL"]].concat(),
+            ),
+            ("LEFT ANTI JOIN", vec!["3,40", "4,NULL"]),
+        ];
+        for (join, expected) in cases {
+            let sql = format!("SELECT * FROM l {join} r ON ts BETWEEN lo AND hi");
+            let dag = parse_sql_query(&sql, &catalog)?;
+            let LogicalPlan::Join(join) = dag.get_node(2) else {
+                panic!("expected join node");
+            };
+            for build_side in [Side::Left, Side::Right] {
+                let mut res = Vec::new();
+                {
+                    let collect = Box::new(Collect::new(&mut res));
+                    let nested_loop_join = NestedLoopJoin::new(
+                        join,
+                        left_schema.clone(),
+                        right_schema.clone(),
+                        build_side,
+                        collect,
+                    );
+                    let (mut left_input, mut right_input) = input_ports(nested_loop_join);
+                    // Probe rows arrive before the build side is complete.
+                    right_input.execute(Arc::new(right.clone()))?;
+                    right_input.all_inputs_received()?;
+                    left_input.execute(Arc::new(left.clone()))?;
+                    left_input.all_inputs_received()?;
+                }
+
+                let options = FormatOptions::default().with_null("NULL");
+                let mut rows = Vec::new();
+                for batch in &res {
+                    let formatters = batch
+                        .columns()
+                        .iter()
+                        .map(|column| ArrayFormatter::try_new(column, &options))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    for row in 0..batch.num_rows() {
+                        let values = formatters.iter().map(|f| f.value(row).to_string());
+                        rows.push(values.collect::<Vec<_>>().join(","));
+                    }
+                }
+                rows.sort();
+                assert_eq!(rows, expected, "{} {build_side:?}", join.kind);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use crate::execution::evaluator::evaluate;
+use crate::execution::operators::{Operator, OperatorState};
+use crate::logical_plan::expr::Expr;
+use arrow::array::{RecordBatch, RecordBatchOptions};
+use arrow::compute::{self, CastOptions};
+use arrow::datatypes::SchemaRef;
+use std::sync::Arc;
+
+/// Evaluates the expressions of a projection over each batch, as columns of
+/// `schema`. Values are cast to the types of the schema where they differ, and
+/// values which don't fit into those types are an error.
+pub struct Project<'i> {
+    successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    expr: Vec<Expr>,
+    schema: SchemaRef,
+}
+
+impl<'i> Project<'i> {
+    pub(crate) fn new(
+        expr: Vec<Expr>,
+        schema: SchemaRef,
+        successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    ) -> Self {
+        Self {
+            successor,
+            expr,
+            schema,
+        }
+    }
+}
+
+impl Operator<Arc<RecordBatch>> for Project<'_> {
+    fn execute(&mut self, input: Arc<RecordBatch>) -> anyhow::Result<OperatorState> {
+        let options = CastOptions {
+            safe: false,
+            ..CastOptions::default()
+        };
+        let columns = self
+            .expr
+            .iter()
+            .zip(self.schema.fields())
+            .map(|(expr, field)| {
+                let column = evaluate(expr, &input)?;
+                if column.data_type() == field.data_type() {
+                    Ok(column)
+                } else {
+                    Ok(compute::cast_with_options(
+                        &column,
+                        field.data_type(),
+                        &options,
+                    )?)
+                }
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        // Projections without columns keep the number of rows.
+        let batch = RecordBatch::try_new_with_options(
+            self.schema.clone(),
+            columns,
+            &RecordBatchOptions::new().with_row_count(Some(input.num_rows())),
+        )?;
+        self.successor.execute(Arc::new(batch))
+    }
+
+    fn all_inputs_received(&mut self) -> anyhow::Result<()> {
+        self.successor.all_inputs_received()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::operators::collect::Collect;
+    use crate::logical_plan::expr::{Alias, Binary, BinaryOp, Ident, IntegerLiteral};
+    use arrow::array::{AsArray, Int64Array};
+    use arrow::datatypes::{DataType, Field, Int64Type, Schema};
+
+    #[test]
+    fn test_project() -> anyhow::Result<()> {
+        let batch = Arc::new(RecordBatch::try_from_iter([(
+            "id",
+            Arc::new(Int64Array::from(vec![1, 2, 3])) as _,
+        )])?);
+        let next = Expr::Alias(Alias {
+            expr: Box::new(Expr::Binary(Binary {
+                lhs: Box::new(Expr::Ident(Ident {
+                    name: "id".to_string(),
+                })),
+                op: BinaryOp::Plus,
+                rhs: Box::new(Expr::IntegerLiteral(IntegerLiteral { value: 1 })),
+            })),
+            name: "next".to_string(),
+        });
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "next",
+            DataType::Int64,
+            false,
+        )]));
+
+        let mut res = Vec::new();
+        {
+            let collect = Box::new(Collect::new(&mut res));
+            let mut project = Project::new(vec![next], schema.clone(), collect);
+            project.execute(batch)?;
+            project.all_inputs_received()?;
+        }
+        assert_eq!(res[0].schema(), schema);
+        assert_eq!(
+            res[0].column(0).as_primitive::<Int64Type>().values(),
+            &[2, 3, 4]
+        );
+
+        // Values which overflow the type of the schema aren't turned into NULL.
+        let narrow = Arc::new(Schema::new(vec![Field::new("id", DataType::Int8, false)]));
+        let batch = Arc::new(RecordBatch::try_from_iter([(
+            "id",
+            Arc::new(Int64Array::from(vec![1, 1000])) as _,
+        )])?);
+        let id = Expr::Ident(Ident {
+            name: "id".to_string(),
+        });
+        let mut res = Vec::new();
+        let mut project = Project::new(vec![id], narrow, Box::new(Collect::new(&mut res)));
+        assert!(project.execute(batch).is_err());
+
+        Ok(())
+    }
+}
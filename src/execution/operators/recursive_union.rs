@@ -0,0 +1,180 @@
+use crate::execution::operators::distinct::DistinctRows;
+use crate::execution::operators::{Operator, OperatorState};
+use crate::logical_plan;
+use arrow::array::RecordBatch;
+use arrow::datatypes::SchemaRef;
+use std::sync::Arc;
+
+/// Number of evaluations of the recursive term after which a recursive query is
+/// aborted, unless configured otherwise.
+pub const DEFAULT_MAX_ITERATIONS: usize = 1000;
+
+/// Evaluates the recursive term of a recursive CTE over the rows of the work
+/// table and returns the rows it produces.
+pub type RecursiveTerm<'i> =
+    Box<dyn FnMut(&[Arc<RecordBatch>]) -> anyhow::Result<Vec<Arc<RecordBatch>>> + 'i>;
+
+/// Recursive union, which receives the rows of the anchor as input. Once all of
+/// them were received, the recursive term is evaluated over the rows added last
+/// (the work table) until it adds no rows or the successor is finished.
+///
+/// Evaluating the recursive term more than `max_iterations` times fails, which
+/// stops queries that never reach a fixpoint, like a `UNION ALL` over a cycle.
+pub struct RecursiveUnion<'i> {
+    successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    recursive_term: RecursiveTerm<'i>,
+    schema: SchemaRef,
+    /// Set for `UNION`, which doesn't add rows which were already returned.
+    seen: Option<DistinctRows>,
+    work_table: Vec<Arc<RecordBatch>>,
+    max_iterations: usize,
+    finished: bool,
+}
+
+impl<'i> RecursiveUnion<'i> {
+    pub(crate) fn new(
+        union: &logical_plan::RecursiveUnion,
+        recursive_term: RecursiveTerm<'i>,
+        successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    ) -> anyhow::Result<Self> {
+        let seen = if union.distinct {
+            Some(DistinctRows::new(&union.schema)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            successor,
+            recursive_term,
+            schema: union.schema.clone(),
+            seen,
+            work_table: Vec::new(),
+            max_iterations: DEFAULT_MAX_ITERATIONS,
+            finished: false,
+        })
+    }
+
+    pub(crate) const fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Adds the new rows of `input` to the work table and forwards them.
+    fn add(&mut self, input: &RecordBatch) -> anyhow::Result<()> {
+        // The recursive term names its columns differently.
+        let mut batch = RecordBatch::try_new(self.schema.clone(), input.columns().to_vec())?;
+        if let Some(seen) = &mut self.seen {
+            batch = seen.retain_new(batch)?;
+        }
+        if batch.num_rows() == 0 {
+            return Ok(());
+        }
+
+        let batch = Arc::new(batch);
+        self.work_table.push(batch.clone());
+        if self.successor.execute(batch)? == OperatorState::Finished {
+            self.finished = true;
+        }
+        Ok(())
+    }
+}
+
+impl Operator<Arc<RecordBatch>> for RecursiveUnion<'_> {
+    fn execute(&mut self, input: Arc<RecordBatch>) -> anyhow::Result<OperatorState> {
+        if !self.finished {
+            self.add(&input)?;
+        }
+        if self.finished {
+            Ok(OperatorState::Finished)
+        } else {
+            Ok(OperatorState::NeedMoreInput)
+        }
+    }
+
+    fn all_inputs_received(&mut self) -> anyhow::Result<()> {
+        let mut iterations = 0;
+        while !self.finished && !self.work_table.is_empty() {
+            if iterations == self.max_iterations {
+                anyhow::bail!(
+                    "Recursive query didn't finish after {} iterations",
+                    self.max_iterations
+                );
+            }
+            iterations += 1;
+
+            let work_table = std::mem::take(&mut self.work_table);
+            for batch in (self.recursive_term)(&work_table)? {
+                self.add(&batch)?;
+                if self.finished {
+                    break;
+                }
+            }
+        }
+        self.successor.all_inputs_received()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::operators::collect::Collect;
+    use crate::execution::operators::limit::Limit;
+    use arrow::array::{AsArray, Int64Array};
+    use arrow::datatypes::{DataType, Field, Int64Type, Schema};
+
+    #[test]
+    fn test_recursive_union() -> anyhow::Result<()> {
+        // Graph with the cycle 1 -> 2 -> 3 -> 1.
+        let edges = [(1, 2), (2, 3), (3, 1), (3, 4), (2, 5)];
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "node",
+            DataType::Int64,
+            false,
+        )]));
+        let batch = |nodes: Vec<i64>| {
+            Arc::new(
+                RecordBatch::try_new(schema.clone(), vec![Arc::new(Int64Array::from(nodes))])
+                    .unwrap(),
+            )
+        };
+        // Successors of the nodes in the work table, in one batch per node.
+        let successors = |work_table: &[Arc<RecordBatch>]| {
+            let mut batches = Vec::new();
+            for nodes in work_table {
+                for node in nodes.column(0).as_primitive::<Int64Type>().values() {
+                    let targets = edges.iter().filter(|(src, _)| src == node);
+                    batches.push(batch(targets.map(|(_, dst)| *dst).collect()));
+                }
+            }
+            Ok(batches)
+        };
+        let run = |distinct: bool, fetch: Option<usize>| -> anyhow::Result<Vec<i64>> {
+            let union = logical_plan::RecursiveUnion {
+                name: "reachable".to_string(),
+                distinct,
+                schema: schema.clone(),
+            };
+            let mut res = Vec::new();
+            {
+                let collect = Box::new(Collect::new(&mut res));
+                let limit = Box::new(Limit::new(0, fetch, collect));
+                let mut union = RecursiveUnion::new(&union, Box::new(successors), limit)?
+                    .with_max_iterations(10);
+                union.execute(batch(vec![1]))?;
+                union.all_inputs_received()?;
+            }
+            Ok(res
+                .iter()
+                .flat_map(|b| b.column(0).as_primitive::<Int64Type>().values().to_vec())
+                .collect())
+        };
+
+        // UNION reaches a fixpoint once no new nodes are found.
+        assert_eq!(run(true, None)?, vec![1, 2, 3, 5, 4]);
+        // UNION ALL follows the cycle until the limit is reached...
+        assert_eq!(run(false, Some(7))?, vec![1, 2, 3, 5, 1, 4, 2]);
+        // ... or the maximum number of iterations.
+        assert!(run(false, None).is_err());
+
+        Ok(())
+    }
+}
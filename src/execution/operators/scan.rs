@@ -1,4 +1,4 @@
-use crate::execution::operators::Operator;
+use crate::execution::operators::{Operator, OperatorState};
 use arrow::datatypes::{Schema, SchemaRef};
 use arrow::record_batch::RecordBatch;
 use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
@@ -20,18 +20,21 @@ impl<'i> Scan<'i> {
 }
 
 impl Operator<(String, usize)> for Scan<'_> {
-    fn execute(&mut self, input: (String, usize)) -> anyhow::Result<()> {
+    fn execute(&mut self, input: (String, usize)) -> anyhow::Result<OperatorState> {
         let (file_path, chunk_size) = input;
         let file = File::open(file_path)?;
         let builder = ParquetRecordBatchReaderBuilder::try_new(file)?.with_batch_size(chunk_size);
         self.schema = builder.schema().clone();
         let reader = builder.build()?;
 
+        // Row groups are decoded lazily, so stopping here skips the rest of the file.
         for b in reader {
-            self.successor.execute(Arc::new(b?))?;
+            if self.successor.execute(Arc::new(b?))? == OperatorState::Finished {
+                return Ok(OperatorState::Finished);
+            }
         }
 
-        Ok(())
+        Ok(OperatorState::NeedMoreInput)
     }
 
     fn all_inputs_received(&mut self) -> anyhow::Result<()> {
@@ -1,5 +1,7 @@
-use crate::execution::operators::Operator;
-use arrow::array::{Array, Int32Array, RecordBatch};
+use crate::execution::operators::{Operator, OperatorState};
+use arrow::array::{ArrayRef, RecordBatch, UInt32Array};
+use arrow::compute::{self, CastOptions};
+use arrow::datatypes::SchemaRef;
 use std::sync::Arc;
 
 pub struct Select<'i> {
@@ -14,29 +16,63 @@ impl<'i> Select<'i> {
 }
 
 impl Operator<(Vec<usize>, Arc<RecordBatch>)> for Select<'_> {
-    fn execute(&mut self, input: (Vec<usize>, Arc<RecordBatch>)) -> anyhow::Result<()> {
-        let mut arrays: Vec<Arc<dyn Array>> = Vec::new();
+    fn execute(&mut self, input: (Vec<usize>, Arc<RecordBatch>)) -> anyhow::Result<OperatorState> {
         let (indexes, batch) = input;
-        for i in 0..batch.num_columns() {
-            let column = batch
-                .column(i)
-                .as_any()
-                .downcast_ref::<Int32Array>()
-                .unwrap();
-            let mut data = Vec::new();
-            for i in &indexes {
-                data.push(column.value(*i));
-            }
-            arrays.push(Arc::new(Int32Array::from(data)));
-        }
-        let new_batch = Arc::new(RecordBatch::try_new(batch.schema(), arrays)?);
-
-        self.successor.execute(new_batch)?;
-
-        Ok(())
+        let indexes = indexes
+            .into_iter()
+            .map(u32::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        let new_batch = Arc::new(take_batch(&batch, &UInt32Array::from(indexes))?);
+
+        self.successor.execute(new_batch)
     }
 
     fn all_inputs_received(&mut self) -> anyhow::Result<()> {
         self.successor.all_inputs_received()
     }
 }
+
+/// Gathers the rows at `indices` from every column of `batch`.
+pub fn take_batch(batch: &RecordBatch, indices: &UInt32Array) -> anyhow::Result<RecordBatch> {
+    let columns = batch
+        .columns()
+        .iter()
+        .map(|column| compute::take(column, indices, None))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(RecordBatch::try_new(batch.schema(), columns)?)
+}
+
+/// Gathers rows from several batches of the same schema, where each index is a
+/// `(batch, row)` pair.
+pub fn interleave_batches(
+    batches: &[RecordBatch],
+    indices: &[(usize, usize)],
+) -> anyhow::Result<RecordBatch> {
+    let schema = batches[0].schema();
+    let columns = (0..schema.fields().len())
+        .map(|i| {
+            let arrays = batches
+                .iter()
+                .map(|b| b.column(i).as_ref())
+                .collect::<Vec<_>>();
+            compute::interleave(&arrays, indices)
+        })
+        .collect::<Result<Vec<ArrayRef>, _>>()?;
+    Ok(RecordBatch::try_new(schema, columns)?)
+}
+
+/// Casts the columns of `batch` to the types of `schema`, whose fields it takes
+/// by position. Values which don't fit into the new type are an error.
+pub fn cast_batch(batch: &RecordBatch, schema: &SchemaRef) -> anyhow::Result<RecordBatch> {
+    let options = CastOptions {
+        safe: false,
+        ..CastOptions::default()
+    };
+    let columns = batch
+        .columns()
+        .iter()
+        .zip(schema.fields())
+        .map(|(column, field)| compute::cast_with_options(column, field.data_type(), &options))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
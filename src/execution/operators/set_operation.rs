@@ -0,0 +1,192 @@
+use crate::execution::operators::select::{cast_batch, take_batch};
+use crate::execution::operators::{BinaryOperator, Operator, OperatorState, Side};
+use crate::logical_plan::SetOperation;
+use arrow::array::{RecordBatch, UInt32Array};
+use arrow::datatypes::SchemaRef;
+use arrow::row::{RowConverter, SortField};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOperator {
+    Intersect,
+    Except,
+}
+
+/// `INTERSECT` or `EXCEPT`, which counts the rows of the right input in a hash
+/// table and then streams the left input through it. Rows are compared by their
+/// row encoding, so NULLs are equal to each other.
+///
+/// Left batches which arrive before the right input is complete are buffered.
+pub struct HashSetOperation<'i> {
+    successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    op: SetOperator,
+    distinct: bool,
+    schema: SchemaRef,
+    converter: RowConverter,
+    /// Copies of each row of the right input which weren't matched yet. With
+    /// `distinct`, rows which were returned are kept with a count of zero.
+    counts: HashMap<Box<[u8]>, usize>,
+    right_done: bool,
+    pending_left: Vec<Arc<RecordBatch>>,
+    left_done: bool,
+}
+
+impl<'i> HashSetOperation<'i> {
+    pub(crate) fn new(
+        op: SetOperator,
+        set_operation: &SetOperation,
+        successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    ) -> anyhow::Result<Self> {
+        let fields = set_operation
+            .schema
+            .fields()
+            .iter()
+            .map(|field| SortField::new(field.data_type().clone()))
+            .collect();
+        Ok(Self {
+            successor,
+            op,
+            distinct: set_operation.distinct,
+            schema: set_operation.schema.clone(),
+            converter: RowConverter::new(fields)?,
+            counts: HashMap::new(),
+            right_done: false,
+            pending_left: Vec::new(),
+            left_done: false,
+        })
+    }
+
+    fn probe(&mut self, input: &RecordBatch) -> anyhow::Result<OperatorState> {
+        let batch = cast_batch(input, &self.schema)?;
+        let rows = self.converter.convert_columns(batch.columns())?;
+        let mut indices = Vec::new();
+        for (i, row) in rows.iter().enumerate() {
+            let count = self.counts.get_mut(row.as_ref());
+            let keep = match (self.op, count) {
+                (SetOperator::Intersect, Some(count)) if *count > 0 => {
+                    *count = if self.distinct { 0 } else { *count - 1 };
+                    true
+                }
+                (SetOperator::Intersect, _) => false,
+                (SetOperator::Except, Some(count)) if self.distinct || *count > 0 => {
+                    *count = count.saturating_sub(1);
+                    false
+                }
+                (SetOperator::Except, _) => {
+                    if self.distinct {
+                        self.counts.insert(row.as_ref().into(), 0);
+                    }
+                    true
+                }
+            };
+            if keep {
+                indices.push(u32::try_from(i)?);
+            }
+        }
+        if indices.is_empty() {
+            return Ok(OperatorState::NeedMoreInput);
+        }
+        let batch = take_batch(&batch, &UInt32Array::from(indices))?;
+        self.successor.execute(Arc::new(batch))
+    }
+}
+
+impl BinaryOperator for HashSetOperation<'_> {
+    fn execute(&mut self, side: Side, input: Arc<RecordBatch>) -> anyhow::Result<OperatorState> {
+        match side {
+            Side::Left if !self.right_done => {
+                self.pending_left.push(input);
+                Ok(OperatorState::NeedMoreInput)
+            }
+            // No left row can be in the intersection with an empty right input.
+            Side::Left if self.op == SetOperator::Intersect && self.counts.is_empty() => {
+                Ok(OperatorState::Finished)
+            }
+            Side::Left => self.probe(&input),
+            Side::Right => {
+                let batch = cast_batch(&input, &self.schema)?;
+                let rows = self.converter.convert_columns(batch.columns())?;
+                for row in &rows {
+                    *self.counts.entry(row.as_ref().into()).or_default() += 1;
+                }
+                Ok(OperatorState::NeedMoreInput)
+            }
+        }
+    }
+
+    fn all_inputs_received(&mut self, side: Side) -> anyhow::Result<()> {
+        match side {
+            Side::Left => self.left_done = true,
+            Side::Right => {
+                self.right_done = true;
+                for batch in std::mem::take(&mut self.pending_left) {
+                    if self.probe(&batch)? == OperatorState::Finished {
+                        break;
+                    }
+                }
+            }
+        }
+        if self.left_done && self.right_done {
+            self.successor.all_inputs_received()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::operators::collect::Collect;
+    use crate::execution::operators::input_ports;
+    use arrow::array::{AsArray, Int64Array};
+    use arrow::datatypes::{DataType, Field, Int64Type, Schema};
+
+    #[test]
+    fn test_intersect_except() -> anyhow::Result<()> {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int64, true)]));
+        let batch = |values: Vec<Option<i64>>| {
+            Arc::new(
+                RecordBatch::try_new(schema.clone(), vec![Arc::new(Int64Array::from(values))])
+                    .unwrap(),
+            )
+        };
+        let left = [Some(1), Some(1), Some(1), None, None, Some(2), Some(3)];
+        let right = vec![Some(1), Some(1), None, Some(3), Some(4)];
+
+        for (op, distinct, expected) in [
+            (SetOperator::Intersect, true, vec![Some(1), None, Some(3)]),
+            (
+                SetOperator::Intersect,
+                false,
+                vec![Some(1), Some(1), None, Some(3)],
+            ),
+            (SetOperator::Except, true, vec![Some(2)]),
+            (SetOperator::Except, false, vec![Some(1), None, Some(2)]),
+        ] {
+            let set_operation = SetOperation {
+                distinct,
+                schema: schema.clone(),
+            };
+            let mut res = Vec::new();
+            {
+                let collect = Box::new(Collect::new(&mut res));
+                let (mut left_input, mut right_input) =
+                    input_ports(HashSetOperation::new(op, &set_operation, collect)?);
+                // Left rows are buffered until the right input is complete.
+                left_input.execute(batch(left[..4].to_vec()))?;
+                right_input.execute(batch(right.clone()))?;
+                right_input.all_inputs_received()?;
+                left_input.execute(batch(left[4..].to_vec()))?;
+                left_input.all_inputs_received()?;
+            }
+            let values = res
+                .iter()
+                .flat_map(|batch| batch.column(0).as_primitive::<Int64Type>().iter())
+                .collect::<Vec<_>>();
+            assert_eq!(values, expected, "{op:?} distinct: {distinct}");
+        }
+
+        Ok(())
+    }
+}
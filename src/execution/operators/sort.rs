@@ -0,0 +1,393 @@
+use crate::execution::evaluator::evaluate;
+use crate::execution::operators::select::{interleave_batches, take_batch};
+use crate::execution::operators::{Operator, OperatorState};
+use crate::execution::spill::{SpillFile, SpillWriter};
+use crate::logical_plan::expr::SortExpr;
+use arrow::array::{RecordBatch, UInt32Array};
+use arrow::compute::{self, SortOptions};
+use arrow::datatypes::Schema;
+use arrow::row::{OwnedRow, RowConverter, Rows, SortField};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::sync::Arc;
+
+type SortedRun = Box<dyn Iterator<Item = anyhow::Result<RecordBatch>>>;
+
+/// Blocking sort. With a memory limit the buffered input is sorted and spilled
+/// to disk as a run whenever it grows beyond the limit, and the runs are
+/// combined by a k-way merge once all inputs were received.
+pub struct Sort<'i> {
+    successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    expr: Vec<SortExpr>,
+    batches: Vec<Arc<RecordBatch>>,
+    batch_size: usize,
+    memory_limit: Option<usize>,
+    buffered_bytes: usize,
+    runs: Vec<SpillFile>,
+}
+
+impl<'i> Sort<'i> {
+    pub(crate) fn new(
+        sort_expr: Vec<SortExpr>,
+        successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    ) -> Self {
+        Self {
+            successor,
+            expr: sort_expr,
+            batches: Vec::new(),
+            batch_size: 0,
+            memory_limit: None,
+            buffered_bytes: 0,
+            runs: Vec::new(),
+        }
+    }
+
+    /// Limits the memory used for buffered input to roughly `bytes`.
+    pub(crate) const fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    /// Sorts and removes everything which is buffered in memory.
+    fn sort_buffered(&mut self) -> anyhow::Result<Option<RecordBatch>> {
+        let Some(first) = self.batches.first() else {
+            return Ok(None);
+        };
+        let batch =
+            compute::concat_batches(&first.schema(), self.batches.iter().map(AsRef::as_ref))?;
+        self.batches.clear();
+        self.buffered_bytes = 0;
+        Ok(Some(sort_batch(&self.expr, &batch)?))
+    }
+
+    fn spill(&mut self) -> anyhow::Result<()> {
+        if let Some(sorted) = self.sort_buffered()? {
+            let mut writer = SpillWriter::try_new(&sorted.schema())?;
+            for batch in self.slices(&sorted) {
+                writer.write(&batch)?;
+            }
+            self.runs.push(writer.finish()?);
+        }
+        Ok(())
+    }
+
+    fn slices(&self, batch: &RecordBatch) -> Vec<RecordBatch> {
+        (0..batch.num_rows())
+            .step_by(self.batch_size)
+            .map(|offset| batch.slice(offset, self.batch_size.min(batch.num_rows() - offset)))
+            .collect()
+    }
+
+    fn emit(&mut self, batch: RecordBatch) -> anyhow::Result<OperatorState> {
+        self.successor.execute(Arc::new(batch))
+    }
+
+    /// Merges the sorted runs. Ties are taken from the earlier run first, so the
+    /// result is the same as of a stable in-memory sort.
+    fn merge(&mut self, runs: Vec<SortedRun>, schema: &Schema) -> anyhow::Result<()> {
+        let converter = sort_converter(&self.expr, schema)?;
+        let mut loaded = Vec::new();
+        let mut cursors = Vec::with_capacity(runs.len());
+        let mut heap = BinaryHeap::with_capacity(runs.len());
+        for (run, batches) in runs.into_iter().enumerate() {
+            let mut cursor = RunCursor {
+                batches,
+                batch: 0,
+                keys: converter.empty_rows(0, 0),
+                row: 0,
+            };
+            if cursor.advance(&self.expr, &converter, &mut loaded)? {
+                heap.push(Reverse((cursor.key(), run)));
+            }
+            cursors.push(cursor);
+        }
+
+        let mut indices = Vec::with_capacity(self.batch_size);
+        while let Some(Reverse((_, run))) = heap.pop() {
+            let cursor = &mut cursors[run];
+            indices.push((cursor.batch, cursor.row));
+            cursor.row += 1;
+            if cursor.row < cursor.keys.num_rows()
+                || cursor.advance(&self.expr, &converter, &mut loaded)?
+            {
+                heap.push(Reverse((cursor.key(), run)));
+            }
+
+            if indices.len() == self.batch_size {
+                let batch = interleave_batches(&loaded, &indices)?;
+                indices.clear();
+                // Only keep the batches the cursors are still reading from.
+                loaded = cursors
+                    .iter_mut()
+                    .enumerate()
+                    .map(|(i, cursor)| {
+                        let batch = loaded[cursor.batch].clone();
+                        cursor.batch = i;
+                        batch
+                    })
+                    .collect();
+                if self.emit(batch)? == OperatorState::Finished {
+                    return Ok(());
+                }
+            }
+        }
+        if !indices.is_empty() {
+            let batch = interleave_batches(&loaded, &indices)?;
+            self.emit(batch)?;
+        }
+        Ok(())
+    }
+}
+
+/// Position in a sorted run during the merge.
+struct RunCursor {
+    batches: SortedRun,
+    /// Index of the current batch in the loaded batches.
+    batch: usize,
+    keys: Rows,
+    row: usize,
+}
+
+impl RunCursor {
+    /// Loads the next non-empty batch of the run, returns false at the end.
+    fn advance(
+        &mut self,
+        sort_expr: &[SortExpr],
+        converter: &RowConverter,
+        loaded: &mut Vec<RecordBatch>,
+    ) -> anyhow::Result<bool> {
+        for batch in self.batches.by_ref() {
+            let batch = batch?;
+            if batch.num_rows() > 0 {
+                self.keys = sort_keys(sort_expr, converter, &batch)?;
+                self.row = 0;
+                self.batch = loaded.len();
+                loaded.push(batch);
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn key(&self) -> OwnedRow {
+        self.keys.row(self.row).owned()
+    }
+}
+
+impl Operator<Arc<RecordBatch>> for Sort<'_> {
+    fn execute(&mut self, input: Arc<RecordBatch>) -> anyhow::Result<OperatorState> {
+        self.batch_size = self.batch_size.max(input.num_rows());
+        self.buffered_bytes += input.get_array_memory_size();
+        self.batches.push(input);
+        if self
+            .memory_limit
+            .is_some_and(|limit| self.buffered_bytes > limit)
+        {
+            self.spill()?;
+        }
+        Ok(OperatorState::NeedMoreInput)
+    }
+
+    fn all_inputs_received(&mut self) -> anyhow::Result<()> {
+        let sorted = self.sort_buffered()?;
+        if self.runs.is_empty() {
+            if let Some(sorted) = sorted {
+                for batch in self.slices(&sorted) {
+                    if self.emit(batch)? == OperatorState::Finished {
+                        break;
+                    }
+                }
+            }
+        } else {
+            // The spill files are deleted once the merge is done.
+            let spilled = std::mem::take(&mut self.runs);
+            let mut runs = spilled
+                .iter()
+                .map(|run| Ok(Box::new(run.read()?) as SortedRun))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let schema = spilled[0].schema();
+            if let Some(sorted) = sorted {
+                runs.push(Box::new(self.slices(&sorted).into_iter().map(Ok)));
+            }
+            self.merge(runs, &schema)?;
+        }
+        self.successor.all_inputs_received()
+    }
+}
+
+/// Creates a converter which encodes sort keys into the arrow row format, so
+/// multi-column comparisons become comparisons of byte strings.
+pub fn sort_converter(sort_expr: &[SortExpr], schema: &Schema) -> anyhow::Result<RowConverter> {
+    let fields = sort_expr
+        .iter()
+        .map(|sort| {
+            let field = sort.expr.to_field(schema)?;
+            let options = SortOptions {
+                descending: !sort.asc,
+                nulls_first: sort.nulls_first,
+            };
+            Ok(SortField::new_with_options(
+                field.data_type().clone(),
+                options,
+            ))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(RowConverter::new(fields)?)
+}
+
+pub fn sort_keys(
+    sort_expr: &[SortExpr],
+    converter: &RowConverter,
+    batch: &RecordBatch,
+) -> anyhow::Result<Rows> {
+    let columns = sort_expr
+        .iter()
+        .map(|sort| evaluate(&sort.expr, batch))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(converter.convert_columns(&columns)?)
+}
+
+pub fn sort_batch(sort_expr: &[SortExpr], batch: &RecordBatch) -> anyhow::Result<RecordBatch> {
+    let converter = sort_converter(sort_expr, &batch.schema())?;
+    let rows = sort_keys(sort_expr, &converter, batch)?;
+
+    let mut indices: Vec<u32> = (0..u32::try_from(batch.num_rows())?).collect();
+    indices.sort_by(|a, b| rows.row(*a as usize).cmp(&rows.row(*b as usize)));
+    take_batch(batch, &UInt32Array::from(indices))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::operators::collect::Collect;
+    use crate::execution::operators::scan::Scan;
+    use crate::logical_plan::expr::{Expr, Ident};
+    use arrow::array::{AsArray, Float64Array, StringArray};
+    use arrow::datatypes::{DataType, Field, Float64Type};
+
+    #[test]
+    fn test_sort_kernel() -> anyhow::Result<()> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("country", DataType::Utf8, true),
+            Field::new("salary", DataType::Float64, true),
+        ]));
+        let batches = [
+            (
+                vec![Some("fr"), None, Some("de")],
+                vec![Some(1.0), Some(2.0), None],
+            ),
+            (vec![Some("de"), Some("fr")], vec![Some(3.0), Some(4.0)]),
+        ];
+
+        let sort_expr = vec![
+            SortExpr {
+                expr: Expr::Ident(Ident {
+                    name: "country".to_string(),
+                }),
+                asc: true,
+                nulls_first: false,
+            },
+            SortExpr {
+                expr: Expr::Ident(Ident {
+                    name: "salary".to_string(),
+                }),
+                asc: false,
+                nulls_first: true,
+            },
+        ];
+
+        let mut res = Vec::new();
+
+        {
+            let collect = Box::new(Collect::new(&mut res));
+            let mut sort = Sort::new(sort_expr, collect);
+            for (country, salary) in batches {
+                let batch = RecordBatch::try_new(
+                    schema.clone(),
+                    vec![
+                        Arc::new(StringArray::from(country)),
+                        Arc::new(Float64Array::from(salary)),
+                    ],
+                )?;
+                sort.execute(Arc::new(batch))?;
+            }
+            sort.all_inputs_received()?;
+        }
+
+        let batch = compute::concat_batches(&schema, res.iter().map(AsRef::as_ref))?;
+        assert_eq!(res.len(), 2);
+        assert_eq!(
+            batch
+                .column(0)
+                .as_string::<i32>()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![Some("de"), Some("de"), Some("fr"), Some("fr"), None]
+        );
+        assert_eq!(
+            batch
+                .column(1)
+                .as_primitive::<Float64Type>()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![None, Some(3.0), Some(4.0), Some(1.0), Some(2.0)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_external_sort() -> anyhow::Result<()> {
+        let mut input = Vec::new();
+        {
+            let mut scan = Scan::new(Box::new(Collect::new(&mut input)));
+            for i in 1..=5 {
+                scan.execute((
+                    format!("samples/sample-data/parquet/userdata{i}.parquet"),
+                    100,
+                ))?;
+            }
+        }
+
+        let sort_expr = vec![
+            SortExpr {
+                expr: Expr::Ident(Ident {
+                    name: "country".to_string(),
+                }),
+                asc: true,
+                nulls_first: false,
+            },
+            SortExpr {
+                expr: Expr::Ident(Ident {
+                    name: "salary".to_string(),
+                }),
+                asc: false,
+                nulls_first: true,
+            },
+        ];
+
+        let mut res = Vec::new();
+        {
+            let collect = Box::new(Collect::new(&mut res));
+            let mut sort = Sort::new(sort_expr.clone(), collect).with_memory_limit(256 * 1024);
+            for batch in &input {
+                sort.execute(batch.clone())?;
+            }
+            assert!(sort.runs.len() > 1);
+            sort.all_inputs_received()?;
+        }
+
+        let schema = input[0].schema();
+        let expected = sort_batch(
+            &sort_expr,
+            &compute::concat_batches(&schema, input.iter().map(AsRef::as_ref))?,
+        )?;
+        assert!(res.iter().all(|batch| batch.num_rows() == 100));
+        assert_eq!(
+            compute::concat_batches(&schema, res.iter().map(AsRef::as_ref))?,
+            expected
+        );
+
+        Ok(())
+    }
+}
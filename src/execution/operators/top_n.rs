@@ -0,0 +1,196 @@
+use crate::execution::operators::select::interleave_batches;
+use crate::execution::operators::sort::{sort_converter, sort_keys};
+use crate::execution::operators::{Operator, OperatorState};
+use crate::logical_plan::expr::SortExpr;
+use arrow::array::RecordBatch;
+use arrow::row::{OwnedRow, RowConverter};
+use std::collections::BinaryHeap;
+use std::sync::Arc;
+
+/// Candidate row of the result. Ties on the sort key are broken by the input
+/// position, so the output matches a stable sort followed by a limit.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct TopNRow {
+    key: OwnedRow,
+    batch: usize,
+    row: usize,
+}
+
+/// Sort with a limit which only keeps the first `fetch` rows in a max-heap
+/// instead of buffering the whole input.
+pub struct TopN<'i> {
+    successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    expr: Vec<SortExpr>,
+    fetch: usize,
+    converter: Option<RowConverter>,
+    heap: BinaryHeap<TopNRow>,
+    /// Input batches which still hold candidate rows.
+    batches: Vec<RecordBatch>,
+    batch_size: usize,
+}
+
+impl<'i> TopN<'i> {
+    pub(crate) fn new(
+        sort_expr: Vec<SortExpr>,
+        fetch: usize,
+        successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    ) -> Self {
+        Self {
+            successor,
+            expr: sort_expr,
+            fetch,
+            converter: None,
+            heap: BinaryHeap::with_capacity(fetch),
+            batches: Vec::new(),
+            batch_size: 0,
+        }
+    }
+
+    /// Gathers the rows of `entries` from the buffered batches.
+    fn take_rows(&self, entries: &[TopNRow]) -> anyhow::Result<RecordBatch> {
+        let indices = entries.iter().map(|e| (e.batch, e.row)).collect::<Vec<_>>();
+        interleave_batches(&self.batches, &indices)
+    }
+
+    /// Copies the candidates into a single batch, so input batches whose rows
+    /// were all evicted from the heap can be released.
+    fn compact(&mut self) -> anyhow::Result<()> {
+        let mut entries = std::mem::take(&mut self.heap).into_vec();
+        entries.sort_by_key(|e| (e.batch, e.row));
+        let batch = self.take_rows(&entries)?;
+        self.batches = vec![batch];
+        self.heap = entries
+            .into_iter()
+            .enumerate()
+            .map(|(row, e)| TopNRow {
+                key: e.key,
+                batch: 0,
+                row,
+            })
+            .collect();
+        Ok(())
+    }
+}
+
+impl Operator<Arc<RecordBatch>> for TopN<'_> {
+    fn execute(&mut self, input: Arc<RecordBatch>) -> anyhow::Result<OperatorState> {
+        self.batch_size = self.batch_size.max(input.num_rows());
+        if self.fetch == 0 {
+            return Ok(OperatorState::Finished);
+        }
+        let converter = match self.converter.take() {
+            Some(converter) => converter,
+            None => sort_converter(&self.expr, &input.schema())?,
+        };
+        let keys = sort_keys(&self.expr, &converter, &input)?;
+        self.converter = Some(converter);
+
+        let batch = self.batches.len();
+        let mut used = false;
+        for (row, key) in keys.iter().enumerate() {
+            if self.heap.len() == self.fetch {
+                // Only the key is compared, earlier rows win ties.
+                let max = self.heap.peek().expect("heap is full");
+                if key >= max.key.row() {
+                    continue;
+                }
+                self.heap.pop();
+            }
+            self.heap.push(TopNRow {
+                key: key.owned(),
+                batch,
+                row,
+            });
+            used = true;
+        }
+
+        if used {
+            self.batches.push(input.as_ref().clone());
+            let buffered: usize = self.batches.iter().map(RecordBatch::num_rows).sum();
+            if buffered > 2 * (self.fetch + self.batch_size) {
+                self.compact()?;
+            }
+        }
+        Ok(OperatorState::NeedMoreInput)
+    }
+
+    fn all_inputs_received(&mut self) -> anyhow::Result<()> {
+        if !self.heap.is_empty() {
+            let entries = std::mem::take(&mut self.heap).into_sorted_vec();
+            let sorted = self.take_rows(&entries)?;
+            self.batches.clear();
+
+            let mut offset = 0;
+            while offset < sorted.num_rows() {
+                let len = self.batch_size.min(sorted.num_rows() - offset);
+                let state = self
+                    .successor
+                    .execute(Arc::new(sorted.slice(offset, len)))?;
+                if state == OperatorState::Finished {
+                    break;
+                }
+                offset += len;
+            }
+        }
+        self.successor.all_inputs_received()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::operators::collect::Collect;
+    use crate::execution::operators::scan::Scan;
+    use crate::execution::operators::sort::sort_batch;
+    use crate::logical_plan::expr::{Expr, Ident};
+    use arrow::compute;
+
+    #[test]
+    fn test_top_n_matches_sort() -> anyhow::Result<()> {
+        let sort_expr = vec![
+            SortExpr {
+                expr: Expr::Ident(Ident {
+                    name: "registration_dttm".to_string(),
+                }),
+                asc: false,
+                nulls_first: true,
+            },
+            SortExpr {
+                expr: Expr::Ident(Ident {
+                    name: "salary".to_string(),
+                }),
+                asc: true,
+                nulls_first: false,
+            },
+        ];
+
+        let mut top = Vec::new();
+        let mut all = Vec::new();
+        {
+            let collect = Box::new(Collect::new(&mut top));
+            let mut scan = Scan::new(Box::new(TopN::new(sort_expr.clone(), 20, collect)));
+            for i in 1..=5 {
+                scan.execute((
+                    format!("samples/sample-data/parquet/userdata{i}.parquet"),
+                    128,
+                ))?;
+            }
+            scan.all_inputs_received()?;
+
+            let mut scan = Scan::new(Box::new(Collect::new(&mut all)));
+            for i in 1..=5 {
+                scan.execute((
+                    format!("samples/sample-data/parquet/userdata{i}.parquet"),
+                    128,
+                ))?;
+            }
+        }
+
+        assert_eq!(top.len(), 1);
+        let all = compute::concat_batches(&all[0].schema(), all.iter().map(AsRef::as_ref))?;
+        let expected = sort_batch(&sort_expr, &all)?.slice(0, 20);
+        assert_eq!(top[0].as_ref(), &expected);
+
+        Ok(())
+    }
+}
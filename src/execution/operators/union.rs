@@ -0,0 +1,149 @@
+use crate::execution::operators::distinct::DistinctRows;
+use crate::execution::operators::select::cast_batch;
+use crate::execution::operators::{Operator, OperatorState};
+use crate::logical_plan::SetOperation;
+use arrow::array::RecordBatch;
+use arrow::datatypes::SchemaRef;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Union of any number of inputs, each fed through its own `UnionInput`. Rows
+/// are forwarded as they arrive, after casting them to the types of the union.
+/// For `UNION`, rows which were forwarded before are dropped.
+pub struct Union<'i> {
+    successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    schema: SchemaRef,
+    seen: Option<DistinctRows>,
+    /// Inputs which may still send rows.
+    open_inputs: usize,
+    finished: bool,
+}
+
+impl<'i> Union<'i> {
+    pub(crate) fn new(
+        union: &SetOperation,
+        inputs: usize,
+        successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    ) -> anyhow::Result<Self> {
+        let seen = if union.distinct {
+            Some(DistinctRows::new(&union.schema)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            successor,
+            schema: union.schema.clone(),
+            seen,
+            open_inputs: inputs,
+            finished: false,
+        })
+    }
+
+    fn execute(&mut self, input: &RecordBatch) -> anyhow::Result<OperatorState> {
+        if self.finished {
+            return Ok(OperatorState::Finished);
+        }
+        let mut batch = cast_batch(input, &self.schema)?;
+        if let Some(seen) = &mut self.seen {
+            batch = seen.retain_new(batch)?;
+        }
+        if batch.num_rows() > 0
+            && self.successor.execute(Arc::new(batch))? == OperatorState::Finished
+        {
+            self.finished = true;
+            return Ok(OperatorState::Finished);
+        }
+        Ok(OperatorState::NeedMoreInput)
+    }
+
+    fn all_inputs_received(&mut self) -> anyhow::Result<()> {
+        self.open_inputs -= 1;
+        if self.open_inputs == 0 {
+            self.successor.all_inputs_received()?;
+        }
+        Ok(())
+    }
+}
+
+/// One input of a shared `Union`.
+pub struct UnionInput<'i> {
+    union: Rc<RefCell<Union<'i>>>,
+}
+
+impl Operator<Arc<RecordBatch>> for UnionInput<'_> {
+    fn execute(&mut self, input: Arc<RecordBatch>) -> anyhow::Result<OperatorState> {
+        self.union.borrow_mut().execute(&input)
+    }
+
+    fn all_inputs_received(&mut self) -> anyhow::Result<()> {
+        self.union.borrow_mut().all_inputs_received()
+    }
+}
+
+/// Returns the inputs of `union`, as many as it was created for.
+pub fn union_inputs(union: Union<'_>) -> Vec<UnionInput<'_>> {
+    let inputs = union.open_inputs;
+    let union = Rc::new(RefCell::new(union));
+    (0..inputs)
+        .map(|_| UnionInput {
+            union: union.clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::operators::collect::Collect;
+    use arrow::array::{AsArray, Int32Array, Int64Array};
+    use arrow::datatypes::{DataType, Field, Int64Type, Schema};
+
+    #[test]
+    fn test_union() -> anyhow::Result<()> {
+        let int32 = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, true)]));
+        let int64 = Arc::new(Schema::new(vec![Field::new("b", DataType::Int64, true)]));
+        let left = RecordBatch::try_new(
+            int32,
+            vec![Arc::new(Int32Array::from(vec![
+                Some(1),
+                None,
+                Some(2),
+                Some(1),
+            ]))],
+        )?;
+        let right = RecordBatch::try_new(
+            int64,
+            vec![Arc::new(Int64Array::from(vec![Some(2), Some(3), None]))],
+        )?;
+
+        for (distinct, expected) in [
+            (true, vec![Some(1), None, Some(2), Some(3)]),
+            (
+                false,
+                vec![Some(1), None, Some(2), Some(1), Some(2), Some(3), None],
+            ),
+        ] {
+            let union = SetOperation {
+                distinct,
+                schema: Arc::new(Schema::new(vec![Field::new("a", DataType::Int64, true)])),
+            };
+            let mut res = Vec::new();
+            {
+                let collect = Box::new(Collect::new(&mut res));
+                let mut inputs = union_inputs(Union::new(&union, 2, collect)?);
+                inputs[0].execute(Arc::new(left.clone()))?;
+                inputs[1].execute(Arc::new(right.clone()))?;
+                inputs[1].all_inputs_received()?;
+                inputs[0].all_inputs_received()?;
+            }
+            let values = res
+                .iter()
+                .flat_map(|batch| batch.column(0).as_primitive::<Int64Type>().iter())
+                .collect::<Vec<_>>();
+            assert_eq!(values, expected, "distinct: {distinct}");
+        }
+
+        Ok(())
+    }
+}
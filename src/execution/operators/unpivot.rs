@@ -0,0 +1,154 @@
+use crate::execution::operators::{Operator, OperatorState};
+use crate::logical_plan;
+use arrow::array::{Array, ArrayRef, RecordBatch, StringArray, UInt32Array};
+use arrow::compute::{self, CastOptions};
+use arrow::datatypes::SchemaRef;
+use std::sync::Arc;
+
+/// Fans each input row out into a row per unpivoted column with a non-NULL
+/// value. The rows of an input row are returned together, in the order of the
+/// unpivoted columns, so the order of the input is kept.
+pub struct Unpivot<'i> {
+    successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    columns: Vec<String>,
+    schema: SchemaRef,
+}
+
+impl<'i> Unpivot<'i> {
+    pub(crate) fn new(
+        unpivot: &logical_plan::Unpivot,
+        successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    ) -> Self {
+        Self {
+            successor,
+            columns: unpivot.columns.clone(),
+            schema: unpivot.schema.clone(),
+        }
+    }
+}
+
+impl Operator<Arc<RecordBatch>> for Unpivot<'_> {
+    fn execute(&mut self, input: Arc<RecordBatch>) -> anyhow::Result<OperatorState> {
+        let fields = self.schema.fields();
+        let value_type = fields[fields.len() - 1].data_type();
+        let values = self
+            .columns
+            .iter()
+            .map(|column| {
+                let values = input
+                    .column_by_name(column)
+                    .ok_or_else(|| anyhow::anyhow!("Column with name {column} not found"))?;
+                let options = CastOptions {
+                    safe: false,
+                    ..CastOptions::default()
+                };
+                Ok(compute::cast_with_options(values, value_type, &options)?)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        // (column, row) of each output row.
+        let mut indices = Vec::with_capacity(input.num_rows() * values.len());
+        for row in 0..input.num_rows() {
+            for (column, values) in values.iter().enumerate() {
+                if values.is_valid(row) {
+                    indices.push((column, row));
+                }
+            }
+        }
+        if indices.is_empty() {
+            return Ok(OperatorState::NeedMoreInput);
+        }
+
+        let rows = indices
+            .iter()
+            .map(|&(_, row)| u32::try_from(row))
+            .collect::<Result<Vec<_>, _>>()?;
+        let rows = UInt32Array::from(rows);
+        let mut columns = Vec::with_capacity(fields.len());
+        for field in &fields[..fields.len() - 2] {
+            let column = input
+                .column_by_name(field.name())
+                .ok_or_else(|| anyhow::anyhow!("Column with name {} not found", field.name()))?;
+            columns.push(compute::take(column, &rows, None)?);
+        }
+        let names = indices
+            .iter()
+            .map(|&(column, _)| self.columns[column].as_str());
+        columns.push(Arc::new(StringArray::from_iter_values(names)) as ArrayRef);
+        let values = values.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+        columns.push(compute::interleave(&values, &indices)?);
+
+        let batch = RecordBatch::try_new(self.schema.clone(), columns)?;
+        self.successor.execute(Arc::new(batch))
+    }
+
+    fn all_inputs_received(&mut self) -> anyhow::Result<()> {
+        self.successor.all_inputs_received()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::DummyCatalog;
+    use crate::execution::operators::collect::Collect;
+    use crate::logical_plan::LogicalPlan;
+    use crate::parser::sql_parser::parse_sql_query;
+    use arrow::array::{AsArray, Int32Array, Int64Array};
+    use arrow::datatypes::{DataType, Field, Int64Type, Schema};
+
+    #[test]
+    fn test_unpivot() -> anyhow::Result<()> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("q1", DataType::Int32, true),
+            Field::new("q2", DataType::Int64, true),
+        ]));
+        let mut catalog = DummyCatalog::new();
+        catalog.add_table("survey", schema.clone());
+        let dag = parse_sql_query(
+            "SELECT s.id, s.question, s.answer \
+             FROM survey UNPIVOT (answer FOR question IN (q1, q2)) AS s",
+            &catalog,
+        )?;
+        let unpivot = (0..dag.len())
+            .find_map(|id| match dag.get_node(id) {
+                LogicalPlan::Unpivot(unpivot) => Some(unpivot),
+                _ => None,
+            })
+            .unwrap();
+        let output = unpivot.schema.fields();
+        assert_eq!(output[1].name(), "question");
+        assert_eq!(output[2].data_type(), &DataType::Int64);
+
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2, 3])),
+                Arc::new(Int32Array::from(vec![Some(5), None, None])),
+                Arc::new(Int64Array::from(vec![Some(4), Some(3), None])),
+            ],
+        )?;
+        let mut res = Vec::new();
+        {
+            let collect = Box::new(Collect::new(&mut res));
+            let mut unpivot = Unpivot::new(unpivot, collect);
+            unpivot.execute(Arc::new(batch))?;
+            unpivot.all_inputs_received()?;
+        }
+
+        // NULL values don't make rows, so the row of id 3 disappears.
+        let result = &res[0];
+        let ids = result.column(0).as_primitive::<Int64Type>();
+        let questions = result.column(1).as_string::<i32>();
+        let answers = result.column(2).as_primitive::<Int64Type>();
+        assert_eq!(ids.values(), &[1, 1, 2]);
+        assert_eq!(
+            questions.iter().flatten().collect::<Vec<_>>(),
+            ["q1", "q2", "q2"]
+        );
+        assert_eq!(answers.values(), &[5, 4, 3]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,822 @@
+use crate::execution::accumulator::{create_accumulator, Accumulator};
+use crate::execution::evaluator::evaluate;
+use crate::execution::operators::sort::sort_batch;
+use crate::execution::operators::{Operator, OperatorState};
+use crate::logical_plan;
+use crate::logical_plan::expr::{
+    AggregateFunc, AggregateFunction, Expr, SortExpr, WindowFrame, WindowFrameBound,
+    WindowFrameUnits, WindowFunc, WindowFunction,
+};
+use arrow::array::{
+    new_empty_array, new_null_array, Array, ArrayRef, AsArray, BooleanArray, Float64Array,
+    Int64Array, RecordBatch, UInt32Array,
+};
+use arrow::compute;
+use arrow::datatypes::{DataType, Float64Type, SchemaRef};
+use arrow::row::{RowConverter, SortField};
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Rows with equal partition keys, and the groups of peers among them, rows
+/// with equal ORDER BY keys. All ranges are indices of the sorted input.
+struct Partition {
+    rows: Range<usize>,
+    peers: Vec<Range<usize>>,
+}
+
+/// Evaluates window functions which have the same PARTITION BY and ORDER BY.
+/// The input is buffered and sorted by the partition keys and then the ORDER
+/// BY keys, unless it's known to be sorted like that. Each function is then
+/// evaluated partition by partition, and the rows are returned in this order
+/// with the values of the functions as additional columns.
+pub struct Window<'i> {
+    successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    functions: Vec<WindowFunction>,
+    partition_by: Vec<Expr>,
+    order_by: Vec<SortExpr>,
+    /// Whether the input is already sorted like `sort_expr` sorts it.
+    sorted: bool,
+    schema: SchemaRef,
+    batches: Vec<Arc<RecordBatch>>,
+    batch_size: usize,
+}
+
+impl<'i> Window<'i> {
+    pub(crate) fn new(
+        window: &logical_plan::Window,
+        sorted: bool,
+        successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    ) -> anyhow::Result<Self> {
+        let functions = window
+            .window_expr
+            .iter()
+            .map(|expr| match expr {
+                Expr::WindowFunction(function) => Ok(function.clone()),
+                _ => Err(anyhow::anyhow!(
+                    "Expression {expr} is not a window function"
+                )),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let (partition_by, order_by) = functions.first().map_or_else(Default::default, |f| {
+            (f.partition_by.clone(), f.order_by.clone())
+        });
+        Ok(Self {
+            successor,
+            functions,
+            partition_by,
+            order_by,
+            sorted,
+            schema: window.schema.clone(),
+            batches: Vec::new(),
+            batch_size: 0,
+        })
+    }
+
+    fn partitions(&self, batch: &RecordBatch) -> anyhow::Result<Vec<Partition>> {
+        let partitions = boundaries(&self.partition_by, batch)?;
+        let order_by = self.order_by.iter().map(|sort| sort.expr.clone());
+        let keys = self.partition_by.iter().cloned().chain(order_by);
+        let mut peers = boundaries(&keys.collect::<Vec<_>>(), batch)?
+            .into_iter()
+            .peekable();
+        Ok(partitions
+            .into_iter()
+            .map(|rows| {
+                let peers =
+                    std::iter::from_fn(|| peers.next_if(|peers| peers.end <= rows.end)).collect();
+                Partition { rows, peers }
+            })
+            .collect())
+    }
+
+    fn evaluate(
+        function: &WindowFunction,
+        batch: &RecordBatch,
+        partitions: &[Partition],
+    ) -> anyhow::Result<ArrayRef> {
+        let mut args = Vec::with_capacity(function.args.len());
+        for arg in &function.args {
+            // count(*) counts every row, so it gets a column without nulls.
+            args.push(match arg {
+                Expr::Wildcard => Arc::new(BooleanArray::from(vec![true; batch.num_rows()])),
+                _ => evaluate(arg, batch)?,
+            });
+        }
+        match &function.func {
+            WindowFunc::RowNumber
+            | WindowFunc::Rank
+            | WindowFunc::DenseRank
+            | WindowFunc::PercentRank
+            | WindowFunc::CumeDist
+            | WindowFunc::Ntile => rank(function, partitions),
+            WindowFunc::Lag | WindowFunc::Lead => offset_value(function, &args, partitions),
+            WindowFunc::FirstValue | WindowFunc::LastValue | WindowFunc::NthValue => {
+                let frames = Frames::new(function, batch)?;
+                let mut indices = Vec::with_capacity(batch.num_rows());
+                let nth = match function.args.get(1) {
+                    Some(Expr::IntegerLiteral(n)) => usize::try_from(n.value)?,
+                    _ => 1,
+                };
+                frames.for_each(partitions, |frame| {
+                    let index = match function.func {
+                        WindowFunc::FirstValue => (!frame.is_empty()).then_some(frame.start),
+                        WindowFunc::LastValue => (!frame.is_empty()).then(|| frame.end - 1),
+                        _ => Some(frame.start + nth - 1).filter(|&index| index < frame.end),
+                    };
+                    indices.push(index.map(u32::try_from).transpose()?);
+                    Ok(())
+                })?;
+                Ok(compute::take(&args[0], &UInt32Array::from(indices), None)?)
+            }
+            WindowFunc::Aggregate(func) => aggregate(function, func, &args, batch, partitions),
+        }
+    }
+
+    fn emit(&mut self, batch: &RecordBatch) -> anyhow::Result<()> {
+        let batch_size = self.batch_size.max(1);
+        for offset in (0..batch.num_rows()).step_by(batch_size) {
+            let len = batch_size.min(batch.num_rows() - offset);
+            if self.successor.execute(Arc::new(batch.slice(offset, len)))?
+                == OperatorState::Finished
+            {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Operator<Arc<RecordBatch>> for Window<'_> {
+    fn execute(&mut self, input: Arc<RecordBatch>) -> anyhow::Result<OperatorState> {
+        self.batch_size = self.batch_size.max(input.num_rows());
+        self.batches.push(input);
+        Ok(OperatorState::NeedMoreInput)
+    }
+
+    fn all_inputs_received(&mut self) -> anyhow::Result<()> {
+        if let Some(first) = self.batches.first() {
+            let batch =
+                compute::concat_batches(&first.schema(), self.batches.iter().map(AsRef::as_ref))?;
+            self.batches.clear();
+            let batch = if self.sorted {
+                batch
+            } else {
+                sort_batch(&sort_expr(&self.partition_by, &self.order_by), &batch)?
+            };
+
+            let partitions = self.partitions(&batch)?;
+            let mut columns = batch.columns().to_vec();
+            for function in &self.functions {
+                columns.push(Self::evaluate(function, &batch, &partitions)?);
+            }
+            let batch = RecordBatch::try_new(self.schema.clone(), columns)?;
+            self.emit(&batch)?;
+        }
+        self.successor.all_inputs_received()
+    }
+}
+
+/// Order in which a window sorts its input: by the partition keys and then by
+/// the ORDER BY keys.
+pub fn sort_expr(partition_by: &[Expr], order_by: &[SortExpr]) -> Vec<SortExpr> {
+    let partition_by = partition_by.iter().map(|expr| SortExpr {
+        expr: expr.clone(),
+        asc: true,
+        nulls_first: false,
+    });
+    partition_by.chain(order_by.iter().cloned()).collect()
+}
+
+/// Splits `batch` into ranges of adjacent rows with equal values of `exprs`.
+fn boundaries(exprs: &[Expr], batch: &RecordBatch) -> anyhow::Result<Vec<Range<usize>>> {
+    let num_rows = batch.num_rows();
+    if exprs.is_empty() {
+        return Ok((num_rows > 0).then_some(0..num_rows).into_iter().collect());
+    }
+    let columns = exprs
+        .iter()
+        .map(|expr| evaluate(expr, batch))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let fields = columns
+        .iter()
+        .map(|column| SortField::new(column.data_type().clone()))
+        .collect();
+    let rows = RowConverter::new(fields)?.convert_columns(&columns)?;
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    for i in 1..=num_rows {
+        if i == num_rows || rows.row(i) != rows.row(i - 1) {
+            ranges.push(start..i);
+            start = i;
+        }
+    }
+    Ok(ranges)
+}
+
+/// Evaluates a ranking function, or `ntile`, which only depend on the position
+/// of a row and its peers in the partition.
+// Fractions of partitions beyond 2^52 rows may be rounded.
+#[allow(clippy::cast_precision_loss)]
+fn rank(function: &WindowFunction, partitions: &[Partition]) -> anyhow::Result<ArrayRef> {
+    let mut ranks = Vec::new();
+    let mut fractions = Vec::new();
+    for partition in partitions {
+        let start = partition.rows.start;
+        let len = partition.rows.len();
+        for (group, peers) in partition.peers.iter().enumerate() {
+            for row in peers.clone() {
+                let rank = peers.start - start + 1;
+                match &function.func {
+                    WindowFunc::RowNumber => ranks.push(i64::try_from(row - start + 1)?),
+                    WindowFunc::Rank => ranks.push(i64::try_from(rank)?),
+                    WindowFunc::DenseRank => ranks.push(i64::try_from(group + 1)?),
+                    WindowFunc::PercentRank if len > 1 => {
+                        fractions.push((rank - 1) as f64 / (len - 1) as f64);
+                    }
+                    WindowFunc::PercentRank => fractions.push(0.0),
+                    WindowFunc::CumeDist => fractions.push((peers.end - start) as f64 / len as f64),
+                    _ => {
+                        let buckets = match function.args.first() {
+                            Some(Expr::IntegerLiteral(n)) => usize::try_from(n.value)?,
+                            _ => 1,
+                        };
+                        ranks.push(i64::try_from(ntile(row - start, len, buckets))?);
+                    }
+                }
+            }
+        }
+    }
+    Ok(match function.func {
+        WindowFunc::PercentRank | WindowFunc::CumeDist => Arc::new(Float64Array::from(fractions)),
+        _ => Arc::new(Int64Array::from(ranks)),
+    })
+}
+
+/// Bucket of the `row`-th of `len` rows split into `buckets` buckets, whose sizes
+/// differ by at most one, larger buckets first.
+const fn ntile(row: usize, len: usize, buckets: usize) -> usize {
+    let size = len / buckets;
+    let larger = len % buckets;
+    if row < larger * (size + 1) {
+        row / (size + 1) + 1
+    } else {
+        larger + (row - larger * (size + 1)) / size + 1
+    }
+}
+
+/// Evaluates `lag` or `lead`, the value of the row `offset` rows before or after
+/// the current row, or the default if there's no such row in the partition.
+fn offset_value(
+    function: &WindowFunction,
+    args: &[ArrayRef],
+    partitions: &[Partition],
+) -> anyhow::Result<ArrayRef> {
+    let values = &args[0];
+    let offset = match function.args.get(1) {
+        Some(Expr::IntegerLiteral(offset)) => usize::try_from(offset.value)?,
+        _ => 1,
+    };
+    let default = match args.get(2) {
+        Some(default) => compute::cast(default, values.data_type())?,
+        None => new_null_array(values.data_type(), values.len()),
+    };
+
+    let mut indices = Vec::with_capacity(values.len());
+    for partition in partitions {
+        for row in partition.rows.clone() {
+            let source = if function.func == WindowFunc::Lag {
+                row.checked_sub(offset)
+            } else {
+                row.checked_add(offset)
+            };
+            match source {
+                Some(source) if partition.rows.contains(&source) => indices.push((0, source)),
+                _ => indices.push((1, row)),
+            }
+        }
+    }
+    Ok(compute::interleave(
+        &[values.as_ref(), default.as_ref()],
+        &indices,
+    )?)
+}
+
+/// Evaluates an aggregate function over the frame of each row. If every frame
+/// of a partition contains the one before it, or the one after it, such as
+/// frames which start at UNBOUNDED PRECEDING or end at UNBOUNDED FOLLOWING, a
+/// single accumulator visits the rows in that order and only adds the rows by
+/// which the frame grew. Other frames slide, and are aggregated from the
+/// partial states of a segment tree.
+fn aggregate(
+    function: &WindowFunction,
+    func: &AggregateFunc,
+    args: &[ArrayRef],
+    batch: &RecordBatch,
+    partitions: &[Partition],
+) -> anyhow::Result<ArrayRef> {
+    let aggregate = AggregateFunction {
+        func: func.clone(),
+        args: function.args.clone(),
+        distinct: false,
+        filter: None,
+    };
+    let arg_types = args
+        .iter()
+        .map(|arg| arg.data_type().clone())
+        .collect::<Vec<_>>();
+    if batch.num_rows() == 0 {
+        return Ok(new_empty_array(&func.return_type(&arg_types)?));
+    }
+    let new_accumulator = || create_accumulator(&aggregate, &arg_types);
+
+    let mut frames = Vec::with_capacity(batch.num_rows());
+    Frames::new(function, batch)?.for_each(partitions, |frame| {
+        frames.push(frame);
+        Ok(())
+    })?;
+    let contains = |frame: &Range<usize>, other: &Range<usize>| {
+        frame.start <= other.start && other.end <= frame.end
+    };
+    let mut values = Vec::with_capacity(batch.num_rows());
+    for partition in partitions {
+        let frames = &frames[partition.rows.clone()];
+        if frames.windows(2).all(|w| contains(&w[1], &w[0])) {
+            values.extend(grow(frames.iter(), args, new_accumulator()?)?);
+        } else if frames.windows(2).all(|w| contains(&w[0], &w[1])) {
+            let mut grown = grow(frames.iter().rev(), args, new_accumulator()?)?;
+            grown.reverse();
+            values.extend(grown);
+        } else {
+            let tree = SegmentTree::new(partition.rows.clone(), args, &new_accumulator)?;
+            for frame in frames {
+                values.push(tree.evaluate(frame.clone(), &new_accumulator)?);
+            }
+        }
+    }
+    let values = values.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+    Ok(compute::concat(&values)?)
+}
+
+/// Evaluates `accumulator` over `frames`, each of which contains the previous
+/// one, by adding only the rows that are new to each frame.
+fn grow<'a>(
+    frames: impl Iterator<Item = &'a Range<usize>>,
+    args: &[ArrayRef],
+    mut accumulator: Box<dyn Accumulator>,
+) -> anyhow::Result<Vec<ArrayRef>> {
+    let mut values = Vec::new();
+    let mut state: Option<(Range<usize>, ArrayRef)> = None;
+    for frame in frames {
+        let value = match state.take() {
+            Some((rows, value)) if rows == *frame => value,
+            previous => {
+                let rows = previous.map_or(frame.start..frame.start, |(rows, _)| rows);
+                update(accumulator.as_mut(), args, frame.start..rows.start)?;
+                update(accumulator.as_mut(), args, rows.end..frame.end)?;
+                accumulator.evaluate()?
+            }
+        };
+        values.push(value.clone());
+        state = Some((frame.clone(), value));
+    }
+    Ok(values)
+}
+
+/// Adds `rows` of the arguments `args` to `accumulator`.
+fn update(
+    accumulator: &mut dyn Accumulator,
+    args: &[ArrayRef],
+    rows: Range<usize>,
+) -> anyhow::Result<()> {
+    if rows.is_empty() {
+        return Ok(());
+    }
+    let args = args
+        .iter()
+        .map(|arg| arg.slice(rows.start, rows.len()))
+        .collect::<Vec<_>>();
+    accumulator.update_batch(&args)
+}
+
+/// Partial aggregates of the rows of a partition, so that the aggregate of any
+/// frame merges the states of at most two nodes per level.
+struct SegmentTree {
+    /// The state of row `offset + i` is at `len + i`, and node `i < len` holds
+    /// the merged states of nodes `2 * i` and `2 * i + 1`.
+    states: Vec<Vec<ArrayRef>>,
+    offset: usize,
+}
+
+impl SegmentTree {
+    fn new(
+        rows: Range<usize>,
+        args: &[ArrayRef],
+        new_accumulator: &dyn Fn() -> anyhow::Result<Box<dyn Accumulator>>,
+    ) -> anyhow::Result<Self> {
+        let len = rows.len();
+        let mut states = vec![Vec::new(); 2 * len];
+        for (i, row) in rows.clone().enumerate() {
+            let mut accumulator = new_accumulator()?;
+            update(accumulator.as_mut(), args, row..row + 1)?;
+            states[len + i] = accumulator.state()?;
+        }
+        for node in (1..len).rev() {
+            let children = [&states[2 * node], &states[2 * node + 1]];
+            states[node] = merge(&children, new_accumulator)?.state()?;
+        }
+        Ok(Self {
+            states,
+            offset: rows.start,
+        })
+    }
+
+    /// Evaluates the aggregate over `frame`, which lies within the partition.
+    fn evaluate(
+        &self,
+        frame: Range<usize>,
+        new_accumulator: &dyn Fn() -> anyhow::Result<Box<dyn Accumulator>>,
+    ) -> anyhow::Result<ArrayRef> {
+        let len = self.states.len() / 2;
+        let mut start = frame.start - self.offset + len;
+        let mut end = frame.end - self.offset + len;
+        let mut nodes = Vec::new();
+        while start < end {
+            if start % 2 == 1 {
+                nodes.push(&self.states[start]);
+                start += 1;
+            }
+            if end % 2 == 1 {
+                end -= 1;
+                nodes.push(&self.states[end]);
+            }
+            start /= 2;
+            end /= 2;
+        }
+        merge(&nodes, new_accumulator)?.evaluate()
+    }
+}
+
+/// Returns a new accumulator which merged `states`.
+fn merge(
+    states: &[&Vec<ArrayRef>],
+    new_accumulator: &dyn Fn() -> anyhow::Result<Box<dyn Accumulator>>,
+) -> anyhow::Result<Box<dyn Accumulator>> {
+    let mut accumulator = new_accumulator()?;
+    let Some(first) = states.first() else {
+        return Ok(accumulator);
+    };
+    let columns = (0..first.len())
+        .map(|column| {
+            let arrays = states
+                .iter()
+                .map(|state| state[column].as_ref())
+                .collect::<Vec<_>>();
+            compute::concat(&arrays)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    accumulator.merge_batch(&columns)?;
+    Ok(accumulator)
+}
+
+/// Computes the frame of each row for functions which use one.
+struct Frames {
+    frame: WindowFrame,
+    /// Values of the single ORDER BY key, negated if it's descending, for RANGE
+    /// frames with offsets.
+    keys: Option<Float64Array>,
+}
+
+impl Frames {
+    fn new(function: &WindowFunction, batch: &RecordBatch) -> anyhow::Result<Self> {
+        let frame = function.frame();
+        let has_offset = [&frame.start, &frame.end].into_iter().any(|bound| {
+            matches!(
+                bound,
+                WindowFrameBound::Preceding(_) | WindowFrameBound::Following(_)
+            )
+        });
+        let keys = match (frame.units, function.order_by.as_slice()) {
+            (WindowFrameUnits::Range, [sort]) if has_offset => {
+                let keys = compute::cast(&evaluate(&sort.expr, batch)?, &DataType::Float64)?;
+                let keys = keys.as_primitive::<Float64Type>();
+                Some(if sort.asc {
+                    keys.clone()
+                } else {
+                    compute::unary(keys, |key: f64| -key)
+                })
+            }
+            _ => None,
+        };
+        Ok(Self { frame, keys })
+    }
+
+    /// Calls `f` with the frame of each row, in the order of the rows.
+    fn for_each(
+        &self,
+        partitions: &[Partition],
+        mut f: impl FnMut(Range<usize>) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        for partition in partitions {
+            let keyed = self.keyed_rows(partition);
+            for (group, peers) in partition.peers.iter().enumerate() {
+                for row in peers.clone() {
+                    let position = Position {
+                        partition,
+                        keyed: keyed.clone(),
+                        group,
+                        row,
+                    };
+                    let start = self.bound(&self.frame.start, true, &position);
+                    let end = self.bound(&self.frame.end, false, &position);
+                    f(start..end.max(start))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Rows of `partition` whose key isn't NULL, which sort before or after
+    /// all others.
+    fn keyed_rows(&self, partition: &Partition) -> Range<usize> {
+        let Some(keys) = &self.keys else {
+            return partition.rows.clone();
+        };
+        let mut rows = partition.rows.clone();
+        let Some(start) = rows.find(|&row| keys.is_valid(row)) else {
+            return partition.rows.start..partition.rows.start;
+        };
+        let end = rows.rev().find(|&row| keys.is_valid(row)).unwrap_or(start);
+        start..end + 1
+    }
+
+    /// Index of the first row of the frame if `start`, otherwise the index
+    /// after its last row.
+    fn bound(&self, bound: &WindowFrameBound, start: bool, position: &Position) -> usize {
+        let rows = &position.partition.rows;
+        let peers = &position.partition.peers;
+        let (offset, following) = match bound {
+            WindowFrameBound::UnboundedPreceding => return rows.start,
+            WindowFrameBound::UnboundedFollowing => return rows.end,
+            WindowFrameBound::CurrentRow if self.frame.units == WindowFrameUnits::Range => {
+                let peers = &peers[position.group];
+                return if start { peers.start } else { peers.end };
+            }
+            WindowFrameBound::CurrentRow => (None, true),
+            WindowFrameBound::Preceding(offset) => (Some(offset.as_ref()), false),
+            WindowFrameBound::Following(offset) => (Some(offset.as_ref()), true),
+        };
+        // Index of the row or group `offset` rows or groups away, if not
+        // before the first one.
+        let shift = |index: usize| {
+            let offset = match offset {
+                Some(Expr::IntegerLiteral(literal)) => {
+                    usize::try_from(literal.value).expect("frame offsets are non-negative")
+                }
+                _ => 0,
+            };
+            if following {
+                Some(index.saturating_add(offset))
+            } else {
+                index.checked_sub(offset)
+            }
+        };
+        match self.frame.units {
+            WindowFrameUnits::Rows => shift(position.row).map_or(rows.start, |target| {
+                (target + usize::from(!start)).clamp(rows.start, rows.end)
+            }),
+            WindowFrameUnits::Groups => match shift(position.group) {
+                None => rows.start,
+                Some(target) => match peers.get(target) {
+                    Some(peers) if start => peers.start,
+                    Some(peers) => peers.end,
+                    None => rows.end,
+                },
+            },
+            WindowFrameUnits::Range => {
+                let keys = self.keys.as_ref().expect("keys are set for offsets");
+                if keys.is_null(position.row) {
+                    // Rows without a key are only in the frames of each other.
+                    let peers = &peers[position.group];
+                    return if start { peers.start } else { peers.end };
+                }
+                let offset = offset.map_or(0.0, offset_of);
+                let key = keys.value(position.row);
+                let target = if following {
+                    key + offset
+                } else {
+                    key - offset
+                };
+                let keyed = &position.keyed;
+                let values = &keys.values()[keyed.clone()];
+                keyed.start
+                    + if start {
+                        values.partition_point(|&value| value < target)
+                    } else {
+                        values.partition_point(|&value| value <= target)
+                    }
+            }
+        }
+    }
+}
+
+/// Row whose frame is computed, with its partition.
+struct Position<'p> {
+    partition: &'p Partition,
+    keyed: Range<usize>,
+    group: usize,
+    row: usize,
+}
+
+fn offset_of(offset: &Expr) -> f64 {
+    match offset {
+        Expr::IntegerLiteral(literal) => f64::from(literal.value),
+        Expr::FloatLiteral(literal) => literal.value,
+        _ => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::DummyCatalog;
+    use crate::execution::operators::collect::Collect;
+    use crate::logical_plan::LogicalPlan;
+    use crate::parser::sql_parser::parse_sql_query;
+    use arrow::array::StringArray;
+    use arrow::datatypes::{Field, Int64Type, Schema};
+
+    /// Evaluates the window of `sql` over `input` and returns its columns.
+    fn run_window(
+        sql: &str,
+        catalog: &DummyCatalog,
+        input: &[Arc<RecordBatch>],
+    ) -> anyhow::Result<Vec<ArrayRef>> {
+        let dag = parse_sql_query(sql, catalog)?;
+        let window = (0..dag.len())
+            .find_map(|id| match dag.get_node(id) {
+                LogicalPlan::Window(window) => Some(window),
+                _ => None,
+            })
+            .unwrap();
+        let mut res = Vec::new();
+        {
+            let collect = Box::new(Collect::new(&mut res));
+            let mut window = Window::new(window, false, collect)?;
+            for batch in input {
+                window.execute(batch.clone())?;
+            }
+            window.all_inputs_received()?;
+        }
+        let batch = compute::concat_batches(&res[0].schema(), res.iter().map(AsRef::as_ref))?;
+        Ok(batch.columns().to_vec())
+    }
+
+    /// Table `t` with partitions a: 1, 2, 2, 4, 7 and b: 5, NULL of `x` by `g`.
+    fn input() -> anyhow::Result<(DummyCatalog, Vec<Arc<RecordBatch>>)> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("g", DataType::Utf8, false),
+            Field::new("x", DataType::Int64, true),
+        ]));
+        let mut catalog = DummyCatalog::new();
+        catalog.add_table("t", schema.clone());
+        let batch = |g: Vec<&str>, x: Vec<Option<i64>>| {
+            RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(StringArray::from(g)),
+                    Arc::new(Int64Array::from(x)),
+                ],
+            )
+            .map(Arc::new)
+        };
+        let input = vec![
+            batch(
+                vec!["a", "b", "a", "a"],
+                vec![Some(2), None, Some(1), Some(7)],
+            )?,
+            batch(vec!["a", "b", "a"], vec![Some(2), Some(5), Some(4)])?,
+        ];
+        Ok((catalog, input))
+    }
+
+    fn ints(array: &ArrayRef) -> Vec<Option<i64>> {
+        let array = compute::cast(array, &DataType::Int64).unwrap();
+        array.as_primitive::<Int64Type>().iter().collect()
+    }
+
+    #[test]
+    fn test_window() -> anyhow::Result<()> {
+        let (catalog, input) = input()?;
+        let run = |sql: &str| run_window(sql, &catalog, &input);
+        let floats = |array: &ArrayRef| array.as_primitive::<Float64Type>().values().to_vec();
+
+        // Partitions a: 1, 2, 2, 4, 7 and b: 5, NULL.
+        let columns = run("SELECT g, x, \
+             row_number() OVER w, rank() OVER w, dense_rank() OVER w, ntile(2) OVER w, \
+             percent_rank() OVER w, cume_dist() OVER w, \
+             lag(x) OVER w, lead(x, 2, 0) OVER w, \
+             sum(x) OVER w, last_value(x) OVER w, \
+             sum(x) OVER (w ROWS BETWEEN 1 PRECEDING AND 1 FOLLOWING), \
+             count(*) OVER (w RANGE BETWEEN 1 PRECEDING AND 1 FOLLOWING), \
+             count(x) OVER (w GROUPS BETWEEN 1 PRECEDING AND CURRENT ROW), \
+             nth_value(x, 2) OVER (w ROWS BETWEEN UNBOUNDED PRECEDING AND UNBOUNDED FOLLOWING) \
+             FROM t"
+            .replace("OVER w", "OVER (PARTITION BY g ORDER BY x)")
+            .replace("(w ", "(PARTITION BY g ORDER BY x ")
+            .as_str())?;
+        let expected = |values: &[i64]| values.iter().map(|&v| Some(v)).collect::<Vec<_>>();
+        assert_eq!(
+            ints(&columns[1]),
+            vec![Some(1), Some(2), Some(2), Some(4), Some(7), Some(5), None]
+        );
+        assert_eq!(ints(&columns[2]), expected(&[1, 2, 3, 4, 5, 1, 2]));
+        assert_eq!(ints(&columns[3]), expected(&[1, 2, 2, 4, 5, 1, 2]));
+        assert_eq!(ints(&columns[4]), expected(&[1, 2, 2, 3, 4, 1, 2]));
+        assert_eq!(ints(&columns[5]), expected(&[1, 1, 1, 2, 2, 1, 2]));
+        assert_eq!(floats(&columns[6]), [0.0, 0.25, 0.25, 0.75, 1.0, 0.0, 1.0]);
+        assert_eq!(floats(&columns[7]), [0.2, 0.6, 0.6, 0.8, 1.0, 0.5, 1.0]);
+        assert_eq!(
+            ints(&columns[8]),
+            vec![None, Some(1), Some(2), Some(2), Some(4), None, Some(5)]
+        );
+        assert_eq!(ints(&columns[9]), expected(&[2, 4, 7, 0, 0, 0, 0]));
+        assert_eq!(ints(&columns[10]), expected(&[1, 5, 5, 9, 16, 5, 5]));
+        assert_eq!(
+            ints(&columns[11]),
+            vec![Some(1), Some(2), Some(2), Some(4), Some(7), Some(5), None]
+        );
+        assert_eq!(ints(&columns[12]), expected(&[3, 5, 8, 13, 11, 5, 5]));
+        // A NULL key is only in the frame of other NULL keys.
+        assert_eq!(ints(&columns[13]), expected(&[3, 3, 3, 1, 1, 1, 1]));
+        assert_eq!(ints(&columns[14]), expected(&[1, 3, 3, 3, 2, 1, 1]));
+        assert_eq!(
+            ints(&columns[15]),
+            vec![Some(2), Some(2), Some(2), Some(2), Some(2), None, None]
+        );
+
+        // Offsets of descending keys go towards larger values, NULLs sort first.
+        let columns = run("SELECT x, \
+             count(*) OVER (ORDER BY x DESC RANGE BETWEEN 2 PRECEDING AND CURRENT ROW), \
+             sum(x) OVER (ORDER BY x DESC ROWS 1 PRECEDING) \
+             FROM t")?;
+        assert_eq!(
+            ints(&columns[1]),
+            vec![None, Some(7), Some(5), Some(4), Some(2), Some(2), Some(1)]
+        );
+        assert_eq!(ints(&columns[2]), expected(&[1, 1, 2, 2, 3, 3, 3]));
+        assert_eq!(
+            ints(&columns[3]),
+            vec![None, Some(7), Some(12), Some(9), Some(6), Some(4), Some(3)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_window_frames() -> anyhow::Result<()> {
+        let (catalog, input) = input()?;
+        let run = |sql: &str| run_window(sql, &catalog, &input);
+
+        // Frames to the end of the partition grow backwards, others slide.
+        let columns = run("SELECT x, \
+             sum(x) OVER (w ROWS BETWEEN CURRENT ROW AND UNBOUNDED FOLLOWING), \
+             max(x) OVER (w ROWS BETWEEN 2 PRECEDING AND 1 PRECEDING), \
+             avg(x) OVER (w ROWS BETWEEN 1 FOLLOWING AND 3 FOLLOWING) \
+             FROM t"
+            .replace("(w ", "(PARTITION BY g ORDER BY x ")
+            .as_str())?;
+        assert_eq!(
+            ints(&columns[2]),
+            vec![
+                Some(16),
+                Some(15),
+                Some(13),
+                Some(11),
+                Some(7),
+                Some(5),
+                None
+            ]
+        );
+        assert_eq!(
+            ints(&columns[3]),
+            vec![None, Some(1), Some(2), Some(2), Some(4), None, Some(5)]
+        );
+        assert_eq!(
+            columns[4]
+                .as_primitive::<Float64Type>()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![
+                Some(8.0 / 3.0),
+                Some(13.0 / 3.0),
+                Some(5.5),
+                Some(7.0),
+                None,
+                None,
+                None
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1147 @@
+use crate::catalog::{Catalog, DummyCatalog, TableSource};
+use crate::dag::Dag;
+use crate::execution::operators::aggregate::HashAggregate;
+use crate::execution::operators::collect::Collect;
+use crate::execution::operators::csv_scan::CsvScan;
+use crate::execution::operators::distinct::Distinct;
+use crate::execution::operators::fanout::Fanout;
+use crate::execution::operators::filter::Filter;
+use crate::execution::operators::hash_join::HashJoin;
+use crate::execution::operators::ipc_scan::IpcScan;
+use crate::execution::operators::json_scan::JsonScan;
+use crate::execution::operators::limit::Limit;
+use crate::execution::operators::merge_join::MergeJoin;
+use crate::execution::operators::multi_file_scan::MultiFileScan;
+use crate::execution::operators::nested_loop_join::NestedLoopJoin;
+use crate::execution::operators::project::Project;
+use crate::execution::operators::recursive_union::{
+    RecursiveTerm, RecursiveUnion, DEFAULT_MAX_ITERATIONS,
+};
+use crate::execution::operators::scan::Scan;
+use crate::execution::operators::select::Select;
+use crate::execution::operators::set_operation::{HashSetOperation, SetOperator};
+use crate::execution::operators::sort::Sort;
+use crate::execution::operators::top_n::TopN;
+use crate::execution::operators::union::{union_inputs, Union, UnionInput};
+use crate::execution::operators::unpivot::Unpivot;
+use crate::execution::operators::window::{self, Window};
+use crate::execution::operators::{input_ports, InputPort, Operator, OperatorState, Side};
+use crate::logical_plan::errors::PlanError;
+use crate::logical_plan::expr::{Expr, Ident, SortExpr};
+use crate::logical_plan::{LogicalPlan, NodeId, TableScan};
+use crate::optimizer::cardinality;
+use crate::optimizer::join_reorder::reorder_joins;
+use crate::parser::sql_parser::Statement;
+use arrow::array::RecordBatch;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Settings for executing a plan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExecutionConfig {
+    /// Number of rows of the batches tables are read in.
+    pub batch_size: usize,
+    /// Number of evaluations of the recursive term of a recursive CTE after
+    /// which the query fails.
+    pub max_recursive_iterations: usize,
+    /// Bytes of input each sort, hash join or distinct buffers before it spills
+    /// to disk, unlimited if `None`.
+    pub memory_limit: Option<usize>,
+}
+
+impl Default for ExecutionConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 8192,
+            max_recursive_iterations: DEFAULT_MAX_ITERATIONS,
+            memory_limit: None,
+        }
+    }
+}
+
+/// Executes the query `dag`, whose root is its last node, reading the tables
+/// from their sources in `catalog`, and returns its rows. The joins are
+/// reordered by the statistics of `catalog` first.
+pub fn execute_query(
+    dag: &Dag<LogicalPlan>,
+    catalog: &DummyCatalog,
+    config: ExecutionConfig,
+) -> anyhow::Result<Vec<Arc<RecordBatch>>> {
+    if dag.is_empty() {
+        anyhow::bail!("Query plan is empty");
+    }
+    let optimized = optimize_query(dag, catalog)?;
+    let mut res = Vec::new();
+    Lowering::new(&optimized, catalog, config, None)
+        .execute(dag.len() - 1, Box::new(Collect::new(&mut res)))?;
+    Ok(res)
+}
+
+/// Copy of the query `dag` with its joins reordered by the statistics of
+/// `catalog`, the plan [`execute_query`] runs. The root keeps its node id.
+pub fn optimize_query(
+    dag: &Dag<LogicalPlan>,
+    catalog: &dyn Catalog,
+) -> Result<Dag<LogicalPlan>, PlanError> {
+    let mut dag = dag.clone();
+    reorder_joins(&mut dag, catalog)?;
+    Ok(dag)
+}
+
+/// Executes `statement` and returns the rows of a query, or no rows for a
+/// statement which changes the catalog.
+pub fn execute_statement(
+    statement: &Statement,
+    catalog: &mut DummyCatalog,
+    config: ExecutionConfig,
+) -> anyhow::Result<Vec<Arc<RecordBatch>>> {
+    match statement {
+        Statement::Query(dag) => execute_query(dag, catalog, config),
+        Statement::Analyze { table_name } => {
+            catalog.analyze(table_name)?;
+            Ok(Vec::new())
+        }
+    }
+}
+
+/// Algorithm used to execute a join node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinStrategy {
+    /// Equi-join through a hash table over the build input.
+    Hash { build_side: Side },
+    /// Equi-join of two inputs which are sorted on their keys.
+    Merge,
+    /// Join on arbitrary predicates, which evaluates them on all pairs of rows.
+    NestedLoop { build_side: Side },
+}
+
+/// Chooses the join algorithm for the join `node`: a merge join if both inputs
+/// are known to be sorted on the equality keys, a hash join for other equality
+/// keys and a nested loop join if the predicate has no equality keys. The input
+/// with fewer estimated rows is buffered, the right one if they're equal.
+pub fn join_strategy(dag: &Dag<LogicalPlan>, node: NodeId, catalog: &dyn Catalog) -> JoinStrategy {
+    let LogicalPlan::Join(join) = dag.get_node(node) else {
+        panic!("node {node} is not a join");
+    };
+    let inputs = dag.get_inputs(node);
+    let left_rows = cardinality::estimate(dag, inputs[0], catalog).rows;
+    let right_rows = cardinality::estimate(dag, inputs[1], catalog).rows;
+    let build_side = if left_rows < right_rows {
+        Side::Left
+    } else {
+        Side::Right
+    };
+    if join.on.is_empty() {
+        return JoinStrategy::NestedLoop { build_side };
+    }
+    let (left_keys, right_keys): (Vec<_>, Vec<_>) = join.on.iter().cloned().unzip();
+    if is_sorted_on(&output_ordering(dag, inputs[0]), &left_keys)
+        && is_sorted_on(&output_ordering(dag, inputs[1]), &right_keys)
+    {
+        JoinStrategy::Merge
+    } else {
+        JoinStrategy::Hash { build_side }
+    }
+}
+
+/// Creates the operator for the join `node` and returns its left and right
+/// inputs. A hash join partitions its inputs on disk if the build input exceeds
+/// `memory_limit` bytes.
+pub fn create_join<'i>(
+    dag: &Dag<LogicalPlan>,
+    node: NodeId,
+    catalog: &dyn Catalog,
+    memory_limit: Option<usize>,
+    successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+) -> anyhow::Result<(InputPort<'i>, InputPort<'i>)> {
+    let LogicalPlan::Join(join) = dag.get_node(node) else {
+        anyhow::bail!("Node {node} is not a join");
+    };
+    let inputs = dag.get_inputs(node);
+    let left_schema = dag.get_node(inputs[0]).get_schema();
+    let right_schema = dag.get_node(inputs[1]).get_schema();
+    Ok(match join_strategy(dag, node, catalog) {
+        JoinStrategy::Hash { build_side } => {
+            let mut hash_join =
+                HashJoin::new(join, left_schema, right_schema, build_side, successor)?;
+            if let Some(bytes) = memory_limit {
+                hash_join = hash_join.with_memory_limit(bytes);
+            }
+            input_ports(hash_join)
+        }
+        JoinStrategy::Merge => {
+            input_ports(MergeJoin::new(join, left_schema, right_schema, successor)?)
+        }
+        JoinStrategy::NestedLoop { build_side } => input_ports(NestedLoopJoin::new(
+            join,
+            left_schema,
+            right_schema,
+            build_side,
+            successor,
+        )),
+    })
+}
+
+/// Creates the operator for the union `node` and returns one input for each of
+/// its inputs.
+pub fn create_union<'i>(
+    dag: &Dag<LogicalPlan>,
+    node: NodeId,
+    successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+) -> anyhow::Result<Vec<UnionInput<'i>>> {
+    let LogicalPlan::Union(union) = dag.get_node(node) else {
+        anyhow::bail!("Node {node} is not a union");
+    };
+    let inputs = dag.get_inputs(node).len();
+    Ok(union_inputs(Union::new(union, inputs, successor)?))
+}
+
+/// Creates the operator for the intersect or except `node` and returns its
+/// left and right inputs.
+pub fn create_set_operation<'i>(
+    dag: &Dag<LogicalPlan>,
+    node: NodeId,
+    successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+) -> anyhow::Result<(InputPort<'i>, InputPort<'i>)> {
+    let (op, set_operation) = match dag.get_node(node) {
+        LogicalPlan::Intersect(intersect) => (SetOperator::Intersect, intersect),
+        LogicalPlan::Except(except) => (SetOperator::Except, except),
+        _ => anyhow::bail!("Node {node} is not an intersect or except"),
+    };
+    Ok(input_ports(HashSetOperation::new(
+        op,
+        set_operation,
+        successor,
+    )?))
+}
+
+/// Creates the operator for the sort `node`, which only keeps the rows it
+/// returns in a heap if a limit above it fetches a bounded number of rows.
+/// Otherwise, it spills if its input exceeds `memory_limit` bytes.
+pub fn create_sort<'i>(
+    dag: &Dag<LogicalPlan>,
+    node: NodeId,
+    memory_limit: Option<usize>,
+    successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+) -> anyhow::Result<Box<dyn Operator<Arc<RecordBatch>> + 'i>> {
+    let LogicalPlan::Sort(sort) = dag.get_node(node) else {
+        anyhow::bail!("Node {node} is not a sort");
+    };
+    if let Some(fetch) = sort.fetch {
+        return Ok(Box::new(TopN::new(sort.expr.clone(), fetch, successor)));
+    }
+    let sort = Sort::new(sort.expr.clone(), successor);
+    Ok(match memory_limit {
+        Some(bytes) => Box::new(sort.with_memory_limit(bytes)),
+        None => Box::new(sort),
+    })
+}
+
+/// Creates the operator for the distinct `node`, which compares each key with
+/// the previous one if the input is sorted on the keys. Otherwise, it spills
+/// the keys it has seen if they exceed `memory_limit` bytes.
+pub fn create_distinct<'i>(
+    dag: &Dag<LogicalPlan>,
+    node: NodeId,
+    memory_limit: Option<usize>,
+    successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+) -> anyhow::Result<Distinct<'i>> {
+    let LogicalPlan::Distinct(distinct) = dag.get_node(node) else {
+        anyhow::bail!("Node {node} is not a distinct");
+    };
+    let input = dag.get_inputs(node)[0];
+    let sorted = is_grouped_by(dag, input, &distinct_keys(dag, node));
+    let distinct = Distinct::new(distinct.on.clone(), &distinct.schema, sorted, successor)?;
+    Ok(match memory_limit {
+        Some(bytes) => distinct.with_memory_limit(bytes),
+        None => distinct,
+    })
+}
+
+/// Expressions the distinct `node` compares: its `on` expressions or all columns.
+fn distinct_keys(dag: &Dag<LogicalPlan>, node: NodeId) -> Vec<Expr> {
+    let LogicalPlan::Distinct(distinct) = dag.get_node(node) else {
+        panic!("node {node} is not a distinct");
+    };
+    if !distinct.on.is_empty() {
+        return distinct.on.clone();
+    }
+    distinct
+        .schema
+        .fields()
+        .iter()
+        .map(|field| {
+            Expr::Ident(Ident {
+                name: field.name().clone(),
+            })
+        })
+        .collect()
+}
+
+/// Whether rows with equal values of `keys` are adjacent in the output of
+/// `node`, which holds if it's sorted on all keys first, in any direction.
+fn is_grouped_by(dag: &Dag<LogicalPlan>, node: NodeId, keys: &[Expr]) -> bool {
+    let ordering = output_ordering(dag, node);
+    ordering.len() >= keys.len()
+        && ordering[..keys.len()]
+            .iter()
+            .all(|sort| keys.contains(&sort.expr))
+}
+
+/// Creates the operator for the window `node`, which doesn't sort its input if
+/// the input is already sorted by the partition and ORDER BY keys.
+pub fn create_window<'i>(
+    dag: &Dag<LogicalPlan>,
+    node: NodeId,
+    successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+) -> anyhow::Result<Window<'i>> {
+    let LogicalPlan::Window(window) = dag.get_node(node) else {
+        anyhow::bail!("Node {node} is not a window");
+    };
+    let sorted = is_sorted_for_window(dag, node);
+    Window::new(window, sorted, successor)
+}
+
+/// Whether the input of the window `node` has the partitions of the window
+/// next to each other, each sorted by its ORDER BY.
+fn is_sorted_for_window(dag: &Dag<LogicalPlan>, node: NodeId) -> bool {
+    let LogicalPlan::Window(window) = dag.get_node(node) else {
+        panic!("node {node} is not a window");
+    };
+    let Some(Expr::WindowFunction(function)) = window.window_expr.first() else {
+        return true;
+    };
+    let input = dag.get_inputs(node)[0];
+    let partitions = function.partition_by.len();
+    let ordering = output_ordering(dag, input);
+    is_grouped_by(dag, input, &function.partition_by)
+        && ordering[partitions..].starts_with(&function.order_by)
+}
+
+/// Creates the operator for the recursive union `node`, which receives the
+/// rows of the anchor. The recursive term is lowered anew for each iteration,
+/// with the rows added last as the source of its work table.
+pub fn create_recursive_union<'i>(
+    dag: &'i Dag<LogicalPlan>,
+    node: NodeId,
+    catalog: &'i DummyCatalog,
+    config: ExecutionConfig,
+    successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+) -> anyhow::Result<RecursiveUnion<'i>> {
+    let LogicalPlan::RecursiveUnion(union) = dag.get_node(node) else {
+        anyhow::bail!("Node {node} is not a recursive union");
+    };
+    let recursive = dag.get_inputs(node)[1];
+    let recursive_term: RecursiveTerm<'i> = Box::new(move |work_table| {
+        let mut res = Vec::new();
+        let work_table = Some((union.name.as_str(), work_table.to_vec()));
+        Lowering::new(dag, catalog, config, work_table)
+            .execute(recursive, Box::new(Collect::new(&mut res)))?;
+        Ok(res)
+    });
+    Ok(RecursiveUnion::new(union, recursive_term, successor)?
+        .with_max_iterations(config.max_recursive_iterations))
+}
+
+/// Operators of the nodes of a plan, which are created from the root down so
+/// that each one is passed the operators consuming its output.
+struct Lowering<'i> {
+    dag: &'i Dag<LogicalPlan>,
+    catalog: &'i DummyCatalog,
+    config: ExecutionConfig,
+    /// Name and rows of the work table of the recursive term being lowered.
+    work_table: Option<(&'i str, Vec<Arc<RecordBatch>>)>,
+    /// Consumers of the output of the nodes which aren't lowered yet.
+    consumers: HashMap<NodeId, Vec<Box<dyn Operator<Arc<RecordBatch>> + 'i>>>,
+    sources: Vec<Source<'i>>,
+}
+
+impl<'i> Lowering<'i> {
+    fn new(
+        dag: &'i Dag<LogicalPlan>,
+        catalog: &'i DummyCatalog,
+        config: ExecutionConfig,
+        work_table: Option<(&'i str, Vec<Arc<RecordBatch>>)>,
+    ) -> Self {
+        Self {
+            dag,
+            catalog,
+            config,
+            work_table,
+            consumers: HashMap::new(),
+            sources: Vec::new(),
+        }
+    }
+
+    /// Lowers the plan below `root`, whose rows are passed to `successor`, and
+    /// runs it by reading its sources one after the other.
+    fn execute(
+        mut self,
+        root: NodeId,
+        successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    ) -> anyhow::Result<()> {
+        self.consumers.insert(root, vec![successor]);
+        for node in lowering_order(self.dag, root, self.catalog) {
+            let mut consumers = self.consumers.remove(&node).unwrap_or_default();
+            let successor: Box<dyn Operator<Arc<RecordBatch>> + 'i> = if consumers.len() == 1 {
+                consumers.remove(0)
+            } else {
+                Box::new(Fanout::new(consumers))
+            };
+            let inputs = self.lower(node, successor)?;
+            for (&input, operator) in self.dag.get_inputs(node).iter().zip(inputs) {
+                self.consumers.entry(input).or_default().push(operator);
+            }
+        }
+        for source in self.sources {
+            source.run(self.config.batch_size)?;
+        }
+        Ok(())
+    }
+
+    /// Creates the operator of `node` and returns the operators receiving the
+    /// rows of its inputs, in the order of the inputs.
+    fn lower(
+        &mut self,
+        node: NodeId,
+        successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    ) -> anyhow::Result<Vec<Box<dyn Operator<Arc<RecordBatch>> + 'i>>> {
+        let dag = self.dag;
+        Ok(match dag.get_node(node) {
+            LogicalPlan::TableScan(scan) => {
+                self.sources
+                    .push(Source::table(self.catalog, scan, successor)?);
+                Vec::new()
+            }
+            LogicalPlan::WorkTable(work_table) => {
+                let batches = match &self.work_table {
+                    Some((name, batches)) if *name == work_table.name => batches.clone(),
+                    _ => anyhow::bail!(
+                        "Work table {} is read outside of its recursive term",
+                        work_table.name
+                    ),
+                };
+                self.sources.push(Source::Batches { successor, batches });
+                Vec::new()
+            }
+            LogicalPlan::Projection(projection) => vec![Box::new(Project::new(
+                projection.expr.clone(),
+                projection.schema.clone(),
+                successor,
+            ))],
+            LogicalPlan::Filter(filter) => vec![Box::new(Filter::new(
+                filter.expr.clone(),
+                Box::new(Select::new(successor)),
+            ))],
+            LogicalPlan::Aggregate(aggregate) => {
+                let input_schema = dag.get_node(dag.get_inputs(node)[0]).get_schema();
+                let aggregate = HashAggregate::new(
+                    aggregate.group_expr.clone(),
+                    &aggregate.grouping_sets,
+                    &aggregate.aggr_expr,
+                    &input_schema,
+                    aggregate.schema.clone(),
+                    successor,
+                )?;
+                vec![Box::new(match self.config.memory_limit {
+                    Some(bytes) => aggregate.with_memory_limit(bytes),
+                    None => aggregate,
+                })]
+            }
+            LogicalPlan::Sort(_) => {
+                vec![create_sort(dag, node, self.config.memory_limit, successor)?]
+            }
+            LogicalPlan::Limit(limit) => {
+                vec![Box::new(Limit::new(limit.skip, limit.fetch, successor))]
+            }
+            LogicalPlan::Distinct(_) => vec![Box::new(create_distinct(
+                dag,
+                node,
+                self.config.memory_limit,
+                successor,
+            )?)],
+            LogicalPlan::Window(_) => vec![Box::new(create_window(dag, node, successor)?)],
+            LogicalPlan::Unpivot(unpivot) => vec![Box::new(Unpivot::new(unpivot, successor))],
+            LogicalPlan::Join(_) => {
+                let (left, right) =
+                    create_join(dag, node, self.catalog, self.config.memory_limit, successor)?;
+                vec![Box::new(left), Box::new(right)]
+            }
+            LogicalPlan::Intersect(_) | LogicalPlan::Except(_) => {
+                let (left, right) = create_set_operation(dag, node, successor)?;
+                vec![Box::new(left), Box::new(right)]
+            }
+            LogicalPlan::Union(_) => create_union(dag, node, successor)?
+                .into_iter()
+                .map(|input| Box::new(input) as Box<dyn Operator<Arc<RecordBatch>> + 'i>)
+                .collect(),
+            LogicalPlan::RecursiveUnion(_) => vec![Box::new(create_recursive_union(
+                dag,
+                node,
+                self.catalog,
+                self.config,
+                successor,
+            )?)],
+        })
+    }
+}
+
+/// Nodes below `root`, each one before its inputs. Only the anchor of a
+/// recursive union is included, as its recursive term is lowered on its own.
+///
+/// Sources are run in this order, so the build input of a join comes before
+/// its probe input, which would otherwise be buffered until the build is done.
+fn lowering_order(dag: &Dag<LogicalPlan>, root: NodeId, catalog: &dyn Catalog) -> Vec<NodeId> {
+    fn visit(
+        dag: &Dag<LogicalPlan>,
+        node: NodeId,
+        catalog: &dyn Catalog,
+        visited: &mut HashSet<NodeId>,
+        order: &mut Vec<NodeId>,
+    ) {
+        if !visited.insert(node) {
+            return;
+        }
+        let mut inputs = dag.get_inputs(node).clone();
+        match dag.get_node(node) {
+            LogicalPlan::RecursiveUnion(_) => inputs.truncate(1),
+            LogicalPlan::Join(_) => {
+                if let JoinStrategy::Hash {
+                    build_side: Side::Left,
+                }
+                | JoinStrategy::NestedLoop {
+                    build_side: Side::Left,
+                } = join_strategy(dag, node, catalog)
+                {
+                    inputs.reverse();
+                }
+            }
+            _ => {}
+        }
+        for input in inputs {
+            visit(dag, input, catalog, visited, order);
+        }
+        order.push(node);
+    }
+
+    let mut order = Vec::new();
+    visit(dag, root, catalog, &mut HashSet::new(), &mut order);
+    order.reverse();
+    order
+}
+
+/// Operator reading the rows of a table or of the work table, which is run once
+/// all operators of the plan are created.
+enum Source<'i> {
+    File {
+        scan: Box<dyn Operator<(String, usize)> + 'i>,
+        path: String,
+    },
+    Files {
+        scan: MultiFileScan<'i>,
+        paths: Vec<String>,
+    },
+    Batches {
+        successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+        batches: Vec<Arc<RecordBatch>>,
+    },
+}
+
+impl<'i> Source<'i> {
+    /// Reads the table of `scan` from its source in `catalog`.
+    fn table(
+        catalog: &DummyCatalog,
+        scan: &TableScan,
+        successor: Box<dyn Operator<Arc<RecordBatch>> + 'i>,
+    ) -> anyhow::Result<Self> {
+        let Some(source) = catalog.get_source(&scan.table_name) else {
+            anyhow::bail!("Table {} has no source to read from", scan.table_name);
+        };
+        let schema = scan.schema.clone();
+        Ok(match source.clone() {
+            TableSource::Parquet(path) => Self::File {
+                scan: Box::new(Scan::new(successor)),
+                path,
+            },
+            TableSource::ParquetFiles(paths) => Self::Files {
+                scan: MultiFileScan::new(schema, successor),
+                paths,
+            },
+            TableSource::Csv { path, options } => Self::File {
+                scan: Box::new(CsvScan::new(schema, options, successor)),
+                path,
+            },
+            TableSource::Json { path, options } => Self::File {
+                scan: Box::new(JsonScan::new(schema, options, successor)),
+                path,
+            },
+            TableSource::Ipc(path) => Self::File {
+                scan: Box::new(IpcScan::new(None, successor)),
+                path,
+            },
+        })
+    }
+
+    fn run(self, batch_size: usize) -> anyhow::Result<()> {
+        match self {
+            Self::File { mut scan, path } => {
+                scan.execute((path, batch_size))?;
+                scan.all_inputs_received()
+            }
+            Self::Files { mut scan, paths } => {
+                scan.execute((paths, batch_size))?;
+                scan.all_inputs_received()
+            }
+            Self::Batches {
+                mut successor,
+                batches,
+            } => {
+                for batch in batches {
+                    if successor.execute(batch)? == OperatorState::Finished {
+                        break;
+                    }
+                }
+                successor.all_inputs_received()
+            }
+        }
+    }
+}
+
+/// Sort order the rows of `node` are known to have, empty if unknown.
+pub fn output_ordering(dag: &Dag<LogicalPlan>, node: NodeId) -> Vec<SortExpr> {
+    let input = || dag.get_inputs(node)[0];
+    match dag.get_node(node) {
+        LogicalPlan::Sort(sort) => sort.expr.clone(),
+        LogicalPlan::Filter(_) | LogicalPlan::Limit(_) => output_ordering(dag, input()),
+        // Hashing keeps the order of rows unless they're spilled.
+        LogicalPlan::Distinct(_) if is_grouped_by(dag, input(), &distinct_keys(dag, node)) => {
+            output_ordering(dag, input())
+        }
+        LogicalPlan::Window(_) if is_sorted_for_window(dag, node) => output_ordering(dag, input()),
+        LogicalPlan::Window(window) => match window.window_expr.first() {
+            Some(Expr::WindowFunction(function)) => {
+                window::sort_expr(&function.partition_by, &function.order_by)
+            }
+            _ => Vec::new(),
+        },
+        LogicalPlan::Projection(projection) => {
+            // Keep the leading sort expressions which are still output columns.
+            let mut ordering = output_ordering(dag, input());
+            let len = ordering
+                .iter_mut()
+                .position(|sort| !project_sort_expr(&projection.expr, &mut sort.expr))
+                .unwrap_or(ordering.len());
+            ordering.truncate(len);
+            ordering
+        }
+        LogicalPlan::Unpivot(unpivot) => {
+            // Rows are expanded in place, so the order by other columns is kept.
+            let mut ordering = output_ordering(dag, input());
+            let len = ordering
+                .iter()
+                .position(|sort| {
+                    let mut columns = HashSet::new();
+                    sort.expr.collect_columns(&mut columns);
+                    unpivot.columns.iter().any(|c| columns.contains(c.as_str()))
+                })
+                .unwrap_or(ordering.len());
+            ordering.truncate(len);
+            ordering
+        }
+        LogicalPlan::TableScan(_)
+        | LogicalPlan::Aggregate(_)
+        | LogicalPlan::Distinct(_)
+        | LogicalPlan::Join(_)
+        | LogicalPlan::WorkTable(_)
+        | LogicalPlan::RecursiveUnion(_)
+        | LogicalPlan::Union(_)
+        | LogicalPlan::Intersect(_)
+        | LogicalPlan::Except(_) => Vec::new(),
+    }
+}
+
+/// Rewrites `expr` to the output column of the projection with the same value.
+fn project_sort_expr(projection: &[Expr], expr: &mut Expr) -> bool {
+    let output = projection.iter().find_map(|projected| match projected {
+        Expr::Alias(alias) if alias.expr.as_ref() == expr => Some(alias.name.clone()),
+        projected if projected == expr => Some(projected.name()),
+        _ => None,
+    });
+    match output {
+        Some(name) => {
+            *expr = Expr::Ident(Ident { name });
+            true
+        }
+        None => false,
+    }
+}
+
+/// Whether rows sorted by `ordering` are sorted ascending on `keys` (in any
+/// order of NULLs, which never match).
+fn is_sorted_on(ordering: &[SortExpr], keys: &[Expr]) -> bool {
+    ordering.len() >= keys.len()
+        && ordering
+            .iter()
+            .zip(keys)
+            .all(|(sort, key)| sort.asc && &sort.expr == key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::{CsvOptions, TableStatistics};
+    use crate::execution::spill;
+    use crate::logical_plan::dag_builder::DagBuilder;
+    use crate::logical_plan::expr::Alias;
+    use crate::logical_plan::JoinType;
+    use crate::parser::sql_parser::{parse_sql_query, parse_sql_statement};
+    use arrow::array::AsArray;
+    use arrow::datatypes::{DataType, Field, Int32Type, Int64Type, Schema};
+    use arrow::util::display::{ArrayFormatter, FormatOptions};
+
+    /// Reads tables in batches small enough to spill with a memory limit.
+    fn small_batches() -> ExecutionConfig {
+        ExecutionConfig {
+            batch_size: 100,
+            ..ExecutionConfig::default()
+        }
+    }
+
+    /// Values of the first column of `batches`, which must be of type `T`.
+    fn column<T: arrow::datatypes::ArrowPrimitiveType>(
+        batches: &[Arc<RecordBatch>],
+    ) -> Vec<T::Native> {
+        batches
+            .iter()
+            .flat_map(|b| b.column(0).as_primitive::<T>().values().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn test_join_strategy() -> anyhow::Result<()> {
+        let left_schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("ts", DataType::Int64, false),
+        ]));
+        let right_schema = Arc::new(Schema::new(vec![
+            Field::new("rid", DataType::Int64, false),
+            Field::new("start", DataType::Int64, false),
+            Field::new("end", DataType::Int64, false),
+        ]));
+        let mut catalog = DummyCatalog::new();
+        catalog.add_table("l", left_schema.clone());
+        catalog.add_table("r", right_schema.clone());
+        let ident = |name: &str| {
+            Expr::Ident(Ident {
+                name: name.to_string(),
+            })
+        };
+        let sort_expr = |name: &str| {
+            vec![SortExpr {
+                expr: ident(name),
+                asc: true,
+                nulls_first: false,
+            }]
+        };
+
+        for (condition, expected) in [
+            (
+                "id = rid",
+                JoinStrategy::Hash {
+                    build_side: Side::Right,
+                },
+            ),
+            (
+                "ts BETWEEN start AND \"end\"",
+                JoinStrategy::NestedLoop {
+                    build_side: Side::Right,
+                },
+            ),
+        ] {
+            let sql = format!("SELECT * FROM l JOIN r ON {condition}");
+            let dag = parse_sql_query(&sql, &catalog)?;
+            assert_eq!(join_strategy(&dag, 2, &catalog), expected, "{condition}");
+        }
+
+        // Sorted inputs are merged, also through a projection which renames the key.
+        let mut dag = Dag::new();
+        let mut builder = DagBuilder::new(&mut dag);
+        let left = builder.create_scan("l".to_string(), left_schema);
+        let left = builder.create_sort(sort_expr("id"), left)?;
+        let left = builder.create_project(
+            vec![
+                Expr::Alias(Alias {
+                    expr: Box::new(ident("id")),
+                    name: "lid".to_string(),
+                }),
+                ident("ts"),
+            ],
+            left,
+        )?;
+        let right = builder.create_scan("r".to_string(), right_schema);
+        let sorted_right = builder.create_sort(sort_expr("rid"), right)?;
+        let on = vec![(ident("lid"), ident("rid"))];
+        let merge = builder.create_join(JoinType::Inner, on.clone(), None, left, sorted_right)?;
+        let hash = builder.create_join(JoinType::Inner, on, None, left, right)?;
+        assert_eq!(join_strategy(&dag, merge, &catalog), JoinStrategy::Merge);
+        assert_eq!(
+            join_strategy(&dag, hash, &catalog),
+            JoinStrategy::Hash {
+                build_side: Side::Right
+            }
+        );
+
+        // The smaller input is buffered.
+        let statistics = |row_count| TableStatistics {
+            row_count,
+            ..TableStatistics::default()
+        };
+        catalog.set_statistics("l", statistics(10));
+        catalog.set_statistics("r", statistics(1000));
+        let dag = parse_sql_query("SELECT * FROM l JOIN r ON id = rid", &catalog)?;
+        assert_eq!(
+            join_strategy(&dag, 2, &catalog),
+            JoinStrategy::Hash {
+                build_side: Side::Left
+            }
+        );
+        // Its rows are read first, so the probe rows needn't be buffered.
+        let order = lowering_order(&dag, 2, &catalog);
+        let position = |node| order.iter().position(|&n| n == node);
+        assert!(position(0) < position(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_recursive_query() -> anyhow::Result<()> {
+        let mut catalog = DummyCatalog::new();
+        catalog.register_csv(
+            "employees",
+            "samples/sample-data/csv/employees.csv",
+            CsvOptions::default(),
+        )?;
+
+        let dag = parse_sql_query(
+            "WITH RECURSIVE reports (eid, depth) AS ( \
+               SELECT id, 0 FROM employees WHERE id = 1 \
+               UNION ALL \
+               SELECT e.id, r.depth + 1 FROM reports r JOIN employees e ON e.manager_id = r.eid \
+             ) \
+             SELECT eid, depth FROM reports WHERE depth < 3",
+            &catalog,
+        )?;
+        let res = execute_query(&dag, &catalog, ExecutionConfig::default())?;
+        let mut rows = column::<Int64Type>(&res)
+            .into_iter()
+            .zip(
+                res.iter()
+                    .flat_map(|b| b.column(1).as_primitive::<Int32Type>().values().to_vec()),
+            )
+            .collect::<Vec<_>>();
+        rows.sort_unstable();
+        assert_eq!(rows, [(1, 0), (2, 1), (3, 1), (4, 2)]);
+
+        // UNION ALL over a cycle runs into the configured maximum of iterations,
+        // while UNION stops once no new rows are found.
+        let sql = |union: &str| {
+            format!(
+                "WITH RECURSIVE r (n) AS ( \
+                   SELECT id FROM employees WHERE id = 1 {union} SELECT n FROM r \
+                 ) \
+                 SELECT n FROM r"
+            )
+        };
+        let config = ExecutionConfig {
+            max_recursive_iterations: 5,
+            ..ExecutionConfig::default()
+        };
+        let dag = parse_sql_query(&sql("UNION ALL"), &catalog)?;
+        let error = execute_query(&dag, &catalog, config).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Recursive query didn't finish after 5 iterations"
+        );
+        let dag = parse_sql_query(&sql("UNION"), &catalog)?;
+        assert_eq!(
+            column::<Int64Type>(&execute_query(&dag, &catalog, config)?),
+            [1]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_sort() -> anyhow::Result<()> {
+        let mut catalog = DummyCatalog::new();
+        catalog.register_csv(
+            "employees",
+            "samples/sample-data/csv/employees.csv",
+            CsvOptions::default(),
+        )?;
+        let schema = catalog.get_schema("employees")?;
+
+        let dag = parse_sql_query(
+            "SELECT id FROM employees ORDER BY id DESC LIMIT 2 OFFSET 1",
+            &catalog,
+        )?;
+        let sort = (0..dag.len())
+            .find(|&id| matches!(dag.get_node(id), LogicalPlan::Sort(_)))
+            .unwrap();
+        // The sort only returns the rows the limit fetches.
+        let mut res = Vec::new();
+        {
+            let mut sort = create_sort(&dag, sort, None, Box::new(Collect::new(&mut res)))?;
+            let source = catalog.get_source("employees").unwrap();
+            for batch in source.read(&schema, 2)? {
+                sort.execute(Arc::new(batch?))?;
+            }
+            sort.all_inputs_received()?;
+        }
+        assert_eq!(column::<Int64Type>(&res), [6, 5, 4]);
+        let res = execute_query(&dag, &catalog, ExecutionConfig::default())?;
+        assert_eq!(column::<Int64Type>(&res), [5, 4]);
+
+        let dag = parse_sql_query("SELECT id FROM employees ORDER BY id DESC", &catalog)?;
+        let res = execute_query(&dag, &catalog, ExecutionConfig::default())?;
+        assert_eq!(column::<Int64Type>(&res), [6, 5, 4, 3, 2, 1]);
+
+        // Without a limit, the input is sorted in runs on disk if it doesn't
+        // fit into memory.
+        catalog.register_parquet("users", "samples/sample-data/parquet/userdata1.parquet")?;
+        let dag = parse_sql_query("SELECT id FROM users ORDER BY salary, id", &catalog)?;
+        let expected = column::<Int32Type>(&execute_query(&dag, &catalog, small_batches())?);
+        let spilled = spill::spill_count();
+        let config = ExecutionConfig {
+            memory_limit: Some(16 * 1024),
+            ..small_batches()
+        };
+        assert_eq!(
+            column::<Int32Type>(&execute_query(&dag, &catalog, config)?),
+            expected
+        );
+        assert!(spill::spill_count() > spilled);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_join_using() -> anyhow::Result<()> {
+        let mut catalog = DummyCatalog::new();
+        catalog.register_csv(
+            "people",
+            "samples/sample-data/csv/people.csv",
+            CsvOptions::default(),
+        )?;
+        catalog.register_csv(
+            "employees",
+            "samples/sample-data/csv/employees.csv",
+            CsvOptions::default(),
+        )?;
+
+        // The key of rows of the right input without a match is kept.
+        let dag = parse_sql_query(
+            "SELECT id, name FROM people RIGHT JOIN employees USING (id)",
+            &catalog,
+        )?;
+        let res = execute_query(&dag, &catalog, ExecutionConfig::default())?;
+        let mut ids = column::<Int64Type>(&res);
+        ids.sort_unstable();
+        assert_eq!(ids, [1, 2, 3, 4, 5, 6]);
+        assert_eq!(
+            res.iter().map(|b| b.column(1).null_count()).sum::<usize>(),
+            2
+        );
+
+        // The build input is partitioned on disk if it doesn't fit into memory.
+        catalog.register_parquet("users", "samples/sample-data/parquet/userdata1.parquet")?;
+        let dag = parse_sql_query(
+            "SELECT id, b.salary FROM users JOIN users b USING (id)",
+            &catalog,
+        )?;
+        let mut expected = column::<Int32Type>(&execute_query(&dag, &catalog, small_batches())?);
+        expected.sort_unstable();
+        assert_eq!(expected, (1..=1000).collect::<Vec<_>>());
+        let spilled = spill::spill_count();
+        let config = ExecutionConfig {
+            memory_limit: Some(16 * 1024),
+            ..small_batches()
+        };
+        let mut ids = column::<Int32Type>(&execute_query(&dag, &catalog, config)?);
+        ids.sort_unstable();
+        assert_eq!(ids, expected);
+        assert!(spill::spill_count() > spilled);
+
+        for sql in [
+            "SELECT id FROM people FULL JOIN employees USING (id)",
+            "SELECT id FROM people NATURAL FULL JOIN employees",
+        ] {
+            assert!(parse_sql_query(sql, &catalog).is_err(), "{sql}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_reordered_joins() -> anyhow::Result<()> {
+        /// Join tree below `node` with the table names as leaves.
+        fn join_tree(dag: &Dag<LogicalPlan>, node: NodeId) -> String {
+            let inputs = dag.get_inputs(node);
+            match dag.get_node(node) {
+                LogicalPlan::TableScan(scan) => scan.table_name.clone(),
+                LogicalPlan::Join(join) => format!(
+                    "({} {} {})",
+                    join_tree(dag, inputs[0]),
+                    join.kind,
+                    join_tree(dag, inputs[1])
+                ),
+                _ => join_tree(dag, inputs[0]),
+            }
+        }
+
+        let mut catalog = DummyCatalog::new();
+        catalog.register_parquet("users", "samples/sample-data/parquet/userdata1.parquet")?;
+        for table in ["people", "employees"] {
+            let path = format!("samples/sample-data/csv/{table}.csv");
+            catalog.register_csv(table, &path, CsvOptions::default())?;
+        }
+        for table in ["users", "people", "employees"] {
+            catalog.analyze(table)?;
+        }
+
+        // The large users table is joined last, and the smaller input of each
+        // join is built.
+        let dag = parse_sql_query(
+            "SELECT u.id, e.manager_id FROM users u, people p, employees e \
+             WHERE u.id = p.id AND p.id = e.id",
+            &catalog,
+        )?;
+        let root = dag.len() - 1;
+        assert_eq!(
+            join_tree(&optimize_query(&dag, &catalog)?, root),
+            "(users INNER (employees INNER people))"
+        );
+
+        let res = execute_query(&dag, &catalog, ExecutionConfig::default())?;
+        let mut rows = res
+            .iter()
+            .flat_map(|batch| {
+                let ids = batch.column(0).as_primitive::<Int32Type>();
+                let managers = batch.column(1).as_primitive::<Int64Type>();
+                ids.iter().zip(managers.iter()).collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        rows.sort_unstable();
+        assert_eq!(
+            rows,
+            [
+                (Some(1), None),
+                (Some(2), Some(1)),
+                (Some(3), Some(1)),
+                (Some(4), Some(2))
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_analyze() -> anyhow::Result<()> {
+        let mut catalog = DummyCatalog::new();
+        catalog.register_csv(
+            "employees",
+            "samples/sample-data/csv/employees.csv",
+            CsvOptions::default(),
+        )?;
+        assert_eq!(catalog.get_statistics("employees"), None);
+
+        let statement = parse_sql_statement("ANALYZE TABLE employees", &catalog)?;
+        assert!(
+            execute_statement(&statement, &mut catalog, ExecutionConfig::default())?.is_empty()
+        );
+        let statistics = catalog.get_statistics("employees").unwrap();
+        assert_eq!(statistics.row_count, 6);
+        assert_eq!(statistics.columns["manager_id"].null_count, Some(1));
+
+        let statement = parse_sql_statement("SELECT id FROM employees WHERE id > 4", &catalog)?;
+        let res = execute_statement(&statement, &mut catalog, ExecutionConfig::default())?;
+        assert_eq!(column::<Int64Type>(&res), [5, 6]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_distinct() -> anyhow::Result<()> {
+        let mut catalog = DummyCatalog::new();
+        catalog.register_parquet("users", "samples/sample-data/parquet/userdata1.parquet")?;
+        let dag = parse_sql_query("SELECT DISTINCT first_name, gender FROM users", &catalog)?;
+        let names = |res: &[Arc<RecordBatch>]| {
+            let mut names = res
+                .iter()
+                .flat_map(|b| {
+                    let names = b.column(0).as_string::<i32>();
+                    names
+                        .iter()
+                        .map(|name| name.map(str::to_string))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            names.sort_unstable();
+            names
+        };
+
+        let expected = names(&execute_query(&dag, &catalog, small_batches())?);
+        // The keys seen are partitioned on disk if they don't fit into memory.
+        let spilled = spill::spill_count();
+        let config = ExecutionConfig {
+            memory_limit: Some(4 * 1024),
+            ..small_batches()
+        };
+        assert_eq!(names(&execute_query(&dag, &catalog, config)?), expected);
+        assert!(spill::spill_count() > spilled);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_aggregate() -> anyhow::Result<()> {
+        let mut catalog = DummyCatalog::new();
+        catalog.register_parquet("users", "samples/sample-data/parquet/userdata1.parquet")?;
+        let dag = parse_sql_query(
+            "SELECT first_name, gender, count(*), count(DISTINCT country), max(salary), \
+             grouping(gender) FROM users GROUP BY ROLLUP(first_name, gender)",
+            &catalog,
+        )?;
+        let rows = |res: &[Arc<RecordBatch>]| -> anyhow::Result<Vec<String>> {
+            let options = FormatOptions::default().with_null("NULL");
+            let mut rows = Vec::new();
+            for batch in res {
+                let formatters = batch
+                    .columns()
+                    .iter()
+                    .map(|column| ArrayFormatter::try_new(column, &options))
+                    .collect::<Result<Vec<_>, _>>()?;
+                for row in 0..batch.num_rows() {
+                    let values = formatters.iter().map(|f| f.value(row).to_string());
+                    rows.push(values.collect::<Vec<_>>().join(","));
+                }
+            }
+            rows.sort();
+            Ok(rows)
+        };
+
+        let expected = rows(&execute_query(&dag, &catalog, small_batches())?)?;
+        assert!(expected.contains(&"NULL,NULL,1000,120,286592.99,1".to_string()));
+        // The partial states of the groups are partitioned on disk if they
+        // don't fit into memory, and merged again.
+        let spilled = spill::spill_count();
+        let config = ExecutionConfig {
+            memory_limit: Some(16 * 1024),
+            ..small_batches()
+        };
+        assert_eq!(rows(&execute_query(&dag, &catalog, config)?)?, expected);
+        assert!(spill::spill_count() > spilled);
+
+        Ok(())
+    }
+}
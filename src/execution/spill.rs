@@ -0,0 +1,88 @@
+use arrow::array::RecordBatch;
+use arrow::datatypes::SchemaRef;
+use arrow::ipc::reader::FileReader;
+use arrow::ipc::writer::FileWriter;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, ErrorKind};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_SPILL_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Number of spill files created so far.
+#[cfg(test)]
+pub fn spill_count() -> usize {
+    NEXT_SPILL_ID.load(Ordering::Relaxed)
+}
+
+/// Writes batches which don't fit into memory to a temporary Arrow IPC file.
+pub struct SpillWriter {
+    writer: FileWriter<BufWriter<File>>,
+    file: SpillFile,
+}
+
+impl SpillWriter {
+    pub fn try_new(schema: &SchemaRef) -> anyhow::Result<Self> {
+        let (path, file) = loop {
+            let path = std::env::temp_dir().join(format!(
+                "inudb-spill-{}-{}.arrow",
+                std::process::id(),
+                NEXT_SPILL_ID.fetch_add(1, Ordering::Relaxed)
+            ));
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => break (path, file),
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => {}
+                Err(err) => return Err(err.into()),
+            }
+        };
+        // Constructed before the writer, so the file is removed if that fails.
+        let file_guard = SpillFile {
+            path,
+            schema: schema.clone(),
+            rows: 0,
+        };
+        Ok(Self {
+            writer: FileWriter::try_new(BufWriter::new(file), schema)?,
+            file: file_guard,
+        })
+    }
+
+    pub fn write(&mut self, batch: &RecordBatch) -> anyhow::Result<()> {
+        self.file.rows += batch.num_rows();
+        Ok(self.writer.write(batch)?)
+    }
+
+    pub fn finish(mut self) -> anyhow::Result<SpillFile> {
+        self.writer.finish()?;
+        Ok(self.file)
+    }
+}
+
+/// Finished spill file, deleted from disk when dropped.
+pub struct SpillFile {
+    path: PathBuf,
+    schema: SchemaRef,
+    rows: usize,
+}
+
+impl SpillFile {
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    pub const fn num_rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Reads the batches back in the order they were written.
+    pub fn read(&self) -> anyhow::Result<impl Iterator<Item = anyhow::Result<RecordBatch>>> {
+        let reader = FileReader::try_new(BufReader::new(File::open(&self.path)?), None)?;
+        Ok(reader.map(|batch| Ok(batch?)))
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
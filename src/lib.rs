@@ -7,4 +7,13 @@ mod catalog;
 mod dag;
 mod execution;
 mod logical_plan;
+mod optimizer;
 mod parser;
+
+pub use catalog::{
+    Catalog, CatalogError, ColumnStatistics, CsvOptions, DummyCatalog, ExtraColumns, IpcReader,
+    JsonOptions, MissingColumns, MultiFileOptions, ScalarValue, TableSource, TableStatistics,
+};
+pub use execution::accumulator::Accumulator;
+pub use logical_plan::errors::PlanError;
+pub use logical_plan::udaf::{AccumulatorFactory, AggregateUdf, ReturnTypeFunction};
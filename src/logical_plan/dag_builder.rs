@@ -1,33 +1,89 @@
 use crate::dag::Dag;
-use crate::logical_plan::expr::Expr;
-use crate::logical_plan::{Filter, NodeId};
-use crate::logical_plan::{LogicalPlan, Projection, TableScan};
-use arrow::datatypes::SchemaRef;
+use crate::logical_plan::errors::PlanError;
+use crate::logical_plan::expr::{AggregateFunc, Expr, SortExpr};
+use crate::logical_plan::{Aggregate, Distinct, Filter, Join, JoinType, Limit, NodeId, Sort};
+use crate::logical_plan::{LogicalPlan, Projection, RecursiveUnion, SetOperation, TableScan};
+use crate::logical_plan::{Unpivot, Window, WorkTable};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::datatypes::{DECIMAL128_MAX_PRECISION, DECIMAL256_MAX_PRECISION};
+use std::sync::Arc;
 
 pub struct DagBuilder<'d> {
     dag: &'d mut Dag<LogicalPlan>,
 }
 
 impl<'d> DagBuilder<'d> {
-    pub fn new(dag: &'d mut Dag<LogicalPlan>) -> Self {
+    pub const fn new(dag: &'d mut Dag<LogicalPlan>) -> Self {
         DagBuilder { dag }
     }
 
+    pub const fn dag(&self) -> &Dag<LogicalPlan> {
+        self.dag
+    }
+
     pub fn create_scan(&mut self, table_name: String, schema: SchemaRef) -> NodeId {
         self.dag
             .new_node(LogicalPlan::TableScan(TableScan { table_name, schema }))
     }
 
-    pub fn create_project(&mut self, expr: Vec<Expr>, input: NodeId) -> NodeId {
-        // TODO(vlad): infer output schema based on input schema using expression
+    pub fn create_project(&mut self, expr: Vec<Expr>, input: NodeId) -> Result<NodeId, PlanError> {
         let prev = self.dag.get_node(input);
-        let schema = prev.get_schema();
+        let schema = Self::infer_schema(&expr, &prev.get_schema())?;
 
         let res = self
             .dag
             .new_node(LogicalPlan::Projection(Projection { expr, schema }));
         self.dag.add_input(res, input);
-        res
+        Ok(res)
+    }
+
+    pub fn create_aggregate(
+        &mut self,
+        group_expr: Vec<Expr>,
+        aggr_expr: Vec<Expr>,
+        input: NodeId,
+    ) -> Result<NodeId, PlanError> {
+        let grouping_sets = vec![(0..group_expr.len()).collect()];
+        self.create_grouping_sets(group_expr, grouping_sets, aggr_expr, input)
+    }
+
+    /// Creates an aggregate which groups every row by each of `grouping_sets`.
+    /// Keys are nullable unless they are part of all sets.
+    pub fn create_grouping_sets(
+        &mut self,
+        group_expr: Vec<Expr>,
+        grouping_sets: Vec<Vec<usize>>,
+        aggr_expr: Vec<Expr>,
+        input: NodeId,
+    ) -> Result<NodeId, PlanError> {
+        let prev = self.dag.get_node(input);
+        let input_schema = prev.get_schema();
+        let mut fields = Vec::with_capacity(group_expr.len() + aggr_expr.len());
+        for (i, expr) in group_expr.iter().enumerate() {
+            let field = expr.to_field(&input_schema)?;
+            let nullable = field.is_nullable() || grouping_sets.iter().any(|set| !set.contains(&i));
+            fields.push(field.with_nullable(nullable));
+        }
+        for expr in &aggr_expr {
+            if let Expr::AggregateFunction(aggregate) = expr {
+                if aggregate.func == AggregateFunc::Grouping {
+                    if let Some(arg) = aggregate.args.iter().find(|arg| !group_expr.contains(arg)) {
+                        return Err(PlanError::NotGrouped(arg.to_string()));
+                    }
+                }
+            }
+            fields.push(expr.to_field(&input_schema)?);
+        }
+        let schema = Arc::new(Schema::new(fields));
+
+        let res = self.dag.new_node(LogicalPlan::Aggregate(Aggregate {
+            group_expr,
+            grouping_sets,
+            aggr_expr,
+            schema,
+        }));
+        self.dag.add_input(res, input);
+        Ok(res)
     }
 
     pub fn create_filter(&mut self, expr: Box<Expr>, scan_id: NodeId) -> NodeId {
@@ -41,8 +97,439 @@ impl<'d> DagBuilder<'d> {
         self.dag.add_input(res, scan_id);
         res
     }
+
+    pub fn create_sort(&mut self, expr: Vec<SortExpr>, input: NodeId) -> Result<NodeId, PlanError> {
+        let prev = self.dag.get_node(input);
+        let schema = prev.get_schema();
+        for sort_expr in &expr {
+            sort_expr.expr.to_field(&schema)?;
+        }
+
+        let res = self.dag.new_node(LogicalPlan::Sort(Sort {
+            expr,
+            fetch: None,
+            schema,
+        }));
+        self.dag.add_input(res, input);
+        Ok(res)
+    }
+
+    /// Creates a limit on top of `input`. If `input` is a sort, possibly below
+    /// projections, the sort is told to keep only the first `skip + fetch` rows.
+    /// Nodes which are also read by other nodes are left untouched.
+    pub fn create_limit(&mut self, skip: usize, fetch: Option<usize>, input: NodeId) -> NodeId {
+        if let Some(fetch) = fetch {
+            let mut node = input;
+            while matches!(self.dag.get_node(node), LogicalPlan::Projection(_))
+                && self.dag.get_usages(node).len() <= 1
+            {
+                node = self.dag.get_inputs(node)[0];
+            }
+            let shared = self.dag.get_usages(node).len() > 1;
+            if let (false, LogicalPlan::Sort(sort)) = (shared, self.dag.get_node_mut(node)) {
+                let fetch = skip + fetch;
+                sort.fetch = Some(sort.fetch.map_or(fetch, |f| f.min(fetch)));
+            }
+        }
+
+        let prev = self.dag.get_node(input);
+        let schema = prev.get_schema();
+
+        let res = self.dag.new_node(LogicalPlan::Limit(Limit {
+            skip,
+            fetch,
+            schema,
+        }));
+        self.dag.add_input(res, input);
+        res
+    }
+
+    /// Creates a distinct on top of `input`, see `Distinct`.
+    pub fn create_distinct(&mut self, on: Vec<Expr>, input: NodeId) -> Result<NodeId, PlanError> {
+        let schema = self.dag.get_node(input).get_schema();
+        for expr in &on {
+            expr.to_field(&schema)?;
+        }
+
+        let res = self
+            .dag
+            .new_node(LogicalPlan::Distinct(Distinct { on, schema }));
+        self.dag.add_input(res, input);
+        Ok(res)
+    }
+
+    /// Creates a window on top of `input`, see `Window`.
+    pub fn create_window(
+        &mut self,
+        window_expr: Vec<Expr>,
+        input: NodeId,
+    ) -> Result<NodeId, PlanError> {
+        let input_schema = self.dag.get_node(input).get_schema();
+        let mut fields = input_schema
+            .fields()
+            .iter()
+            .map(|field| field.as_ref().clone())
+            .collect::<Vec<_>>();
+        let mut first = None;
+        for expr in &window_expr {
+            let Expr::WindowFunction(window) = expr else {
+                return Err(PlanError::Unsupported(format!(
+                    "Window over {expr}, which is not a window function"
+                )));
+            };
+            if !first.get_or_insert(window).same_window(window) {
+                return Err(PlanError::Unsupported(format!(
+                    "Window over {expr} with a different PARTITION BY or ORDER BY"
+                )));
+            }
+            fields.push(expr.to_field(&input_schema)?);
+        }
+
+        let res = self.dag.new_node(LogicalPlan::Window(Window {
+            window_expr,
+            schema: Arc::new(Schema::new(fields)),
+        }));
+        self.dag.add_input(res, input);
+        Ok(res)
+    }
+
+    /// Creates an unpivot of `columns` of `input`, whose values are coerced to
+    /// a common type.
+    pub fn create_unpivot(
+        &mut self,
+        columns: Vec<String>,
+        name: String,
+        value: String,
+        input: NodeId,
+    ) -> Result<NodeId, PlanError> {
+        let input_schema = self.dag.get_node(input).get_schema();
+        let mut value_type = DataType::Null;
+        for column in &columns {
+            let field = input_schema
+                .field_with_name(column)
+                .map_err(|_| PlanError::ColumnNotFound(column.clone()))?;
+            value_type = set_operation_type(&value_type, field.data_type()).ok_or_else(|| {
+                PlanError::Unsupported(format!(
+                    "UNPIVOT of column {column} of type {} with columns of type {value_type}",
+                    field.data_type()
+                ))
+            })?;
+        }
+        let mut fields = input_schema
+            .fields()
+            .iter()
+            .filter(|field| !columns.contains(field.name()))
+            .map(|field| field.as_ref().clone())
+            .collect::<Vec<_>>();
+        for output in [&name, &value] {
+            if fields.iter().any(|field| field.name() == output) || name == value {
+                return Err(PlanError::AmbiguousColumn(output.clone()));
+            }
+        }
+        fields.push(Field::new(&name, DataType::Utf8, false));
+        fields.push(Field::new(&value, value_type, false));
+
+        let res = self.dag.new_node(LogicalPlan::Unpivot(Unpivot {
+            columns,
+            name,
+            value,
+            schema: Arc::new(Schema::new(fields)),
+        }));
+        self.dag.add_input(res, input);
+        Ok(res)
+    }
+
+    pub fn create_join(
+        &mut self,
+        join_type: JoinType,
+        on: Vec<(Expr, Expr)>,
+        filter: Option<Box<Expr>>,
+        left: NodeId,
+        right: NodeId,
+    ) -> Result<NodeId, PlanError> {
+        let left_schema = self.dag.get_node(left).get_schema();
+        let right_schema = self.dag.get_node(right).get_schema();
+        let join = Self::build_join(join_type, on, filter, &left_schema, &right_schema)?;
+        let res = self.dag.new_node(LogicalPlan::Join(join));
+        self.dag.add_input(res, left);
+        self.dag.add_input(res, right);
+        Ok(res)
+    }
+
+    pub fn create_work_table(&mut self, name: String, schema: SchemaRef) -> NodeId {
+        self.dag
+            .new_node(LogicalPlan::WorkTable(WorkTable { name, schema }))
+    }
+
+    /// Creates the union of the `anchor` and the `recursive` term of the
+    /// recursive CTE `name`, whose columns must have the same types. The
+    /// columns are named like those of the anchor.
+    pub fn create_recursive_union(
+        &mut self,
+        name: String,
+        distinct: bool,
+        anchor: NodeId,
+        recursive: NodeId,
+    ) -> Result<NodeId, PlanError> {
+        let anchor_schema = self.dag.get_node(anchor).get_schema();
+        let recursive_schema = self.dag.get_node(recursive).get_schema();
+        if anchor_schema.fields().len() != recursive_schema.fields().len() {
+            return Err(PlanError::Unsupported(format!(
+                "Recursive term with {} columns for {} columns of {name}",
+                recursive_schema.fields().len(),
+                anchor_schema.fields().len()
+            )));
+        }
+        let mut fields = Vec::with_capacity(anchor_schema.fields().len());
+        for (field, recursive_field) in anchor_schema.fields().iter().zip(recursive_schema.fields())
+        {
+            if field.data_type() != recursive_field.data_type() {
+                return Err(PlanError::Unsupported(format!(
+                    "Recursive term column {} of type {} for column {} of type {}",
+                    recursive_field.name(),
+                    recursive_field.data_type(),
+                    field.name(),
+                    field.data_type()
+                )));
+            }
+            let nullable = field.is_nullable() || recursive_field.is_nullable();
+            fields.push(Field::new(
+                field.name(),
+                field.data_type().clone(),
+                nullable,
+            ));
+        }
+
+        let res = self
+            .dag
+            .new_node(LogicalPlan::RecursiveUnion(RecursiveUnion {
+                name,
+                distinct,
+                schema: Arc::new(Schema::new(fields)),
+            }));
+        self.dag.add_input(res, anchor);
+        self.dag.add_input(res, recursive);
+        Ok(res)
+    }
+
+    /// Creates the union of all `inputs`, see `SetOperation`.
+    pub fn create_union(&mut self, distinct: bool, inputs: &[NodeId]) -> Result<NodeId, PlanError> {
+        let schema = self.set_operation_schema("UNION", inputs)?;
+        let res = self
+            .dag
+            .new_node(LogicalPlan::Union(SetOperation { distinct, schema }));
+        for &input in inputs {
+            self.dag.add_input(res, input);
+        }
+        Ok(res)
+    }
+
+    pub fn create_intersect(
+        &mut self,
+        distinct: bool,
+        left: NodeId,
+        right: NodeId,
+    ) -> Result<NodeId, PlanError> {
+        let schema = self.set_operation_schema("INTERSECT", &[left, right])?;
+        let res = self
+            .dag
+            .new_node(LogicalPlan::Intersect(SetOperation { distinct, schema }));
+        self.dag.add_input(res, left);
+        self.dag.add_input(res, right);
+        Ok(res)
+    }
+
+    pub fn create_except(
+        &mut self,
+        distinct: bool,
+        left: NodeId,
+        right: NodeId,
+    ) -> Result<NodeId, PlanError> {
+        let schema = self.set_operation_schema("EXCEPT", &[left, right])?;
+        let res = self
+            .dag
+            .new_node(LogicalPlan::Except(SetOperation { distinct, schema }));
+        self.dag.add_input(res, left);
+        self.dag.add_input(res, right);
+        Ok(res)
+    }
+
+    /// Schema of the set operation `op` over `inputs`: the column names of the
+    /// first input with the common types of the columns of all inputs.
+    fn set_operation_schema(&self, op: &str, inputs: &[NodeId]) -> Result<SchemaRef, PlanError> {
+        let schemas = inputs
+            .iter()
+            .map(|&input| self.dag.get_node(input).get_schema())
+            .collect::<Vec<_>>();
+        let first = &schemas[0];
+        let mut fields = first
+            .fields()
+            .iter()
+            .map(|field| field.as_ref().clone())
+            .collect::<Vec<_>>();
+        for schema in &schemas[1..] {
+            if schema.fields().len() != fields.len() {
+                return Err(PlanError::Unsupported(format!(
+                    "{op} of queries with {} and {} columns",
+                    fields.len(),
+                    schema.fields().len()
+                )));
+            }
+            for (field, other) in fields.iter_mut().zip(schema.fields()) {
+                let data_type = set_operation_type(field.data_type(), other.data_type())
+                    .ok_or_else(|| {
+                        PlanError::Unsupported(format!(
+                            "{op} of columns {} of type {} and {} of type {}",
+                            field.name(),
+                            field.data_type(),
+                            other.name(),
+                            other.data_type()
+                        ))
+                    })?;
+                let nullable = field.is_nullable() || other.is_nullable();
+                *field = Field::new(field.name(), data_type, nullable);
+            }
+        }
+        Ok(Arc::new(Schema::new(fields)))
+    }
+
+    /// Validates a join of inputs with the given schemas and infers its schema.
+    pub fn build_join(
+        join_type: JoinType,
+        on: Vec<(Expr, Expr)>,
+        filter: Option<Box<Expr>>,
+        left_schema: &SchemaRef,
+        right_schema: &SchemaRef,
+    ) -> Result<Join, PlanError> {
+        for field in right_schema.fields() {
+            if left_schema.field_with_name(field.name()).is_ok() {
+                return Err(PlanError::AmbiguousColumn(field.name().clone()));
+            }
+        }
+
+        for (left_key, right_key) in &on {
+            left_key.to_field(left_schema)?;
+            right_key.to_field(right_schema)?;
+        }
+        let combined = Schema::new(
+            left_schema
+                .fields()
+                .iter()
+                .chain(right_schema.fields())
+                .cloned()
+                .collect::<Vec<_>>(),
+        );
+        if let Some(filter) = &filter {
+            filter.to_field(&combined)?;
+        }
+
+        // Columns of a side whose rows may be missing in the result become nullable.
+        let (left_nullable, right_nullable) = match join_type {
+            JoinType::Left => (false, true),
+            JoinType::Right => (true, false),
+            JoinType::Full => (true, true),
+            _ => (false, false),
+        };
+        let nullable = |schema: &SchemaRef, nullable: bool| {
+            schema
+                .fields()
+                .iter()
+                .map(|field| {
+                    let nullable = nullable || field.is_nullable();
+                    field.as_ref().clone().with_nullable(nullable)
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut fields = nullable(left_schema, left_nullable);
+        if !matches!(join_type, JoinType::Semi | JoinType::Anti) {
+            fields.extend(nullable(right_schema, right_nullable));
+        }
+
+        Ok(Join {
+            kind: join_type,
+            on,
+            filter,
+            schema: Arc::new(Schema::new(fields)),
+        })
+    }
+
+    fn infer_schema(expr: &[Expr], input: &Schema) -> Result<SchemaRef, PlanError> {
+        let fields = expr
+            .iter()
+            .map(|e| e.to_field(input))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Arc::new(Schema::new(fields)))
+    }
+}
+
+/// Type both columns of a set operation are coerced to, if there is one. It
+/// holds all values of both types, except that mixing floats with integers or
+/// decimals, `UInt64` with signed integers, or decimals which need more than 76
+/// digits gives `Float64`.
+pub fn set_operation_type(lhs: &DataType, rhs: &DataType) -> Option<DataType> {
+    if lhs == rhs || rhs == &DataType::Null {
+        Some(lhs.clone())
+    } else if lhs == &DataType::Null {
+        Some(rhs.clone())
+    } else if !lhs.is_numeric() || !rhs.is_numeric() {
+        None
+    } else if lhs.is_floating() || rhs.is_floating() {
+        Some(DataType::Float64)
+    } else if is_decimal(lhs) || is_decimal(rhs) {
+        Some(decimal_type(lhs, rhs))
+    } else if lhs.is_unsigned_integer() && rhs.is_unsigned_integer() {
+        Some(DataType::UInt64)
+    } else if matches!(lhs, DataType::UInt64) || matches!(rhs, DataType::UInt64) {
+        Some(DataType::Float64)
+    } else {
+        Some(DataType::Int64)
+    }
 }
 
+pub const fn is_decimal(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Decimal128(..) | DataType::Decimal256(..)
+    )
+}
+
+/// Decimal type holding all values of `lhs` and `rhs`, which are decimals or
+/// integers, or `Float64` if it would need more than 76 digits.
+pub fn decimal_type(lhs: &DataType, rhs: &DataType) -> DataType {
+    let (Some((lhs_precision, lhs_scale)), Some((rhs_precision, rhs_scale))) =
+        (decimal_digits(lhs), decimal_digits(rhs))
+    else {
+        return DataType::Float64;
+    };
+    let scale = lhs_scale.max(rhs_scale);
+    let integer_digits = (i16::from(lhs_precision) - i16::from(lhs_scale))
+        .max(i16::from(rhs_precision) - i16::from(rhs_scale));
+    let wide = matches!(lhs, DataType::Decimal256(..)) || matches!(rhs, DataType::Decimal256(..));
+    match u8::try_from(integer_digits + i16::from(scale)) {
+        Ok(precision) if precision <= DECIMAL128_MAX_PRECISION && !wide => {
+            DataType::Decimal128(precision, scale)
+        }
+        Ok(precision) if precision <= DECIMAL256_MAX_PRECISION => {
+            DataType::Decimal256(precision, scale)
+        }
+        _ => DataType::Float64,
+    }
+}
+
+/// Precision and scale of the decimals holding all values of `data_type`.
+const fn decimal_digits(data_type: &DataType) -> Option<(u8, i8)> {
+    match data_type {
+        DataType::Decimal128(precision, scale) | DataType::Decimal256(precision, scale) => {
+            Some((*precision, *scale))
+        }
+        DataType::Int8 | DataType::UInt8 => Some((3, 0)),
+        DataType::Int16 | DataType::UInt16 => Some((5, 0)),
+        DataType::Int32 | DataType::UInt32 => Some((10, 0)),
+        DataType::Int64 => Some((19, 0)),
+        DataType::UInt64 => Some((20, 0)),
+        _ => None,
+    }
+}
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -57,7 +544,7 @@ mod tests {
         let mut builder = DagBuilder::new(&mut dag);
 
         let scan = builder.create_scan("table".to_string(), Arc::new(Schema::empty()));
-        let project = builder.create_project(Vec::new(), scan);
+        let project = builder.create_project(Vec::new(), scan).unwrap();
 
         assert_eq!(
             dag.get_node(scan),
@@ -77,4 +564,44 @@ mod tests {
         assert_eq!(dag.get_inputs(scan).len(), 0);
         assert_eq!(dag.get_inputs(project)[0], scan);
     }
+
+    #[test]
+    fn test_set_operation_type() {
+        for (lhs, rhs, expected) in [
+            (DataType::Int32, DataType::Int64, Some(DataType::Int64)),
+            (DataType::UInt8, DataType::UInt64, Some(DataType::UInt64)),
+            (DataType::UInt32, DataType::Int8, Some(DataType::Int64)),
+            (DataType::UInt64, DataType::Int64, Some(DataType::Float64)),
+            (DataType::Int32, DataType::Float32, Some(DataType::Float64)),
+            (
+                DataType::Decimal128(10, 2),
+                DataType::Decimal128(5, 4),
+                Some(DataType::Decimal128(12, 4)),
+            ),
+            (
+                DataType::Decimal128(10, 2),
+                DataType::Int64,
+                Some(DataType::Decimal128(21, 2)),
+            ),
+            (
+                DataType::Decimal128(38, 0),
+                DataType::Decimal128(38, 10),
+                Some(DataType::Decimal256(48, 10)),
+            ),
+            (
+                DataType::Decimal256(76, 0),
+                DataType::Decimal128(20, 10),
+                Some(DataType::Float64),
+            ),
+            (
+                DataType::Decimal128(10, 2),
+                DataType::Float64,
+                Some(DataType::Float64),
+            ),
+            (DataType::Int32, DataType::Utf8, None),
+        ] {
+            assert_eq!(set_operation_type(&lhs, &rhs), expected, "{lhs} and {rhs}");
+            assert_eq!(set_operation_type(&rhs, &lhs), expected, "{rhs} and {lhs}");
+        }
+    }
 }
@@ -0,0 +1,68 @@
+use crate::catalog::CatalogError;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanError {
+    SqlParser(String),
+    Catalog(CatalogError),
+    ColumnNotFound(String),
+    AmbiguousColumn(String),
+    DuplicateTable(String),
+    NotGrouped(String),
+    AggregateInWhere(String),
+    NestedAggregate(String),
+    MisplacedWindowFunction(String),
+    Unsupported(String),
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::SqlParser(message) => write!(f, "Plan Error: {message}"),
+            Self::Catalog(err) => write!(f, "{err}"),
+            Self::ColumnNotFound(column) => {
+                write!(f, "Plan Error: Column with name {column} not found")
+            }
+            Self::AmbiguousColumn(column) => {
+                write!(f, "Plan Error: Column reference {column} is ambiguous")
+            }
+            Self::DuplicateTable(name) => {
+                write!(f, "Plan Error: Table name {name} specified more than once")
+            }
+            Self::NotGrouped(column) => {
+                write!(
+                    f,
+                    "Plan Error: Column {column} must appear in the GROUP BY clause or be used in an aggregate function"
+                )
+            }
+            Self::AggregateInWhere(expr) => {
+                write!(
+                    f,
+                    "Plan Error: Aggregate function {expr} is not allowed in WHERE"
+                )
+            }
+            Self::NestedAggregate(expr) => {
+                write!(
+                    f,
+                    "Plan Error: Aggregate function calls cannot be nested: {expr}"
+                )
+            }
+            Self::MisplacedWindowFunction(expr) => {
+                write!(
+                    f,
+                    "Plan Error: Window function {expr} is only allowed in the select list, ORDER BY and QUALIFY"
+                )
+            }
+            Self::Unsupported(what) => write!(f, "Plan Error: {what} is not supported"),
+        }
+    }
+}
+
+impl Error for PlanError {}
+
+impl From<CatalogError> for PlanError {
+    fn from(err: CatalogError) -> Self {
+        Self::Catalog(err)
+    }
+}
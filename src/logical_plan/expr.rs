@@ -1,69 +1,1146 @@
+use crate::catalog::Catalog;
+use crate::logical_plan::errors::PlanError;
+use crate::logical_plan::scope::Scope;
+use crate::logical_plan::udaf::AggregateUdf;
+use arrow::datatypes::{DataType, Field, Schema};
 use sqlparser::ast;
 use sqlparser::ast::BinaryOperator;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum BinaryOp {
     And,
     Or,
     Lt,
     Gt,
+    LtEq,
+    GtEq,
+    Eq,
+    NotEq,
+    Plus,
+    Minus,
+    Multiply,
+    Divide,
 }
 
-#[derive(PartialEq, Eq, Debug)]
+impl BinaryOp {
+    pub const fn is_logical(self) -> bool {
+        matches!(self, Self::And | Self::Or)
+    }
+
+    pub const fn is_comparison(self) -> bool {
+        matches!(
+            self,
+            Self::Lt | Self::Gt | Self::LtEq | Self::GtEq | Self::Eq | Self::NotEq
+        )
+    }
+
+    pub const fn is_arithmetic(self) -> bool {
+        matches!(
+            self,
+            Self::Plus | Self::Minus | Self::Multiply | Self::Divide
+        )
+    }
+
+    /// Binding strength of the operator in SQL, higher binds tighter.
+    pub const fn precedence(self) -> u8 {
+        match self {
+            Self::Or => 1,
+            Self::And => 2,
+            Self::Lt | Self::Gt | Self::LtEq | Self::GtEq | Self::Eq | Self::NotEq => 3,
+            Self::Plus | Self::Minus => 4,
+            Self::Multiply | Self::Divide => 5,
+        }
+    }
+}
+
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let op = match self {
+            Self::And => "AND",
+            Self::Or => "OR",
+            Self::Lt => "<",
+            Self::Gt => ">",
+            Self::LtEq => "<=",
+            Self::GtEq => ">=",
+            Self::Eq => "=",
+            Self::NotEq => "!=",
+            Self::Plus => "+",
+            Self::Minus => "-",
+            Self::Multiply => "*",
+            Self::Divide => "/",
+        };
+        write!(f, "{op}")
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
 pub struct Binary {
     pub lhs: Box<Expr>,
     pub op: BinaryOp,
     pub rhs: Box<Expr>,
 }
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug, Clone)]
 pub struct Ident {
     pub name: String,
 }
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug, Clone)]
 pub struct IntegerLiteral {
     pub value: i32,
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct FloatLiteral {
+    pub value: f64,
+}
+
+impl PartialEq for FloatLiteral {
+    fn eq(&self, other: &Self) -> bool {
+        self.value.to_bits() == other.value.to_bits()
+    }
+}
+
+impl Eq for FloatLiteral {}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct StringLiteral {
+    pub value: String,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum AggregateFunc {
+    Count,
+    Sum,
+    Min,
+    Max,
+    Avg,
+    ApproxCountDistinct,
+    ApproxPercentileCont,
+    ApproxMedian,
+    ApproxTopK,
+    /// Bit mask of the arguments which are not grouped by the grouping set of
+    /// the row, with the first argument as the most significant bit.
+    Grouping,
+    Udaf(Arc<AggregateUdf>),
+}
+
+impl AggregateFunc {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "count" => Some(Self::Count),
+            "sum" => Some(Self::Sum),
+            "min" => Some(Self::Min),
+            "max" => Some(Self::Max),
+            "avg" => Some(Self::Avg),
+            "approx_count_distinct" => Some(Self::ApproxCountDistinct),
+            "approx_percentile_cont" => Some(Self::ApproxPercentileCont),
+            "approx_median" => Some(Self::ApproxMedian),
+            "approx_top_k" => Some(Self::ApproxTopK),
+            "grouping" => Some(Self::Grouping),
+            _ => None,
+        }
+    }
+
+    pub fn return_type(&self, arg_types: &[DataType]) -> Result<DataType, PlanError> {
+        match (self, arg_types) {
+            (Self::Udaf(udaf), _) => udaf.return_type(arg_types),
+            (Self::Count, _) | (Self::ApproxCountDistinct, [_]) => Ok(DataType::Int64),
+            (Self::Min | Self::Max, [arg]) => Ok(arg.clone()),
+            (Self::Avg, [arg]) if arg.is_numeric() => Ok(DataType::Float64),
+            (Self::Sum, [arg]) if arg.is_integer() => Ok(DataType::Int64),
+            (Self::Sum, [arg]) if arg.is_floating() => Ok(DataType::Float64),
+            (Self::ApproxMedian, [arg]) | (Self::ApproxPercentileCont, [arg, _])
+                if arg.is_numeric() =>
+            {
+                Ok(DataType::Float64)
+            }
+            (Self::ApproxTopK, [arg, k]) if k.is_integer() => {
+                Ok(DataType::new_list(arg.clone(), true))
+            }
+            (Self::Grouping, args) if (1..64).contains(&args.len()) => Ok(DataType::Int64),
+            _ => Err(PlanError::Unsupported(format!(
+                "{self}({})",
+                arg_types
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for AggregateFunc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Count => "count",
+            Self::Sum => "sum",
+            Self::Min => "min",
+            Self::Max => "max",
+            Self::Avg => "avg",
+            Self::ApproxCountDistinct => "approx_count_distinct",
+            Self::ApproxPercentileCont => "approx_percentile_cont",
+            Self::ApproxMedian => "approx_median",
+            Self::ApproxTopK => "approx_top_k",
+            Self::Grouping => "grouping",
+            Self::Udaf(udaf) => udaf.name(),
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct AggregateFunction {
+    pub func: AggregateFunc,
+    pub args: Vec<Expr>,
+    pub distinct: bool,
+    /// Predicate of `FILTER (WHERE ...)`, only rows for which it is true are
+    /// aggregated.
+    pub filter: Option<Box<Expr>>,
+}
+
+/// Function evaluated over a window of rows, see `WindowFunction`.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum WindowFunc {
+    RowNumber,
+    Rank,
+    DenseRank,
+    PercentRank,
+    CumeDist,
+    Ntile,
+    Lag,
+    Lead,
+    FirstValue,
+    LastValue,
+    NthValue,
+    /// Aggregate function over the rows of the window frame.
+    Aggregate(AggregateFunc),
+}
+
+impl WindowFunc {
+    /// Window function called `name`, other than an aggregate function.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "row_number" => Some(Self::RowNumber),
+            "rank" => Some(Self::Rank),
+            "dense_rank" => Some(Self::DenseRank),
+            "percent_rank" => Some(Self::PercentRank),
+            "cume_dist" => Some(Self::CumeDist),
+            "ntile" => Some(Self::Ntile),
+            "lag" => Some(Self::Lag),
+            "lead" => Some(Self::Lead),
+            "first_value" => Some(Self::FirstValue),
+            "last_value" => Some(Self::LastValue),
+            "nth_value" => Some(Self::NthValue),
+            _ => None,
+        }
+    }
+
+    pub fn return_type(&self, arg_types: &[DataType]) -> Result<DataType, PlanError> {
+        match (self, arg_types) {
+            (Self::Aggregate(func), _) => func.return_type(arg_types),
+            (Self::RowNumber | Self::Rank | Self::DenseRank, [])
+            | (Self::Ntile, [DataType::Int32]) => Ok(DataType::Int64),
+            (Self::PercentRank | Self::CumeDist, []) => Ok(DataType::Float64),
+            (Self::Lag | Self::Lead, [arg, ..]) | (Self::FirstValue | Self::LastValue, [arg]) => {
+                Ok(arg.clone())
+            }
+            (Self::NthValue, [arg, DataType::Int32]) => Ok(arg.clone()),
+            _ => Err(PlanError::Unsupported(format!(
+                "{self}({})",
+                arg_types
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+
+    /// Whether the function is evaluated over the window frame, rather than
+    /// over the whole partition.
+    pub const fn uses_frame(&self) -> bool {
+        matches!(
+            self,
+            Self::FirstValue | Self::LastValue | Self::NthValue | Self::Aggregate(_)
+        )
+    }
+}
+
+impl fmt::Display for WindowFunc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::RowNumber => "row_number",
+            Self::Rank => "rank",
+            Self::DenseRank => "dense_rank",
+            Self::PercentRank => "percent_rank",
+            Self::CumeDist => "cume_dist",
+            Self::Ntile => "ntile",
+            Self::Lag => "lag",
+            Self::Lead => "lead",
+            Self::FirstValue => "first_value",
+            Self::LastValue => "last_value",
+            Self::NthValue => "nth_value",
+            Self::Aggregate(func) => return write!(f, "{func}"),
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum WindowFrameUnits {
+    /// Offsets count rows.
+    Rows,
+    /// Offsets are differences of the value of the single ORDER BY key, and
+    /// the current row stands for all its peers.
+    Range,
+    /// Offsets count groups of peers, rows with equal ORDER BY keys.
+    Groups,
+}
+
+/// Bound of a window frame, whose offsets are non-negative literals.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum WindowFrameBound {
+    UnboundedPreceding,
+    Preceding(Box<Expr>),
+    CurrentRow,
+    Following(Box<Expr>),
+    UnboundedFollowing,
+}
+
+impl WindowFrameBound {
+    /// Rank of the bound from the start to the end of a partition, ignoring
+    /// offsets.
+    const fn position(&self) -> u8 {
+        match self {
+            Self::UnboundedPreceding => 0,
+            Self::Preceding(_) => 1,
+            Self::CurrentRow => 2,
+            Self::Following(_) => 3,
+            Self::UnboundedFollowing => 4,
+        }
+    }
+}
+
+impl fmt::Display for WindowFrameBound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnboundedPreceding => write!(f, "UNBOUNDED PRECEDING"),
+            Self::Preceding(offset) => write!(f, "{offset} PRECEDING"),
+            Self::CurrentRow => write!(f, "CURRENT ROW"),
+            Self::Following(offset) => write!(f, "{offset} FOLLOWING"),
+            Self::UnboundedFollowing => write!(f, "UNBOUNDED FOLLOWING"),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct WindowFrame {
+    pub units: WindowFrameUnits,
+    pub start: WindowFrameBound,
+    pub end: WindowFrameBound,
+}
+
+impl WindowFrame {
+    /// Frame of windows without an explicit one: the whole partition without
+    /// ORDER BY, otherwise the rows up to the last peer of the current row.
+    pub const fn default_for(ordered: bool) -> Self {
+        if ordered {
+            Self {
+                units: WindowFrameUnits::Range,
+                start: WindowFrameBound::UnboundedPreceding,
+                end: WindowFrameBound::CurrentRow,
+            }
+        } else {
+            Self {
+                units: WindowFrameUnits::Rows,
+                start: WindowFrameBound::UnboundedPreceding,
+                end: WindowFrameBound::UnboundedFollowing,
+            }
+        }
+    }
+}
+
+impl fmt::Display for WindowFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let units = match self.units {
+            WindowFrameUnits::Rows => "ROWS",
+            WindowFrameUnits::Range => "RANGE",
+            WindowFrameUnits::Groups => "GROUPS",
+        };
+        write!(f, "{units} BETWEEN {} AND {}", self.start, self.end)
+    }
+}
+
+/// Call of `func` for each row, over the rows of its partition, those with the
+/// same values of `partition_by`, sorted by `order_by`. Functions which depend
+/// on a frame see the rows of `frame`, or of the default frame.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct WindowFunction {
+    pub func: WindowFunc,
+    pub args: Vec<Expr>,
+    pub partition_by: Vec<Expr>,
+    pub order_by: Vec<SortExpr>,
+    pub frame: Option<WindowFrame>,
+}
+
+impl WindowFunction {
+    /// Whether both functions are evaluated over the same partitions and order.
+    pub fn same_window(&self, other: &Self) -> bool {
+        self.partition_by == other.partition_by && self.order_by == other.order_by
+    }
+
+    pub fn frame(&self) -> WindowFrame {
+        self.frame
+            .clone()
+            .unwrap_or_else(|| WindowFrame::default_for(!self.order_by.is_empty()))
+    }
+}
+
+impl fmt::Display for WindowFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let args = self
+            .args
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut window = Vec::new();
+        if !self.partition_by.is_empty() {
+            let partition_by = self.partition_by.iter().map(ToString::to_string);
+            window.push(format!(
+                "PARTITION BY {}",
+                partition_by.collect::<Vec<_>>().join(", ")
+            ));
+        }
+        if !self.order_by.is_empty() {
+            let order_by = self.order_by.iter().map(ToString::to_string);
+            window.push(format!(
+                "ORDER BY {}",
+                order_by.collect::<Vec<_>>().join(", ")
+            ));
+        }
+        if let Some(frame) = &self.frame {
+            window.push(frame.to_string());
+        }
+        write!(f, "{}({args}) OVER ({})", self.func, window.join(" "))
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Alias {
+    pub expr: Box<Expr>,
+    pub name: String,
+}
+
 #[allow(dead_code)]
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug, Clone)]
 pub enum Expr {
     Binary(Binary),
     Ident(Ident),
     IntegerLiteral(IntegerLiteral),
+    FloatLiteral(FloatLiteral),
+    StringLiteral(StringLiteral),
+    AggregateFunction(AggregateFunction),
+    WindowFunction(WindowFunction),
+    Alias(Alias),
+    /// Logical negation of a boolean expression.
+    Not(Box<Self>),
+    /// Whether the value of the expression is NULL, never NULL itself.
+    IsNull(Box<Self>),
+    Wildcard,
 }
 
-pub struct VisitExpression {}
+impl Expr {
+    /// Name of the column produced by this expression.
+    pub fn name(&self) -> String {
+        match self {
+            Self::Alias(alias) => alias.name.clone(),
+            _ => self.to_string(),
+        }
+    }
+
+    pub fn to_field(&self, schema: &Schema) -> Result<Field, PlanError> {
+        let (data_type, nullable) = match self {
+            Self::Ident(ident) => {
+                let field = schema
+                    .field_with_name(&ident.name)
+                    .map_err(|_| PlanError::ColumnNotFound(ident.name.clone()))?;
+                (field.data_type().clone(), field.is_nullable())
+            }
+            Self::IntegerLiteral(_) => (DataType::Int32, false),
+            Self::FloatLiteral(_) => (DataType::Float64, false),
+            Self::StringLiteral(_) => (DataType::Utf8, false),
+            Self::Binary(binary) => {
+                let lhs = binary.lhs.to_field(schema)?;
+                let rhs = binary.rhs.to_field(schema)?;
+                let nullable = lhs.is_nullable() || rhs.is_nullable();
+                if binary.op.is_arithmetic() {
+                    (arithmetic_type(lhs.data_type(), rhs.data_type())?, nullable)
+                } else {
+                    (DataType::Boolean, nullable)
+                }
+            }
+            Self::AggregateFunction(aggregate) => {
+                let mut arg_types = Vec::with_capacity(aggregate.args.len());
+                for arg in &aggregate.args {
+                    if *arg != Self::Wildcard || aggregate.func != AggregateFunc::Count {
+                        arg_types.push(arg.to_field(schema)?.data_type().clone());
+                    }
+                }
+                let nullable = !matches!(
+                    aggregate.func,
+                    AggregateFunc::Count | AggregateFunc::Grouping
+                );
+                (aggregate.func.return_type(&arg_types)?, nullable)
+            }
+            Self::WindowFunction(window) => {
+                let mut arg_types = Vec::with_capacity(window.args.len());
+                for arg in &window.args {
+                    if *arg != Self::Wildcard {
+                        arg_types.push(arg.to_field(schema)?.data_type().clone());
+                    }
+                }
+                for expr in &window.partition_by {
+                    expr.to_field(schema)?;
+                }
+                for sort in &window.order_by {
+                    sort.expr.to_field(schema)?;
+                }
+                let nullable = match &window.func {
+                    WindowFunc::Aggregate(func) => *func != AggregateFunc::Count,
+                    func => func.uses_frame() || matches!(func, WindowFunc::Lag | WindowFunc::Lead),
+                };
+                (window.func.return_type(&arg_types)?, nullable)
+            }
+            Self::Alias(alias) => {
+                let field = alias.expr.to_field(schema)?;
+                (field.data_type().clone(), field.is_nullable())
+            }
+            Self::Not(expr) => {
+                let field = expr.to_field(schema)?;
+                if field.data_type() != &DataType::Boolean {
+                    return Err(PlanError::Unsupported(format!(
+                        "NOT of {} value {expr}",
+                        field.data_type()
+                    )));
+                }
+                (DataType::Boolean, field.is_nullable())
+            }
+            Self::IsNull(expr) => {
+                expr.to_field(schema)?;
+                (DataType::Boolean, false)
+            }
+            Self::Wildcard => return Err(PlanError::Unsupported(format!("Expression {self}"))),
+        };
+        Ok(Field::new(self.name(), data_type, nullable))
+    }
 
-impl VisitExpression {
-    pub fn visit(expr: &ast::Expr) -> Expr {
+    /// Adds the names of all columns referenced by this expression to `names`.
+    pub fn collect_columns<'e>(&'e self, names: &mut HashSet<&'e str>) {
+        match self {
+            Self::Ident(ident) => {
+                names.insert(&ident.name);
+            }
+            Self::Binary(binary) => {
+                binary.lhs.collect_columns(names);
+                binary.rhs.collect_columns(names);
+            }
+            Self::AggregateFunction(aggregate) => {
+                for expr in aggregate.args.iter().chain(aggregate.filter.as_deref()) {
+                    expr.collect_columns(names);
+                }
+            }
+            Self::WindowFunction(window) => {
+                let order_by = window.order_by.iter().map(|sort| &sort.expr);
+                for expr in window
+                    .args
+                    .iter()
+                    .chain(&window.partition_by)
+                    .chain(order_by)
+                {
+                    expr.collect_columns(names);
+                }
+            }
+            Self::Alias(alias) => alias.expr.collect_columns(names),
+            Self::Not(expr) | Self::IsNull(expr) => expr.collect_columns(names),
+            Self::IntegerLiteral(_)
+            | Self::FloatLiteral(_)
+            | Self::StringLiteral(_)
+            | Self::Wildcard => {}
+        }
+    }
+
+    /// Splits a predicate into the operands of its top-level ANDs.
+    pub fn into_conjuncts(self) -> Vec<Self> {
+        match self {
+            Self::Binary(binary) if binary.op == BinaryOp::And => {
+                let mut conjuncts = binary.lhs.into_conjuncts();
+                conjuncts.extend(binary.rhs.into_conjuncts());
+                conjuncts
+            }
+            _ => vec![self],
+        }
+    }
+
+    /// Combines predicates with AND, the inverse of `into_conjuncts`.
+    pub fn conjunction(conjuncts: Vec<Self>) -> Option<Self> {
+        conjuncts.into_iter().reduce(|lhs, rhs| {
+            Self::Binary(Binary {
+                lhs: Box::new(lhs),
+                op: BinaryOp::And,
+                rhs: Box::new(rhs),
+            })
+        })
+    }
+
+    pub fn contains_aggregate(&self) -> bool {
+        match self {
+            Self::AggregateFunction(_) => true,
+            Self::WindowFunction(window) => {
+                let order_by = window.order_by.iter().map(|sort| &sort.expr);
+                window
+                    .args
+                    .iter()
+                    .chain(&window.partition_by)
+                    .chain(order_by)
+                    .any(Self::contains_aggregate)
+            }
+            Self::Binary(binary) => {
+                binary.lhs.contains_aggregate() || binary.rhs.contains_aggregate()
+            }
+            Self::Alias(alias) => alias.expr.contains_aggregate(),
+            Self::Not(expr) | Self::IsNull(expr) => expr.contains_aggregate(),
+            Self::Ident(_)
+            | Self::IntegerLiteral(_)
+            | Self::FloatLiteral(_)
+            | Self::StringLiteral(_)
+            | Self::Wildcard => false,
+        }
+    }
+
+    pub fn contains_window_function(&self) -> bool {
+        match self {
+            Self::WindowFunction(_) => true,
+            Self::AggregateFunction(aggregate) => aggregate
+                .args
+                .iter()
+                .chain(aggregate.filter.as_deref())
+                .any(Self::contains_window_function),
+            Self::Binary(binary) => {
+                binary.lhs.contains_window_function() || binary.rhs.contains_window_function()
+            }
+            Self::Alias(alias) => alias.expr.contains_window_function(),
+            Self::Not(expr) | Self::IsNull(expr) => expr.contains_window_function(),
+            Self::Ident(_)
+            | Self::IntegerLiteral(_)
+            | Self::FloatLiteral(_)
+            | Self::StringLiteral(_)
+            | Self::Wildcard => false,
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Binary(binary) => {
+                // Operators are left-associative, so an operand on the right
+                // with the same precedence is parenthesized as well.
+                let precedence = binary.op.precedence();
+                fmt_operand(f, &binary.lhs, |op| op.precedence() < precedence)?;
+                write!(f, " {} ", binary.op)?;
+                fmt_operand(f, &binary.rhs, |op| op.precedence() <= precedence)
+            }
+            Self::Ident(ident) => write!(f, "{}", ident.name),
+            Self::IntegerLiteral(literal) => write!(f, "{}", literal.value),
+            Self::FloatLiteral(literal) => write!(f, "{:?}", literal.value),
+            Self::StringLiteral(literal) => write!(f, "'{}'", literal.value.replace('\'', "''")),
+            Self::AggregateFunction(aggregate) => {
+                let args = aggregate
+                    .args
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                if aggregate.distinct {
+                    write!(f, "{}(DISTINCT {args})", aggregate.func)?;
+                } else {
+                    write!(f, "{}({args})", aggregate.func)?;
+                }
+                match &aggregate.filter {
+                    Some(filter) => write!(f, " FILTER (WHERE {filter})"),
+                    None => Ok(()),
+                }
+            }
+            Self::WindowFunction(window) => write!(f, "{window}"),
+            Self::Alias(alias) => write!(f, "{} AS {}", alias.expr, alias.name),
+            Self::Not(expr) => {
+                write!(f, "NOT ")?;
+                fmt_operand(f, expr, |_| true)
+            }
+            Self::IsNull(expr) => {
+                fmt_operand(f, expr, |_| true)?;
+                write!(f, " IS NULL")
+            }
+            Self::Wildcard => write!(f, "*"),
+        }
+    }
+}
+
+/// Writes the operand `expr` of an operator, in parentheses if it's a binary
+/// expression whose operator binds weaker.
+fn fmt_operand(
+    f: &mut fmt::Formatter,
+    expr: &Expr,
+    binds_weaker: impl Fn(BinaryOp) -> bool,
+) -> fmt::Result {
+    match expr {
+        Expr::Binary(binary) if binds_weaker(binary.op) => write!(f, "({expr})"),
+        expr => write!(f, "{expr}"),
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct SortExpr {
+    pub expr: Expr,
+    pub asc: bool,
+    pub nulls_first: bool,
+}
+
+impl fmt::Display for SortExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let direction = if self.asc { "ASC" } else { "DESC" };
+        let nulls = if self.nulls_first { "FIRST" } else { "LAST" };
+        write!(f, "{} {direction} NULLS {nulls}", self.expr)
+    }
+}
+
+pub fn arithmetic_type(lhs: &DataType, rhs: &DataType) -> Result<DataType, PlanError> {
+    if !lhs.is_numeric() || !rhs.is_numeric() {
+        return Err(PlanError::Unsupported(format!(
+            "Arithmetic between {lhs} and {rhs}"
+        )));
+    }
+    if lhs.is_floating() || rhs.is_floating() {
+        Ok(DataType::Float64)
+    } else if lhs == rhs {
+        Ok(lhs.clone())
+    } else {
+        Ok(DataType::Int64)
+    }
+}
+
+pub struct VisitExpression<'c> {
+    catalog: &'c dyn Catalog,
+    scope: Option<&'c Scope>,
+    outer_scope: Option<&'c Scope>,
+    subqueries: Option<&'c HashMap<String, String>>,
+}
+
+impl<'c> VisitExpression<'c> {
+    pub fn new(catalog: &'c dyn Catalog) -> Self {
+        Self {
+            catalog,
+            scope: None,
+            outer_scope: None,
+            subqueries: None,
+        }
+    }
+
+    /// Resolves identifiers to the columns of the FROM clause described by `scope`.
+    pub const fn with_scope(mut self, scope: &'c Scope) -> Self {
+        self.scope = Some(scope);
+        self
+    }
+
+    /// Resolves identifiers which aren't columns of `scope` to the columns of
+    /// the query enclosing a subquery, making the subquery correlated.
+    pub const fn with_outer_scope(mut self, outer_scope: &'c Scope) -> Self {
+        self.outer_scope = Some(outer_scope);
+        self
+    }
+
+    /// Replaces scalar subqueries by the columns which hold their values, by
+    /// the SQL text of the subquery.
+    pub const fn with_subqueries(mut self, subqueries: &'c HashMap<String, String>) -> Self {
+        self.subqueries = Some(subqueries);
+        self
+    }
+
+    fn visit_identifier(&self, qualifier: Option<&str>, name: &str) -> Result<Expr, PlanError> {
+        let column = match (self.scope, qualifier) {
+            (Some(scope), _) => scope.resolve(qualifier, name),
+            (None, Some(qualifier)) => {
+                Err(PlanError::ColumnNotFound(format!("{qualifier}.{name}")))
+            }
+            (None, None) => Ok(None),
+        };
+        let column = match column {
+            Ok(None) | Err(PlanError::ColumnNotFound(_)) => {
+                match self.outer_scope.map(|scope| scope.resolve(qualifier, name)) {
+                    Some(Ok(Some(outer))) => Ok(Some(outer)),
+                    _ => column,
+                }
+            }
+            _ => column,
+        }?;
+        let name = column.map_or(name, |column| &column.field);
+        Ok(Expr::Ident(Ident {
+            name: name.to_string(),
+        }))
+    }
+
+    pub fn visit(&self, expr: &ast::Expr) -> Result<Expr, PlanError> {
         match expr {
-            ast::Expr::BinaryOp { left, op, right } => Expr::Binary(Binary {
-                lhs: Box::new(Self::visit(left)),
-                op: Self::visit_binary_op(op),
-                rhs: Box::new(Self::visit(right)),
-            }),
-            ast::Expr::Identifier(ident) => Expr::Ident(Ident {
-                name: ident.value.to_string(),
-            }),
+            ast::Expr::BinaryOp { left, op, right } => Ok(Expr::Binary(Binary {
+                lhs: Box::new(self.visit(left)?),
+                op: Self::visit_binary_op(op)?,
+                rhs: Box::new(self.visit(right)?),
+            })),
+            ast::Expr::Identifier(ident) => self.visit_identifier(None, &ident.value),
+            ast::Expr::CompoundIdentifier(idents) if idents.len() == 2 => {
+                self.visit_identifier(Some(&idents[0].value), &idents[1].value)
+            }
+            ast::Expr::Nested(expr) => self.visit(expr),
+            ast::Expr::UnaryOp {
+                op: ast::UnaryOperator::Not,
+                expr,
+            } => Ok(Expr::Not(Box::new(self.visit(expr)?))),
+            ast::Expr::IsNull(expr) => Ok(Expr::IsNull(Box::new(self.visit(expr)?))),
+            ast::Expr::IsNotNull(expr) => Ok(Expr::Not(Box::new(Expr::IsNull(Box::new(
+                self.visit(expr)?,
+            ))))),
+            ast::Expr::Between {
+                expr,
+                negated,
+                low,
+                high,
+            } => {
+                // `x BETWEEN a AND b` is `x >= a AND x <= b`, `NOT BETWEEN` its negation.
+                let expr = self.visit(expr)?;
+                let compare = |op, bound: &ast::Expr| -> Result<Expr, PlanError> {
+                    Ok(Expr::Binary(Binary {
+                        lhs: Box::new(expr.clone()),
+                        op,
+                        rhs: Box::new(self.visit(bound)?),
+                    }))
+                };
+                let (low, high, op) = if *negated {
+                    (
+                        compare(BinaryOp::Lt, low)?,
+                        compare(BinaryOp::Gt, high)?,
+                        BinaryOp::Or,
+                    )
+                } else {
+                    (
+                        compare(BinaryOp::GtEq, low)?,
+                        compare(BinaryOp::LtEq, high)?,
+                        BinaryOp::And,
+                    )
+                };
+                Ok(Expr::Binary(Binary {
+                    lhs: Box::new(low),
+                    op,
+                    rhs: Box::new(high),
+                }))
+            }
             ast::Expr::Value(value) => match value {
-                ast::Value::Number(number, flag) => Expr::IntegerLiteral(IntegerLiteral {
-                    value: number.parse().unwrap(),
-                }),
-                _ => unimplemented!(),
+                ast::Value::Number(number, flag) => {
+                    if let Ok(value) = number.parse::<i32>() {
+                        Ok(Expr::IntegerLiteral(IntegerLiteral { value }))
+                    } else if let Ok(value) = number.parse::<f64>() {
+                        Ok(Expr::FloatLiteral(FloatLiteral { value }))
+                    } else {
+                        Err(PlanError::Unsupported(format!("Literal {number}")))
+                    }
+                }
+                ast::Value::SingleQuotedString(value) => Ok(Expr::StringLiteral(StringLiteral {
+                    value: value.clone(),
+                })),
+                _ => Err(PlanError::Unsupported(format!("Literal {value}"))),
             },
-            _ => unimplemented!(),
+            ast::Expr::Function(function) => self.visit_function(function),
+            ast::Expr::Subquery(query) => {
+                let name = self
+                    .subqueries
+                    .and_then(|subqueries| subqueries.get(&format!("({query})")))
+                    .ok_or_else(|| PlanError::Unsupported(format!("Subquery {expr}")))?;
+                Ok(Expr::Ident(Ident { name: name.clone() }))
+            }
+            _ => Err(PlanError::Unsupported(format!("Expression {expr}"))),
+        }
+    }
+
+    fn visit_function(&self, function: &ast::Function) -> Result<Expr, PlanError> {
+        if let Some(over) = &function.over {
+            return self.visit_window_function(function, over);
+        }
+        let name = function.name.to_string();
+        if WindowFunc::from_name(&name).is_some() {
+            return Err(PlanError::Unsupported(format!(
+                "Window function {name} without OVER"
+            )));
+        }
+        let func = self.aggregate_func(&name)?;
+        if func == AggregateFunc::Grouping && (function.distinct || function.filter.is_some()) {
+            return Err(PlanError::Unsupported(format!("Function call {function}")));
+        }
+
+        let args = self.visit_args(function, func == AggregateFunc::Count)?;
+        let filter = function
+            .filter
+            .as_ref()
+            .map(|filter| self.visit(filter))
+            .transpose()?;
+        for expr in args.iter().chain(&filter) {
+            if expr.contains_aggregate() {
+                return Err(PlanError::NestedAggregate(function.to_string()));
+            }
+            if expr.contains_window_function() {
+                return Err(PlanError::MisplacedWindowFunction(function.to_string()));
+            }
+        }
+        Self::check_aggregate_args(function, &func, &args)?;
+
+        Ok(Expr::AggregateFunction(AggregateFunction {
+            func,
+            args,
+            distinct: function.distinct,
+            filter: filter.map(Box::new),
+        }))
+    }
+
+    fn aggregate_func(&self, name: &str) -> Result<AggregateFunc, PlanError> {
+        if let Some(func) = AggregateFunc::from_name(name) {
+            Ok(func)
+        } else if let Some(udaf) = self.catalog.get_udaf(name) {
+            Ok(AggregateFunc::Udaf(udaf))
+        } else {
+            Err(PlanError::Unsupported(format!("Function {name}")))
+        }
+    }
+
+    /// Visits the arguments of `function`, which may be `*` with `wildcard`.
+    /// Window functions are not allowed in arguments.
+    fn visit_args(&self, function: &ast::Function, wildcard: bool) -> Result<Vec<Expr>, PlanError> {
+        let mut args = Vec::with_capacity(function.args.len());
+        for arg in &function.args {
+            match arg {
+                ast::FunctionArg::Unnamed(ast::FunctionArgExpr::Expr(expr)) => {
+                    let expr = self.visit(expr)?;
+                    if expr.contains_window_function() {
+                        return Err(PlanError::MisplacedWindowFunction(function.to_string()));
+                    }
+                    args.push(expr);
+                }
+                ast::FunctionArg::Unnamed(ast::FunctionArgExpr::Wildcard) if wildcard => {
+                    args.push(Expr::Wildcard);
+                }
+                _ => return Err(PlanError::Unsupported(format!("Function argument {arg}"))),
+            }
+        }
+        Ok(args)
+    }
+
+    fn check_aggregate_args(
+        function: &ast::Function,
+        func: &AggregateFunc,
+        args: &[Expr],
+    ) -> Result<(), PlanError> {
+        match (func, args.get(1)) {
+            (AggregateFunc::ApproxPercentileCont, Some(Expr::FloatLiteral(p)))
+                if (0.0..=1.0).contains(&p.value) => {}
+            (AggregateFunc::ApproxPercentileCont, Some(Expr::IntegerLiteral(p)))
+                if p.value == 0 || p.value == 1 => {}
+            (AggregateFunc::ApproxTopK, Some(Expr::IntegerLiteral(k))) if k.value > 0 => {}
+            (AggregateFunc::ApproxPercentileCont | AggregateFunc::ApproxTopK, _) => {
+                return Err(PlanError::Unsupported(format!(
+                    "Function call {function}, the last argument must be a valid constant"
+                )));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn visit_window_function(
+        &self,
+        function: &ast::Function,
+        over: &ast::WindowType,
+    ) -> Result<Expr, PlanError> {
+        let ast::WindowType::WindowSpec(spec) = over else {
+            return Err(PlanError::Unsupported(format!("Named window {over}")));
+        };
+        let name = function.name.to_string();
+        let func = match WindowFunc::from_name(&name) {
+            Some(func) => func,
+            None => WindowFunc::Aggregate(self.aggregate_func(&name)?),
+        };
+        if function.distinct
+            || func == WindowFunc::Aggregate(AggregateFunc::Grouping)
+            || function.filter.is_some()
+            || function.null_treatment.is_some()
+            || !function.order_by.is_empty()
+        {
+            return Err(PlanError::Unsupported(format!(
+                "Window function call {function}"
+            )));
+        }
+
+        let count = func == WindowFunc::Aggregate(AggregateFunc::Count);
+        let args = self.visit_args(function, count)?;
+        let offset = |arg: Option<&Expr>, min: i32| match arg {
+            Some(Expr::IntegerLiteral(literal)) => literal.value >= min,
+            _ => false,
+        };
+        let valid = match &func {
+            WindowFunc::RowNumber
+            | WindowFunc::Rank
+            | WindowFunc::DenseRank
+            | WindowFunc::PercentRank
+            | WindowFunc::CumeDist => args.is_empty(),
+            WindowFunc::Ntile => args.len() == 1 && offset(args.first(), 1),
+            WindowFunc::Lag | WindowFunc::Lead => {
+                (1..=3).contains(&args.len()) && (args.len() == 1 || offset(args.get(1), 0))
+            }
+            WindowFunc::FirstValue | WindowFunc::LastValue => args.len() == 1,
+            WindowFunc::NthValue => args.len() == 2 && offset(args.get(1), 1),
+            WindowFunc::Aggregate(func) => {
+                Self::check_aggregate_args(function, func, &args)?;
+                true
+            }
+        };
+        if !valid {
+            return Err(PlanError::Unsupported(format!(
+                "Window function call {function}"
+            )));
+        }
+
+        let visit = |expr: &ast::Expr| {
+            let expr = self.visit(expr)?;
+            if expr.contains_window_function() {
+                return Err(PlanError::MisplacedWindowFunction(function.to_string()));
+            }
+            Ok(expr)
+        };
+        let partition_by = spec
+            .partition_by
+            .iter()
+            .map(visit)
+            .collect::<Result<Vec<_>, _>>()?;
+        let order_by = spec
+            .order_by
+            .iter()
+            .map(|item| {
+                let asc = item.asc.unwrap_or(true);
+                Ok(SortExpr {
+                    expr: visit(&item.expr)?,
+                    asc,
+                    nulls_first: item.nulls_first.unwrap_or(!asc),
+                })
+            })
+            .collect::<Result<Vec<_>, PlanError>>()?;
+        let frame = spec
+            .window_frame
+            .as_ref()
+            .map(|frame| self.visit_window_frame(frame, order_by.len()))
+            .transpose()?;
+
+        Ok(Expr::WindowFunction(WindowFunction {
+            func,
+            args,
+            partition_by,
+            order_by,
+            frame,
+        }))
+    }
+
+    fn visit_window_frame(
+        &self,
+        frame: &ast::WindowFrame,
+        order_by_len: usize,
+    ) -> Result<WindowFrame, PlanError> {
+        let units = match frame.units {
+            ast::WindowFrameUnits::Rows => WindowFrameUnits::Rows,
+            ast::WindowFrameUnits::Range => WindowFrameUnits::Range,
+            ast::WindowFrameUnits::Groups => WindowFrameUnits::Groups,
+        };
+        let end = frame.end_bound.as_ref();
+        let unsupported = || {
+            PlanError::Unsupported(format!(
+                "Window frame {} BETWEEN {} AND {}",
+                frame.units,
+                frame.start_bound,
+                end.unwrap_or(&ast::WindowFrameBound::CurrentRow)
+            ))
+        };
+        let bound = |bound: &ast::WindowFrameBound| -> Result<WindowFrameBound, PlanError> {
+            let offset = |offset: &ast::Expr| match self.visit(offset)? {
+                Expr::IntegerLiteral(literal) if literal.value >= 0 => {
+                    Ok(Box::new(Expr::IntegerLiteral(literal)))
+                }
+                Expr::FloatLiteral(literal)
+                    if literal.value >= 0.0 && units == WindowFrameUnits::Range =>
+                {
+                    Ok(Box::new(Expr::FloatLiteral(literal)))
+                }
+                _ => Err(unsupported()),
+            };
+            Ok(match bound {
+                ast::WindowFrameBound::Preceding(None) => WindowFrameBound::UnboundedPreceding,
+                ast::WindowFrameBound::Preceding(Some(expr)) => {
+                    WindowFrameBound::Preceding(offset(expr)?)
+                }
+                ast::WindowFrameBound::CurrentRow => WindowFrameBound::CurrentRow,
+                ast::WindowFrameBound::Following(Some(expr)) => {
+                    WindowFrameBound::Following(offset(expr)?)
+                }
+                ast::WindowFrameBound::Following(None) => WindowFrameBound::UnboundedFollowing,
+            })
+        };
+        let start = bound(&frame.start_bound)?;
+        let end = match end {
+            Some(end) => bound(end)?,
+            None => WindowFrameBound::CurrentRow,
+        };
+
+        let has_offset = [&start, &end].into_iter().any(|bound| {
+            matches!(
+                bound,
+                WindowFrameBound::Preceding(_) | WindowFrameBound::Following(_)
+            )
+        });
+        if start == WindowFrameBound::UnboundedFollowing
+            || end == WindowFrameBound::UnboundedPreceding
+            || start.position() > end.position()
+            || (units == WindowFrameUnits::Range && has_offset && order_by_len != 1)
+            || (units == WindowFrameUnits::Groups && order_by_len == 0)
+        {
+            return Err(unsupported());
         }
+        Ok(WindowFrame { units, start, end })
     }
 
-    fn visit_binary_op(binary_op: &BinaryOperator) -> BinaryOp {
+    fn visit_binary_op(binary_op: &BinaryOperator) -> Result<BinaryOp, PlanError> {
         match binary_op {
-            BinaryOperator::Gt => BinaryOp::Gt,
-            BinaryOperator::Lt => BinaryOp::Lt,
-            BinaryOperator::And => BinaryOp::And,
-            BinaryOperator::Or => BinaryOp::Or,
-            _ => unimplemented!(),
+            BinaryOperator::Gt => Ok(BinaryOp::Gt),
+            BinaryOperator::Lt => Ok(BinaryOp::Lt),
+            BinaryOperator::GtEq => Ok(BinaryOp::GtEq),
+            BinaryOperator::LtEq => Ok(BinaryOp::LtEq),
+            BinaryOperator::Eq => Ok(BinaryOp::Eq),
+            BinaryOperator::NotEq => Ok(BinaryOp::NotEq),
+            BinaryOperator::And => Ok(BinaryOp::And),
+            BinaryOperator::Or => Ok(BinaryOp::Or),
+            BinaryOperator::Plus => Ok(BinaryOp::Plus),
+            BinaryOperator::Minus => Ok(BinaryOp::Minus),
+            BinaryOperator::Multiply => Ok(BinaryOp::Multiply),
+            BinaryOperator::Divide => Ok(BinaryOp::Divide),
+            _ => Err(PlanError::Unsupported(format!("Operator {binary_op}"))),
         }
     }
 }
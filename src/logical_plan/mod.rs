@@ -1,34 +1,176 @@
 pub use crate::dag::*;
 
 pub mod dag_builder;
+pub mod errors;
 pub mod expr;
+pub mod scope;
+pub mod udaf;
 
 use arrow::datatypes::SchemaRef;
-use expr::Expr;
+use expr::{Expr, SortExpr};
+use std::fmt;
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug, Clone)]
 pub struct TableScan {
     pub table_name: String,
     pub schema: SchemaRef,
 }
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug, Clone)]
 pub struct Projection {
     pub expr: Vec<Expr>,
     pub schema: SchemaRef,
 }
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug, Clone)]
 pub struct Filter {
     pub expr: Box<Expr>,
     pub schema: SchemaRef,
 }
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Aggregate {
+    pub group_expr: Vec<Expr>,
+    /// Positions in `group_expr` of the keys of each grouping set. A plain
+    /// GROUP BY has a single set of all keys. Keys which are not in the set of
+    /// a row are NULL.
+    pub grouping_sets: Vec<Vec<usize>>,
+    pub aggr_expr: Vec<Expr>,
+    pub schema: SchemaRef,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Sort {
+    pub expr: Vec<SortExpr>,
+    /// Number of leading rows which are actually needed, set when a `LIMIT`
+    /// follows the sort so that only the top rows have to be kept.
+    pub fetch: Option<usize>,
+    pub schema: SchemaRef,
+}
+
+/// Skips the first `skip` rows and returns at most `fetch` rows after them.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Limit {
+    pub skip: usize,
+    pub fetch: Option<usize>,
+    pub schema: SchemaRef,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum JoinType {
+    Inner,
+    Left,
+    Right,
+    Full,
+    /// Left rows with at least one match on the right.
+    Semi,
+    /// Left rows without any match on the right.
+    Anti,
+    Cross,
+}
+
+impl fmt::Display for JoinType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Inner => write!(f, "INNER"),
+            Self::Left => write!(f, "LEFT"),
+            Self::Right => write!(f, "RIGHT"),
+            Self::Full => write!(f, "FULL"),
+            Self::Semi => write!(f, "SEMI"),
+            Self::Anti => write!(f, "ANTI"),
+            Self::Cross => write!(f, "CROSS"),
+        }
+    }
+}
+
+/// Drops duplicate rows, comparing only the values of `on`, or all columns if
+/// `on` is empty. Of rows with the same values, the first one is kept.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Distinct {
+    pub on: Vec<Expr>,
+    pub schema: SchemaRef,
+}
+
+/// Columns of the input followed by the value of each of `window_expr`, which
+/// are window functions over the same partitions and order.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Window {
+    pub window_expr: Vec<Expr>,
+    pub schema: SchemaRef,
+}
+
+/// Turns every row of the input into a row per column of `columns`, holding
+/// the name of the column in `name` and its value in `value` next to the other
+/// columns of the input. Rows with a NULL value are skipped.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Unpivot {
+    pub columns: Vec<String>,
+    pub name: String,
+    pub value: String,
+    pub schema: SchemaRef,
+}
+
+/// Join of the first (left) and the second (right) input, whose column names
+/// are disjoint. Rows match if all pairs of `on` keys are equal, the first key
+/// evaluated on the left and the second on the right row, and `filter`
+/// evaluated on the columns of both rows holds.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Join {
+    pub kind: JoinType,
+    pub on: Vec<(Expr, Expr)>,
+    pub filter: Option<Box<Expr>>,
+    pub schema: SchemaRef,
+}
+
+/// Set operation over inputs with the same number of columns, which are matched
+/// by position and coerced to common types. The columns are named like those of
+/// the first input. With `distinct`, the result has no duplicate rows; without,
+/// rows are repeated as often as the operation's multiset semantics require.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct SetOperation {
+    pub distinct: bool,
+    pub schema: SchemaRef,
+}
+
+/// Rows added to the recursive CTE `name` by the last evaluation of its
+/// recursive term, which the next evaluation reads.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct WorkTable {
+    pub name: String,
+    pub schema: SchemaRef,
+}
+
+/// Rows of the recursive CTE `name`: those of the first input, the anchor, and
+/// of the second, the recursive term. The recursive term reads the rows added
+/// by its last evaluation from a `WorkTable` and is evaluated again until it
+/// adds none. With `distinct`, rows which were already returned aren't added.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct RecursiveUnion {
+    pub name: String,
+    pub distinct: bool,
+    pub schema: SchemaRef,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
 pub enum LogicalPlan {
     TableScan(TableScan),
     Projection(Projection),
     Filter(Filter),
+    Aggregate(Aggregate),
+    Sort(Sort),
+    Limit(Limit),
+    Distinct(Distinct),
+    Window(Window),
+    Unpivot(Unpivot),
+    Join(Join),
+    WorkTable(WorkTable),
+    RecursiveUnion(RecursiveUnion),
+    /// Rows of all inputs.
+    Union(SetOperation),
+    /// Rows of the first input which are also in the second.
+    Intersect(SetOperation),
+    /// Rows of the first input which are not in the second.
+    Except(SetOperation),
 }
 
 impl LogicalPlan {
@@ -37,6 +179,18 @@ impl LogicalPlan {
             Self::TableScan(scan) => scan.schema.clone(),
             Self::Projection(proj) => proj.schema.clone(),
             Self::Filter(filter) => filter.schema.clone(),
+            Self::Aggregate(aggregate) => aggregate.schema.clone(),
+            Self::Sort(sort) => sort.schema.clone(),
+            Self::Limit(limit) => limit.schema.clone(),
+            Self::Distinct(distinct) => distinct.schema.clone(),
+            Self::Window(window) => window.schema.clone(),
+            Self::Unpivot(unpivot) => unpivot.schema.clone(),
+            Self::Join(join) => join.schema.clone(),
+            Self::WorkTable(work_table) => work_table.schema.clone(),
+            Self::RecursiveUnion(union) => union.schema.clone(),
+            Self::Union(union) => union.schema.clone(),
+            Self::Intersect(intersect) => intersect.schema.clone(),
+            Self::Except(except) => except.schema.clone(),
         }
     }
 }
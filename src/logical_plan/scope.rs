@@ -0,0 +1,85 @@
+use crate::logical_plan::errors::PlanError;
+use arrow::datatypes::Schema;
+
+/// Column of a relation in the FROM clause.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeColumn {
+    /// Table name or alias the column can be qualified with.
+    pub qualifier: String,
+    pub name: String,
+    /// Name of the column in the schema of the plan.
+    pub field: String,
+    /// False if the column can only be referenced with its qualifier, like the
+    /// second column of a `USING` pair.
+    pub unqualified: bool,
+}
+
+/// Columns visible to the expressions of a query, in the order of the fields
+/// of the plan's schema. Used to resolve (qualified) identifiers to fields.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Scope {
+    columns: Vec<ScopeColumn>,
+}
+
+impl Scope {
+    pub fn for_table(qualifier: &str, schema: &Schema) -> Self {
+        let columns = schema
+            .fields()
+            .iter()
+            .map(|field| ScopeColumn {
+                qualifier: qualifier.to_string(),
+                name: field.name().clone(),
+                field: field.name().clone(),
+                unqualified: true,
+            })
+            .collect();
+        Self { columns }
+    }
+
+    pub const fn new(columns: Vec<ScopeColumn>) -> Self {
+        Self { columns }
+    }
+
+    pub fn columns(&self) -> &[ScopeColumn] {
+        &self.columns
+    }
+
+    pub fn columns_mut(&mut self) -> &mut [ScopeColumn] {
+        &mut self.columns
+    }
+
+    pub fn into_columns(self) -> Vec<ScopeColumn> {
+        self.columns
+    }
+
+    pub fn has_qualifier(&self, qualifier: &str) -> bool {
+        self.columns.iter().any(|c| c.qualifier == qualifier)
+    }
+
+    /// Finds the column referenced by `qualifier.name` or by a bare `name`.
+    /// Returns `None` if a bare name isn't a column, as it may still refer to
+    /// an output column of the select list.
+    pub fn resolve(
+        &self,
+        qualifier: Option<&str>,
+        name: &str,
+    ) -> Result<Option<&ScopeColumn>, PlanError> {
+        let mut matches = self.columns.iter().filter(|c| {
+            c.name == name
+                && match qualifier {
+                    Some(qualifier) => c.qualifier == qualifier,
+                    None => c.unqualified,
+                }
+        });
+        let column = matches.next();
+        if matches.next().is_some() {
+            return Err(PlanError::AmbiguousColumn(name.to_string()));
+        }
+        match (column, qualifier) {
+            (None, Some(qualifier)) => {
+                Err(PlanError::ColumnNotFound(format!("{qualifier}.{name}")))
+            }
+            _ => Ok(column),
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use crate::execution::accumulator::Accumulator;
+use crate::logical_plan::errors::PlanError;
+use arrow::datatypes::DataType;
+use std::fmt;
+use std::sync::Arc;
+
+pub type ReturnTypeFunction = Arc<dyn Fn(&[DataType]) -> Result<DataType, PlanError> + Send + Sync>;
+
+pub type AccumulatorFactory =
+    Arc<dyn Fn(&[DataType]) -> anyhow::Result<Box<dyn Accumulator>> + Send + Sync>;
+
+/// User-defined aggregate function which can be registered in the catalog and
+/// called from SQL like any built-in aggregate.
+pub struct AggregateUdf {
+    name: String,
+    return_type: ReturnTypeFunction,
+    accumulator: AccumulatorFactory,
+}
+
+impl AggregateUdf {
+    pub fn new(
+        name: &str,
+        return_type: ReturnTypeFunction,
+        accumulator: AccumulatorFactory,
+    ) -> Self {
+        Self {
+            name: name.to_lowercase(),
+            return_type,
+            accumulator,
+        }
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Type of the result for arguments of `arg_types`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the function doesn't accept arguments of these types.
+    pub fn return_type(&self, arg_types: &[DataType]) -> Result<DataType, PlanError> {
+        (self.return_type)(arg_types)
+    }
+
+    /// Creates the state of the function for a group of rows.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the function can't aggregate arguments of `arg_types`.
+    pub fn create_accumulator(
+        &self,
+        arg_types: &[DataType],
+    ) -> anyhow::Result<Box<dyn Accumulator>> {
+        (self.accumulator)(arg_types)
+    }
+}
+
+impl PartialEq for AggregateUdf {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for AggregateUdf {}
+
+impl fmt::Debug for AggregateUdf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AggregateUdf")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
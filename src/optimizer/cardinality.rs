@@ -0,0 +1,354 @@
+use crate::catalog::Catalog;
+use crate::dag::Dag;
+use crate::logical_plan::expr::{Binary, BinaryOp, Expr};
+use crate::logical_plan::{Aggregate, Distinct, Join, JoinType, LogicalPlan, NodeId, Unpivot};
+use arrow::datatypes::Schema;
+use std::collections::HashMap;
+
+/// Row count of tables without statistics.
+const DEFAULT_ROWS: f64 = 1000.0;
+/// Selectivity of range comparisons like `a < b`.
+const RANGE_SELECTIVITY: f64 = 1.0 / 3.0;
+/// Selectivity of predicates nothing is known about.
+const DEFAULT_SELECTIVITY: f64 = 0.5;
+
+/// Estimated number of rows of a plan and of distinct values of its columns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Estimate {
+    pub rows: f64,
+    pub distinct: HashMap<String, f64>,
+}
+
+impl Estimate {
+    /// Estimated number of distinct values of `column`.
+    pub fn distinct(&self, column: &str) -> f64 {
+        self.distinct
+            .get(column)
+            .copied()
+            .unwrap_or(self.rows)
+            .clamp(1.0, self.rows.max(1.0))
+    }
+
+    /// Estimated fraction of rows for which `predicate` holds.
+    pub fn selectivity(&self, predicate: &Expr) -> f64 {
+        let Expr::Binary(binary) = predicate else {
+            return DEFAULT_SELECTIVITY;
+        };
+        let (lhs, rhs) = (binary.lhs.as_ref(), binary.rhs.as_ref());
+        match binary.op {
+            BinaryOp::And => self.selectivity(lhs) * self.selectivity(rhs),
+            BinaryOp::Or => {
+                let (lhs, rhs) = (self.selectivity(lhs), self.selectivity(rhs));
+                lhs.mul_add(-rhs, lhs + rhs)
+            }
+            BinaryOp::Eq => self.equality_selectivity(lhs, rhs),
+            BinaryOp::NotEq => 1.0 - self.equality_selectivity(lhs, rhs),
+            BinaryOp::Lt | BinaryOp::LtEq | BinaryOp::Gt | BinaryOp::GtEq => RANGE_SELECTIVITY,
+            _ => DEFAULT_SELECTIVITY,
+        }
+    }
+
+    /// Each value of a column matches `1 / distinct` of the rows, and values
+    /// of two columns are assumed to come from the smaller domain.
+    fn equality_selectivity(&self, lhs: &Expr, rhs: &Expr) -> f64 {
+        match (lhs, rhs) {
+            (Expr::Ident(lhs), Expr::Ident(rhs)) => {
+                1.0 / self.distinct(&lhs.name).max(self.distinct(&rhs.name))
+            }
+            (Expr::Ident(column), _) | (_, Expr::Ident(column)) => {
+                1.0 / self.distinct(&column.name)
+            }
+            _ => DEFAULT_SELECTIVITY,
+        }
+    }
+
+    /// Rows remaining after filtering by `predicate`.
+    #[must_use]
+    pub fn filter(mut self, predicate: &Expr) -> Self {
+        self.rows *= self.selectivity(predicate);
+        self.cap_distinct();
+        self
+    }
+
+    /// Cartesian product of the rows of both estimates.
+    #[must_use]
+    pub fn cross(mut self, other: Self) -> Self {
+        self.rows *= other.rows;
+        self.distinct.extend(other.distinct);
+        self
+    }
+
+    fn cap_distinct(&mut self) {
+        let rows = self.rows.max(1.0);
+        for distinct in self.distinct.values_mut() {
+            *distinct = distinct.min(rows);
+        }
+    }
+}
+
+/// Estimates the size of the result of `node` from the catalog's statistics.
+// Row counts beyond 2^52, which lose precision as `f64`, are estimates anyway.
+#[allow(clippy::cast_precision_loss)]
+pub fn estimate(dag: &Dag<LogicalPlan>, node: NodeId, catalog: &dyn Catalog) -> Estimate {
+    let input = |i: usize| estimate(dag, dag.get_inputs(node)[i], catalog);
+    match dag.get_node(node) {
+        LogicalPlan::TableScan(scan) => {
+            let statistics = catalog.get_statistics(&scan.table_name);
+            let rows = statistics
+                .as_ref()
+                .map_or(DEFAULT_ROWS, |statistics| statistics.row_count as f64);
+            let distinct = scan
+                .schema
+                .fields()
+                .iter()
+                .map(|field| {
+                    let distinct = statistics
+                        .as_ref()
+                        .and_then(|statistics| statistics.columns.get(field.name()))
+                        .and_then(|column| column.distinct_count)
+                        .map_or(rows, |distinct| distinct as f64);
+                    (field.name().clone(), distinct)
+                })
+                .collect();
+            Estimate { rows, distinct }
+        }
+        LogicalPlan::Filter(filter) => input(0).filter(&filter.expr),
+        LogicalPlan::Projection(projection) => {
+            let input = input(0);
+            let distinct = projection
+                .expr
+                .iter()
+                .filter_map(|expr| match expr {
+                    Expr::Ident(ident) => Some((ident.name.clone(), input.distinct(&ident.name))),
+                    Expr::Alias(alias) => match alias.expr.as_ref() {
+                        Expr::Ident(ident) => {
+                            Some((alias.name.clone(), input.distinct(&ident.name)))
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect();
+            Estimate {
+                rows: input.rows,
+                distinct,
+            }
+        }
+        LogicalPlan::Aggregate(aggregate) => estimate_aggregate(aggregate, &input(0)),
+        LogicalPlan::Distinct(distinct) => estimate_distinct(distinct, input(0)),
+        LogicalPlan::Window(_) => input(0),
+        LogicalPlan::Unpivot(unpivot) => estimate_unpivot(unpivot, input(0)),
+        LogicalPlan::Sort(sort) => {
+            let mut input = input(0);
+            if let Some(fetch) = sort.fetch {
+                input.rows = input.rows.min(fetch as f64);
+                input.cap_distinct();
+            }
+            input
+        }
+        LogicalPlan::Limit(limit) => {
+            let mut input = input(0);
+            input.rows = (input.rows - limit.skip as f64).max(0.0);
+            if let Some(fetch) = limit.fetch {
+                input.rows = input.rows.min(fetch as f64);
+            }
+            input.cap_distinct();
+            input
+        }
+        LogicalPlan::Join(join) => estimate_join(join, input(0), input(1)),
+        LogicalPlan::WorkTable(work_table) => Estimate {
+            rows: DEFAULT_ROWS,
+            distinct: work_table
+                .schema
+                .fields()
+                .iter()
+                .map(|field| (field.name().clone(), DEFAULT_ROWS))
+                .collect(),
+        },
+        LogicalPlan::Union(_) | LogicalPlan::Intersect(_) | LogicalPlan::Except(_) => {
+            estimate_set_operation(dag, node, catalog)
+        }
+        // The number of iterations isn't known, assume a single one.
+        LogicalPlan::RecursiveUnion(_) => {
+            let mut anchor = input(0);
+            anchor.rows += input(1).rows;
+            anchor
+        }
+    }
+}
+
+/// One row per distinct combination of the keys, or of all columns.
+fn estimate_distinct(distinct: &Distinct, mut input: Estimate) -> Estimate {
+    input.rows = if distinct.on.is_empty() {
+        let columns = distinct.schema.fields().iter();
+        columns
+            .map(|field| input.distinct(field.name()))
+            .product::<f64>()
+            .min(input.rows.max(1.0))
+    } else {
+        groups(&input, &distinct.on)
+    };
+    input.cap_distinct();
+    input
+}
+
+/// Number of distinct combinations of the values of `keys`, at most one per row.
+/// Estimates an aggregate, which returns the groups of each grouping set.
+fn estimate_aggregate(aggregate: &Aggregate, input: &Estimate) -> Estimate {
+    let rows = aggregate
+        .grouping_sets
+        .iter()
+        .map(|set| {
+            let keys = set.iter().map(|&i| aggregate.group_expr[i].clone());
+            groups(input, &keys.collect::<Vec<_>>())
+        })
+        .sum();
+    let mut estimate = Estimate {
+        rows,
+        distinct: aggregate
+            .group_expr
+            .iter()
+            .map(|expr| (expr.name(), key_distinct(input, expr)))
+            .collect(),
+    };
+    estimate.cap_distinct();
+    estimate
+}
+
+/// Estimates an unpivot as a row per unpivoted column of each input row.
+fn estimate_unpivot(unpivot: &Unpivot, mut input: Estimate) -> Estimate {
+    let columns = u32::try_from(unpivot.columns.len()).map_or(f64::MAX, f64::from);
+    input.rows *= columns;
+    for column in &unpivot.columns {
+        input.distinct.remove(column);
+    }
+    input.distinct.insert(unpivot.name.clone(), columns);
+    input.cap_distinct();
+    input
+}
+
+fn groups(input: &Estimate, keys: &[Expr]) -> f64 {
+    let groups = keys
+        .iter()
+        .map(|key| key_distinct(input, key))
+        .product::<f64>();
+    groups.min(input.rows.max(1.0))
+}
+
+fn key_distinct(input: &Estimate, key: &Expr) -> f64 {
+    match key {
+        Expr::Ident(ident) => input.distinct(&ident.name),
+        _ => input.rows,
+    }
+}
+
+/// Estimates a union of all inputs, or an intersect or except, which returns
+/// rows of the first input only.
+fn estimate_set_operation(dag: &Dag<LogicalPlan>, node: NodeId, catalog: &dyn Catalog) -> Estimate {
+    let inputs = dag
+        .get_inputs(node)
+        .iter()
+        .map(|&input| estimate(dag, input, catalog))
+        .collect::<Vec<_>>();
+    let mut estimate = match dag.get_node(node) {
+        LogicalPlan::Union(union) => {
+            let rows = inputs.iter().map(|input| input.rows).sum::<f64>();
+            let distinct = set_operation_distinct(dag, node, &union.schema, &inputs);
+            Estimate {
+                rows: if union.distinct {
+                    distinct.values().product::<f64>().min(rows)
+                } else {
+                    rows
+                },
+                distinct,
+            }
+        }
+        LogicalPlan::Intersect(set_operation) => Estimate {
+            rows: inputs[0].rows.min(inputs[1].rows),
+            distinct: set_operation_distinct(dag, node, &set_operation.schema, &inputs[..1]),
+        },
+        LogicalPlan::Except(set_operation) => Estimate {
+            rows: inputs[0].rows,
+            distinct: set_operation_distinct(dag, node, &set_operation.schema, &inputs[..1]),
+        },
+        _ => panic!("node {node} is not a set operation"),
+    };
+    estimate.cap_distinct();
+    estimate
+}
+
+/// Distinct values of the columns of a set operation, which has the columns
+/// of its `inputs` at the same positions.
+fn set_operation_distinct(
+    dag: &Dag<LogicalPlan>,
+    node: NodeId,
+    schema: &Schema,
+    inputs: &[Estimate],
+) -> HashMap<String, f64> {
+    schema
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let distinct = inputs
+                .iter()
+                .zip(dag.get_inputs(node))
+                .map(|(input, &id)| {
+                    let schema = dag.get_node(id).get_schema();
+                    input.distinct(schema.field(i).name())
+                })
+                .sum();
+            (field.name().clone(), distinct)
+        })
+        .collect()
+}
+
+fn estimate_join(join: &Join, left: Estimate, right: Estimate) -> Estimate {
+    let (left_rows, right_rows) = (left.rows, right.rows);
+    let left_distinct = left.distinct.clone();
+    let mut matches = left.cross(right);
+    for (left_key, right_key) in &join.on {
+        matches = matches.filter(&equals(left_key.clone(), right_key.clone()));
+    }
+    if let Some(filter) = &join.filter {
+        matches = matches.filter(filter);
+    }
+    match join.kind {
+        JoinType::Inner | JoinType::Cross => matches,
+        JoinType::Left => Estimate {
+            rows: matches.rows.max(left_rows),
+            ..matches
+        },
+        JoinType::Right => Estimate {
+            rows: matches.rows.max(right_rows),
+            ..matches
+        },
+        JoinType::Full => Estimate {
+            rows: matches.rows.max(left_rows).max(right_rows),
+            ..matches
+        },
+        JoinType::Semi | JoinType::Anti => {
+            let semi = matches.rows.min(left_rows);
+            let rows = if join.kind == JoinType::Semi {
+                semi
+            } else {
+                left_rows - semi
+            };
+            let mut estimate = Estimate {
+                rows,
+                distinct: left_distinct,
+            };
+            estimate.cap_distinct();
+            estimate
+        }
+    }
+}
+
+/// The predicate `lhs = rhs`.
+pub fn equals(lhs: Expr, rhs: Expr) -> Expr {
+    Expr::Binary(Binary {
+        lhs: Box::new(lhs),
+        op: BinaryOp::Eq,
+        rhs: Box::new(rhs),
+    })
+}
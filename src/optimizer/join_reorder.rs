@@ -0,0 +1,540 @@
+use crate::catalog::Catalog;
+use crate::dag::Dag;
+use crate::logical_plan::dag_builder::DagBuilder;
+use crate::logical_plan::errors::PlanError;
+use crate::logical_plan::expr::{BinaryOp, Expr, Ident};
+use crate::logical_plan::{JoinType, LogicalPlan, NodeId, Projection};
+use crate::optimizer::cardinality::{equals, estimate, Estimate};
+use arrow::datatypes::SchemaRef;
+use std::collections::{HashMap, HashSet};
+
+/// Joins of up to this many inputs are ordered by dynamic programming over all
+/// subsets of inputs, larger ones greedily.
+const DP_MAX_INPUTS: usize = 10;
+/// Inputs are tracked as bits of a `u64`.
+const MAX_INPUTS: usize = 64;
+
+/// Tree of inner and cross joins whose inputs can be joined in any order.
+struct JoinRegion {
+    /// Node whose result the reordered joins have to reproduce, the topmost
+    /// join or a filter on top of it.
+    root: NodeId,
+    joins: Vec<NodeId>,
+    inputs: Vec<NodeId>,
+    predicates: Vec<Expr>,
+}
+
+/// Predicate of a region and the inputs whose columns it references.
+struct Predicate {
+    expr: Expr,
+    inputs: u64,
+    selectivity: f64,
+}
+
+/// Reorders trees of inner joins to minimize the estimated sizes of their
+/// intermediate results, based on the statistics of `catalog`, and puts the
+/// smaller input on the right side of each join, which hash joins build their
+/// table on. Outer joins keep their order but swap inputs the same way.
+///
+/// Reordered joins reuse the node ids of the original ones, and a projection
+/// restores the original column order, so the rest of the plan is unaffected.
+pub fn reorder_joins(dag: &mut Dag<LogicalPlan>, catalog: &dyn Catalog) -> Result<(), PlanError> {
+    let roots = (0..dag.len())
+        .filter(|&node| is_inner_join(dag, node) && !is_nested(dag, node))
+        .collect::<Vec<_>>();
+    for root in roots {
+        let region = collect_region(dag, root);
+        if region.inputs.len() <= MAX_INPUTS {
+            reorder_region(dag, region, catalog)?;
+        }
+    }
+
+    for node in 0..dag.len() {
+        if let LogicalPlan::Join(join) = dag.get_node(node) {
+            if matches!(join.kind, JoinType::Left | JoinType::Right | JoinType::Full) {
+                let inputs = dag.get_inputs(node);
+                let left = estimate(dag, inputs[0], catalog).rows;
+                let right = estimate(dag, inputs[1], catalog).rows;
+                if left < right {
+                    swap_inputs(dag, node)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn is_inner_join(dag: &Dag<LogicalPlan>, node: NodeId) -> bool {
+    matches!(
+        dag.get_node(node),
+        LogicalPlan::Join(join) if matches!(join.kind, JoinType::Inner | JoinType::Cross)
+    )
+}
+
+/// Whether `node` is an inner join which is only used by another inner join,
+/// and can therefore be reordered together with it.
+fn is_nested(dag: &Dag<LogicalPlan>, node: NodeId) -> bool {
+    let usages = dag.get_usages(node);
+    is_inner_join(dag, node)
+        && usages.len() == 1
+        && usages.iter().all(|&usage| is_inner_join(dag, usage))
+}
+
+fn collect_region(dag: &Dag<LogicalPlan>, root: NodeId) -> JoinRegion {
+    let mut region = JoinRegion {
+        root,
+        joins: Vec::new(),
+        inputs: Vec::new(),
+        predicates: Vec::new(),
+    };
+    collect_joins(dag, root, &mut region);
+
+    // The conditions of a filter on top of the joins, like those of a WHERE
+    // clause over a comma-separated FROM list, can be evaluated by the joins.
+    let usages = dag.get_usages(root);
+    if let [usage] = usages.iter().copied().collect::<Vec<_>>()[..] {
+        if let LogicalPlan::Filter(filter) = dag.get_node(usage) {
+            region.root = usage;
+            region
+                .predicates
+                .extend(filter.expr.as_ref().clone().into_conjuncts());
+        }
+    }
+    region
+}
+
+fn collect_joins(dag: &Dag<LogicalPlan>, node: NodeId, region: &mut JoinRegion) {
+    let LogicalPlan::Join(join) = dag.get_node(node) else {
+        unreachable!("only joins are collected");
+    };
+    region.joins.push(node);
+    for (left, right) in &join.on {
+        region.predicates.push(equals(left.clone(), right.clone()));
+    }
+    if let Some(filter) = &join.filter {
+        region
+            .predicates
+            .extend(filter.as_ref().clone().into_conjuncts());
+    }
+    for &input in dag.get_inputs(node) {
+        if is_nested(dag, input) {
+            collect_joins(dag, input, region);
+        } else {
+            region.inputs.push(input);
+        }
+    }
+}
+
+/// Bit set of the inputs whose columns `expr` references.
+fn referenced_inputs(expr: &Expr, schemas: &[SchemaRef]) -> u64 {
+    let mut columns = HashSet::new();
+    expr.collect_columns(&mut columns);
+    columns.into_iter().fold(0, |inputs, column| {
+        inputs
+            | schemas
+                .iter()
+                .enumerate()
+                .filter(|(_, schema)| schema.field_with_name(column).is_ok())
+                .fold(0, |inputs, (i, _)| inputs | 1 << i)
+    })
+}
+
+const fn is_subset(subset: u64, set: u64) -> bool {
+    subset & !set == 0
+}
+
+/// Estimated row counts of joins of subsets of the inputs of a region.
+struct JoinGraph {
+    inputs: Vec<Estimate>,
+    predicates: Vec<Predicate>,
+}
+
+impl JoinGraph {
+    /// Estimated rows of the join of the `inputs`, independent of the order.
+    fn rows(&self, inputs: u64) -> f64 {
+        let rows = (0..self.inputs.len())
+            .filter(|i| inputs & 1 << i != 0)
+            .map(|i| self.inputs[i].rows)
+            .product::<f64>();
+        self.predicates
+            .iter()
+            .filter(|p| p.inputs != 0 && is_subset(p.inputs, inputs))
+            .fold(rows, |rows, p| rows * p.selectivity)
+    }
+
+    /// Whether a predicate connects the `left` and the `right` inputs.
+    fn connected(&self, left: u64, right: u64) -> bool {
+        self.predicates.iter().any(|p| {
+            p.inputs & left != 0 && p.inputs & right != 0 && is_subset(p.inputs, left | right)
+        })
+    }
+
+    /// Finds the join order with the smallest sum of intermediate result
+    /// sizes. Cross products are only considered if the inputs can't be
+    /// joined by predicates alone. Returns how each joined subset is split
+    /// into its two inputs.
+    fn dynamic_programming(&self) -> HashMap<u64, (u64, u64)> {
+        let all = (1u64 << self.inputs.len()) - 1;
+        let mut best = self.best_splits(false);
+        if !best.contains_key(&all) {
+            best = self.best_splits(true);
+        }
+
+        let mut splits = HashMap::new();
+        let mut pending = vec![all];
+        while let Some(set) = pending.pop() {
+            if set.count_ones() > 1 {
+                let (_, left) = best[&set];
+                splits.insert(set, (left, set ^ left));
+                pending.extend([left, set ^ left]);
+            }
+        }
+        splits
+    }
+
+    /// Cost and left input of the cheapest plan for each subset of inputs.
+    fn best_splits(&self, cross_products: bool) -> HashMap<u64, (f64, u64)> {
+        let all = (1u64 << self.inputs.len()) - 1;
+        let mut best = HashMap::new();
+        for i in 0..self.inputs.len() {
+            best.insert(1 << i, (0.0, 0));
+        }
+        for set in (1..=all).filter(|set| set.count_ones() > 1) {
+            let rows = self.rows(set);
+            let mut left = (set - 1) & set;
+            while left > 0 {
+                let right = set ^ left;
+                // Each split is considered once, the sides are chosen when building.
+                if left > right && (cross_products || self.connected(left, right)) {
+                    if let (Some((left_cost, _)), Some((right_cost, _))) =
+                        (best.get(&left), best.get(&right))
+                    {
+                        let cost = left_cost + right_cost + rows;
+                        if best.get(&set).is_none_or(|(best, _)| cost < *best) {
+                            best.insert(set, (cost, left));
+                        }
+                    }
+                }
+                left = (left - 1) & set;
+            }
+        }
+        best
+    }
+
+    /// Repeatedly joins the two connected subsets with the smallest result.
+    fn greedy(&self) -> HashMap<u64, (u64, u64)> {
+        let mut sets = (0..self.inputs.len())
+            .map(|i| 1u64 << i)
+            .collect::<Vec<_>>();
+        let mut splits = HashMap::new();
+        while sets.len() > 1 {
+            let mut best = None;
+            for i in 0..sets.len() {
+                for j in i + 1..sets.len() {
+                    let key = (
+                        !self.connected(sets[i], sets[j]),
+                        self.rows(sets[i] | sets[j]),
+                    );
+                    if best.as_ref().is_none_or(|(best, _, _)| {
+                        key.partial_cmp(best).is_some_and(std::cmp::Ordering::is_lt)
+                    }) {
+                        best = Some((key, i, j));
+                    }
+                }
+            }
+            let (_, i, j) = best.expect("at least two subsets");
+            let (left, right) = (sets[i], sets.remove(j));
+            sets[i] = left | right;
+            splits.insert(left | right, (left, right));
+        }
+        splits
+    }
+}
+
+/// Builds the joins of a region in a new order.
+struct RegionBuilder<'a> {
+    dag: &'a mut Dag<LogicalPlan>,
+    graph: JoinGraph,
+    splits: HashMap<u64, (u64, u64)>,
+    inputs: Vec<NodeId>,
+    /// Node ids of the original joins, which are reused.
+    free: Vec<NodeId>,
+}
+
+impl RegionBuilder<'_> {
+    fn build(&mut self, set: u64, all: u64) -> Result<NodeId, PlanError> {
+        if set.is_power_of_two() {
+            return Ok(self.inputs[set.trailing_zeros() as usize]);
+        }
+        let (mut left, mut right) = self.splits[&set];
+        if self.graph.rows(left) < self.graph.rows(right) {
+            (left, right) = (right, left);
+        }
+        let left_node = self.build(left, all)?;
+        let right_node = self.build(right, all)?;
+        let left_schema = self.dag.get_node(left_node).get_schema();
+        let right_schema = self.dag.get_node(right_node).get_schema();
+
+        let mut on = Vec::new();
+        let mut filter = Vec::new();
+        for predicate in &self.graph.predicates {
+            let applies = if predicate.inputs == 0 {
+                set == all
+            } else {
+                is_subset(predicate.inputs, set)
+                    && !is_subset(predicate.inputs, left)
+                    && !is_subset(predicate.inputs, right)
+            };
+            if !applies {
+                continue;
+            }
+            match &predicate.expr {
+                Expr::Binary(binary) if binary.op == BinaryOp::Eq => {
+                    let (lhs, rhs) = (binary.lhs.as_ref(), binary.rhs.as_ref());
+                    let side = |expr: &Expr| {
+                        let inputs =
+                            referenced_inputs(expr, &[left_schema.clone(), right_schema.clone()]);
+                        (inputs != 0 && is_subset(inputs, 1), inputs == 2)
+                    };
+                    match (side(lhs), side(rhs)) {
+                        ((true, _), (_, true)) => on.push((lhs.clone(), rhs.clone())),
+                        ((_, true), (true, _)) => on.push((rhs.clone(), lhs.clone())),
+                        _ => filter.push(predicate.expr.clone()),
+                    }
+                }
+                expr => filter.push(expr.clone()),
+            }
+        }
+
+        let kind = if on.is_empty() && filter.is_empty() {
+            JoinType::Cross
+        } else {
+            JoinType::Inner
+        };
+        let filter = Expr::conjunction(filter).map(Box::new);
+        let join = DagBuilder::build_join(kind, on, filter, &left_schema, &right_schema)?;
+        let node = match self.free.pop() {
+            Some(node) => {
+                *self.dag.get_node_mut(node) = LogicalPlan::Join(join);
+                node
+            }
+            None => self.dag.new_node(LogicalPlan::Join(join)),
+        };
+        self.dag.replace_inputs(node, vec![left_node, right_node]);
+        Ok(node)
+    }
+}
+
+fn reorder_region(
+    dag: &mut Dag<LogicalPlan>,
+    region: JoinRegion,
+    catalog: &dyn Catalog,
+) -> Result<(), PlanError> {
+    let JoinRegion {
+        root,
+        joins,
+        mut inputs,
+        predicates,
+    } = region;
+    let schema = dag.get_node(root).get_schema();
+    let schemas = inputs
+        .iter()
+        .map(|&input| dag.get_node(input).get_schema())
+        .collect::<Vec<_>>();
+
+    // Conditions on a single input are evaluated by a filter below the joins.
+    let mut input_filters = vec![Vec::new(); inputs.len()];
+    let mut join_predicates = Vec::new();
+    for expr in predicates {
+        let referenced = referenced_inputs(&expr, &schemas);
+        if referenced.is_power_of_two() {
+            input_filters[referenced.trailing_zeros() as usize].push(expr);
+        } else {
+            join_predicates.push((expr, referenced));
+        }
+    }
+    let mut builder = DagBuilder::new(dag);
+    for (input, filters) in inputs.iter_mut().zip(input_filters) {
+        if let Some(filter) = Expr::conjunction(filters) {
+            *input = builder.create_filter(Box::new(filter), *input);
+        }
+    }
+
+    let estimates = inputs
+        .iter()
+        .map(|&input| estimate(dag, input, catalog))
+        .collect::<Vec<_>>();
+    let all_inputs = estimates
+        .iter()
+        .cloned()
+        .reduce(Estimate::cross)
+        .expect("joins have inputs");
+    let predicates = join_predicates
+        .into_iter()
+        .map(|(expr, inputs)| Predicate {
+            selectivity: all_inputs.selectivity(&expr),
+            expr,
+            inputs,
+        })
+        .collect();
+    let graph = JoinGraph {
+        inputs: estimates,
+        predicates,
+    };
+    let splits = if inputs.len() <= DP_MAX_INPUTS {
+        graph.dynamic_programming()
+    } else {
+        graph.greedy()
+    };
+
+    // The root becomes a projection on top of the joins, the other nodes are reused.
+    let free = joins.into_iter().filter(|&join| join != root).collect();
+    let all = u64::MAX >> (64 - inputs.len());
+    let mut builder = RegionBuilder {
+        dag,
+        graph,
+        splits,
+        inputs,
+        free,
+    };
+    let top = builder.build(all, all)?;
+    debug_assert!(builder.free.is_empty());
+
+    *dag.get_node_mut(root) = identity_projection(schema);
+    dag.replace_inputs(root, vec![top]);
+    Ok(())
+}
+
+/// Swaps the inputs of the outer join `node`, which keeps its id as projection
+/// restoring the original column order.
+fn swap_inputs(dag: &mut Dag<LogicalPlan>, node: NodeId) -> Result<(), PlanError> {
+    let LogicalPlan::Join(join) = dag.get_node(node) else {
+        unreachable!("only joins are swapped");
+    };
+    let kind = match join.kind {
+        JoinType::Left => JoinType::Right,
+        JoinType::Right => JoinType::Left,
+        kind => kind,
+    };
+    let on = join
+        .on
+        .iter()
+        .map(|(left, right)| (right.clone(), left.clone()))
+        .collect();
+    let filter = join.filter.clone();
+    let schema = join.schema.clone();
+    let inputs = dag.get_inputs(node).clone();
+
+    let swapped = DagBuilder::new(dag).create_join(kind, on, filter, inputs[1], inputs[0])?;
+    *dag.get_node_mut(node) = identity_projection(schema);
+    dag.replace_inputs(node, vec![swapped]);
+    Ok(())
+}
+
+/// Projection to the columns of `schema` in their order.
+fn identity_projection(schema: SchemaRef) -> LogicalPlan {
+    let expr = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            Expr::Ident(Ident {
+                name: field.name().clone(),
+            })
+        })
+        .collect();
+    LogicalPlan::Projection(Projection { expr, schema })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::{ColumnStatistics, DummyCatalog, TableStatistics};
+    use crate::parser::sql_parser::parse_sql_query;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use std::sync::Arc;
+
+    /// Join tree below `node` with the table names as leaves.
+    fn join_tree(dag: &Dag<LogicalPlan>, node: NodeId) -> String {
+        let inputs = dag.get_inputs(node);
+        match dag.get_node(node) {
+            LogicalPlan::TableScan(scan) => scan.table_name.clone(),
+            LogicalPlan::Join(join) => format!(
+                "({} {} {})",
+                join_tree(dag, inputs[0]),
+                join.kind,
+                join_tree(dag, inputs[1])
+            ),
+            _ => join_tree(dag, inputs[0]),
+        }
+    }
+
+    #[test]
+    fn test_reorder_joins() -> Result<(), PlanError> {
+        let mut catalog = DummyCatalog::new();
+        let tables = [
+            ("countries", 50, vec![("cid", 50)]),
+            ("users", 10_000, vec![("id", 10_000), ("country_id", 50)]),
+            (
+                "orders",
+                1_000_000,
+                vec![("user_id", 10_000), ("product_id", 100)],
+            ),
+            ("products", 100, vec![("pid", 100), ("price", 100)]),
+        ];
+        for (table, row_count, columns) in tables {
+            let fields = columns
+                .iter()
+                .map(|(name, _)| Field::new(*name, DataType::Int64, false))
+                .collect::<Vec<_>>();
+            catalog.add_table(table, Arc::new(Schema::new(fields)));
+            let columns = columns
+                .into_iter()
+                .map(|(name, distinct)| {
+                    let statistics = ColumnStatistics {
+                        distinct_count: Some(distinct),
+                        ..ColumnStatistics::default()
+                    };
+                    (name.to_string(), statistics)
+                })
+                .collect();
+            let statistics = TableStatistics {
+                row_count,
+                columns,
+                ..TableStatistics::default()
+            };
+            catalog.set_statistics(table, statistics);
+        }
+
+        let mut dag = parse_sql_query(
+            "SELECT * FROM countries, users, orders, products \
+             WHERE cid = country_id AND id = user_id AND pid = product_id AND price > 10",
+            &catalog,
+        )?;
+        let root = (0..dag.len())
+            .find(|&node| dag.get_usages(node).is_empty())
+            .unwrap();
+        let schema = dag.get_node(root).get_schema();
+        reorder_joins(&mut dag, &catalog)?;
+
+        // The large orders are joined with the filtered products first, and
+        // the smaller input of each join is built.
+        assert_eq!(
+            join_tree(&dag, root),
+            "((orders INNER products) INNER (users INNER countries))"
+        );
+        assert_eq!(dag.get_node(root).get_schema(), schema);
+        let unused = (0..dag.len())
+            .filter(|&node| dag.get_usages(node).is_empty())
+            .collect::<Vec<_>>();
+        assert_eq!(unused, vec![root]);
+
+        let mut dag = parse_sql_query(
+            "SELECT * FROM countries LEFT JOIN users ON cid = country_id",
+            &catalog,
+        )?;
+        reorder_joins(&mut dag, &catalog)?;
+        assert_eq!(join_tree(&dag, 3), "(users RIGHT countries)");
+
+        Ok(())
+    }
+}
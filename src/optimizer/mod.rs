@@ -0,0 +1,2 @@
+pub mod cardinality;
+pub mod join_reorder;
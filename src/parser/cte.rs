@@ -0,0 +1,201 @@
+use crate::catalog::DummyCatalog;
+use crate::dag::NodeId;
+use crate::logical_plan::dag_builder::DagBuilder;
+use crate::logical_plan::errors::PlanError;
+use crate::logical_plan::expr::{Alias, Expr, Ident};
+use crate::parser::sql_parser::{parse_query, parse_set_expr, parse_set_quantifier};
+use arrow::datatypes::SchemaRef;
+use sqlparser::ast;
+use std::cell::Cell;
+
+/// Common table expression of a `WITH` clause.
+pub struct Cte<'a> {
+    definition: &'a ast::Cte,
+    /// Plan of the query, shared by all references to the CTE.
+    node: Cell<Option<NodeId>>,
+    /// Schema of the work table while the recursive term of the CTE is planned.
+    work_table: Cell<Option<SchemaRef>>,
+}
+
+/// Common table expressions visible to a query: those defined by the `WITH`
+/// clauses of the query and the queries enclosing it. A CTE is planned on its
+/// first reference, so unused ones don't add nodes to the plan.
+#[derive(Default)]
+pub struct Ctes<'a> {
+    parent: Option<&'a Self>,
+    tables: &'a [Cte<'a>],
+    /// Whether the CTEs were defined by `WITH RECURSIVE`.
+    recursive: bool,
+}
+
+impl<'a> Cte<'a> {
+    pub const fn new(definition: &'a ast::Cte) -> Self {
+        Self {
+            definition,
+            node: Cell::new(None),
+            work_table: Cell::new(None),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.definition.alias.name.value
+    }
+}
+
+impl<'a> Ctes<'a> {
+    /// CTEs defined by a `WITH` clause, which hide those of `parent` with the
+    /// same name.
+    pub fn new(
+        parent: &'a Self,
+        tables: &'a [Cte<'a>],
+        recursive: bool,
+    ) -> Result<Self, PlanError> {
+        for (i, table) in tables.iter().enumerate() {
+            if tables[..i].iter().any(|other| other.name() == table.name()) {
+                return Err(PlanError::DuplicateTable(table.name().to_string()));
+            }
+        }
+        Ok(Self {
+            parent: Some(parent),
+            tables,
+            recursive,
+        })
+    }
+
+    /// Plan of the CTE `name`, if there is one.
+    pub fn resolve(
+        &self,
+        name: &str,
+        dag_builder: &mut DagBuilder,
+        catalog: &DummyCatalog,
+    ) -> Result<Option<NodeId>, PlanError> {
+        let Some(i) = self.tables.iter().position(|table| table.name() == name) else {
+            return match self.parent {
+                Some(parent) => parent.resolve(name, dag_builder, catalog),
+                None => Ok(None),
+            };
+        };
+        let table = &self.tables[i];
+        if let Some(node) = table.node.get() {
+            return Ok(Some(node));
+        }
+        if let Some(schema) = table.work_table.take() {
+            // Reference of the recursive term to the CTE itself.
+            let node = dag_builder.create_work_table(name.to_string(), schema);
+            table.node.set(Some(node));
+            return Ok(Some(node));
+        }
+
+        // A CTE sees the CTEs defined before it.
+        let visible = Self {
+            parent: self.parent,
+            tables: &self.tables[..i],
+            recursive: self.recursive,
+        };
+        let query = &table.definition.query;
+        let node = match query.body.as_ref() {
+            ast::SetExpr::SetOperation {
+                op: ast::SetOperator::Union,
+                set_quantifier,
+                left,
+                right,
+            } if self.recursive && is_plain(query) => {
+                let distinct = parse_set_quantifier(*set_quantifier)?;
+                visible.resolve_recursive(table, distinct, left, right, dag_builder, catalog)?
+            }
+            _ => {
+                let planned = parse_query(query, None, &visible, dag_builder, catalog)?;
+                rename_columns(planned.node, &table.definition.alias, dag_builder)?
+            }
+        };
+        table.node.set(Some(node));
+        Ok(Some(node))
+    }
+
+    /// Plans the recursive CTE `table`, defined as `anchor UNION recursive`,
+    /// where `recursive` reads the rows added last by referencing `table`.
+    /// Without such a reference, it's planned as a plain union.
+    fn resolve_recursive(
+        &self,
+        table: &Cte,
+        distinct: bool,
+        anchor: &ast::SetExpr,
+        recursive: &ast::SetExpr,
+        dag_builder: &mut DagBuilder,
+        catalog: &DummyCatalog,
+    ) -> Result<NodeId, PlanError> {
+        let planned = parse_set_expr(anchor, &[], None, self, dag_builder, catalog)?;
+        let anchor = rename_columns(planned.node, &table.definition.alias, dag_builder)?;
+
+        let with_table = Ctes {
+            parent: Some(self),
+            tables: std::slice::from_ref(table),
+            recursive: self.recursive,
+        };
+        let schema = dag_builder.dag().get_node(anchor).get_schema();
+        table.work_table.set(Some(schema));
+        let planned = parse_set_expr(recursive, &[], None, &with_table, dag_builder, catalog);
+        table.work_table.set(None);
+        let work_table = table.node.take();
+        let recursive = planned?.node;
+
+        if work_table.is_some() {
+            dag_builder.create_recursive_union(
+                table.name().to_string(),
+                distinct,
+                anchor,
+                recursive,
+            )
+        } else {
+            dag_builder.create_union(distinct, &[anchor, recursive])
+        }
+    }
+}
+
+/// Whether `query` is nothing but its body.
+const fn is_plain(query: &ast::Query) -> bool {
+    query.with.is_none()
+        && query.order_by.is_empty()
+        && query.limit.is_none()
+        && query.offset.is_none()
+        && query.fetch.is_none()
+}
+
+/// Renames the leading columns of `input` to the column names of `alias`, like
+/// `t (a, b)`.
+pub fn rename_columns(
+    input: NodeId,
+    alias: &ast::TableAlias,
+    dag_builder: &mut DagBuilder,
+) -> Result<NodeId, PlanError> {
+    if alias.columns.is_empty() {
+        return Ok(input);
+    }
+    let schema = dag_builder.dag().get_node(input).get_schema();
+    if alias.columns.len() > schema.fields().len() {
+        return Err(PlanError::Unsupported(format!(
+            "{} column names for {} columns of {}",
+            alias.columns.len(),
+            schema.fields().len(),
+            alias.name
+        )));
+    }
+    let expr = schema
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let column = Expr::Ident(Ident {
+                name: field.name().clone(),
+            });
+            match alias.columns.get(i) {
+                Some(name) => Expr::Alias(Alias {
+                    expr: Box::new(column),
+                    name: name.value.clone(),
+                }),
+                None => column,
+            }
+        })
+        .collect();
+    dag_builder.create_project(expr, input)
+}
@@ -1 +1,4 @@
-mod sql_parser;
\ No newline at end of file
+mod cte;
+mod pivot;
+pub mod sql_parser;
+mod subquery;
@@ -0,0 +1,107 @@
+use crate::catalog::DummyCatalog;
+use crate::dag::NodeId;
+use crate::logical_plan::dag_builder::DagBuilder;
+use crate::logical_plan::errors::PlanError;
+use crate::logical_plan::expr::{
+    AggregateFunction, Alias, Binary, BinaryOp, Expr, Ident, VisitExpression,
+};
+use crate::logical_plan::scope::Scope;
+use sqlparser::ast;
+use std::collections::HashSet;
+
+/// Plans `PIVOT (agg FOR column IN (values))` over `input` as an aggregate
+/// grouped by the columns which neither `agg` nor `column` reference. Each
+/// value gets a copy of `agg` filtered to the rows where `column` equals the
+/// value, whose output column is named after the value.
+pub fn plan_pivot(
+    input: NodeId,
+    scope: &Scope,
+    aggregate_function: &ast::Expr,
+    value_column: &[ast::Ident],
+    pivot_values: &[ast::Value],
+    dag_builder: &mut DagBuilder,
+    catalog: &DummyCatalog,
+) -> Result<NodeId, PlanError> {
+    let visitor = VisitExpression::new(catalog).with_scope(scope);
+    let Expr::AggregateFunction(aggregate) = visitor.visit(aggregate_function)? else {
+        return Err(PlanError::Unsupported(format!(
+            "PIVOT of {aggregate_function}, which is not an aggregate function"
+        )));
+    };
+    let column = match value_column {
+        [name] => ast::Expr::Identifier(name.clone()),
+        _ => ast::Expr::CompoundIdentifier(value_column.to_vec()),
+    };
+    let column = visitor.visit(&column)?;
+
+    let mut used = HashSet::new();
+    column.collect_columns(&mut used);
+    for expr in aggregate.args.iter().chain(aggregate.filter.as_deref()) {
+        expr.collect_columns(&mut used);
+    }
+    let group_expr = scope
+        .columns()
+        .iter()
+        .filter(|scope_column| !used.contains(scope_column.field.as_str()))
+        .map(|scope_column| {
+            Expr::Ident(Ident {
+                name: scope_column.field.clone(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut projection = group_expr.clone();
+    let mut aggr_expr = Vec::with_capacity(pivot_values.len());
+    for value in pivot_values {
+        let literal = visitor.visit(&ast::Expr::Value(value.clone()))?;
+        let name = match &literal {
+            Expr::StringLiteral(literal) => literal.value.clone(),
+            _ => literal.to_string(),
+        };
+        if projection.iter().any(|expr| expr.name() == name) {
+            return Err(PlanError::AmbiguousColumn(name));
+        }
+        let matches = Expr::Binary(Binary {
+            lhs: Box::new(column.clone()),
+            op: BinaryOp::Eq,
+            rhs: Box::new(literal),
+        });
+        let filter = match &aggregate.filter {
+            Some(filter) => Expr::conjunction(vec![filter.as_ref().clone(), matches]),
+            None => Some(matches),
+        };
+        let expr = Expr::AggregateFunction(AggregateFunction {
+            filter: filter.map(Box::new),
+            ..aggregate.clone()
+        });
+        projection.push(Expr::Alias(Alias {
+            expr: Box::new(Expr::Ident(Ident { name: expr.name() })),
+            name,
+        }));
+        aggr_expr.push(expr);
+    }
+
+    let aggregate = dag_builder.create_aggregate(group_expr, aggr_expr, input)?;
+    dag_builder.create_project(projection, aggregate)
+}
+
+/// Plans `UNPIVOT (value FOR name IN (columns))` over `input`.
+pub fn plan_unpivot(
+    input: NodeId,
+    scope: &Scope,
+    value: &ast::Ident,
+    name: &ast::Ident,
+    columns: &[ast::Ident],
+    dag_builder: &mut DagBuilder,
+    catalog: &DummyCatalog,
+) -> Result<NodeId, PlanError> {
+    let visitor = VisitExpression::new(catalog).with_scope(scope);
+    let mut fields = Vec::with_capacity(columns.len());
+    for column in columns {
+        match visitor.visit(&ast::Expr::Identifier(column.clone()))? {
+            Expr::Ident(ident) => fields.push(ident.name),
+            expr => return Err(PlanError::ColumnNotFound(expr.to_string())),
+        }
+    }
+    dag_builder.create_unpivot(fields, name.value.clone(), value.value.clone(), input)
+}
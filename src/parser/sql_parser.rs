@@ -3,110 +3,1320 @@ use sqlparser::ast;
 
 use crate::dag::NodeId;
 use crate::logical_plan::dag_builder::DagBuilder;
-use crate::logical_plan::expr::{Expr, Ident, VisitExpression};
-use crate::logical_plan::{Dag, LogicalPlan};
+use crate::logical_plan::errors::PlanError;
+use crate::logical_plan::expr::{
+    Alias, Binary, BinaryOp, Expr, Ident, SortExpr, VisitExpression, WindowFunction,
+};
+use crate::logical_plan::scope::Scope;
+use crate::logical_plan::{Dag, JoinType, LogicalPlan};
+use crate::parser::cte::{self, Cte, Ctes};
+use crate::parser::pivot;
+use crate::parser::subquery::{self, OuterQuery, PlannedQuery};
 use sqlparser::dialect::GenericDialect;
 use sqlparser::parser::Parser;
+use std::collections::HashSet;
 
-pub fn parse_sql_query(sql_query: &str, catalog: &DummyCatalog) -> Dag<LogicalPlan> {
-    let dialect = GenericDialect {};
-    let statements = Parser::parse_sql(&dialect, sql_query).unwrap();
+/// A parsed SQL statement.
+#[derive(Debug)]
+pub enum Statement {
+    Query(Dag<LogicalPlan>),
+    /// `ANALYZE TABLE name`, which refreshes the statistics of a table.
+    Analyze {
+        table_name: String,
+    },
+}
 
-    // println!("{:?}", {statements.clone()});
+pub fn parse_sql_query(
+    sql_query: &str,
+    catalog: &DummyCatalog,
+) -> Result<Dag<LogicalPlan>, PlanError> {
+    match parse_sql_statement(sql_query, catalog)? {
+        Statement::Query(dag) => Ok(dag),
+        Statement::Analyze { table_name } => Err(PlanError::Unsupported(format!(
+            "ANALYZE TABLE {table_name} as a query"
+        ))),
+    }
+}
+
+pub fn parse_sql_statement(sql: &str, catalog: &DummyCatalog) -> Result<Statement, PlanError> {
+    let dialect = GenericDialect {};
+    let statements =
+        Parser::parse_sql(&dialect, sql).map_err(|err| PlanError::SqlParser(err.to_string()))?;
 
-    assert_eq!(statements.len(), 1);
-    let statement = &statements[0];
-    let mut dag = Dag::new();
-    let mut dag_builder = DagBuilder::new(&mut dag);
+    let [statement] = statements.as_slice() else {
+        return Err(PlanError::Unsupported(format!(
+            "{} statements, expected exactly one",
+            statements.len()
+        )));
+    };
 
     match statement {
         ast::Statement::Query(q) => {
-            parse_query(q, &mut dag_builder, catalog);
+            let mut dag = Dag::new();
+            let mut dag_builder = DagBuilder::new(&mut dag);
+            parse_query(q, None, &Ctes::default(), &mut dag_builder, catalog)?;
+            Ok(Statement::Query(dag))
+        }
+        ast::Statement::Analyze {
+            table_name,
+            partitions: None,
+            for_columns: false,
+            columns,
+            cache_metadata: false,
+            noscan: false,
+            compute_statistics: false,
+        } if columns.is_empty() => {
+            let table_name = table_name.to_string();
+            catalog.get_schema(&table_name)?;
+            Ok(Statement::Analyze { table_name })
+        }
+        _ => Err(PlanError::Unsupported(format!("Statement {statement}"))),
+    }
+}
+
+/// Plans a query, or a subquery of the query block `outer`.
+pub(super) fn parse_query(
+    query: &ast::Query,
+    outer: Option<&OuterQuery>,
+    ctes: &Ctes,
+    dag_builder: &mut DagBuilder,
+    catalog: &DummyCatalog,
+) -> Result<PlannedQuery, PlanError> {
+    let tables;
+    let nested;
+    let ctes = match &query.with {
+        Some(with) => {
+            tables = with.cte_tables.iter().map(Cte::new).collect::<Vec<_>>();
+            nested = Ctes::new(ctes, &tables, with.recursive)?;
+            &nested
+        }
+        None => ctes,
+    };
+
+    let mut result = parse_set_expr(
+        &query.body,
+        &query.order_by,
+        outer,
+        ctes,
+        dag_builder,
+        catalog,
+    )?;
+
+    let (skip, fetch) = parse_limit(query)?;
+    if skip > 0 || fetch.is_some() {
+        if !result.correlated.is_empty() {
+            return Err(PlanError::Unsupported(format!(
+                "LIMIT in correlated subquery {query}"
+            )));
+        }
+        result.node = dag_builder.create_limit(skip, fetch, result.node);
+    }
+    Ok(result)
+}
+
+pub(super) fn parse_set_expr(
+    set_expr: &ast::SetExpr,
+    order_by: &[ast::OrderByExpr],
+    outer: Option<&OuterQuery>,
+    ctes: &Ctes,
+    dag_builder: &mut DagBuilder,
+    catalog: &DummyCatalog,
+) -> Result<PlannedQuery, PlanError> {
+    match set_expr {
+        ast::SetExpr::Select(select) => {
+            parse_select(select, order_by, outer, ctes, dag_builder, catalog)
+        }
+        ast::SetExpr::Query(query) if order_by.is_empty() => {
+            parse_query(query, outer, ctes, dag_builder, catalog)
+        }
+        ast::SetExpr::SetOperation {
+            op,
+            set_quantifier,
+            left,
+            right,
+        } => {
+            let distinct = parse_set_quantifier(*set_quantifier)?;
+            let mut branches = vec![left.as_ref(), right.as_ref()];
+            if *op == ast::SetOperator::Union {
+                branches = Vec::new();
+                collect_union_branches(set_expr, distinct, &mut branches);
+            }
+            let mut inputs = Vec::with_capacity(branches.len());
+            for branch in branches {
+                let planned = parse_set_expr(branch, &[], outer, ctes, dag_builder, catalog)?;
+                if !planned.correlated.is_empty() {
+                    return Err(PlanError::Unsupported(format!(
+                        "Correlated subquery {set_expr}"
+                    )));
+                }
+                inputs.push(planned.node);
+            }
+            let mut node = match op {
+                ast::SetOperator::Union => dag_builder.create_union(distinct, &inputs)?,
+                ast::SetOperator::Intersect => {
+                    dag_builder.create_intersect(distinct, inputs[0], inputs[1])?
+                }
+                ast::SetOperator::Except => {
+                    dag_builder.create_except(distinct, inputs[0], inputs[1])?
+                }
+            };
+
+            // The result can only be sorted by its columns.
+            let schema = dag_builder.dag().get_node(node).get_schema();
+            let scope = Scope::for_table("", &schema);
+            let visitor = VisitExpression::new(catalog).with_scope(&scope);
+            let columns = schema
+                .fields()
+                .iter()
+                .map(|field| {
+                    Expr::Ident(Ident {
+                        name: field.name().clone(),
+                    })
+                })
+                .collect::<Vec<_>>();
+            let (sort_expr, hidden) = parse_order_by(order_by, &columns, &visitor)?;
+            if let Some(expr) = hidden.first() {
+                return Err(PlanError::ColumnNotFound(expr.to_string()));
+            }
+            if !sort_expr.is_empty() {
+                node = dag_builder.create_sort(sort_expr, node)?;
+            }
+            Ok(PlannedQuery {
+                node,
+                columns: columns.len(),
+                correlated: Vec::new(),
+                grouped_by_correlation: false,
+            })
         }
-        _ => unimplemented!(),
+        _ => Err(PlanError::Unsupported(format!("Query {set_expr}"))),
     }
-    dag
 }
 
-fn parse_query(query: &ast::Query, dag_builder: &mut DagBuilder, catalog: &DummyCatalog) -> NodeId {
-    match *query.body {
-        ast::SetExpr::Select(ref select) => parse_select(select, dag_builder, catalog),
-        _ => unimplemented!(),
+/// Whether a set operation removes duplicate rows.
+pub(super) fn parse_set_quantifier(quantifier: ast::SetQuantifier) -> Result<bool, PlanError> {
+    match quantifier {
+        ast::SetQuantifier::All => Ok(false),
+        ast::SetQuantifier::Distinct | ast::SetQuantifier::None => Ok(true),
+        _ => Err(PlanError::Unsupported(format!(
+            "Set quantifier {quantifier}"
+        ))),
+    }
+}
+
+/// Collects the branches of nested unions which can be combined into a single
+/// union with `distinct`. A `UNION ALL` below a `UNION` can, as the outer union
+/// removes the duplicates anyway.
+fn collect_union_branches<'a>(
+    set_expr: &'a ast::SetExpr,
+    distinct: bool,
+    branches: &mut Vec<&'a ast::SetExpr>,
+) {
+    match set_expr {
+        ast::SetExpr::SetOperation {
+            op: ast::SetOperator::Union,
+            set_quantifier,
+            left,
+            right,
+        } if parse_set_quantifier(*set_quantifier).is_ok_and(|inner| distinct || !inner) => {
+            collect_union_branches(left, distinct, branches);
+            collect_union_branches(right, distinct, branches);
+        }
+        _ => branches.push(set_expr),
+    }
+}
+
+/// Returns the number of rows to skip and to fetch from `LIMIT`, `OFFSET` and
+/// `FETCH FIRST n ROWS ONLY`.
+fn parse_limit(query: &ast::Query) -> Result<(usize, Option<usize>), PlanError> {
+    let skip = match &query.offset {
+        Some(offset) => parse_row_count(&offset.value)?,
+        None => 0,
+    };
+
+    let mut fetch = query.limit.as_ref().map(parse_row_count).transpose()?;
+    if let Some(fetch_clause) = &query.fetch {
+        if fetch_clause.with_ties || fetch_clause.percent {
+            return Err(PlanError::Unsupported(format!("{fetch_clause}")));
+        }
+        if fetch.is_some() {
+            return Err(PlanError::Unsupported(
+                "LIMIT together with FETCH".to_string(),
+            ));
+        }
+        fetch = Some(match &fetch_clause.quantity {
+            Some(quantity) => parse_row_count(quantity)?,
+            None => 1,
+        });
+    }
+    Ok((skip, fetch))
+}
+
+fn parse_row_count(expr: &ast::Expr) -> Result<usize, PlanError> {
+    match expr {
+        ast::Expr::Value(ast::Value::Number(value, _)) => value
+            .parse()
+            .map_err(|_| PlanError::Unsupported(format!("Row count {expr}"))),
+        _ => Err(PlanError::Unsupported(format!("Row count {expr}"))),
     }
 }
 
 fn parse_select(
     select: &ast::Select,
+    order_by: &[ast::OrderByExpr],
+    outer: Option<&OuterQuery>,
+    ctes: &Ctes,
     dag_builder: &mut DagBuilder,
     catalog: &DummyCatalog,
-) -> NodeId {
-    assert_eq!(select.from.len(), 1);
-    let from_id = parse_from(&select.from[0], dag_builder, catalog);
-
-    let mut result = from_id;
+) -> Result<PlannedQuery, PlanError> {
+    let (mut result, mut scope) = parse_from(&select.from, ctes, dag_builder, catalog)?;
+    if let Some(outer) = outer {
+        result = outer.rename_inner(result, &mut scope, dag_builder)?;
+    }
 
+    let mut correlated = Vec::new();
     if let Some(filter) = &select.selection {
-        result = parse_where(filter, dag_builder, result);
+        (result, correlated) =
+            parse_where(filter, &scope, outer, ctes, dag_builder, catalog, result)?;
+    }
+    // Without a LIMIT, the order of a correlated subquery doesn't matter.
+    let order_by = if correlated.is_empty() { order_by } else { &[] };
+
+    let items = select
+        .projection
+        .iter()
+        .filter_map(|item| match item {
+            ast::SelectItem::UnnamedExpr(expr) | ast::SelectItem::ExprWithAlias { expr, .. } => {
+                Some(expr)
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let subqueries;
+    (result, subqueries) =
+        subquery::plan_scalar_subqueries(&items, &scope, result, ctes, dag_builder, catalog)?;
+    let visitor = VisitExpression::new(catalog)
+        .with_scope(&scope)
+        .with_subqueries(&subqueries);
+    let mut projection = parse_projection(&select.projection, &scope, &visitor)?;
+
+    let visitor = VisitExpression::new(catalog).with_scope(&scope);
+    let (mut having, mut qualify) = parse_having_qualify(select, &projection, &scope, &visitor)?;
+    let (mut group_expr, mut grouping_sets) = parse_group_by(&select.group_by, &visitor)?;
+    let (sort_expr, mut hidden) = parse_order_by(order_by, &projection, &visitor)?;
+    let distinct_on = parse_distinct(select.distinct.as_ref(), &projection, &visitor, &mut hidden)?;
+    if distinct_on.as_ref().is_some_and(|on| !on.is_empty()) && !correlated.is_empty() {
+        return Err(PlanError::Unsupported(
+            "DISTINCT ON in a correlated subquery".to_string(),
+        ));
+    }
+
+    let mut aggr_expr = Vec::new();
+    let clauses = having.iter().chain(&hidden).chain(&qualify);
+    for expr in projection.iter().chain(clauses) {
+        collect_aggregates(expr, &mut aggr_expr);
     }
 
-    parse_projection(&select.projection, dag_builder, result)
+    let mut grouped_by_correlation = false;
+    if !group_expr.is_empty() || !aggr_expr.is_empty() || having.is_some() {
+        if !subqueries.is_empty() {
+            return Err(PlanError::Unsupported(
+                "Scalar subquery in the select list of an aggregate query".to_string(),
+            ));
+        }
+        if let (Some(outer), false) = (outer, correlated.is_empty()) {
+            grouped_by_correlation = group_expr.is_empty();
+            let keys = group_expr.len();
+            correlated = subquery::group_by_correlation(correlated, &mut group_expr, outer)?;
+            for set in &mut grouping_sets {
+                set.extend(keys..group_expr.len());
+            }
+        }
+        let clauses = having.iter_mut().chain(&mut hidden).chain(&mut qualify);
+        for expr in projection.iter_mut().chain(clauses) {
+            *expr = rewrite_for_aggregate(std::mem::replace(expr, Expr::Wildcard), &group_expr)?;
+        }
+        result = dag_builder.create_grouping_sets(group_expr, grouping_sets, aggr_expr, result)?;
+    }
+
+    if let Some(having) = having {
+        result = dag_builder.create_filter(Box::new(having), result);
+    }
+    let windows = (
+        projection.as_mut_slice(),
+        hidden.as_mut_slice(),
+        &mut qualify,
+    );
+    result = plan_windows(windows, !correlated.is_empty(), result, dag_builder)?;
+    if let Some(qualify) = qualify {
+        result = dag_builder.create_filter(Box::new(qualify), result);
+    }
+
+    // Columns read by correlated predicates follow the output columns.
+    let columns = projection.len();
+    if let Some(outer) = outer {
+        for column in subquery::correlation_columns(&correlated, outer) {
+            if !projection.contains(&column) {
+                projection.push(column);
+            }
+        }
+    }
+    Ok(PlannedQuery {
+        node: plan_select_list(
+            projection,
+            hidden,
+            sort_expr,
+            distinct_on,
+            result,
+            dag_builder,
+        )?,
+        columns,
+        correlated,
+        grouped_by_correlation,
+    })
+}
+
+/// Plans the HAVING and QUALIFY clauses of `select`. QUALIFY may refer to
+/// aliases of the select list.
+fn parse_having_qualify(
+    select: &ast::Select,
+    projection: &[Expr],
+    scope: &Scope,
+    visitor: &VisitExpression,
+) -> Result<(Option<Expr>, Option<Expr>), PlanError> {
+    let having = select
+        .having
+        .as_ref()
+        .map(|expr| visitor.visit(expr))
+        .transpose()?;
+    if let Some(expr) = having
+        .as_ref()
+        .filter(|expr| expr.contains_window_function())
+    {
+        return Err(PlanError::MisplacedWindowFunction(expr.to_string()));
+    }
+    let qualify = select
+        .qualify
+        .as_ref()
+        .map(|expr| visitor.visit(expr))
+        .transpose()?
+        .map(|expr| resolve_aliases(expr, projection, scope));
+    Ok((having, qualify))
 }
 
+/// Plans the window functions of the select list, the hidden ORDER BY items
+/// and QUALIFY, with one window per distinct PARTITION BY and ORDER BY, and
+/// replaces them by the columns holding their values.
+fn plan_windows(
+    (projection, hidden, qualify): (&mut [Expr], &mut [Expr], &mut Option<Expr>),
+    correlated: bool,
+    mut result: NodeId,
+    dag_builder: &mut DagBuilder,
+) -> Result<NodeId, PlanError> {
+    let mut window_expr = Vec::new();
+    for expr in projection.iter().chain(hidden.iter()).chain(qualify.iter()) {
+        collect_window_functions(expr, &mut window_expr);
+    }
+    if correlated && !window_expr.is_empty() {
+        return Err(PlanError::Unsupported(
+            "Window function in a correlated subquery".to_string(),
+        ));
+    }
+    while !window_expr.is_empty() {
+        let Expr::WindowFunction(first) = &window_expr[0] else {
+            unreachable!("only window functions are collected")
+        };
+        let first = first.clone();
+        let (same, other) = window_expr.into_iter().partition(
+            |expr| matches!(expr, Expr::WindowFunction(window) if window.same_window(&first)),
+        );
+        result = dag_builder.create_window(same, result)?;
+        window_expr = other;
+    }
+
+    for expr in projection.iter_mut().chain(hidden).chain(qualify) {
+        *expr = replace_window_functions(std::mem::replace(expr, Expr::Wildcard));
+    }
+    Ok(result)
+}
+
+/// Projects the select list, followed by the hidden ORDER BY items, and
+/// applies DISTINCT and ORDER BY to it.
+fn plan_select_list(
+    mut projection: Vec<Expr>,
+    hidden: Vec<Expr>,
+    sort_expr: Vec<SortExpr>,
+    distinct_on: Option<Vec<Expr>>,
+    mut result: NodeId,
+    dag_builder: &mut DagBuilder,
+) -> Result<NodeId, PlanError> {
+    if sort_expr.is_empty() && distinct_on.is_none() {
+        return dag_builder.create_project(projection, result);
+    }
+
+    // Columns only needed for sorting are projected next to the output columns
+    // and removed again by a final projection after the sort.
+    let output: Vec<Expr> = projection
+        .iter()
+        .map(|expr| Expr::Ident(Ident { name: expr.name() }))
+        .collect();
+    let has_hidden = !hidden.is_empty();
+    projection.extend(hidden);
+    result = dag_builder.create_project(projection, result)?;
+    match distinct_on {
+        Some(on) if !on.is_empty() => {
+            // The first row of each group in the order of ORDER BY is kept.
+            result = dag_builder.create_sort(distinct_on_order(on.clone(), sort_expr)?, result)?;
+            result = dag_builder.create_distinct(on, result)?;
+        }
+        _ => {
+            if distinct_on.is_some() {
+                result = dag_builder.create_distinct(Vec::new(), result)?;
+            }
+            if !sort_expr.is_empty() {
+                result = dag_builder.create_sort(sort_expr, result)?;
+            }
+        }
+    }
+    if has_hidden {
+        result = dag_builder.create_project(output, result)?;
+    }
+    Ok(result)
+}
+
+/// Resolves the keys of `SELECT DISTINCT ON (...)` like ORDER BY items, adding
+/// those which are not in the select list to `hidden`. Plain `SELECT DISTINCT`
+/// has no keys and allows no hidden ORDER BY items, which would make rows with
+/// the same output distinct.
+fn parse_distinct(
+    distinct: Option<&ast::Distinct>,
+    projection: &[Expr],
+    visitor: &VisitExpression,
+    hidden: &mut Vec<Expr>,
+) -> Result<Option<Vec<Expr>>, PlanError> {
+    match distinct {
+        None => Ok(None),
+        Some(ast::Distinct::Distinct) => match hidden.first() {
+            Some(expr) => Err(PlanError::Unsupported(format!(
+                "ORDER BY {expr} not in the select list of SELECT DISTINCT"
+            ))),
+            None => Ok(Some(Vec::new())),
+        },
+        Some(ast::Distinct::On(exprs)) => {
+            let items = exprs
+                .iter()
+                .map(|expr| ast::OrderByExpr {
+                    expr: expr.clone(),
+                    asc: None,
+                    nulls_first: None,
+                })
+                .collect::<Vec<_>>();
+            let (on, on_hidden) = parse_order_by(&items, projection, visitor)?;
+            for expr in on_hidden {
+                if !hidden.contains(&expr) {
+                    hidden.push(expr);
+                }
+            }
+            Ok(Some(on.into_iter().map(|sort| sort.expr).collect()))
+        }
+    }
+}
+
+/// Sort order for `DISTINCT ON (on)`: the ORDER BY items, which must start with
+/// the keys, or the keys if there's no ORDER BY.
+fn distinct_on_order(on: Vec<Expr>, sort_expr: Vec<SortExpr>) -> Result<Vec<SortExpr>, PlanError> {
+    if sort_expr.is_empty() {
+        return Ok(on
+            .into_iter()
+            .map(|expr| SortExpr {
+                expr,
+                asc: true,
+                nulls_first: false,
+            })
+            .collect());
+    }
+    if sort_expr.len() < on.len()
+        || sort_expr[..on.len()]
+            .iter()
+            .any(|sort| !on.contains(&sort.expr))
+    {
+        return Err(PlanError::Unsupported(
+            "ORDER BY which doesn't start with the DISTINCT ON expressions".to_string(),
+        ));
+    }
+    Ok(sort_expr)
+}
+
+/// Plans the FROM clause, comma-separated items are cross joined.
 fn parse_from(
-    table: &ast::TableWithJoins,
+    from: &[ast::TableWithJoins],
+    ctes: &Ctes,
+    dag_builder: &mut DagBuilder,
+    catalog: &DummyCatalog,
+) -> Result<(NodeId, Scope), PlanError> {
+    let mut result = None;
+    for table in from {
+        let mut relation = parse_table_factor(&table.relation, ctes, dag_builder, catalog)?;
+        for join in &table.joins {
+            let right = parse_table_factor(&join.relation, ctes, dag_builder, catalog)?;
+            relation = parse_join(&join.join_operator, relation, right, dag_builder, catalog)?;
+        }
+        result = Some(match result {
+            Some(left) => plan_join(
+                JoinType::Cross,
+                &ast::JoinConstraint::None,
+                left,
+                relation,
+                dag_builder,
+                catalog,
+            )?,
+            None => relation,
+        });
+    }
+    result.ok_or_else(|| PlanError::Unsupported("SELECT without FROM".to_string()))
+}
+
+fn parse_table_factor(
+    relation: &ast::TableFactor,
+    ctes: &Ctes,
     dag_builder: &mut DagBuilder,
     catalog: &DummyCatalog,
-) -> NodeId {
-    assert!(table.joins.is_empty());
-    match &table.relation {
-        ast::TableFactor::Table { name, .. } => {
+) -> Result<(NodeId, Scope), PlanError> {
+    let (node, qualifier) = match relation {
+        ast::TableFactor::Table { name, alias, .. } => {
             let table_name = name.to_string();
-            let schema = catalog.get_schema(table_name.clone().leak()).unwrap();
-            dag_builder.create_scan(table_name, schema)
+            let node = if let Some(node) = ctes.resolve(&table_name, dag_builder, catalog)? {
+                node
+            } else {
+                let schema = catalog.get_schema(&table_name)?;
+                dag_builder.create_scan(table_name.clone(), schema)
+            };
+            match alias {
+                Some(alias) => (
+                    cte::rename_columns(node, alias, dag_builder)?,
+                    alias.name.value.clone(),
+                ),
+                None => (node, table_name),
+            }
+        }
+        ast::TableFactor::Derived {
+            lateral: false,
+            subquery,
+            alias: Some(alias),
+        } => {
+            let planned = parse_query(subquery, None, ctes, dag_builder, catalog)?;
+            (
+                cte::rename_columns(planned.node, alias, dag_builder)?,
+                alias.name.value.clone(),
+            )
+        }
+        ast::TableFactor::Pivot {
+            table,
+            aggregate_function,
+            value_column,
+            pivot_values,
+            alias,
+        } => {
+            let (input, scope) = parse_table_factor(table, ctes, dag_builder, catalog)?;
+            let node = pivot::plan_pivot(
+                input,
+                &scope,
+                aggregate_function,
+                value_column,
+                pivot_values,
+                dag_builder,
+                catalog,
+            )?;
+            alias_reshaped(node, alias.as_ref(), &scope, dag_builder)?
+        }
+        ast::TableFactor::Unpivot {
+            table,
+            value,
+            name,
+            columns,
+            alias,
+        } => {
+            let (input, scope) = parse_table_factor(table, ctes, dag_builder, catalog)?;
+            let node =
+                pivot::plan_unpivot(input, &scope, value, name, columns, dag_builder, catalog)?;
+            alias_reshaped(node, alias.as_ref(), &scope, dag_builder)?
         }
-        _ => unimplemented!(),
+        _ => return Err(PlanError::Unsupported(format!("Table factor {relation}"))),
+    };
+    let scope = Scope::for_table(&qualifier, &dag_builder.dag().get_node(node).get_schema());
+    Ok((node, scope))
+}
+
+/// Applies the alias of a PIVOT or UNPIVOT to its plan `node`. Without one,
+/// its columns keep the qualifier of the table it reshapes.
+fn alias_reshaped(
+    node: NodeId,
+    alias: Option<&ast::TableAlias>,
+    scope: &Scope,
+    dag_builder: &mut DagBuilder,
+) -> Result<(NodeId, String), PlanError> {
+    if let Some(alias) = alias {
+        return Ok((
+            cte::rename_columns(node, alias, dag_builder)?,
+            alias.name.value.clone(),
+        ));
     }
+    let qualifier = scope.columns().first().map(|column| &column.qualifier);
+    Ok((node, qualifier.cloned().unwrap_or_default()))
 }
 
-fn parse_projection(
-    projection: &[ast::SelectItem],
+fn parse_join(
+    operator: &ast::JoinOperator,
+    left: (NodeId, Scope),
+    right: (NodeId, Scope),
     dag_builder: &mut DagBuilder,
-    from_id: NodeId,
-) -> NodeId {
-    let columns: Vec<_> = projection
+    catalog: &DummyCatalog,
+) -> Result<(NodeId, Scope), PlanError> {
+    let (join_type, constraint) = match operator {
+        ast::JoinOperator::Inner(constraint) => (JoinType::Inner, constraint),
+        ast::JoinOperator::LeftOuter(constraint) => (JoinType::Left, constraint),
+        ast::JoinOperator::RightOuter(constraint) => (JoinType::Right, constraint),
+        ast::JoinOperator::FullOuter(constraint) => (JoinType::Full, constraint),
+        ast::JoinOperator::LeftSemi(constraint) => (JoinType::Semi, constraint),
+        ast::JoinOperator::LeftAnti(constraint) => (JoinType::Anti, constraint),
+        // Right semi and anti joins are planned as left joins with swapped inputs.
+        ast::JoinOperator::RightSemi(constraint) => {
+            return plan_join(
+                JoinType::Semi,
+                constraint,
+                right,
+                left,
+                dag_builder,
+                catalog,
+            );
+        }
+        ast::JoinOperator::RightAnti(constraint) => {
+            return plan_join(
+                JoinType::Anti,
+                constraint,
+                right,
+                left,
+                dag_builder,
+                catalog,
+            );
+        }
+        ast::JoinOperator::CrossJoin => (JoinType::Cross, &ast::JoinConstraint::None),
+        _ => return Err(PlanError::Unsupported(format!("Join {operator:?}"))),
+    };
+    plan_join(join_type, constraint, left, right, dag_builder, catalog)
+}
+
+fn plan_join(
+    join_type: JoinType,
+    constraint: &ast::JoinConstraint,
+    left: (NodeId, Scope),
+    right: (NodeId, Scope),
+    dag_builder: &mut DagBuilder,
+    catalog: &DummyCatalog,
+) -> Result<(NodeId, Scope), PlanError> {
+    let (mut left, mut left_scope) = left;
+    let (mut right, mut right_scope) = right;
+    for column in right_scope.columns() {
+        if left_scope.has_qualifier(&column.qualifier) {
+            return Err(PlanError::DuplicateTable(column.qualifier.clone()));
+        }
+    }
+
+    // Columns with the same name on both sides are renamed to `qualifier.name`.
+    let left_fields: HashSet<String> = left_scope
+        .columns()
         .iter()
-        .map(|item| match item {
-            ast::SelectItem::UnnamedExpr(expr) => match expr {
-                ast::Expr::Identifier(ident) => ident.value.clone(),
-                _ => unimplemented!(),
-            },
-            _ => unimplemented!(),
+        .map(|column| column.field.clone())
+        .collect();
+    let duplicates: HashSet<String> = right_scope
+        .columns()
+        .iter()
+        .map(|column| column.field.clone())
+        .filter(|field| left_fields.contains(field))
+        .collect();
+    left = qualify_duplicates(left, &mut left_scope, &duplicates, dag_builder)?;
+    right = qualify_duplicates(right, &mut right_scope, &duplicates, dag_builder)?;
+
+    let mut combined = left_scope.clone().into_columns();
+    combined.extend(right_scope.clone().into_columns());
+    let mut combined = Scope::new(combined);
+
+    let mut on = Vec::new();
+    let mut filter = Vec::new();
+    let using: Vec<String> = match constraint {
+        ast::JoinConstraint::On(expr) => {
+            let visitor = VisitExpression::new(catalog).with_scope(&combined);
+            let expr = visitor.visit(expr)?;
+            if expr.contains_aggregate() {
+                return Err(PlanError::Unsupported(format!(
+                    "Aggregate function in JOIN condition {expr}"
+                )));
+            }
+            if expr.contains_window_function() {
+                return Err(PlanError::MisplacedWindowFunction(expr.to_string()));
+            }
+            let left_fields = left_scope
+                .columns()
+                .iter()
+                .map(|column| column.field.as_str())
+                .collect();
+            (on, filter) = split_join_condition(expr, &left_fields);
+            Vec::new()
+        }
+        ast::JoinConstraint::Using(idents) => idents.iter().map(|i| i.value.clone()).collect(),
+        ast::JoinConstraint::Natural => left_scope
+            .columns()
+            .iter()
+            .filter(|l| {
+                l.unqualified
+                    && right_scope
+                        .columns()
+                        .iter()
+                        .any(|r| r.unqualified && r.name == l.name)
+            })
+            .map(|column| column.name.clone())
+            .collect(),
+        ast::JoinConstraint::None => Vec::new(),
+    };
+    // The key column of a full join would have to take the value of either side.
+    if join_type == JoinType::Full && !using.is_empty() {
+        return Err(PlanError::Unsupported(
+            "FULL JOIN with USING or NATURAL".to_string(),
+        ));
+    }
+
+    for name in &using {
+        let column = |scope: &Scope| {
+            scope
+                .resolve(None, name)?
+                .map(|column| column.field.clone())
+                .ok_or_else(|| PlanError::ColumnNotFound(name.clone()))
+        };
+        let (left_field, right_field) = (column(&left_scope)?, column(&right_scope)?);
+        // Only one column of each pair can be referenced without a qualifier.
+        let hidden = if join_type == JoinType::Right {
+            &left_field
+        } else {
+            &right_field
+        };
+        for column in combined.columns_mut() {
+            if &column.field == hidden {
+                column.unqualified = false;
+            }
+        }
+        on.push((
+            Expr::Ident(Ident { name: left_field }),
+            Expr::Ident(Ident { name: right_field }),
+        ));
+    }
+
+    let filter = Expr::conjunction(filter).map(Box::new);
+    let join = dag_builder.create_join(join_type, on, filter, left, right)?;
+    let scope = match join_type {
+        JoinType::Semi | JoinType::Anti => left_scope,
+        _ => combined,
+    };
+    Ok((join, scope))
+}
+
+/// Splits a join condition into pairs of equal left and right keys and the
+/// remaining predicates.
+pub(super) fn split_join_condition(
+    expr: Expr,
+    left_fields: &HashSet<&str>,
+) -> (Vec<(Expr, Expr)>, Vec<Expr>) {
+    let mut on = Vec::new();
+    let mut filter = Vec::new();
+    for conjunct in expr.into_conjuncts() {
+        match conjunct {
+            Expr::Binary(binary) if binary.op == BinaryOp::Eq => {
+                match (
+                    key_side(&binary.lhs, left_fields),
+                    key_side(&binary.rhs, left_fields),
+                ) {
+                    (Some(true), Some(false)) => on.push((*binary.lhs, *binary.rhs)),
+                    (Some(false), Some(true)) => on.push((*binary.rhs, *binary.lhs)),
+                    _ => filter.push(Expr::Binary(binary)),
+                }
+            }
+            _ => filter.push(conjunct),
+        }
+    }
+    (on, filter)
+}
+
+/// Returns `Some(true)` if `expr` only reads columns of the left input and
+/// `Some(false)` if it only reads columns of the right input.
+fn key_side(expr: &Expr, left_fields: &HashSet<&str>) -> Option<bool> {
+    let mut columns = HashSet::new();
+    expr.collect_columns(&mut columns);
+    if columns.is_empty() {
+        None
+    } else if columns.is_subset(left_fields) {
+        Some(true)
+    } else if columns.is_disjoint(left_fields) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Renames the columns of `input` which appear in `duplicates` to `qualifier.name`.
+pub(super) fn qualify_duplicates(
+    input: NodeId,
+    scope: &mut Scope,
+    duplicates: &HashSet<String>,
+    dag_builder: &mut DagBuilder,
+) -> Result<NodeId, PlanError> {
+    if !scope
+        .columns()
+        .iter()
+        .any(|column| duplicates.contains(&column.field))
+    {
+        return Ok(input);
+    }
+    let expr = scope
+        .columns_mut()
+        .iter_mut()
+        .map(|column| {
+            let ident = Expr::Ident(Ident {
+                name: column.field.clone(),
+            });
+            if duplicates.contains(&column.field) {
+                column.field = format!("{}.{}", column.qualifier, column.name);
+                Expr::Alias(Alias {
+                    expr: Box::new(ident),
+                    name: column.field.clone(),
+                })
+            } else {
+                ident
+            }
         })
         .collect();
+    dag_builder.create_project(expr, input)
+}
 
-    let mut vec_expr: Vec<Expr> = vec![];
-    for column_name in &columns {
-        vec_expr.push(Expr::Ident(Ident {
-            name: column_name.to_string(),
-        }));
+fn parse_projection(
+    projection: &[ast::SelectItem],
+    scope: &Scope,
+    visitor: &VisitExpression,
+) -> Result<Vec<Expr>, PlanError> {
+    let mut result = Vec::with_capacity(projection.len());
+    for item in projection {
+        match item {
+            ast::SelectItem::UnnamedExpr(expr) => result.push(visitor.visit(expr)?),
+            ast::SelectItem::ExprWithAlias { expr, alias } => result.push(Expr::Alias(Alias {
+                expr: Box::new(visitor.visit(expr)?),
+                name: alias.value.clone(),
+            })),
+            ast::SelectItem::Wildcard(options) if is_plain_wildcard(options) => {
+                result.extend(
+                    scope
+                        .columns()
+                        .iter()
+                        .filter(|column| column.unqualified)
+                        .map(|column| {
+                            Expr::Ident(Ident {
+                                name: column.field.clone(),
+                            })
+                        }),
+                );
+            }
+            ast::SelectItem::QualifiedWildcard(name, options) if is_plain_wildcard(options) => {
+                let qualifier = name.to_string();
+                if !scope.has_qualifier(&qualifier) {
+                    return Err(PlanError::ColumnNotFound(format!("{qualifier}.*")));
+                }
+                result.extend(
+                    scope
+                        .columns()
+                        .iter()
+                        .filter(|column| column.qualifier == qualifier)
+                        .map(|column| {
+                            Expr::Ident(Ident {
+                                name: column.field.clone(),
+                            })
+                        }),
+                );
+            }
+            _ => return Err(PlanError::Unsupported(format!("Select item {item}"))),
+        }
+    }
+    Ok(result)
+}
+
+const fn is_plain_wildcard(options: &ast::WildcardAdditionalOptions) -> bool {
+    options.opt_exclude.is_none()
+        && options.opt_except.is_none()
+        && options.opt_rename.is_none()
+        && options.opt_replace.is_none()
+}
+
+/// Number of `CUBE` elements, which make twice as many grouping sets each.
+const MAX_CUBE_ELEMENTS: usize = 12;
+
+/// Plans the GROUP BY clause as distinct keys and the grouping sets made of
+/// them. Plain expressions are part of every grouping set, and the sets of
+/// `ROLLUP`, `CUBE` and `GROUPING SETS` items are combined with each other.
+fn parse_group_by(
+    group_by: &ast::GroupByExpr,
+    visitor: &VisitExpression,
+) -> Result<(Vec<Expr>, Vec<Vec<usize>>), PlanError> {
+    let ast::GroupByExpr::Expressions(items) = group_by else {
+        return Err(PlanError::Unsupported("GROUP BY ALL".to_string()));
+    };
+    let mut group_expr = Vec::new();
+    let mut grouping_sets = vec![Vec::new()];
+    for item in items {
+        let item_sets: Vec<Vec<&ast::Expr>> = match item {
+            ast::Expr::Rollup(lists) => (0..=lists.len())
+                .rev()
+                .map(|len| lists[..len].iter().flatten().collect())
+                .collect(),
+            ast::Expr::Cube(lists) if lists.len() > MAX_CUBE_ELEMENTS => {
+                return Err(PlanError::Unsupported(format!(
+                    "CUBE of more than {MAX_CUBE_ELEMENTS} elements"
+                )));
+            }
+            ast::Expr::Cube(lists) => (0..1usize << lists.len())
+                .rev()
+                .map(|mask| {
+                    let included = |i: &usize| mask >> (lists.len() - 1 - i) & 1 == 1;
+                    (0..lists.len())
+                        .filter(included)
+                        .flat_map(|i| &lists[i])
+                        .collect()
+                })
+                .collect(),
+            ast::Expr::GroupingSets(sets) => sets.iter().map(|set| set.iter().collect()).collect(),
+            expr => vec![vec![expr]],
+        };
+
+        let mut item_indices = Vec::with_capacity(item_sets.len());
+        for set in item_sets {
+            let mut indices = Vec::with_capacity(set.len());
+            for expr in set {
+                let expr = visit_group_expr(expr, visitor)?;
+                let index = group_expr.iter().position(|key| *key == expr);
+                let index = index.unwrap_or_else(|| {
+                    group_expr.push(expr);
+                    group_expr.len() - 1
+                });
+                if !indices.contains(&index) {
+                    indices.push(index);
+                }
+            }
+            item_indices.push(indices);
+        }
+        grouping_sets = grouping_sets
+            .iter()
+            .flat_map(|prefix| {
+                item_indices.iter().map(move |indices| {
+                    let mut set = prefix.clone();
+                    set.extend(indices.iter().filter(|&i| !prefix.contains(i)));
+                    set
+                })
+            })
+            .collect();
     }
+    Ok((group_expr, grouping_sets))
+}
 
-    dag_builder.create_project(vec_expr, from_id)
+fn visit_group_expr(expr: &ast::Expr, visitor: &VisitExpression) -> Result<Expr, PlanError> {
+    let expr = visitor.visit(expr)?;
+    if expr.contains_aggregate() {
+        return Err(PlanError::Unsupported(format!(
+            "Aggregate function in GROUP BY {expr}"
+        )));
+    }
+    if expr.contains_window_function() {
+        return Err(PlanError::MisplacedWindowFunction(expr.to_string()));
+    }
+    Ok(expr)
 }
 
-fn parse_where(expr: &ast::Expr, dag_builder: &mut DagBuilder, input: NodeId) -> NodeId {
-    let expression = VisitExpression::visit(expr);
-    dag_builder.create_filter(Box::new(expression), input)
+/// Resolves ORDER BY items against the select list, by output name, by
+/// expression or by position. Items which are not in the select list are
+/// returned separately, as they must be computed before sorting.
+fn parse_order_by(
+    order_by: &[ast::OrderByExpr],
+    projection: &[Expr],
+    visitor: &VisitExpression,
+) -> Result<(Vec<SortExpr>, Vec<Expr>), PlanError> {
+    let mut sort_expr = Vec::with_capacity(order_by.len());
+    let mut hidden = Vec::new();
+    for item in order_by {
+        let asc = item.asc.unwrap_or(true);
+        let nulls_first = item.nulls_first.unwrap_or(!asc);
+        let expr = visitor.visit(&item.expr)?;
+
+        let name = if let Expr::IntegerLiteral(position) = &expr {
+            usize::try_from(position.value)
+                .ok()
+                .and_then(|position| position.checked_sub(1))
+                .and_then(|position| projection.get(position))
+                .ok_or_else(|| {
+                    PlanError::Unsupported(format!("ORDER BY position {}", position.value))
+                })?
+                .name()
+        } else if let Some(column) = projection.iter().find(|column| {
+            column.name() == expr.name()
+                || matches!(column, Expr::Alias(alias) if *alias.expr == expr)
+        }) {
+            column.name()
+        } else {
+            if !hidden.contains(&expr) {
+                hidden.push(expr.clone());
+            }
+            expr.name()
+        };
+
+        sort_expr.push(SortExpr {
+            expr: Expr::Ident(Ident { name }),
+            asc,
+            nulls_first,
+        });
+    }
+    Ok((sort_expr, hidden))
+}
+
+/// Plans the WHERE clause on top of `input`. IN and EXISTS subqueries become
+/// semi and anti joins. Predicates of a subquery which reference the
+/// enclosing query `outer` are returned instead of applied.
+fn parse_where(
+    expr: &ast::Expr,
+    scope: &Scope,
+    outer: Option<&OuterQuery>,
+    ctes: &Ctes,
+    dag_builder: &mut DagBuilder,
+    catalog: &DummyCatalog,
+    input: NodeId,
+) -> Result<(NodeId, Vec<Expr>), PlanError> {
+    let (subquery_predicates, predicates): (Vec<_>, Vec<_>) = subquery::split_conjuncts(expr)
+        .into_iter()
+        .partition(|conjunct| subquery::is_subquery_predicate(conjunct));
+
+    let (mut result, subqueries) =
+        subquery::plan_scalar_subqueries(&predicates, scope, input, ctes, dag_builder, catalog)?;
+    let mut visitor = VisitExpression::new(catalog)
+        .with_scope(scope)
+        .with_subqueries(&subqueries);
+    if let Some(outer) = outer {
+        visitor = visitor.with_outer_scope(outer.scope);
+    }
+    let mut filter = Vec::new();
+    let mut correlated = Vec::new();
+    for predicate in predicates {
+        let expression = visitor.visit(predicate)?;
+        if expression.contains_aggregate() {
+            return Err(PlanError::AggregateInWhere(expression.to_string()));
+        }
+        if expression.contains_window_function() {
+            return Err(PlanError::MisplacedWindowFunction(expression.to_string()));
+        }
+        if outer.is_some_and(|outer| outer.is_referenced_by(&expression)) {
+            correlated.push(expression);
+        } else {
+            filter.push(expression);
+        }
+    }
+    if let Some(filter) = Expr::conjunction(filter) {
+        result = dag_builder.create_filter(Box::new(filter), result);
+    }
+
+    for predicate in subquery_predicates {
+        result = subquery::plan_subquery_predicate(
+            predicate,
+            scope,
+            result,
+            ctes,
+            dag_builder,
+            catalog,
+        )?;
+    }
+    Ok((result, correlated))
+}
+
+/// Appends every distinct aggregate call found in `expr` to `aggr_expr`.
+fn collect_aggregates(expr: &Expr, aggr_expr: &mut Vec<Expr>) {
+    match expr {
+        Expr::AggregateFunction(_) => {
+            if !aggr_expr.contains(expr) {
+                aggr_expr.push(expr.clone());
+            }
+        }
+        Expr::WindowFunction(window) => {
+            let order_by = window.order_by.iter().map(|sort| &sort.expr);
+            for expr in window
+                .args
+                .iter()
+                .chain(&window.partition_by)
+                .chain(order_by)
+            {
+                collect_aggregates(expr, aggr_expr);
+            }
+        }
+        Expr::Binary(binary) => {
+            collect_aggregates(&binary.lhs, aggr_expr);
+            collect_aggregates(&binary.rhs, aggr_expr);
+        }
+        Expr::Alias(alias) => collect_aggregates(&alias.expr, aggr_expr),
+        Expr::Not(expr) | Expr::IsNull(expr) => collect_aggregates(expr, aggr_expr),
+        Expr::Ident(_)
+        | Expr::IntegerLiteral(_)
+        | Expr::FloatLiteral(_)
+        | Expr::StringLiteral(_)
+        | Expr::Wildcard => {}
+    }
+}
+
+/// Replaces references to aliases of the select list, which are not columns of
+/// `scope`, by the aliased expressions, for QUALIFY.
+fn resolve_aliases(expr: Expr, projection: &[Expr], scope: &Scope) -> Expr {
+    match expr {
+        Expr::Ident(ident) => {
+            let aliased = projection.iter().find_map(|column| match column {
+                Expr::Alias(alias) if alias.name == ident.name => Some(alias.expr.as_ref()),
+                _ => None,
+            });
+            match (aliased, scope.resolve(None, &ident.name)) {
+                (Some(aliased), Ok(None)) => aliased.clone(),
+                _ => Expr::Ident(ident),
+            }
+        }
+        Expr::Binary(binary) => Expr::Binary(Binary {
+            lhs: Box::new(resolve_aliases(*binary.lhs, projection, scope)),
+            op: binary.op,
+            rhs: Box::new(resolve_aliases(*binary.rhs, projection, scope)),
+        }),
+        Expr::Not(expr) => Expr::Not(Box::new(resolve_aliases(*expr, projection, scope))),
+        Expr::IsNull(expr) => Expr::IsNull(Box::new(resolve_aliases(*expr, projection, scope))),
+        Expr::Alias(_)
+        | Expr::AggregateFunction(_)
+        | Expr::WindowFunction(_)
+        | Expr::IntegerLiteral(_)
+        | Expr::FloatLiteral(_)
+        | Expr::StringLiteral(_)
+        | Expr::Wildcard => expr,
+    }
+}
+
+/// Appends every distinct window function call found in `expr` to `window_expr`.
+fn collect_window_functions(expr: &Expr, window_expr: &mut Vec<Expr>) {
+    match expr {
+        Expr::WindowFunction(_) => {
+            if !window_expr.contains(expr) {
+                window_expr.push(expr.clone());
+            }
+        }
+        Expr::Binary(binary) => {
+            collect_window_functions(&binary.lhs, window_expr);
+            collect_window_functions(&binary.rhs, window_expr);
+        }
+        Expr::Alias(alias) => collect_window_functions(&alias.expr, window_expr),
+        Expr::Not(expr) | Expr::IsNull(expr) => collect_window_functions(expr, window_expr),
+        Expr::AggregateFunction(_)
+        | Expr::Ident(_)
+        | Expr::IntegerLiteral(_)
+        | Expr::FloatLiteral(_)
+        | Expr::StringLiteral(_)
+        | Expr::Wildcard => {}
+    }
+}
+
+/// Replaces window function calls by the columns of a window which hold their
+/// values.
+fn replace_window_functions(expr: Expr) -> Expr {
+    match expr {
+        Expr::WindowFunction(_) => Expr::Ident(Ident { name: expr.name() }),
+        Expr::Binary(binary) => Expr::Binary(Binary {
+            lhs: Box::new(replace_window_functions(*binary.lhs)),
+            op: binary.op,
+            rhs: Box::new(replace_window_functions(*binary.rhs)),
+        }),
+        Expr::Alias(alias) => Expr::Alias(Alias {
+            expr: Box::new(replace_window_functions(*alias.expr)),
+            name: alias.name,
+        }),
+        Expr::Not(expr) => Expr::Not(Box::new(replace_window_functions(*expr))),
+        Expr::IsNull(expr) => Expr::IsNull(Box::new(replace_window_functions(*expr))),
+        Expr::AggregateFunction(_)
+        | Expr::Ident(_)
+        | Expr::IntegerLiteral(_)
+        | Expr::FloatLiteral(_)
+        | Expr::StringLiteral(_)
+        | Expr::Wildcard => expr,
+    }
+}
+
+/// Rewrites an expression evaluated above an aggregate so that group keys and
+/// aggregate calls become references to the corresponding aggregate output columns.
+fn rewrite_for_aggregate(expr: Expr, group_expr: &[Expr]) -> Result<Expr, PlanError> {
+    if group_expr.contains(&expr) {
+        return Ok(Expr::Ident(Ident { name: expr.name() }));
+    }
+    match expr {
+        Expr::AggregateFunction(_) => Ok(Expr::Ident(Ident { name: expr.name() })),
+        Expr::WindowFunction(window) => {
+            let rewrite = |exprs: Vec<Expr>| {
+                exprs
+                    .into_iter()
+                    .map(|expr| rewrite_for_aggregate(expr, group_expr))
+                    .collect::<Result<Vec<_>, _>>()
+            };
+            let order_by = window
+                .order_by
+                .into_iter()
+                .map(|sort| {
+                    Ok(SortExpr {
+                        expr: rewrite_for_aggregate(sort.expr, group_expr)?,
+                        ..sort
+                    })
+                })
+                .collect::<Result<_, PlanError>>()?;
+            let args = match window.args.as_slice() {
+                [Expr::Wildcard] => window.args,
+                _ => rewrite(window.args)?,
+            };
+            Ok(Expr::WindowFunction(WindowFunction {
+                args,
+                partition_by: rewrite(window.partition_by)?,
+                order_by,
+                ..window
+            }))
+        }
+        Expr::Binary(binary) => Ok(Expr::Binary(Binary {
+            lhs: Box::new(rewrite_for_aggregate(*binary.lhs, group_expr)?),
+            op: binary.op,
+            rhs: Box::new(rewrite_for_aggregate(*binary.rhs, group_expr)?),
+        })),
+        Expr::Alias(alias) => Ok(Expr::Alias(Alias {
+            expr: Box::new(rewrite_for_aggregate(*alias.expr, group_expr)?),
+            name: alias.name,
+        })),
+        Expr::Not(expr) => Ok(Expr::Not(Box::new(rewrite_for_aggregate(
+            *expr, group_expr,
+        )?))),
+        Expr::IsNull(expr) => Ok(Expr::IsNull(Box::new(rewrite_for_aggregate(
+            *expr, group_expr,
+        )?))),
+        Expr::Ident(ident) => Err(PlanError::NotGrouped(ident.name)),
+        Expr::IntegerLiteral(_) | Expr::FloatLiteral(_) | Expr::StringLiteral(_) => Ok(expr),
+        Expr::Wildcard => Err(PlanError::Unsupported(format!("Expression {expr}"))),
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::catalog::DummyCatalog;
+    use crate::catalog::{CatalogError, DummyCatalog};
     use crate::dag::Dag;
 
     use crate::logical_plan::dag_builder::DagBuilder;
-    use crate::logical_plan::expr::{Binary, BinaryOp, Expr, Ident, IntegerLiteral};
-    use crate::logical_plan::LogicalPlan;
+    use crate::logical_plan::errors::PlanError;
+    use crate::logical_plan::expr::{
+        AggregateFunc, AggregateFunction, Alias, Binary, BinaryOp, Expr, Ident, IntegerLiteral,
+        SortExpr,
+    };
+    use crate::logical_plan::{JoinType, LogicalPlan};
     use crate::parser::sql_parser::parse_sql_query;
     use sqlparser::dialect::GenericDialect;
     use std::sync::Arc;
@@ -129,7 +1339,7 @@ mod tests {
         ]));
         catalog.add_table("table_1", table_1_schema.clone());
 
-        let logical_plan_actual = parse_sql_query(sql_query, &catalog);
+        let logical_plan_actual = parse_sql_query(sql_query, &catalog).unwrap();
 
         let mut logical_plan_excepted: Dag<LogicalPlan> = Dag::new();
 
@@ -144,7 +1354,7 @@ mod tests {
             }),
         ];
         let scan_id = dag_builder.create_scan("table_1".to_string(), table_1_schema);
-        dag_builder.create_project(project_expr, scan_id);
+        dag_builder.create_project(project_expr, scan_id).unwrap();
 
         assert_eq!(
             logical_plan_actual.get_node(0),
@@ -156,6 +1366,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_sql_parser_names_nested_binaries() {
+        let mut catalog = DummyCatalog::new();
+        catalog.add_table(
+            "table_1",
+            Arc::new(arrow::datatypes::Schema::new(vec![
+                arrow::datatypes::Field::new("a", arrow::datatypes::DataType::Int32, false),
+                arrow::datatypes::Field::new("b", arrow::datatypes::DataType::Int32, false),
+                arrow::datatypes::Field::new("c", arrow::datatypes::DataType::Int32, false),
+            ])),
+        );
+
+        let dag = parse_sql_query(
+            "SELECT (a + b) * c, a + b * c, a - (b - c), a - b - c, NOT (a > b OR c > b) \
+             FROM table_1",
+            &catalog,
+        )
+        .unwrap();
+        let schema = dag.get_node(dag.len() - 1).get_schema();
+        let names = schema
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "(a + b) * c",
+                "a + b * c",
+                "a - (b - c)",
+                "a - b - c",
+                "NOT (a > b OR c > b)"
+            ]
+        );
+    }
+
     #[test]
     fn test_sql_parser_with_filter() {
         let dialect = GenericDialect {};
@@ -174,7 +1420,7 @@ mod tests {
         ]));
         catalog.add_table("table_1", table_1_schema.clone());
 
-        let logical_plan_actual = parse_sql_query(sql_query, &catalog);
+        let logical_plan_actual = parse_sql_query(sql_query, &catalog).unwrap();
 
         let mut logical_plan_excepted: Dag<LogicalPlan> = Dag::new();
 
@@ -208,7 +1454,7 @@ mod tests {
 
         let scan = dag_builder.create_scan("table_1".to_string(), table_1_schema);
         let filter = dag_builder.create_filter(filter_expr, scan);
-        let project = dag_builder.create_project(project_expr, filter);
+        let project = dag_builder.create_project(project_expr, filter).unwrap();
 
         assert_eq!(
             logical_plan_actual.get_node(0),
@@ -223,4 +1469,871 @@ mod tests {
             logical_plan_excepted.get_node(2)
         );
     }
+
+    #[test]
+    fn test_sql_parser_with_having() {
+        let sql_query = "SELECT country, sum(salary) / count(*) FROM users GROUP BY country HAVING count(*) > 10";
+
+        let mut catalog = DummyCatalog::new();
+
+        let users_schema = Arc::new(arrow::datatypes::Schema::new(vec![
+            arrow::datatypes::Field::new("country", arrow::datatypes::DataType::Utf8, true),
+            arrow::datatypes::Field::new("salary", arrow::datatypes::DataType::Float64, true),
+        ]));
+        catalog.add_table("users", users_schema.clone());
+
+        let logical_plan_actual = parse_sql_query(sql_query, &catalog).unwrap();
+
+        let mut logical_plan_excepted: Dag<LogicalPlan> = Dag::new();
+
+        let mut dag_builder = DagBuilder::new(&mut logical_plan_excepted);
+
+        let ident = |name: &str| {
+            Expr::Ident(Ident {
+                name: name.to_string(),
+            })
+        };
+        let sum = Expr::AggregateFunction(AggregateFunction {
+            func: AggregateFunc::Sum,
+            args: vec![ident("salary")],
+            distinct: false,
+            filter: None,
+        });
+        let count = Expr::AggregateFunction(AggregateFunction {
+            func: AggregateFunc::Count,
+            args: vec![Expr::Wildcard],
+            distinct: false,
+            filter: None,
+        });
+        let having_expr = Box::from(Expr::Binary(Binary {
+            lhs: Box::from(ident("count(*)")),
+            op: BinaryOp::Gt,
+            rhs: Box::from(Expr::IntegerLiteral(IntegerLiteral { value: 10 })),
+        }));
+        let project_expr = vec![
+            ident("country"),
+            Expr::Binary(Binary {
+                lhs: Box::from(ident("sum(salary)")),
+                op: BinaryOp::Divide,
+                rhs: Box::from(ident("count(*)")),
+            }),
+        ];
+
+        let scan = dag_builder.create_scan("users".to_string(), users_schema);
+        let aggregate = dag_builder
+            .create_aggregate(vec![ident("country")], vec![sum, count], scan)
+            .unwrap();
+        let filter = dag_builder.create_filter(having_expr, aggregate);
+        dag_builder.create_project(project_expr, filter).unwrap();
+
+        for id in 0..4 {
+            assert_eq!(
+                logical_plan_actual.get_node(id),
+                logical_plan_excepted.get_node(id)
+            );
+        }
+
+        let schema = logical_plan_actual.get_node(3).get_schema();
+        assert_eq!(schema.field(1).name(), "sum(salary) / count(*)");
+        assert_eq!(
+            schema.field(1).data_type(),
+            &arrow::datatypes::DataType::Float64
+        );
+    }
+
+    #[test]
+    fn test_sql_parser_not_grouped_column() {
+        let mut catalog = DummyCatalog::new();
+
+        let users_schema = Arc::new(arrow::datatypes::Schema::new(vec![
+            arrow::datatypes::Field::new("country", arrow::datatypes::DataType::Utf8, true),
+            arrow::datatypes::Field::new("salary", arrow::datatypes::DataType::Float64, true),
+        ]));
+        catalog.add_table("users", users_schema);
+
+        assert_eq!(
+            parse_sql_query(
+                "SELECT country, salary FROM users GROUP BY country",
+                &catalog
+            )
+            .unwrap_err(),
+            PlanError::NotGrouped("salary".to_string())
+        );
+        assert_eq!(
+            parse_sql_query("SELECT count(*) FROM users HAVING salary > 10", &catalog).unwrap_err(),
+            PlanError::NotGrouped("salary".to_string())
+        );
+        assert_eq!(
+            parse_sql_query("SELECT country FROM users WHERE count(*) > 10", &catalog).unwrap_err(),
+            PlanError::AggregateInWhere("count(*) > 10".to_string())
+        );
+    }
+
+    #[test]
+    fn test_sql_parser_approx_aggregates() {
+        let mut catalog = DummyCatalog::new();
+
+        let users_schema = Arc::new(arrow::datatypes::Schema::new(vec![
+            arrow::datatypes::Field::new("email", arrow::datatypes::DataType::Utf8, true),
+            arrow::datatypes::Field::new("salary", arrow::datatypes::DataType::Float64, true),
+        ]));
+        catalog.add_table("users", users_schema);
+
+        let dag = parse_sql_query(
+            "SELECT approx_count_distinct(email), approx_percentile_cont(salary, 0.9), approx_top_k(email, 5) FROM users",
+            &catalog,
+        )
+        .unwrap();
+        let schema = dag.get_node(1).get_schema();
+        assert_eq!(schema.field(0).name(), "approx_count_distinct(email)");
+        assert_eq!(
+            schema.field(1).name(),
+            "approx_percentile_cont(salary, 0.9)"
+        );
+        assert_eq!(
+            schema.field(2).data_type(),
+            &arrow::datatypes::DataType::new_list(arrow::datatypes::DataType::Utf8, true)
+        );
+
+        assert!(matches!(
+            parse_sql_query(
+                "SELECT approx_percentile_cont(salary, 1.5) FROM users",
+                &catalog
+            ),
+            Err(PlanError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_sql_parser_with_order_by() {
+        let mut catalog = DummyCatalog::new();
+
+        let users_schema = Arc::new(arrow::datatypes::Schema::new(vec![
+            arrow::datatypes::Field::new("first_name", arrow::datatypes::DataType::Utf8, true),
+            arrow::datatypes::Field::new("salary", arrow::datatypes::DataType::Float64, true),
+        ]));
+        catalog.add_table("users", users_schema.clone());
+
+        let logical_plan_actual = parse_sql_query(
+            "SELECT first_name AS name FROM users ORDER BY salary DESC, 1",
+            &catalog,
+        )
+        .unwrap();
+
+        let mut logical_plan_excepted: Dag<LogicalPlan> = Dag::new();
+
+        let mut dag_builder = DagBuilder::new(&mut logical_plan_excepted);
+
+        let ident = |name: &str| {
+            Expr::Ident(Ident {
+                name: name.to_string(),
+            })
+        };
+        let project_expr = vec![
+            Expr::Alias(Alias {
+                expr: Box::new(ident("first_name")),
+                name: "name".to_string(),
+            }),
+            ident("salary"),
+        ];
+        let sort_expr = vec![
+            SortExpr {
+                expr: ident("salary"),
+                asc: false,
+                nulls_first: true,
+            },
+            SortExpr {
+                expr: ident("name"),
+                asc: true,
+                nulls_first: false,
+            },
+        ];
+
+        let scan = dag_builder.create_scan("users".to_string(), users_schema);
+        let project = dag_builder.create_project(project_expr, scan).unwrap();
+        let sort = dag_builder.create_sort(sort_expr, project).unwrap();
+        dag_builder
+            .create_project(vec![ident("name")], sort)
+            .unwrap();
+
+        for id in 0..4 {
+            assert_eq!(
+                logical_plan_actual.get_node(id),
+                logical_plan_excepted.get_node(id)
+            );
+        }
+    }
+
+    #[test]
+    fn test_sql_parser_with_limit() {
+        let mut catalog = DummyCatalog::new();
+
+        let users_schema = Arc::new(arrow::datatypes::Schema::new(vec![
+            arrow::datatypes::Field::new("id", arrow::datatypes::DataType::Int32, true),
+            arrow::datatypes::Field::new("salary", arrow::datatypes::DataType::Float64, true),
+        ]));
+        catalog.add_table("users", users_schema);
+
+        let logical_plan_actual = parse_sql_query(
+            "SELECT id FROM users ORDER BY salary LIMIT 10 OFFSET 5",
+            &catalog,
+        )
+        .unwrap();
+
+        let LogicalPlan::Limit(limit) = logical_plan_actual.get_node(4) else {
+            panic!("expected limit node");
+        };
+        assert_eq!(limit.skip, 5);
+        assert_eq!(limit.fetch, Some(10));
+        assert_eq!(limit.schema.fields().len(), 1);
+        assert_eq!(logical_plan_actual.get_inputs(4), &vec![3]);
+        let LogicalPlan::Sort(sort) = logical_plan_actual.get_node(2) else {
+            panic!("expected sort node");
+        };
+        assert_eq!(sort.fetch, Some(15));
+
+        let logical_plan_actual = parse_sql_query(
+            "SELECT * FROM users ORDER BY salary DESC LIMIT 20",
+            &catalog,
+        )
+        .unwrap();
+        let LogicalPlan::Sort(sort) = logical_plan_actual.get_node(2) else {
+            panic!("expected sort node");
+        };
+        assert_eq!(sort.fetch, Some(20));
+
+        assert!(parse_sql_query("SELECT id FROM users LIMIT id", &catalog).is_err());
+    }
+
+    #[test]
+    fn test_sql_parser_with_joins() {
+        use arrow::datatypes::{DataType, Field, Schema};
+
+        let mut catalog = DummyCatalog::new();
+        catalog.add_table(
+            "users",
+            Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new("first_name", DataType::Utf8, false),
+            ])),
+        );
+        catalog.add_table(
+            "orders",
+            Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new("user_id", DataType::Int32, false),
+                Field::new("amount", DataType::Float64, false),
+            ])),
+        );
+        let ident = |name: &str| {
+            Expr::Ident(Ident {
+                name: name.to_string(),
+            })
+        };
+        let join = |dag: &Dag<LogicalPlan>| {
+            (0..)
+                .map(|id| dag.get_node(id))
+                .find_map(|node| match node {
+                    LogicalPlan::Join(join) => {
+                        Some((join.kind, join.on.clone(), join.schema.clone()))
+                    }
+                    _ => None,
+                })
+                .unwrap()
+        };
+
+        let dag = parse_sql_query(
+            "SELECT u.id, first_name, amount FROM users u \
+             LEFT JOIN orders o ON u.id = o.user_id AND o.amount > 10",
+            &catalog,
+        )
+        .unwrap();
+        let LogicalPlan::Join(left_join) = dag.get_node(4) else {
+            panic!("expected join node");
+        };
+        assert_eq!(left_join.kind, JoinType::Left);
+        assert_eq!(left_join.on, vec![(ident("u.id"), ident("user_id"))]);
+        assert_eq!(
+            left_join.filter.as_deref().map(ToString::to_string),
+            Some("amount > 10".to_string())
+        );
+        let names: Vec<_> = left_join
+            .schema
+            .fields()
+            .iter()
+            .map(|f| f.name().as_str())
+            .collect();
+        assert_eq!(names, ["u.id", "first_name", "o.id", "user_id", "amount"]);
+        assert!(!left_join.schema.field(0).is_nullable());
+        assert!(left_join.schema.field(4).is_nullable());
+        assert_eq!(dag.get_inputs(4), &vec![2, 3]);
+        assert_eq!(dag.get_node(5).get_schema().fields()[0].name(), "u.id");
+
+        let dag = parse_sql_query("SELECT * FROM orders JOIN users USING (id)", &catalog).unwrap();
+        let (join_type, on, _) = join(&dag);
+        assert_eq!(join_type, JoinType::Inner);
+        assert_eq!(on, vec![(ident("orders.id"), ident("users.id"))]);
+        assert_eq!(dag.get_node(5).get_schema().fields().len(), 4);
+
+        let dag = parse_sql_query(
+            "SELECT first_name FROM orders RIGHT SEMI JOIN users ON user_id = users.id",
+            &catalog,
+        )
+        .unwrap();
+        let (join_type, on, schema) = join(&dag);
+        assert_eq!(join_type, JoinType::Semi);
+        assert_eq!(on, vec![(ident("users.id"), ident("user_id"))]);
+        assert_eq!(schema.fields().len(), 2);
+
+        let dag =
+            parse_sql_query("SELECT first_name, amount FROM users, orders", &catalog).unwrap();
+        assert_eq!(join(&dag).0, JoinType::Cross);
+
+        assert_eq!(
+            parse_sql_query("SELECT id FROM users, orders", &catalog).unwrap_err(),
+            PlanError::AmbiguousColumn("id".to_string())
+        );
+        assert_eq!(
+            parse_sql_query("SELECT 1 FROM users JOIN users ON 1 = 1", &catalog).unwrap_err(),
+            PlanError::DuplicateTable("users".to_string())
+        );
+    }
+
+    #[test]
+    fn test_sql_parser_with_subqueries() {
+        use arrow::datatypes::{DataType, Field, Schema};
+
+        let mut catalog = DummyCatalog::new();
+        catalog.add_table(
+            "users",
+            Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new("first_name", DataType::Utf8, false),
+            ])),
+        );
+        catalog.add_table(
+            "orders",
+            Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new("user_id", DataType::Int32, true),
+                Field::new("amount", DataType::Float64, false),
+            ])),
+        );
+        let joins = |sql: &str| {
+            let dag = parse_sql_query(sql, &catalog).unwrap();
+            (0..dag.len())
+                .filter_map(|id| match dag.get_node(id) {
+                    LogicalPlan::Join(join) => Some(format!(
+                        "{} {:?} {}",
+                        join.kind,
+                        join.on
+                            .iter()
+                            .map(|(l, r)| format!("{l} = {r}"))
+                            .collect::<Vec<_>>(),
+                        join.filter
+                            .as_deref()
+                            .map_or(String::new(), ToString::to_string)
+                    )),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            joins("SELECT first_name FROM users WHERE id IN (SELECT user_id FROM orders)"),
+            ["SEMI [\"id = user_id\"] "]
+        );
+        // Columns of the subquery with the same name as outer ones are qualified.
+        assert_eq!(
+            joins(
+                "SELECT id FROM users u WHERE u.id > 5 AND NOT EXISTS \
+                 (SELECT 1 FROM orders o WHERE o.user_id = u.id AND amount > 10 AND o.id < u.id)"
+            ),
+            ["ANTI [\"id = user_id\"] o.id < id"]
+        );
+        // `NOT IN` of a nullable column also matches NULLs, which filters them out.
+        assert_eq!(
+            joins("SELECT id FROM users WHERE id NOT IN (SELECT user_id FROM orders)"),
+            ["ANTI [] id = user_id OR id IS NULL OR user_id IS NULL"]
+        );
+
+        // Correlated scalar subqueries are aggregated per key and left joined.
+        let dag = parse_sql_query(
+            "SELECT first_name, (SELECT max(amount) FROM orders WHERE user_id = users.id) \
+             AS max_amount FROM users",
+            &catalog,
+        )
+        .unwrap();
+        let join = (0..dag.len())
+            .find(|&id| matches!(dag.get_node(id), LogicalPlan::Join(_)))
+            .unwrap();
+        let LogicalPlan::Join(left_join) = dag.get_node(join) else {
+            unreachable!()
+        };
+        assert_eq!(left_join.kind, JoinType::Left);
+        let aggregate = dag.get_inputs(dag.get_inputs(dag.get_inputs(join)[1])[0])[0];
+        let LogicalPlan::Aggregate(aggregate) = dag.get_node(aggregate) else {
+            panic!("expected aggregate node");
+        };
+        assert_eq!(aggregate.group_expr.len(), 1);
+        let output = dag.get_node(dag.len() - 1).get_schema();
+        assert_eq!(output.field(1).name(), "max_amount");
+        assert!(output.field(1).is_nullable());
+
+        // Uncorrelated scalar subqueries must return a single row.
+        assert_eq!(
+            joins("SELECT id FROM orders WHERE amount > (SELECT avg(amount) FROM orders)"),
+            ["LEFT [] "]
+        );
+        for sql in [
+            "SELECT id FROM orders WHERE amount > (SELECT amount FROM orders)",
+            "SELECT id, (SELECT count(*) FROM orders WHERE user_id = users.id) FROM users",
+            "SELECT id FROM users WHERE id IN (SELECT user_id, amount FROM orders)",
+        ] {
+            assert!(
+                matches!(
+                    parse_sql_query(sql, &catalog),
+                    Err(PlanError::Unsupported(_))
+                ),
+                "{sql}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_sql_parser_with_ctes() {
+        use arrow::datatypes::{DataType, Field, Schema};
+
+        let mut catalog = DummyCatalog::new();
+        catalog.add_table(
+            "users",
+            Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new("first_name", DataType::Utf8, false),
+            ])),
+        );
+        catalog.add_table(
+            "orders",
+            Arc::new(Schema::new(vec![
+                Field::new("user_id", DataType::Int32, false),
+                Field::new("amount", DataType::Float64, false),
+            ])),
+        );
+        let scans = |dag: &Dag<LogicalPlan>| {
+            (0..dag.len())
+                .filter(|&id| matches!(dag.get_node(id), LogicalPlan::TableScan(_)))
+                .collect::<Vec<_>>()
+        };
+        let names = |dag: &Dag<LogicalPlan>| {
+            let schema = dag.get_node(dag.len() - 1).get_schema();
+            schema
+                .fields()
+                .iter()
+                .map(|f| f.name().clone())
+                .collect::<Vec<_>>()
+        };
+
+        // Both references read the same plan of the CTE.
+        let dag = parse_sql_query(
+            "WITH big (uid, amount) AS (SELECT user_id, amount FROM orders WHERE amount > 100), \
+             unused AS (SELECT id FROM users) \
+             SELECT a.uid, b.amount FROM big a JOIN big b ON a.uid = b.uid",
+            &catalog,
+        )
+        .unwrap();
+        let scan = scans(&dag);
+        assert_eq!(scan.len(), 1);
+        let cte = (0..dag.len())
+            .find(|&id| dag.get_usages(id).len() > 1)
+            .unwrap();
+        assert_eq!(names(&dag), ["a.uid", "b.amount"]);
+        assert_eq!(dag.get_node(cte).get_schema().field(0).name(), "uid");
+        assert_eq!(
+            (0..dag.len())
+                .filter(|&id| dag.get_usages(id).is_empty())
+                .count(),
+            1
+        );
+
+        // Later CTEs, subqueries and nested queries see earlier CTEs.
+        let dag = parse_sql_query(
+            "WITH totals AS (SELECT user_id, sum(amount) AS total FROM orders GROUP BY user_id), \
+             vip AS (SELECT user_id FROM totals WHERE total > 1000) \
+             SELECT first_name FROM users WHERE id IN (SELECT user_id FROM vip)",
+            &catalog,
+        )
+        .unwrap();
+        assert_eq!(scans(&dag).len(), 2);
+        assert_eq!(names(&dag), ["first_name"]);
+
+        let dag = parse_sql_query(
+            "SELECT t.total FROM \
+             (SELECT user_id, sum(amount) FROM orders GROUP BY user_id) AS t (uid, total) \
+             WHERE t.total > 10 ORDER BY uid",
+            &catalog,
+        )
+        .unwrap();
+        assert_eq!(names(&dag), ["total"]);
+
+        assert_eq!(
+            parse_sql_query("WITH c AS (SELECT * FROM c) SELECT * FROM c", &catalog).unwrap_err(),
+            PlanError::Catalog(CatalogError::TableNotFound("c".to_string()))
+        );
+        assert_eq!(
+            parse_sql_query(
+                "WITH c AS (SELECT id FROM users), c AS (SELECT id FROM users) SELECT * FROM c",
+                &catalog
+            )
+            .unwrap_err(),
+            PlanError::DuplicateTable("c".to_string())
+        );
+    }
+
+    #[test]
+    fn test_sql_parser_with_recursive_ctes() {
+        use crate::logical_plan::{RecursiveUnion, WorkTable};
+        use arrow::datatypes::{DataType, Field, Schema};
+
+        let mut catalog = DummyCatalog::new();
+        catalog.add_table(
+            "employees",
+            Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new("manager_id", DataType::Int32, true),
+            ])),
+        );
+
+        let dag = parse_sql_query(
+            "WITH RECURSIVE reports (eid, depth) AS ( \
+               SELECT id, 0 FROM employees WHERE id = 1 \
+               UNION ALL \
+               SELECT e.id, r.depth + 1 FROM reports r JOIN employees e ON e.manager_id = r.eid \
+             ) \
+             SELECT eid FROM reports WHERE depth < 3",
+            &catalog,
+        )
+        .unwrap();
+        let union = (0..dag.len())
+            .find(|&id| matches!(dag.get_node(id), LogicalPlan::RecursiveUnion(_)))
+            .unwrap();
+        let LogicalPlan::RecursiveUnion(RecursiveUnion {
+            distinct, schema, ..
+        }) = dag.get_node(union)
+        else {
+            unreachable!()
+        };
+        assert!(!distinct);
+        assert_eq!(schema.field(0).name(), "eid");
+        assert_eq!(schema.field(1).name(), "depth");
+
+        // The recursive term reads the work table, which has the columns of the CTE.
+        let work_table = (0..dag.len())
+            .find(|&id| matches!(dag.get_node(id), LogicalPlan::WorkTable(_)))
+            .unwrap();
+        assert_eq!(
+            dag.get_node(work_table),
+            &LogicalPlan::WorkTable(WorkTable {
+                name: "reports".to_string(),
+                schema: dag.get_node(dag.get_inputs(union)[0]).get_schema(),
+            })
+        );
+        let mut node = work_table;
+        while node != union {
+            node = *dag.get_usages(node).iter().next().unwrap();
+        }
+
+        // Without a reference to itself, the CTE is a plain union.
+        let dag = parse_sql_query(
+            "WITH RECURSIVE r AS (SELECT id FROM employees UNION SELECT manager_id FROM employees) \
+             SELECT * FROM r",
+            &catalog,
+        )
+        .unwrap();
+        assert!((0..dag.len()).any(|id| matches!(dag.get_node(id), LogicalPlan::Union(_))));
+        assert!(!(0..dag.len()).any(|id| matches!(dag.get_node(id), LogicalPlan::WorkTable(_))));
+        assert!(parse_sql_query(
+            "WITH RECURSIVE r (n) AS (SELECT id FROM employees UNION SELECT n, n FROM r) \
+             SELECT * FROM r",
+            &catalog,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_sql_parser_with_set_operations() {
+        use crate::logical_plan::SetOperation;
+        use arrow::datatypes::{DataType, Field, Schema};
+
+        fn root(dag: &Dag<LogicalPlan>) -> &LogicalPlan {
+            dag.get_node(dag.len() - 1)
+        }
+
+        let mut catalog = DummyCatalog::new();
+        catalog.add_table(
+            "users",
+            Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new("country", DataType::Utf8, true),
+            ])),
+        );
+        catalog.add_table(
+            "admins",
+            Arc::new(Schema::new(vec![
+                Field::new("admin_id", DataType::Int64, false),
+                Field::new("region", DataType::Utf8, false),
+            ])),
+        );
+
+        // Nested unions become one union over all branches, with common types.
+        let dag = parse_sql_query(
+            "SELECT id, country FROM users UNION ALL SELECT admin_id, region FROM admins \
+             UNION SELECT id, country FROM users",
+            &catalog,
+        )
+        .unwrap();
+        let LogicalPlan::Union(SetOperation { distinct, schema }) = root(&dag) else {
+            panic!("expected a union, got {:?}", root(&dag));
+        };
+        assert!(distinct);
+        assert_eq!(dag.get_inputs(dag.len() - 1).len(), 3);
+        assert_eq!(
+            schema.as_ref(),
+            &Schema::new(vec![
+                Field::new("id", DataType::Int64, false),
+                Field::new("country", DataType::Utf8, true),
+            ])
+        );
+
+        // UNION ALL over a UNION is not flattened, INTERSECT binds tighter.
+        let dag = parse_sql_query(
+            "SELECT id FROM users UNION ALL (SELECT id FROM users UNION SELECT admin_id FROM admins)",
+            &catalog,
+        )
+        .unwrap();
+        assert!(matches!(
+            root(&dag),
+            LogicalPlan::Union(SetOperation {
+                distinct: false,
+                ..
+            })
+        ));
+        assert_eq!(dag.get_inputs(dag.len() - 1).len(), 2);
+        let dag = parse_sql_query(
+            "SELECT id FROM users EXCEPT ALL SELECT id FROM users INTERSECT SELECT admin_id FROM admins \
+             ORDER BY 1 DESC LIMIT 5",
+            &catalog,
+        )
+        .unwrap();
+        let except = dag.get_inputs(dag.get_inputs(dag.len() - 1)[0])[0];
+        assert!(matches!(
+            dag.get_node(except),
+            LogicalPlan::Except(SetOperation {
+                distinct: false,
+                ..
+            })
+        ));
+        assert!(matches!(
+            dag.get_node(dag.get_inputs(except)[1]),
+            LogicalPlan::Intersect(SetOperation { distinct: true, .. })
+        ));
+
+        assert_eq!(
+            parse_sql_query(
+                "SELECT id FROM users UNION SELECT admin_id, region FROM admins",
+                &catalog
+            )
+            .unwrap_err(),
+            PlanError::Unsupported("UNION of queries with 1 and 2 columns".to_string())
+        );
+        assert!(matches!(
+            parse_sql_query(
+                "SELECT id FROM users UNION SELECT region FROM admins",
+                &catalog
+            ),
+            Err(PlanError::Unsupported(_))
+        ));
+        assert_eq!(
+            parse_sql_query(
+                "SELECT id FROM users UNION SELECT admin_id FROM admins ORDER BY admin_id",
+                &catalog
+            )
+            .unwrap_err(),
+            PlanError::ColumnNotFound("admin_id".to_string())
+        );
+    }
+
+    #[test]
+    fn test_sql_parser_with_distinct() {
+        use crate::logical_plan::Distinct;
+        use arrow::datatypes::{DataType, Field, Schema};
+
+        let mut catalog = DummyCatalog::new();
+        catalog.add_table(
+            "users",
+            Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new("country", DataType::Utf8, true),
+                Field::new("age", DataType::Int32, true),
+            ])),
+        );
+        let names = |dag: &Dag<LogicalPlan>| {
+            let schema = dag.get_node(dag.len() - 1).get_schema();
+            schema
+                .fields()
+                .iter()
+                .map(|f| f.name().clone())
+                .collect::<Vec<_>>()
+        };
+        let ident = |name: &str| {
+            Expr::Ident(Ident {
+                name: name.to_string(),
+            })
+        };
+
+        // SELECT DISTINCT deduplicates the output columns before sorting.
+        let dag = parse_sql_query(
+            "SELECT DISTINCT country FROM users ORDER BY country",
+            &catalog,
+        )
+        .unwrap();
+        let sort = dag.len() - 1;
+        let distinct = dag.get_inputs(sort)[0];
+        assert!(matches!(
+            dag.get_node(distinct),
+            LogicalPlan::Distinct(Distinct { on, .. }) if on.is_empty()
+        ));
+        assert_eq!(
+            parse_sql_query("SELECT DISTINCT country FROM users ORDER BY age", &catalog)
+                .unwrap_err(),
+            PlanError::Unsupported(
+                "ORDER BY age not in the select list of SELECT DISTINCT".to_string()
+            )
+        );
+
+        // DISTINCT ON keeps the first row of each group in the sort order, and
+        // may use keys which are not in the select list.
+        let dag = parse_sql_query(
+            "SELECT DISTINCT ON (country) id FROM users ORDER BY country, age DESC",
+            &catalog,
+        )
+        .unwrap();
+        let project = dag.len() - 1;
+        assert_eq!(names(&dag), ["id"]);
+        let distinct = dag.get_inputs(project)[0];
+        let LogicalPlan::Distinct(Distinct { on, .. }) = dag.get_node(distinct) else {
+            panic!("expected a distinct, got {:?}", dag.get_node(distinct));
+        };
+        assert_eq!(on, &[ident("country")]);
+        assert!(matches!(
+            dag.get_node(dag.get_inputs(distinct)[0]),
+            LogicalPlan::Sort(_)
+        ));
+        assert!(matches!(
+            parse_sql_query(
+                "SELECT DISTINCT ON (country) id FROM users ORDER BY age",
+                &catalog
+            ),
+            Err(PlanError::Unsupported(_))
+        ));
+
+        // Distinct aggregates are planned like other aggregates.
+        let dag = parse_sql_query(
+            "SELECT country, count(DISTINCT age) FROM users GROUP BY country",
+            &catalog,
+        )
+        .unwrap();
+        assert_eq!(names(&dag), ["country", "count(DISTINCT age)"]);
+    }
+
+    #[test]
+    fn test_sql_parser_with_window_functions() {
+        use crate::logical_plan::expr::WindowFunc;
+        use crate::logical_plan::Window;
+        use arrow::datatypes::{DataType, Field, Schema};
+
+        fn windows(dag: &Dag<LogicalPlan>) -> Vec<&Window> {
+            (0..dag.len())
+                .filter_map(|id| match dag.get_node(id) {
+                    LogicalPlan::Window(window) => Some(window),
+                    _ => None,
+                })
+                .collect()
+        }
+
+        let mut catalog = DummyCatalog::new();
+        catalog.add_table(
+            "users",
+            Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new("country", DataType::Utf8, true),
+                Field::new("age", DataType::Int32, true),
+            ])),
+        );
+        // Functions over the same window share a node, QUALIFY filters on
+        // aliases of the select list.
+        let dag = parse_sql_query(
+            "SELECT id, row_number() OVER (PARTITION BY country ORDER BY age DESC) AS rn, \
+               avg(age) OVER (PARTITION BY country ORDER BY age DESC ROWS 2 PRECEDING) AS avg_age, \
+               count(*) OVER () AS total \
+             FROM users QUALIFY rn <= 3",
+            &catalog,
+        )
+        .unwrap();
+        let planned = windows(&dag);
+        assert_eq!(planned.len(), 2);
+        assert_eq!(planned[0].window_expr.len(), 2);
+        let schema = &planned[0].schema;
+        assert_eq!(schema.fields().len(), 5);
+        assert_eq!(schema.field(3).data_type(), &DataType::Int64);
+        assert!(!schema.field(3).is_nullable());
+        assert_eq!(schema.field(4).data_type(), &DataType::Float64);
+        let project = dag.len() - 1;
+        let filter = dag.get_inputs(project)[0];
+        let LogicalPlan::Filter(filter) = dag.get_node(filter) else {
+            panic!("expected a filter, got {:?}", dag.get_node(filter));
+        };
+        assert_eq!(
+            filter.expr.to_string(),
+            "row_number() OVER (PARTITION BY country ORDER BY age DESC NULLS FIRST) <= 3"
+        );
+
+        // Window functions see the result of GROUP BY.
+        let dag = parse_sql_query(
+            "SELECT country, rank() OVER (ORDER BY count(*) DESC) FROM users GROUP BY country",
+            &catalog,
+        )
+        .unwrap();
+        let window = windows(&dag)[0];
+        let Expr::WindowFunction(function) = &window.window_expr[0] else {
+            unreachable!()
+        };
+        assert_eq!(function.func, WindowFunc::Rank);
+        assert!(matches!(
+            dag.get_node(dag.get_inputs(dag.len() - 2)[0]),
+            LogicalPlan::Aggregate(_)
+        ));
+
+        for (sql, misplaced) in [
+            ("SELECT id FROM users WHERE row_number() OVER () > 1", true),
+            ("SELECT sum(row_number() OVER ()) FROM users", true),
+            ("SELECT rank() FROM users", false),
+            ("SELECT ntile(0) OVER () FROM users", false),
+            ("SELECT count(DISTINCT id) OVER () FROM users", false),
+            (
+                "SELECT sum(age) OVER (ORDER BY age, id RANGE 1 PRECEDING) FROM users",
+                false,
+            ),
+            (
+                "SELECT sum(age) OVER (ORDER BY age ROWS BETWEEN CURRENT ROW AND 1 PRECEDING) \
+                 FROM users",
+                false,
+            ),
+        ] {
+            match parse_sql_query(sql, &catalog) {
+                Err(PlanError::MisplacedWindowFunction(_)) if misplaced => {}
+                Err(PlanError::Unsupported(_)) if !misplaced => {}
+                res => panic!("unexpected result of {sql}: {res:?}"),
+            }
+        }
+    }
 }
@@ -0,0 +1,430 @@
+use crate::catalog::DummyCatalog;
+use crate::dag::{Dag, NodeId};
+use crate::logical_plan::dag_builder::DagBuilder;
+use crate::logical_plan::errors::PlanError;
+use crate::logical_plan::expr::{Alias, Binary, BinaryOp, Expr, Ident, VisitExpression};
+use crate::logical_plan::scope::Scope;
+use crate::logical_plan::{JoinType, LogicalPlan};
+use crate::parser::cte::Ctes;
+use crate::parser::sql_parser::{parse_query, qualify_duplicates, split_join_condition};
+use arrow::datatypes::Schema;
+use sqlparser::ast;
+use std::collections::{HashMap, HashSet};
+
+/// Query block enclosing a subquery, whose columns the subquery may reference.
+pub struct OuterQuery<'a> {
+    pub scope: &'a Scope,
+    /// Fields of the plan the subquery is joined to.
+    pub fields: HashSet<String>,
+}
+
+/// Plan of a (sub)query. Predicates of a subquery which reference the
+/// enclosing query are not applied, but returned to become join conditions.
+pub struct PlannedQuery {
+    pub node: NodeId,
+    /// Number of leading output columns which belong to the select list, the
+    /// others are read by the correlated predicates.
+    pub columns: usize,
+    pub correlated: Vec<Expr>,
+    /// Whether an aggregate without GROUP BY was grouped by the keys of the
+    /// correlated predicates, which yields no row instead of one for keys
+    /// without input rows.
+    pub grouped_by_correlation: bool,
+}
+
+impl<'a> OuterQuery<'a> {
+    pub fn new(scope: &'a Scope, schema: &Schema) -> Self {
+        Self {
+            scope,
+            fields: schema.fields().iter().map(|f| f.name().clone()).collect(),
+        }
+    }
+
+    /// Renames the columns of a subquery's FROM clause which have the same
+    /// name as a column of the enclosing query to `qualifier.name`.
+    pub fn rename_inner(
+        &self,
+        input: NodeId,
+        scope: &mut Scope,
+        dag_builder: &mut DagBuilder,
+    ) -> Result<NodeId, PlanError> {
+        let duplicates = scope
+            .columns()
+            .iter()
+            .map(|column| column.field.clone())
+            .filter(|field| self.fields.contains(field))
+            .collect();
+        let input = qualify_duplicates(input, scope, &duplicates, dag_builder)?;
+        match scope
+            .columns()
+            .iter()
+            .find(|column| self.fields.contains(&column.field))
+        {
+            Some(column) => Err(PlanError::AmbiguousColumn(column.field.clone())),
+            None => Ok(input),
+        }
+    }
+
+    /// Whether `expr` reads columns of the enclosing query.
+    pub fn is_referenced_by(&self, expr: &Expr) -> bool {
+        let mut columns = HashSet::new();
+        expr.collect_columns(&mut columns);
+        columns.iter().any(|column| self.fields.contains(*column))
+    }
+
+    /// Splits correlated predicates into equal keys of the enclosing query
+    /// (left) and the subquery (right) and the remaining filter.
+    fn join_condition(&self, correlated: Vec<Expr>) -> (Vec<(Expr, Expr)>, Vec<Expr>) {
+        let Some(condition) = Expr::conjunction(correlated) else {
+            return (Vec::new(), Vec::new());
+        };
+        let fields = self.fields.iter().map(String::as_str).collect();
+        split_join_condition(condition, &fields)
+    }
+}
+
+/// Splits a predicate into the operands of its top-level ANDs.
+pub fn split_conjuncts(expr: &ast::Expr) -> Vec<&ast::Expr> {
+    match expr {
+        ast::Expr::BinaryOp {
+            left,
+            op: ast::BinaryOperator::And,
+            right,
+        } => {
+            let mut conjuncts = split_conjuncts(left);
+            conjuncts.extend(split_conjuncts(right));
+            conjuncts
+        }
+        ast::Expr::Nested(expr) => split_conjuncts(expr),
+        _ => vec![expr],
+    }
+}
+
+/// Removes NOTs and parentheses around `expr`, returns whether it was negated.
+fn strip_not(expr: &ast::Expr) -> (&ast::Expr, bool) {
+    match expr {
+        ast::Expr::Nested(expr) => strip_not(expr),
+        ast::Expr::UnaryOp {
+            op: ast::UnaryOperator::Not,
+            expr,
+        } => {
+            let (expr, negated) = strip_not(expr);
+            (expr, !negated)
+        }
+        _ => (expr, false),
+    }
+}
+
+/// Whether a conjunct of a WHERE clause is an (optionally negated) EXISTS or
+/// IN subquery, which is planned as a semi or anti join.
+pub fn is_subquery_predicate(expr: &ast::Expr) -> bool {
+    matches!(
+        strip_not(expr).0,
+        ast::Expr::Exists { .. } | ast::Expr::InSubquery { .. }
+    )
+}
+
+/// Filters `input` by an EXISTS or IN subquery predicate: rows with a match
+/// in the subquery are kept by a semi join, rows without one by an anti join.
+pub fn plan_subquery_predicate(
+    expr: &ast::Expr,
+    scope: &Scope,
+    input: NodeId,
+    ctes: &Ctes,
+    dag_builder: &mut DagBuilder,
+    catalog: &DummyCatalog,
+) -> Result<NodeId, PlanError> {
+    let (expr, negated) = strip_not(expr);
+    let input_schema = dag_builder.dag().get_node(input).get_schema();
+    let outer = OuterQuery::new(scope, &input_schema);
+    let join_type = |negated| {
+        if negated {
+            JoinType::Anti
+        } else {
+            JoinType::Semi
+        }
+    };
+
+    match expr {
+        ast::Expr::Exists {
+            subquery,
+            negated: not_exists,
+        } => {
+            let planned = parse_query(subquery, Some(&outer), ctes, dag_builder, catalog)?;
+            if planned.grouped_by_correlation {
+                return Err(PlanError::Unsupported(format!(
+                    "Correlated EXISTS subquery with aggregates {subquery}"
+                )));
+            }
+            let mut right = planned.node;
+            if planned.correlated.is_empty() {
+                // Any row of the subquery matches every row of the input.
+                right = dag_builder.create_limit(0, Some(1), right);
+            }
+            let (on, filter) = outer.join_condition(planned.correlated);
+            let filter = Expr::conjunction(filter).map(Box::new);
+            dag_builder.create_join(join_type(negated != *not_exists), on, filter, input, right)
+        }
+        ast::Expr::InSubquery {
+            expr,
+            subquery,
+            negated: not_in,
+        } => {
+            let negated = negated != *not_in;
+            let lhs = VisitExpression::new(catalog)
+                .with_scope(scope)
+                .visit(expr)?;
+            let planned = parse_query(subquery, Some(&outer), ctes, dag_builder, catalog)?;
+            let column = single_column(dag_builder.dag(), &planned, subquery)?;
+            if planned.grouped_by_correlation && (negated || !column.is_nullable()) {
+                // An aggregate over no rows would have to be compared.
+                return Err(PlanError::Unsupported(format!(
+                    "Correlated IN subquery with aggregates {subquery}"
+                )));
+            }
+            let (right, rhs) = if outer.fields.contains(column.name()) {
+                let name = format!("({subquery})");
+                (rename_output(&planned, &name, dag_builder)?, name)
+            } else {
+                (planned.node, column.name().clone())
+            };
+            let rhs = Expr::Ident(Ident { name: rhs });
+
+            let (mut on, mut filter) = outer.join_condition(planned.correlated);
+            let nullable = lhs.to_field(&input_schema)?.is_nullable() || column.is_nullable();
+            if negated && nullable {
+                // `x NOT IN (...)` is NULL, so false, if x or any value is NULL.
+                let is_null = |expr: &Expr| Expr::IsNull(Box::new(expr.clone()));
+                let matches = binary(lhs.clone(), BinaryOp::Eq, rhs.clone());
+                let matches = binary(matches, BinaryOp::Or, is_null(&lhs));
+                filter.push(binary(matches, BinaryOp::Or, is_null(&rhs)));
+            } else {
+                on.push((lhs, rhs));
+            }
+            let filter = Expr::conjunction(filter).map(Box::new);
+            dag_builder.create_join(join_type(negated), on, filter, input, right)
+        }
+        _ => Err(PlanError::Unsupported(format!("Subquery predicate {expr}"))),
+    }
+}
+
+/// Left joins the scalar subqueries in `exprs` to `input`. Returns the
+/// columns holding their values by the SQL text of the subquery.
+pub fn plan_scalar_subqueries(
+    exprs: &[&ast::Expr],
+    scope: &Scope,
+    input: NodeId,
+    ctes: &Ctes,
+    dag_builder: &mut DagBuilder,
+    catalog: &DummyCatalog,
+) -> Result<(NodeId, HashMap<String, String>), PlanError> {
+    let mut queries = Vec::new();
+    for expr in exprs {
+        collect_scalar_subqueries(expr, &mut queries);
+    }
+
+    let mut result = input;
+    let mut names = HashMap::new();
+    for query in queries {
+        let name = format!("({query})");
+        if names.contains_key(&name) {
+            continue;
+        }
+        let outer = OuterQuery::new(scope, &dag_builder.dag().get_node(result).get_schema());
+        let planned = parse_query(query, Some(&outer), ctes, dag_builder, catalog)?;
+        let column = single_column(dag_builder.dag(), &planned, query)?;
+        if planned.correlated.is_empty() {
+            if !returns_single_row(dag_builder.dag(), planned.node) {
+                return Err(PlanError::Unsupported(format!(
+                    "Scalar subquery {name} which may return more than one row"
+                )));
+            }
+        } else if !planned.grouped_by_correlation {
+            return Err(PlanError::Unsupported(format!(
+                "Correlated scalar subquery {name} which isn't an aggregate without GROUP BY"
+            )));
+        } else if !column.is_nullable() {
+            // Keys without rows would get NULL from the join instead of e.g. a count of 0.
+            return Err(PlanError::Unsupported(format!(
+                "Correlated scalar subquery {name} which isn't NULL without rows"
+            )));
+        }
+
+        let right = rename_output(&planned, &name, dag_builder)?;
+        let (on, filter) = outer.join_condition(planned.correlated);
+        let filter = Expr::conjunction(filter).map(Box::new);
+        result = dag_builder.create_join(JoinType::Left, on, filter, result, right)?;
+        names.insert(name.clone(), name);
+    }
+    Ok((result, names))
+}
+
+fn collect_scalar_subqueries<'a>(expr: &'a ast::Expr, queries: &mut Vec<&'a ast::Query>) {
+    match expr {
+        ast::Expr::Subquery(query) => queries.push(query),
+        ast::Expr::BinaryOp { left, right, .. } => {
+            collect_scalar_subqueries(left, queries);
+            collect_scalar_subqueries(right, queries);
+        }
+        ast::Expr::Between {
+            expr, low, high, ..
+        } => {
+            for expr in [expr, low, high] {
+                collect_scalar_subqueries(expr, queries);
+            }
+        }
+        ast::Expr::Nested(expr)
+        | ast::Expr::UnaryOp { expr, .. }
+        | ast::Expr::IsNull(expr)
+        | ast::Expr::IsNotNull(expr)
+        | ast::Expr::InSubquery { expr, .. } => collect_scalar_subqueries(expr, queries),
+        ast::Expr::Function(function) => {
+            for arg in &function.args {
+                if let ast::FunctionArg::Unnamed(ast::FunctionArgExpr::Expr(expr)) = arg {
+                    collect_scalar_subqueries(expr, queries);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The only column of the select list of a subquery.
+fn single_column(
+    dag: &Dag<LogicalPlan>,
+    planned: &PlannedQuery,
+    query: &ast::Query,
+) -> Result<arrow::datatypes::Field, PlanError> {
+    if planned.columns != 1 {
+        return Err(PlanError::Unsupported(format!(
+            "Subquery {query} with {} columns",
+            planned.columns
+        )));
+    }
+    Ok(dag.get_node(planned.node).get_schema().field(0).clone())
+}
+
+/// Renames the first output column of a subquery to `name`.
+fn rename_output(
+    planned: &PlannedQuery,
+    name: &str,
+    dag_builder: &mut DagBuilder,
+) -> Result<NodeId, PlanError> {
+    let schema = dag_builder.dag().get_node(planned.node).get_schema();
+    let expr = schema
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let column = Expr::Ident(Ident {
+                name: field.name().clone(),
+            });
+            if i == 0 {
+                Expr::Alias(Alias {
+                    expr: Box::new(column),
+                    name: name.to_string(),
+                })
+            } else {
+                column
+            }
+        })
+        .collect();
+    dag_builder.create_project(expr, planned.node)
+}
+
+/// Whether `node` is known to produce at most one row.
+fn returns_single_row(dag: &Dag<LogicalPlan>, node: NodeId) -> bool {
+    match dag.get_node(node) {
+        LogicalPlan::Aggregate(aggregate) => aggregate.group_expr.is_empty(),
+        LogicalPlan::Limit(limit) if limit.fetch.is_some_and(|fetch| fetch <= 1) => true,
+        LogicalPlan::Projection(_)
+        | LogicalPlan::Filter(_)
+        | LogicalPlan::Sort(_)
+        | LogicalPlan::Limit(_)
+        | LogicalPlan::Distinct(_)
+        | LogicalPlan::Window(_) => returns_single_row(dag, dag.get_inputs(node)[0]),
+        LogicalPlan::TableScan(_)
+        | LogicalPlan::Unpivot(_)
+        | LogicalPlan::Join(_)
+        | LogicalPlan::WorkTable(_)
+        | LogicalPlan::RecursiveUnion(_)
+        | LogicalPlan::Union(_)
+        | LogicalPlan::Intersect(_)
+        | LogicalPlan::Except(_) => false,
+    }
+}
+
+/// Turns the correlated predicates of an aggregate subquery, which must be
+/// equalities, into group keys: the subquery is evaluated for all keys at
+/// once, and the predicates compare the keys with the enclosing query.
+pub fn group_by_correlation(
+    correlated: Vec<Expr>,
+    group_expr: &mut Vec<Expr>,
+    outer: &OuterQuery,
+) -> Result<Vec<Expr>, PlanError> {
+    correlated
+        .into_iter()
+        .map(|predicate| {
+            let unsupported = || {
+                PlanError::Unsupported(format!(
+                    "Correlated predicate {predicate} in an aggregate subquery"
+                ))
+            };
+            let Expr::Binary(equality) = &predicate else {
+                return Err(unsupported());
+            };
+            let (lhs, rhs) = (equality.lhs.as_ref(), equality.rhs.as_ref());
+            let (inner, outer_expr) = if is_outer_only(rhs, outer) && !outer.is_referenced_by(lhs) {
+                (lhs, rhs)
+            } else if is_outer_only(lhs, outer) && !outer.is_referenced_by(rhs) {
+                (rhs, lhs)
+            } else {
+                return Err(unsupported());
+            };
+            if equality.op != BinaryOp::Eq {
+                return Err(unsupported());
+            }
+            if !group_expr.contains(inner) {
+                group_expr.push(inner.clone());
+            }
+            let key = Expr::Ident(Ident { name: inner.name() });
+            Ok(binary(key, BinaryOp::Eq, outer_expr.clone()))
+        })
+        .collect()
+}
+
+fn is_outer_only(expr: &Expr, outer: &OuterQuery) -> bool {
+    let mut columns = HashSet::new();
+    expr.collect_columns(&mut columns);
+    columns.iter().all(|column| outer.fields.contains(*column))
+}
+
+/// Columns of the subquery read by its correlated predicates, which have to
+/// be part of its output.
+pub fn correlation_columns(correlated: &[Expr], outer: &OuterQuery) -> Vec<Expr> {
+    let mut columns = HashSet::new();
+    for predicate in correlated {
+        predicate.collect_columns(&mut columns);
+    }
+    let mut columns = columns
+        .into_iter()
+        .filter(|column| !outer.fields.contains(*column))
+        .collect::<Vec<_>>();
+    columns.sort_unstable();
+    columns
+        .into_iter()
+        .map(|name| {
+            Expr::Ident(Ident {
+                name: name.to_string(),
+            })
+        })
+        .collect()
+}
+
+fn binary(lhs: Expr, op: BinaryOp, rhs: Expr) -> Expr {
+    Expr::Binary(Binary {
+        lhs: Box::new(lhs),
+        op,
+        rhs: Box::new(rhs),
+    })
+}